@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use opendal::Operator;
+
+use super::Catalog;
+use super::NamespaceIdent;
+use super::TableIdent;
+use crate::io;
+use crate::types;
+use crate::Error;
+use crate::ErrorKind;
+use crate::Table;
+
+/// HadoopCatalog is the catalog that stores tables in the file system
+/// directly.
+///
+/// Tables are located at `<warehouse>/<namespace levels>/<table name>` and
+/// the current version of table is tracked by `metadata/version-hint.text`.
+pub struct HadoopCatalog {
+    warehouse: String,
+    op: Operator,
+}
+
+impl HadoopCatalog {
+    /// Create a new hadoop catalog with the uri of warehouse like
+    /// `s3://bucket/warehouse` or `/path/to/warehouse`.
+    pub fn new(warehouse: &str) -> Result<Self> {
+        let warehouse = warehouse.trim_end_matches('/').to_string();
+        let op = io::build_operator(&warehouse)?;
+
+        Ok(Self { warehouse, op })
+    }
+
+    /// Get the path of namespace relative to the warehouse, which always
+    /// ends with `/`.
+    fn namespace_path(namespace: &NamespaceIdent) -> String {
+        namespace.levels().iter().map(|v| format!("{v}/")).collect()
+    }
+
+    /// Get the path of table relative to the warehouse, which always ends
+    /// with `/`.
+    fn table_path(table: &TableIdent) -> String {
+        format!("{}{}/", Self::namespace_path(&table.namespace), table.name)
+    }
+}
+
+#[async_trait]
+impl Catalog for HadoopCatalog {
+    async fn list_tables(&self, namespace: &NamespaceIdent) -> Result<Vec<TableIdent>> {
+        let path = match Self::namespace_path(namespace) {
+            v if v.is_empty() => "/".to_string(),
+            v => v,
+        };
+
+        let mut lister = self
+            .op
+            .list(&path)
+            .await
+            .map_err(|err| anyhow!("list namespace {} failed: {}", namespace, err))?;
+
+        let mut tables = vec![];
+        while let Some(entry) = lister.next().await {
+            let entry = entry.map_err(|err| anyhow!("list namespace entry failed: {}", err))?;
+
+            // Only directories with a `metadata` sub directory are tables.
+            if !entry.path().ends_with('/') {
+                continue;
+            }
+            if !self
+                .op
+                .is_exist(&format!("{}metadata/", entry.path()))
+                .await?
+            {
+                continue;
+            }
+
+            tables.push(TableIdent::new(
+                namespace.clone(),
+                entry.name().trim_end_matches('/'),
+            ));
+        }
+
+        tables.sort();
+        Ok(tables)
+    }
+
+    async fn load_table(&self, table: &TableIdent) -> Result<Table> {
+        let path = Self::table_path(table);
+        if !self.op.is_exist(&format!("{path}metadata/")).await? {
+            return Err(anyhow!("table {} is not found", table));
+        }
+
+        Table::open(&format!(
+            "{}/{}",
+            self.warehouse,
+            path.trim_end_matches('/')
+        ))
+        .await
+    }
+
+    /// Create a new table under the warehouse.
+    ///
+    /// Hadoop catalog doesn't have a separate registry, the table is
+    /// registered once its metadata and version hint are written.
+    async fn create_table(
+        &self,
+        table: &TableIdent,
+        schema: types::Schema,
+        partition_spec: types::PartitionSpec,
+        properties: HashMap<String, String>,
+    ) -> Result<Table> {
+        let path = Self::table_path(table);
+        if self.op.is_exist(&format!("{path}metadata/")).await? {
+            return Err(anyhow!("table {} already exists", table));
+        }
+
+        let location = format!("{}/{}", self.warehouse, path.trim_end_matches('/'));
+        let op = io::build_operator(&location)?;
+
+        Table::create(op, &location, schema, partition_spec, properties).await
+    }
+
+    /// Drop the table by removing its `metadata` directory.
+    ///
+    /// Data files are kept unless `purge` is true.
+    async fn drop_table(&self, table: &TableIdent, purge: bool) -> Result<()> {
+        let path = Self::table_path(table);
+
+        if purge {
+            let files = self.load_table(table).await?.reachable_files().await?;
+            self.op
+                .remove(files.into_iter().map(|v| format!("{path}{v}")).collect())
+                .await?;
+        } else if !self.op.is_exist(&format!("{path}metadata/")).await? {
+            return Err(anyhow!("table {} is not found", table));
+        }
+
+        self.op.remove_all(&format!("{path}metadata/")).await?;
+        Ok(())
+    }
+
+    /// Rename is not supported by hadoop catalog.
+    ///
+    /// The location of table is decided by its identifier, and table
+    /// metadata, manifest lists and manifests record absolute paths under
+    /// this location. Moving the table directory will break all of them.
+    async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::IcebergFeatureUnsupported,
+            "rename table is not supported by hadoop catalog",
+        )
+        .with_context("from", from.to_string())
+        .with_context("to", to.to_string())
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn testdata_catalog() -> HadoopCatalog {
+        let path = format!(
+            "{}/testdata",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        HadoopCatalog::new(&path).expect("create catalog must succeed")
+    }
+
+    #[tokio::test]
+    async fn test_hadoop_catalog_list_tables() -> Result<()> {
+        let catalog = testdata_catalog();
+
+        let tables = catalog.list_tables(&NamespaceIdent::default()).await?;
+        assert!(tables.contains(&"simple_table".parse()?));
+        assert!(tables.contains(&"no_hint_table".parse()?));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hadoop_catalog_load_table() -> Result<()> {
+        let catalog = testdata_catalog();
+
+        let table = catalog.load_table(&"simple_table".parse()?).await?;
+        let table_metadata = table.current_table_metadata()?;
+        assert_eq!(table_metadata.format_version, types::TableFormatVersion::V1);
+        assert_eq!(table_metadata.last_updated_ms, 1686911671713);
+
+        assert!(catalog.load_table(&"not_exist".parse()?).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hadoop_catalog_create_table() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let catalog = HadoopCatalog::new(&dir.path().to_string_lossy())?;
+
+        let ident: TableIdent = "db.tbl".parse()?;
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![types::Field {
+                id: 1,
+                name: "id".to_string(),
+                required: true,
+                field_type: types::Any::Primitive(types::Primitive::Long),
+                comment: None,
+            }],
+        };
+        let partition_spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+
+        let table = catalog
+            .create_table(
+                &ident,
+                schema.clone(),
+                partition_spec.clone(),
+                HashMap::new(),
+            )
+            .await?;
+        let table_metadata = table.current_table_metadata()?;
+        assert_eq!(table_metadata.format_version, types::TableFormatVersion::V2);
+        assert_eq!(table_metadata.schemas, vec![schema.clone()]);
+        assert_eq!(table_metadata.partition_specs, vec![partition_spec.clone()]);
+        assert_eq!(table_metadata.last_column_id, 1);
+        assert_eq!(table_metadata.current_snapshot_id, None);
+
+        let table = catalog.load_table(&ident).await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v1.metadata.json")
+        );
+        assert_eq!(
+            catalog.list_tables(&"db".parse()?).await?,
+            vec![ident.clone()]
+        );
+
+        assert!(catalog
+            .create_table(&ident, schema, partition_spec, HashMap::new())
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hadoop_catalog_drop_table() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let catalog = HadoopCatalog::new(&dir.path().to_string_lossy())?;
+
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![],
+        };
+        let partition_spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+
+        for (name, purge) in [("keep", false), ("purge", true)] {
+            let ident = TableIdent::new("db".parse()?, name);
+            catalog
+                .create_table(
+                    &ident,
+                    schema.clone(),
+                    partition_spec.clone(),
+                    HashMap::new(),
+                )
+                .await?;
+            let data_file = dir.path().join("db").join(name).join("data/00000.parquet");
+            std::fs::create_dir_all(data_file.parent().unwrap())?;
+            std::fs::write(&data_file, "not referenced by table")?;
+
+            catalog.drop_table(&ident, purge).await?;
+
+            assert!(catalog.load_table(&ident).await.is_err());
+            assert!(!dir.path().join("db").join(name).join("metadata").exists());
+            // Files not reachable from metadata are never deleted.
+            assert!(data_file.exists());
+            assert!(catalog.drop_table(&ident, purge).await.is_err());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hadoop_catalog_drop_table_purge_missing_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let catalog = HadoopCatalog::new(&dir.path().to_string_lossy())?;
+        let ident: TableIdent = "db.tbl".parse()?;
+        let table_dir = dir.path().join("db").join("tbl");
+
+        let mut table = catalog
+            .create_table(
+                &ident,
+                types::Schema {
+                    schema_id: 0,
+                    identifier_field_ids: None,
+                    fields: vec![],
+                },
+                types::PartitionSpec {
+                    spec_id: 0,
+                    fields: vec![],
+                },
+                HashMap::new(),
+            )
+            .await?;
+        for name in ["a", "b"] {
+            let path = table_dir.join(format!("data/{name}.parquet"));
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, name)?;
+
+            let mut data_file = crate::operation::test_utils::data_file(name, None, 1);
+            data_file.file_path = path.to_string_lossy().to_string();
+            data_file.partition = vec![];
+            table.new_append().add_data_file(data_file).commit().await?;
+        }
+
+        // The manifest list of the first snapshot has been removed, like
+        // by an interrupted expiration.
+        let meta = table.current_table_metadata()?;
+        let first = &meta.snapshots.as_ref().unwrap()[0];
+        std::fs::remove_file(&first.manifest_list)?;
+
+        catalog.drop_table(&ident, true).await?;
+
+        assert!(!table_dir.join("metadata").exists());
+        // Data files are still reachable from the current snapshot.
+        assert!(!table_dir.join("data/a.parquet").exists());
+        assert!(!table_dir.join("data/b.parquet").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hadoop_catalog_rename_table() -> Result<()> {
+        let catalog = testdata_catalog();
+
+        let err = catalog
+            .rename_table(&"simple_table".parse()?, &"renamed_table".parse()?)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>().map(|v| v.kind()),
+            Some(ErrorKind::IcebergFeatureUnsupported)
+        );
+        assert!(catalog.load_table(&"simple_table".parse()?).await.is_ok());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use crate::Error;
+use crate::ErrorKind;
+use crate::Result;
+
+/// NamespaceIdent is the identifier of a namespace.
+///
+/// Iceberg namespaces are hierarchical, so a namespace is a list of
+/// levels like `["a", "b", "c"]`, which is represented as `a.b.c` in
+/// dotted string.
+///
+/// Levels that contain `.` or `\` must be escaped by `\` in dotted string,
+/// for example `a\.b.c` represents `["a.b", "c"]`.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+pub struct NamespaceIdent(Vec<String>);
+
+impl NamespaceIdent {
+    /// Create a new namespace identifier from levels.
+    pub fn new(levels: Vec<String>) -> Self {
+        Self(levels)
+    }
+
+    /// Return the levels of this namespace.
+    pub fn levels(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Check if this namespace is empty (the root namespace).
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl FromStr for NamespaceIdent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+
+        Ok(Self(split_dotted(s)?))
+    }
+}
+
+impl Display for NamespaceIdent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", join_dotted(&self.0))
+    }
+}
+
+/// TableIdent is the identifier of a table, which is the table name under
+/// a namespace.
+///
+/// The dotted string of a table identifier is the dotted string of the
+/// namespace with the table name as the last level, for example `a.b.tbl`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+pub struct TableIdent {
+    /// The namespace of this table.
+    pub namespace: NamespaceIdent,
+    /// The name of this table.
+    pub name: String,
+}
+
+impl TableIdent {
+    /// Create a new table identifier.
+    pub fn new(namespace: NamespaceIdent, name: impl Into<String>) -> Self {
+        Self {
+            namespace,
+            name: name.into(),
+        }
+    }
+}
+
+impl FromStr for TableIdent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut levels = split_dotted(s)?;
+        let name = levels.pop().expect("split levels must not be empty");
+
+        Ok(Self {
+            namespace: NamespaceIdent(levels),
+            name,
+        })
+    }
+}
+
+impl Display for TableIdent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if !self.namespace.is_empty() {
+            write!(f, "{}.", self.namespace)?;
+        }
+        write!(f, "{}", join_dotted(std::slice::from_ref(&self.name)))
+    }
+}
+
+/// Split dotted string into levels with escaping rules applied.
+fn split_dotted(s: &str) -> Result<Vec<String>> {
+    let mut levels = vec![];
+    let mut level = String::new();
+
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ ('.' | '\\')) => level.push(c),
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidArgument,
+                        "identifier contains invalid escape sequence",
+                    )
+                    .with_context("identifier", s))
+                }
+            },
+            '.' => levels.push(std::mem::take(&mut level)),
+            c => level.push(c),
+        }
+    }
+    levels.push(level);
+
+    if levels.iter().any(|v| v.is_empty()) {
+        return Err(Error::new(
+            ErrorKind::InvalidArgument,
+            "identifier contains empty level",
+        )
+        .with_context("identifier", s));
+    }
+
+    Ok(levels)
+}
+
+/// Join levels into dotted string with escaping rules applied.
+fn join_dotted(levels: &[String]) -> String {
+    levels
+        .iter()
+        .map(|v| v.replace('\\', "\\\\").replace('.', "\\."))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_namespace_ident() {
+        let cases = vec![
+            ("", vec![]),
+            ("a", vec!["a"]),
+            ("a.b.c", vec!["a", "b", "c"]),
+            (r"a\.b.c", vec!["a.b", "c"]),
+            (r"a\\.b", vec![r"a\", "b"]),
+        ];
+
+        for (input, expected) in cases {
+            let actual: NamespaceIdent = input.parse().unwrap();
+            assert_eq!(
+                actual.levels(),
+                expected,
+                "namespace is not match for {input}"
+            );
+            assert_eq!(
+                actual.to_string(),
+                input,
+                "display is not match for {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_namespace_ident_invalid() {
+        for input in ["a..b", ".a", "a.", r"a\b"] {
+            let err = input.parse::<NamespaceIdent>().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidArgument, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_table_ident() {
+        let ident: TableIdent = "a.b.tbl".parse().unwrap();
+        assert_eq!(ident.namespace.levels(), ["a", "b"]);
+        assert_eq!(ident.name, "tbl");
+        assert_eq!(ident.to_string(), "a.b.tbl");
+
+        let ident: TableIdent = r"a.t\.bl".parse().unwrap();
+        assert_eq!(ident.namespace.levels(), ["a"]);
+        assert_eq!(ident.name, "t.bl");
+        assert_eq!(ident.to_string(), r"a.t\.bl");
+
+        let ident: TableIdent = "tbl".parse().unwrap();
+        assert!(ident.namespace.is_empty());
+        assert_eq!(ident.name, "tbl");
+        assert_eq!(ident.to_string(), "tbl");
+
+        assert!("".parse::<TableIdent>().is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! catalog module provides the catalog trait to manage iceberg tables and
+//! the implementations of it.
+
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::types;
+use crate::Error;
+use crate::ErrorKind;
+use crate::Table;
+
+mod ident;
+pub use ident::NamespaceIdent;
+pub use ident::TableIdent;
+
+mod hadoop;
+pub use hadoop::HadoopCatalog;
+
+mod rest;
+pub use rest::RestCatalog;
+
+/// Catalog is the trait to manage iceberg tables by their identifiers.
+#[async_trait]
+pub trait Catalog: Send + Sync {
+    /// List all tables under the given namespace.
+    async fn list_tables(&self, namespace: &NamespaceIdent) -> Result<Vec<TableIdent>>;
+
+    /// Load the table with the given identifier.
+    async fn load_table(&self, table: &TableIdent) -> Result<Table>;
+
+    /// Create a new table with the given identifier, schema, partition spec
+    /// and properties, then register it in the catalog.
+    ///
+    /// Returns error if the table already exists.
+    async fn create_table(
+        &self,
+        table: &TableIdent,
+        schema: types::Schema,
+        partition_spec: types::PartitionSpec,
+        properties: HashMap<String, String>,
+    ) -> Result<Table>;
+
+    /// Drop the table with the given identifier from the catalog.
+    ///
+    /// If `purge` is true, all files reachable from the table metadata
+    /// will be deleted too, see [`Table::reachable_files`] for details.
+    async fn drop_table(&self, table: &TableIdent, purge: bool) -> Result<()>;
+
+    /// Rename the table from `from` to `to`.
+    ///
+    /// For catalogs that store a pointer to the table metadata, rename only
+    /// updates the pointer and never moves any files. Catalogs that locate
+    /// tables by their paths should return an `IcebergFeatureUnsupported`
+    /// error instead, since table metadata records absolute paths.
+    ///
+    /// Semantics of the catalogs in this crate:
+    ///
+    /// - [`RestCatalog`] renames the pointer by `POST /v1/tables/rename`,
+    ///   the destination namespace must exist.
+    /// - [`HadoopCatalog`] returns `IcebergFeatureUnsupported`, since the
+    ///   table location is derived from its identifier.
+    /// - Hive catalogs are not implemented, `hive://` uris are rejected by
+    ///   [`Table::open_catalog`] with `IcebergFeatureUnsupported`
+    ///   before any table can be renamed.
+    async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<()>;
+}
+
+/// Build the catalog and parse the table identifier from a catalog uri
+/// like `<catalog>[+<storage>]://<location>#<namespace>.<table>`.
+///
+/// The catalog implementation is selected by the scheme, and the table
+/// identifier is parsed from the fragment in dotted string. For example:
+///
+/// - `hadoop:///path/to/warehouse#db.tbl`
+/// - `hadoop+s3://bucket/warehouse#db.tbl`
+/// - `rest://host/v1#db.tbl`, which is served over https
+/// - `rest+http://localhost:8181#db.tbl`
+///
+/// The trailing `v1` of rest catalog uri is optional. `hive` catalogs are
+/// recognized but not supported yet.
+pub(crate) fn parse_catalog_uri(uri: &str) -> Result<(Box<dyn Catalog>, TableIdent)> {
+    let (location, ident) = uri
+        .rsplit_once('#')
+        .ok_or_else(|| anyhow!("table identifier is required in fragment of uri {}", uri))?;
+    if ident.is_empty() {
+        return Err(anyhow!(
+            "table identifier is required in fragment of uri {}",
+            uri
+        ));
+    }
+    let ident: TableIdent = ident.parse()?;
+
+    let (scheme, rest) = location
+        .split_once("://")
+        .ok_or_else(|| anyhow!("catalog type is required in scheme of uri {}", uri))?;
+    let (catalog, storage) = match scheme.split_once('+') {
+        Some((catalog, storage)) => (catalog, Some(storage)),
+        None => (scheme, None),
+    };
+
+    let catalog: Box<dyn Catalog> = match catalog {
+        "hadoop" => {
+            let warehouse = match storage {
+                Some(storage) => format!("{storage}://{rest}"),
+                None => rest.to_string(),
+            };
+            Box::new(HadoopCatalog::new(&warehouse)?)
+        }
+        "rest" => {
+            let transport = match storage {
+                None | Some("https") => "https",
+                Some("http") => "http",
+                Some(v) => {
+                    return Err(anyhow!(
+                        "rest catalog can't be served over {} in uri {}",
+                        v,
+                        uri
+                    ))
+                }
+            };
+            // Endpoints under `v1/` are built by the catalog.
+            let rest = rest.trim_end_matches('/');
+            let rest = rest.strip_suffix("/v1").unwrap_or(rest);
+            Box::new(RestCatalog::new(&format!("{transport}://{rest}"))?)
+        }
+        "hive" => {
+            return Err(Error::new(
+                ErrorKind::IcebergFeatureUnsupported,
+                "hive catalog is not supported yet",
+            )
+            .with_context("uri", uri)
+            .into())
+        }
+        v => {
+            return Err(Error::new(
+                ErrorKind::IcebergFeatureUnsupported,
+                format!("catalog {v:?} is not supported yet"),
+            )
+            .with_context("uri", uri)
+            .into())
+        }
+    };
+
+    Ok((catalog, ident))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_parse_catalog_uri() -> Result<()> {
+        let warehouse = format!(
+            "{}/testdata",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        for uri in [
+            format!("hadoop://{warehouse}#simple_table"),
+            format!("hadoop+file://{warehouse}#simple_table"),
+        ] {
+            let (catalog, ident) = parse_catalog_uri(&uri)?;
+            assert_eq!(ident, "simple_table".parse()?);
+            catalog.load_table(&ident).await?;
+        }
+
+        let (_, ident) = parse_catalog_uri("hadoop+https://cdn.example.com/warehouse#a.b.tbl")?;
+        assert_eq!(ident, "a.b.tbl".parse()?);
+
+        for uri in [
+            "rest://host/v1#db.tbl",
+            "rest+https://host/prefix/v1/#db.tbl",
+            "rest+http://127.0.0.1:8181#db.tbl",
+        ] {
+            let (_, ident) = parse_catalog_uri(uri)?;
+            assert_eq!(ident, "db.tbl".parse()?);
+        }
+
+        for uri in [
+            "rest+ftp://host/v1#db.tbl",
+            "hadoop:///warehouse",
+            "hadoop:///warehouse#",
+            "/warehouse#db.tbl",
+        ] {
+            assert!(parse_catalog_uri(uri).is_err(), "{uri} must be invalid");
+        }
+
+        // Hive rejects all operations explicitly, including rename.
+        let err = parse_catalog_uri("hive://thrift-host:9083#db.tbl")
+            .err()
+            .expect("hive catalog must be unsupported");
+        assert_eq!(
+            err.downcast_ref::<Error>().map(|v| v.kind()),
+            Some(ErrorKind::IcebergFeatureUnsupported)
+        );
+
+        Ok(())
+    }
+}
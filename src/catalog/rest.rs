@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Method;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::Catalog;
+use super::NamespaceIdent;
+use super::TableIdent;
+use crate::io;
+use crate::types;
+use crate::Table;
+
+/// RestCatalog is the catalog served by the iceberg REST catalog API.
+///
+/// Versioned catalogs like Nessie keep tables on branches, set the branch,
+/// tag or commit hash to work on by `with_ref`, which is passed as the
+/// `ref` query parameter of all requests.
+pub struct RestCatalog {
+    uri: String,
+    reference: Option<String>,
+    client: reqwest::Client,
+}
+
+impl RestCatalog {
+    /// Create a new rest catalog with the base uri like
+    /// `http://localhost:8181`, under which `v1/` endpoints are served.
+    pub fn new(uri: &str) -> Result<Self> {
+        let url = Url::parse(uri).map_err(|err| anyhow!("parse uri {} failed: {}", uri, err))?;
+        if url.cannot_be_a_base() {
+            return Err(anyhow!("uri {} can't be the base of endpoints", uri));
+        }
+
+        Ok(Self {
+            uri: uri.to_string(),
+            reference: None,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Set the catalog reference to read tables at, like a branch of
+    /// Nessie. The default branch of catalog is used if not set.
+    pub fn with_ref(mut self, reference: &str) -> Self {
+        self.reference = Some(reference.to_string());
+        self
+    }
+
+    /// Build the url of endpoint under `v1/` with the given path segments.
+    fn url(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = Url::parse(&self.uri)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("uri {} can't be the base of endpoints", self.uri))?
+            .pop_if_empty()
+            .push("v1")
+            .extend(segments);
+        if let Some(reference) = &self.reference {
+            url.query_pairs_mut().append_pair("ref", reference);
+        }
+
+        Ok(url)
+    }
+
+    /// Send a GET request and returns the response body, non-2xx responses
+    /// are returned as errors with the body as message.
+    async fn get(&self, url: Url) -> Result<Vec<u8>> {
+        self.request(Method::GET, url, None).await
+    }
+
+    /// Send a request with the optional json body and returns the response
+    /// body, non-2xx responses are returned as errors with the body as
+    /// message.
+    async fn request(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<serde_json::Value>,
+    ) -> Result<Vec<u8>> {
+        let mut req = self.client.request(method, url.clone());
+        if let Some(body) = &body {
+            req = req
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(serde_json::to_vec(body)?);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|err| anyhow!("request {} failed: {}", url, err))?;
+        let status = resp.status();
+        let body = resp
+            .bytes()
+            .await
+            .map_err(|err| anyhow!("read response of {} failed: {}", url, err))?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "request {} failed with status {}: {}",
+                url,
+                status,
+                String::from_utf8_lossy(&body)
+            ));
+        }
+
+        Ok(body.to_vec())
+    }
+
+    /// Build the url of the given table.
+    fn table_url(&self, table: &TableIdent) -> Result<Url> {
+        self.url(&[
+            "namespaces",
+            &namespace_segment(&table.namespace),
+            "tables",
+            &table.name,
+        ])
+    }
+
+    /// Open the table loaded or created by the catalog.
+    ///
+    /// The table is opened by `metadata-location` of the response, or by
+    /// the metadata in the response if the location is absent.
+    async fn open_table(table: &TableIdent, resp: &[u8]) -> Result<Table> {
+        let resp: LoadTableResponse = serde_json::from_slice(resp)?;
+
+        if let Some(location) = &resp.metadata_location {
+            return Table::open_with_metadata_location(location).await;
+        }
+        let location = resp
+            .metadata
+            .get("location")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("location of table {} is not found", table))?;
+        let op = io::build_operator(location)?;
+        Table::from_metadata_bytes(op, &serde_json::to_vec(&resp.metadata)?)
+    }
+}
+
+/// Encode the namespace in a path segment, whose levels are separated by
+/// the unit separator `0x1F`.
+fn namespace_segment(namespace: &NamespaceIdent) -> String {
+    namespace.levels().join("\u{1f}")
+}
+
+#[derive(Deserialize)]
+struct ListTablesResponse {
+    identifiers: Vec<TableIdentifier>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct TableIdentifier {
+    namespace: Vec<String>,
+    name: String,
+}
+
+impl From<&TableIdent> for TableIdentifier {
+    fn from(v: &TableIdent) -> Self {
+        Self {
+            namespace: v.namespace.levels().to_vec(),
+            name: v.name.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LoadTableResponse {
+    #[serde(rename = "metadata-location")]
+    metadata_location: Option<String>,
+    #[serde(default)]
+    metadata: serde_json::Value,
+}
+
+#[async_trait]
+impl Catalog for RestCatalog {
+    async fn list_tables(&self, namespace: &NamespaceIdent) -> Result<Vec<TableIdent>> {
+        let url = self.url(&["namespaces", &namespace_segment(namespace), "tables"])?;
+        let resp: ListTablesResponse = serde_json::from_slice(&self.get(url).await?)?;
+
+        let mut tables: Vec<_> = resp
+            .identifiers
+            .into_iter()
+            .map(|v| TableIdent::new(NamespaceIdent::new(v.namespace), v.name))
+            .collect();
+        tables.sort();
+        Ok(tables)
+    }
+
+    /// Load the table at the catalog reference.
+    async fn load_table(&self, table: &TableIdent) -> Result<Table> {
+        let url = self.table_url(table)?;
+        Self::open_table(table, &self.get(url).await?).await
+    }
+
+    /// Create the table by the catalog, which decides the table location
+    /// and writes the initial metadata.
+    async fn create_table(
+        &self,
+        table: &TableIdent,
+        schema: types::Schema,
+        partition_spec: types::PartitionSpec,
+        properties: HashMap<String, String>,
+    ) -> Result<Table> {
+        let url = self.url(&["namespaces", &namespace_segment(&table.namespace), "tables"])?;
+        let body = serde_json::json!({
+            "name": table.name,
+            "schema": serde_json::from_slice::<serde_json::Value>(
+                &types::serialize_schema(&schema)?
+            )?,
+            "partition-spec": serde_json::from_slice::<serde_json::Value>(
+                &types::serialize_partition_spec(&partition_spec)?
+            )?,
+            "properties": properties,
+        });
+        let resp = self.request(Method::POST, url, Some(body)).await?;
+        Self::open_table(table, &resp).await
+    }
+
+    /// Drop the table from the catalog, the catalog deletes its files if
+    /// `purge` is true.
+    async fn drop_table(&self, table: &TableIdent, purge: bool) -> Result<()> {
+        let mut url = self.table_url(table)?;
+        url.query_pairs_mut()
+            .append_pair("purgeRequested", &purge.to_string());
+        self.request(Method::DELETE, url, None).await?;
+        Ok(())
+    }
+
+    /// Rename the table in the catalog, no files are moved.
+    async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<()> {
+        let url = self.url(&["tables", "rename"])?;
+        let body = serde_json::json!({
+            "source": TableIdentifier::from(from),
+            "destination": TableIdentifier::from(to),
+        });
+        self.request(Method::POST, url, Some(body)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use super::*;
+
+    /// Serve one request with the given body at the returned uri, the
+    /// handle resolves to the request line and the request body.
+    async fn serve_once(body: String) -> Result<(String, JoinHandle<Result<(String, String)>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let uri = format!("http://{}", listener.local_addr()?);
+
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+            let header_len = loop {
+                if let Some(pos) = request.windows(4).position(|v| v == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+                let n = stream.read(&mut buf).await?;
+                if n == 0 {
+                    break request.len();
+                }
+                request.extend_from_slice(&buf[..n]);
+            };
+            let headers = String::from_utf8_lossy(&request[..header_len]).to_lowercase();
+            let content_length = headers
+                .lines()
+                .find_map(|v| v.strip_prefix("content-length:"))
+                .map(|v| v.trim().parse::<usize>())
+                .transpose()?
+                .unwrap_or_default();
+            while request.len() < header_len + content_length {
+                let n = stream.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .as_bytes(),
+                )
+                .await?;
+
+            let line = String::from_utf8_lossy(&request[..header_len])
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            Ok((line, String::from_utf8(request[header_len..].to_vec())?))
+        });
+
+        Ok((uri, handle))
+    }
+
+    #[tokio::test]
+    async fn test_rest_catalog_load_table_at_ref() -> Result<()> {
+        let location = format!(
+            "file://{}/testdata/simple_table/metadata/v1.metadata.json",
+            env::current_dir()?.to_string_lossy()
+        );
+        let body = serde_json::json!({
+            "metadata-location": location,
+            "metadata": {},
+        });
+        let (uri, handle) = serve_once(body.to_string()).await?;
+
+        let catalog = RestCatalog::new(&uri)?.with_ref("dev");
+        let table = catalog.load_table(&"a.b.simple_table".parse()?).await?;
+        assert_eq!(table.current_metadata_version(), Some(1));
+        assert_eq!(
+            handle.await??.0,
+            "GET /v1/namespaces/a%1Fb/tables/simple_table?ref=dev HTTP/1.1"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_catalog_list_tables() -> Result<()> {
+        let body = serde_json::json!({
+            "identifiers": [
+                {"namespace": ["db"], "name": "t2"},
+                {"namespace": ["db"], "name": "t1"},
+            ],
+        });
+        let (uri, handle) = serve_once(body.to_string()).await?;
+
+        let catalog = RestCatalog::new(&format!("{uri}/"))?;
+        let tables = catalog.list_tables(&"db".parse()?).await?;
+        assert_eq!(tables, vec!["db.t1".parse()?, "db.t2".parse()?]);
+        assert_eq!(handle.await??.0, "GET /v1/namespaces/db/tables HTTP/1.1");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_catalog_create_table() -> Result<()> {
+        let location = format!(
+            "file://{}/testdata/simple_table/metadata/v1.metadata.json",
+            env::current_dir()?.to_string_lossy()
+        );
+        let body = serde_json::json!({ "metadata-location": location });
+        let (uri, handle) = serve_once(body.to_string()).await?;
+
+        let catalog = RestCatalog::new(&uri)?;
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![types::Field {
+                id: 1,
+                name: "id".to_string(),
+                required: true,
+                field_type: types::Any::Primitive(types::Primitive::Long),
+                comment: None,
+            }],
+        };
+        let partition_spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+        let table = catalog
+            .create_table(
+                &"db.t".parse()?,
+                schema,
+                partition_spec,
+                HashMap::from([("k".to_string(), "v".to_string())]),
+            )
+            .await?;
+        assert_eq!(table.current_metadata_version(), Some(1));
+
+        let (line, body) = handle.await??;
+        assert_eq!(line, "POST /v1/namespaces/db/tables HTTP/1.1");
+        let body: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(body["name"], "t");
+        assert_eq!(body["schema"]["fields"][0]["name"], "id");
+        assert_eq!(body["partition-spec"]["spec-id"], 0);
+        assert_eq!(body["properties"]["k"], "v");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_catalog_drop_table() -> Result<()> {
+        let (uri, handle) = serve_once(String::new()).await?;
+
+        let catalog = RestCatalog::new(&uri)?.with_ref("dev");
+        catalog.drop_table(&"db.t".parse()?, true).await?;
+        assert_eq!(
+            handle.await??.0,
+            "DELETE /v1/namespaces/db/tables/t?ref=dev&purgeRequested=true HTTP/1.1"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_catalog_rename_table() -> Result<()> {
+        let (uri, handle) = serve_once(String::new()).await?;
+
+        let catalog = RestCatalog::new(&uri)?;
+        catalog
+            .rename_table(&"a.b.t1".parse()?, &"db.t2".parse()?)
+            .await?;
+
+        let (line, body) = handle.await??;
+        assert_eq!(line, "POST /v1/tables/rename HTTP/1.1");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body)?,
+            serde_json::json!({
+                "source": {"namespace": ["a", "b"], "name": "t1"},
+                "destination": {"namespace": ["db"], "name": "t2"},
+            })
+        );
+
+        Ok(())
+    }
+}
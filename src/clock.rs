@@ -0,0 +1,28 @@
+//! clock module provides the source of timestamps written by commits.
+
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// Clock provides the current time for `last-updated-ms` of table
+/// metadata and `timestamp-ms` of snapshots.
+///
+/// Inject a fixed clock via `Table::with_clock` to make metadata written
+/// by tests deterministic.
+pub trait Clock: Send + Sync {
+    /// Milliseconds since the unix epoch.
+    fn now_ms(&self) -> i64;
+}
+
+/// SystemClock reads the system time, which is the default clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        // The system time is never earlier than the epoch in practice.
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|v| v.as_millis() as i64)
+            .unwrap_or_default()
+    }
+}
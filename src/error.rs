@@ -24,6 +24,16 @@ pub enum ErrorKind {
     ///
     /// This error is returned when given iceberg feature is not supported.
     IcebergFeatureUnsupported,
+    /// The argument passed by user is invalid.
+    ///
+    /// For example, the table identifier can't be parsed.
+    InvalidArgument,
+    /// Iceberg metadata is corrupted.
+    ///
+    /// This error is returned when a required field of metadata is missing
+    /// or mistyped. The offending field and the metadata path are carried
+    /// in the context.
+    Corrupt,
 }
 
 impl ErrorKind {
@@ -45,6 +55,8 @@ impl From<ErrorKind> for &'static str {
             ErrorKind::Unexpected => "Unexpected",
             ErrorKind::IcebergDataInvalid => "IcebergDataInvalid",
             ErrorKind::IcebergFeatureUnsupported => "IcebergFeatureUnsupported",
+            ErrorKind::InvalidArgument => "InvalidArgument",
+            ErrorKind::Corrupt => "Corrupt",
         }
     }
 }
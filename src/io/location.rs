@@ -0,0 +1,195 @@
+//! location module provides the helpers to build operators from iceberg
+//! locations.
+
+use opendal::layers::LoggingLayer;
+use opendal::services::Fs;
+use opendal::services::Http;
+use opendal::services::S3;
+use opendal::Operator;
+
+use super::OpenOptions;
+use crate::Error;
+use crate::ErrorKind;
+use crate::Result;
+
+/// Build an operator rooted at the given uri.
+///
+/// Supported uris are:
+///
+/// - `s3://bucket/path/to/table` and `s3a://bucket/path/to/table`
+/// - `file:///path/to/table`
+/// - `http://host/path/to/table` and `https://host/path/to/table`
+/// - `/path/to/table`
+///
+/// HTTP(S) locations are read-only and can't be listed, so tables served
+/// over HTTP(S) must have a version hint.
+pub(crate) fn build_operator(uri: &str) -> Result<Operator> {
+    build_operator_with_options(uri, &OpenOptions::default())
+}
+
+/// Build an operator rooted at the given uri with options.
+pub(crate) fn build_operator_with_options(uri: &str, options: &OpenOptions) -> Result<Operator> {
+    let op = match uri.split_once("://") {
+        Some(("s3" | "s3a", rest)) => {
+            let (bucket, root) = rest.split_once('/').unwrap_or((rest, ""));
+            if bucket.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    "bucket is required in s3 location",
+                )
+                .with_context("uri", uri));
+            }
+
+            let mut builder = S3::default();
+            builder.bucket(bucket);
+            builder.root(&format!("/{root}"));
+            if let Some(endpoint) = &options.endpoint {
+                builder.endpoint(endpoint);
+            }
+            if let Some(region) = &options.region {
+                builder.region(region);
+            }
+            if let Some(access_key_id) = &options.access_key_id {
+                builder.access_key_id(access_key_id);
+            }
+            if let Some(secret_access_key) = &options.secret_access_key {
+                builder.secret_access_key(secret_access_key);
+            }
+            if options.enable_virtual_host_style {
+                builder.enable_virtual_host_style();
+            }
+
+            Operator::new(builder)?
+                .layer(LoggingLayer::default())
+                .finish()
+        }
+        Some((scheme @ ("http" | "https"), rest)) => {
+            let (host, root) = rest.split_once('/').unwrap_or((rest, ""));
+            if host.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    "host is required in http location",
+                )
+                .with_context("uri", uri));
+            }
+
+            let mut builder = Http::default();
+            builder.endpoint(&format!("{scheme}://{host}"));
+            builder.root(&format!("/{root}"));
+
+            Operator::new(builder)?
+                .layer(LoggingLayer::default())
+                .finish()
+        }
+        Some(("file", path)) => {
+            let mut builder = Fs::default();
+            builder.root(path);
+
+            Operator::new(builder)?
+                .layer(LoggingLayer::default())
+                .finish()
+        }
+        Some((scheme, _)) => {
+            return Err(Error::new(
+                ErrorKind::IcebergFeatureUnsupported,
+                format!("location scheme {scheme:?} is not supported"),
+            )
+            .with_context("uri", uri))
+        }
+        None => {
+            let mut builder = Fs::default();
+            builder.root(uri);
+
+            Operator::new(builder)?
+                .layer(LoggingLayer::default())
+                .finish()
+        }
+    };
+
+    Ok(op)
+}
+
+/// Split an absolute metadata location into the table root and the path
+/// of metadata file relative to it.
+///
+/// For example, `s3://bucket/db/tbl/metadata/v5.metadata.json` will be
+/// split into `s3://bucket/db/tbl` and `metadata/v5.metadata.json`.
+///
+/// If the metadata file is not stored under a `metadata` directory, the
+/// parent directory of the file will be used as the table root.
+pub(crate) fn split_metadata_location(location: &str) -> Result<(&str, &str)> {
+    if let Some(idx) = location.rfind("/metadata/") {
+        return Ok((&location[..idx], &location[idx + 1..]));
+    }
+
+    match location.rsplit_once('/') {
+        Some((root, file)) if !file.is_empty() => Ok((root, file)),
+        _ => Err(Error::new(
+            ErrorKind::IcebergDataInvalid,
+            "metadata location is not a valid file path",
+        )
+        .with_context("location", location)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_metadata_location() {
+        let cases = vec![
+            (
+                "s3://bucket/db/tbl/metadata/v5.metadata.json",
+                ("s3://bucket/db/tbl", "metadata/v5.metadata.json"),
+            ),
+            (
+                "file:///tmp/warehouse/tbl/metadata/00001-abc.metadata.json",
+                (
+                    "file:///tmp/warehouse/tbl",
+                    "metadata/00001-abc.metadata.json",
+                ),
+            ),
+            (
+                "/tmp/tbl/v1.metadata.json",
+                ("/tmp/tbl", "v1.metadata.json"),
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let actual = split_metadata_location(input).unwrap();
+            assert_eq!(actual, expected, "split is not match for {input}");
+        }
+
+        assert!(split_metadata_location("s3://bucket/db/tbl/").is_err());
+    }
+
+    #[test]
+    fn test_build_operator_with_options() {
+        let options = OpenOptions::new()
+            .endpoint("http://127.0.0.1:9000")
+            .region("us-east-1")
+            .access_key_id("minioadmin")
+            .secret_access_key("minioadmin");
+
+        let op = build_operator_with_options("s3://bucket/db/tbl", &options).unwrap();
+        assert_eq!(op.info().name(), "bucket");
+        assert_eq!(op.info().root(), "/db/tbl/");
+    }
+
+    #[test]
+    fn test_build_operator_http() {
+        let op = build_operator("https://cdn.example.com/db/tbl").unwrap();
+        assert_eq!(op.info().scheme(), opendal::Scheme::Http);
+        assert_eq!(op.info().root(), "/db/tbl/");
+        assert!(!op.info().capability().list);
+
+        assert!(build_operator("https:///db/tbl").is_err());
+    }
+
+    #[test]
+    fn test_build_operator_unsupported_scheme() {
+        let err = build_operator("ftp://host/tbl").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::IcebergFeatureUnsupported);
+    }
+}
@@ -0,0 +1,81 @@
+//! metadata_io module provides the storage of table metadata files.
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use opendal::Operator;
+
+use crate::Error;
+use crate::ErrorKind;
+use crate::Result;
+
+/// MetadataIo is the storage of table metadata files and version hint,
+/// all paths are relative to the table root.
+///
+/// `Operator` implements it by default. Inject a custom one via
+/// `Table::with_metadata_io` to keep metadata in storage which has no
+/// opendal service, like a content-addressable store.
+#[async_trait]
+pub trait MetadataIo: Send + Sync {
+    /// Read the whole content of file.
+    async fn read(&self, path: &str) -> Result<Vec<u8>>;
+
+    /// List paths of files directly under the directory `prefix` which
+    /// ends with `/`, directories are not returned.
+    async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+
+    /// Check whether the file exists.
+    async fn is_exist(&self, path: &str) -> Result<bool>;
+
+    /// Write the whole content of file, replacing the existing one.
+    async fn write(&self, path: &str, bs: Vec<u8>) -> Result<()>;
+
+    /// Delete the file, deleting a missing file succeeds.
+    async fn delete(&self, path: &str) -> Result<()>;
+
+    /// Whether `list` is supported, tables on storage which can't list
+    /// require a version hint.
+    fn can_list(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl MetadataIo for Operator {
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(Operator::read(self, path).await?)
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut lister = Operator::list(self, prefix).await.map_err(|err| {
+            Error::new(ErrorKind::Unexpected, "list metadata failed")
+                .with_context("prefix", prefix)
+                .set_source(err)
+        })?;
+
+        let mut paths = vec![];
+        while let Some(entry) = lister.next().await {
+            let entry = entry?;
+            if !entry.path().ends_with('/') {
+                paths.push(entry.path().to_string());
+            }
+        }
+
+        Ok(paths)
+    }
+
+    async fn is_exist(&self, path: &str) -> Result<bool> {
+        Ok(Operator::is_exist(self, path).await?)
+    }
+
+    async fn write(&self, path: &str, bs: Vec<u8>) -> Result<()> {
+        Ok(Operator::write(self, path, bs).await?)
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        Ok(Operator::delete(self, path).await?)
+    }
+
+    fn can_list(&self) -> bool {
+        self.info().capability().list
+    }
+}
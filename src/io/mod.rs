@@ -1,2 +1,19 @@
 #[cfg(feature = "io_parquet")]
 pub mod parquet;
+
+mod location;
+mod metadata_io;
+pub(crate) use location::build_operator;
+pub(crate) use location::build_operator_with_options;
+pub use metadata_io::MetadataIo;
+mod options;
+mod prefix;
+pub(crate) use location::split_metadata_location;
+pub use options::LoadPolicy;
+pub use options::OpenOptions;
+pub(crate) use prefix::PrefixLayer;
+
+#[cfg(feature = "zip")]
+mod zip;
+#[cfg(feature = "zip")]
+pub(crate) use self::zip::build_zip_operator;
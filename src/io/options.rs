@@ -0,0 +1,113 @@
+/// LoadPolicy decides how tables are loaded when metadata is missing or
+/// inconsistent, which covers:
+///
+/// - The version hint is missing, points to a missing metadata file, or
+///   is older than the latest metadata file listed.
+/// - Several metadata files have the same max version, for example, in
+///   different metadata directories.
+/// - A data manifest referenced by the manifest list is missing. Missing
+///   delete manifests always fail, since skipping them would bring
+///   deleted rows back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadPolicy {
+    /// Fail on any of the cases.
+    Strict,
+    /// Fallback to the latest metadata file listed, pick the first one of
+    /// ambiguous versions and skip missing manifests, with warnings.
+    Lenient,
+    /// Like `Lenient`, and additionally rewrite the version hint to the
+    /// latest metadata file found.
+    RepairHint,
+}
+
+/// OpenOptions configures the storage services used to open tables.
+///
+/// Options only take effect on the matching storage, for example,
+/// `endpoint` is only used by s3 locations. Unset options are loaded
+/// from the environment by the storage service.
+#[derive(Clone, Debug, Default)]
+pub struct OpenOptions {
+    pub(crate) endpoint: Option<String>,
+    pub(crate) region: Option<String>,
+    pub(crate) access_key_id: Option<String>,
+    pub(crate) secret_access_key: Option<String>,
+    pub(crate) enable_virtual_host_style: bool,
+    pub(crate) version_hint_file: Option<String>,
+    pub(crate) metadata_file_prefix: Option<String>,
+    pub(crate) keep_metadata_json: bool,
+    pub(crate) load_policy: Option<LoadPolicy>,
+}
+
+impl OpenOptions {
+    /// Initiate new options with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the endpoint of s3 service, like `http://127.0.0.1:9000` for
+    /// a local MinIO.
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Set the region of s3 service.
+    pub fn region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_string());
+        self
+    }
+
+    /// Set the access key id of s3 service.
+    pub fn access_key_id(mut self, access_key_id: &str) -> Self {
+        self.access_key_id = Some(access_key_id.to_string());
+        self
+    }
+
+    /// Set the secret access key of s3 service.
+    pub fn secret_access_key(mut self, secret_access_key: &str) -> Self {
+        self.secret_access_key = Some(secret_access_key.to_string());
+        self
+    }
+
+    /// Use virtual host style addressing like `https://bucket.endpoint`
+    /// for s3 service.
+    ///
+    /// Path style addressing like `https://endpoint/bucket` is used by
+    /// default, which is required by MinIO and LocalStack.
+    pub fn enable_virtual_host_style(mut self, enable: bool) -> Self {
+        self.enable_virtual_host_style = enable;
+        self
+    }
+
+    /// Set the name of version hint file in metadata directory,
+    /// `version-hint.text` by default.
+    ///
+    /// The content of the file can be either a version number or a path
+    /// of metadata file.
+    pub fn version_hint_file(mut self, name: &str) -> Self {
+        self.version_hint_file = Some(name.to_string());
+        self
+    }
+
+    /// Only consider metadata files whose names start with the prefix
+    /// when the metadata directory is listed, which is useful if the
+    /// directory is shared with other tools.
+    pub fn metadata_file_prefix(mut self, prefix: &str) -> Self {
+        self.metadata_file_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Set the policy of loading tables with missing or inconsistent
+    /// metadata, see `Table::with_load_policy`.
+    pub fn load_policy(mut self, policy: LoadPolicy) -> Self {
+        self.load_policy = Some(policy);
+        self
+    }
+
+    /// Keep the raw json of loaded metadata file, see
+    /// `Table::current_metadata_json`.
+    pub fn keep_metadata_json(mut self, keep: bool) -> Self {
+        self.keep_metadata_json = keep;
+        self
+    }
+}
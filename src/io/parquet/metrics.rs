@@ -0,0 +1,864 @@
+use std::collections::HashMap;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::Date32Type;
+use arrow_array::types::Decimal128Type;
+use arrow_array::types::Float32Type;
+use arrow_array::types::Float64Type;
+use arrow_array::types::Int32Type;
+use arrow_array::types::Int64Type;
+use arrow_array::types::Time64MicrosecondType;
+use arrow_array::types::TimestampMicrosecondType;
+use arrow_array::Array;
+use arrow_array::FixedSizeBinaryArray;
+use arrow_array::RecordBatch;
+use arrow_schema::DataType;
+use arrow_schema::Schema as ArrowSchema;
+use arrow_schema::TimeUnit;
+use parquet::basic::ConvertedType;
+use parquet::basic::LogicalType;
+use parquet::basic::TimeUnit as ParquetTimeUnit;
+use parquet::file::metadata::ParquetMetaData;
+use parquet::file::statistics::Statistics;
+use parquet::format::FileMetaData;
+use parquet::schema::types::ColumnDescriptor;
+
+use crate::types;
+
+/// Bounds of strings and binaries are truncated to this length, which is
+/// the default metrics mode `truncate(16)` of iceberg.
+const TRUNCATE_LENGTH: usize = 16;
+
+/// DataFileMetrics is the metrics of columns collected while writing a
+/// data file, which are used to fill the `DataFile` entry of it.
+///
+/// Metrics are keyed by field id, and bounds are encoded by binary
+/// single-value serialization.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataFileMetrics {
+    /// Number of records in the file.
+    pub record_count: i64,
+    /// Number of values in each column, including nulls.
+    pub value_counts: HashMap<i32, i64>,
+    /// Number of null values in each column.
+    pub null_value_counts: HashMap<i32, i64>,
+    /// Number of NaN values in each float and double column.
+    pub nan_value_counts: HashMap<i32, i64>,
+    /// Size in bytes of each column on disk, which are only available
+    /// after the file is written.
+    pub column_sizes: HashMap<i32, i64>,
+    /// Lower bounds of non-null values in each column.
+    ///
+    /// Strings and binaries are truncated, which are still lower bounds.
+    pub lower_bounds: HashMap<i32, Vec<u8>>,
+    /// Upper bounds of non-null values in each column.
+    ///
+    /// Strings and binaries are truncated and then rounded up, the bound
+    /// is absent if it can't be rounded up.
+    pub upper_bounds: HashMap<i32, Vec<u8>>,
+}
+
+/// Metrics of a tracked column.
+struct ColumnMetrics {
+    field_id: i32,
+    name: String,
+    typ: types::Primitive,
+
+    value_count: i64,
+    null_count: i64,
+    nan_count: i64,
+    column_size: Option<i64>,
+    lower: Option<types::Datum>,
+    upper: Option<types::Datum>,
+}
+
+/// MetricsCollector collects metrics from record batches to write.
+///
+/// Only top level primitive columns are tracked, which are matched with
+/// fields of the table schema by name. Floating NaNs are not counted in
+/// bounds.
+pub(crate) struct MetricsCollector {
+    record_count: i64,
+    columns: Vec<Option<ColumnMetrics>>,
+}
+
+impl MetricsCollector {
+    /// Create a new collector for record batches of `arrow_schema`.
+    ///
+    /// No column is tracked without table schema.
+    pub fn new(arrow_schema: &ArrowSchema, schema: Option<&types::Schema>) -> Self {
+        let columns = arrow_schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let field = schema?.fields.iter().find(|v| &v.name == field.name())?;
+                let types::Any::Primitive(typ) = &field.field_type else {
+                    return None;
+                };
+                Some(ColumnMetrics {
+                    field_id: field.id,
+                    name: field.name.clone(),
+                    typ: *typ,
+
+                    value_count: 0,
+                    null_count: 0,
+                    nan_count: 0,
+                    column_size: None,
+                    lower: None,
+                    upper: None,
+                })
+            })
+            .collect();
+
+        Self {
+            record_count: 0,
+            columns,
+        }
+    }
+
+    /// Update metrics with the record batch.
+    pub fn update(&mut self, batch: &RecordBatch) {
+        self.record_count += batch.num_rows() as i64;
+
+        for (column, array) in self.columns.iter_mut().zip(batch.columns()) {
+            let Some(column) = column else {
+                continue;
+            };
+
+            column.value_count += array.len() as i64;
+            column.null_count += array.null_count() as i64;
+            column.nan_count += array_nan_count(array.as_ref(), &column.typ);
+            if let Some((lower, upper)) = array_bounds(array.as_ref(), &column.typ) {
+                if column.lower.as_ref().is_none_or(|v| lower < *v) {
+                    column.lower = Some(lower);
+                }
+                if column.upper.as_ref().is_none_or(|v| upper > *v) {
+                    column.upper = Some(upper);
+                }
+            }
+        }
+    }
+
+    /// Update column sizes with the metadata of written parquet file.
+    ///
+    /// Sizes of all row groups are summed up, and only columns stored in
+    /// a single parquet column are tracked.
+    pub fn update_column_sizes(&mut self, file_metadata: &FileMetaData) {
+        for column in self.columns.iter_mut().flatten() {
+            let chunks = file_metadata
+                .row_groups
+                .iter()
+                .flat_map(|rg| &rg.columns)
+                .filter_map(|chunk| chunk.meta_data.as_ref())
+                .filter(|meta| meta.path_in_schema == [column.name.as_str()]);
+            column.column_size = Some(chunks.map(|meta| meta.total_compressed_size).sum());
+        }
+    }
+
+    /// Finish collecting and build the metrics.
+    pub fn finish(self) -> DataFileMetrics {
+        let mut metrics = DataFileMetrics {
+            record_count: self.record_count,
+            ..Default::default()
+        };
+
+        for column in self.columns.into_iter().flatten() {
+            metrics
+                .value_counts
+                .insert(column.field_id, column.value_count);
+            metrics
+                .null_value_counts
+                .insert(column.field_id, column.null_count);
+            if matches!(
+                column.typ,
+                types::Primitive::Float | types::Primitive::Double
+            ) {
+                metrics
+                    .nan_value_counts
+                    .insert(column.field_id, column.nan_count);
+            }
+            if let Some(v) = column.column_size {
+                metrics.column_sizes.insert(column.field_id, v);
+            }
+            if let Some(v) = column.lower {
+                metrics
+                    .lower_bounds
+                    .insert(column.field_id, truncate_lower_bound(v).to_bytes());
+            }
+            if let Some(v) = column.upper.and_then(truncate_upper_bound) {
+                metrics.upper_bounds.insert(column.field_id, v.to_bytes());
+            }
+        }
+
+        metrics
+    }
+}
+
+/// Count NaN values in float and double array.
+fn array_nan_count(array: &dyn Array, typ: &types::Primitive) -> i64 {
+    let count = match (array.data_type(), typ) {
+        (DataType::Float32, types::Primitive::Float) => array
+            .as_primitive::<Float32Type>()
+            .iter()
+            .flatten()
+            .filter(|v| v.is_nan())
+            .count(),
+        (DataType::Float64, types::Primitive::Double) => array
+            .as_primitive::<Float64Type>()
+            .iter()
+            .flatten()
+            .filter(|v| v.is_nan())
+            .count(),
+        _ => 0,
+    };
+
+    count as i64
+}
+
+/// Get the min and max of values.
+fn min_max<T: PartialOrd + Copy>(values: impl Iterator<Item = T>) -> Option<(T, T)> {
+    values.fold(None, |acc, v| match acc {
+        None => Some((v, v)),
+        Some((min, max)) => Some((if v < min { v } else { min }, if v > max { v } else { max })),
+    })
+}
+
+/// Get the bounds of non-null values in array as datums of `typ`.
+///
+/// `None` will be returned if the array is all null, or its data type
+/// doesn't match `typ`.
+fn array_bounds(array: &dyn Array, typ: &types::Primitive) -> Option<(types::Datum, types::Datum)> {
+    fn map<T>(
+        v: Option<(T, T)>,
+        f: impl Fn(T) -> types::Datum,
+    ) -> Option<(types::Datum, types::Datum)> {
+        v.map(|(min, max)| (f(min), f(max)))
+    }
+
+    match (array.data_type(), typ) {
+        (DataType::Boolean, types::Primitive::Boolean) => {
+            map(min_max(array.as_boolean().iter().flatten()), |v| {
+                types::Datum::Boolean(v)
+            })
+        }
+        (DataType::Int32, types::Primitive::Int) => map(
+            min_max(array.as_primitive::<Int32Type>().iter().flatten()),
+            types::Datum::Int,
+        ),
+        (DataType::Date32, types::Primitive::Date) => map(
+            min_max(array.as_primitive::<Date32Type>().iter().flatten()),
+            types::Datum::Date,
+        ),
+        (DataType::Int64, types::Primitive::Long) => map(
+            min_max(array.as_primitive::<Int64Type>().iter().flatten()),
+            types::Datum::Long,
+        ),
+        (DataType::Time64(TimeUnit::Microsecond), types::Primitive::Time) => map(
+            min_max(
+                array
+                    .as_primitive::<Time64MicrosecondType>()
+                    .iter()
+                    .flatten(),
+            ),
+            types::Datum::Time,
+        ),
+        (DataType::Timestamp(TimeUnit::Microsecond, _), types::Primitive::Timestamp) => map(
+            min_max(
+                array
+                    .as_primitive::<TimestampMicrosecondType>()
+                    .iter()
+                    .flatten(),
+            ),
+            types::Datum::Timestamp,
+        ),
+        (DataType::Timestamp(TimeUnit::Microsecond, _), types::Primitive::Timestampz) => map(
+            min_max(
+                array
+                    .as_primitive::<TimestampMicrosecondType>()
+                    .iter()
+                    .flatten(),
+            ),
+            types::Datum::Timestamptz,
+        ),
+        (DataType::Float32, types::Primitive::Float) => map(
+            min_max(
+                array
+                    .as_primitive::<Float32Type>()
+                    .iter()
+                    .flatten()
+                    .filter(|v| !v.is_nan()),
+            ),
+            |v| types::Datum::Float(v.into()),
+        ),
+        (DataType::Float64, types::Primitive::Double) => map(
+            min_max(
+                array
+                    .as_primitive::<Float64Type>()
+                    .iter()
+                    .flatten()
+                    .filter(|v| !v.is_nan()),
+            ),
+            |v| types::Datum::Double(v.into()),
+        ),
+        (DataType::Decimal128(_, _), types::Primitive::Decimal { .. }) => map(
+            min_max(array.as_primitive::<Decimal128Type>().iter().flatten()),
+            types::Datum::Decimal,
+        ),
+        (DataType::Utf8, types::Primitive::String) => {
+            map(min_max(array.as_string::<i32>().iter().flatten()), |v| {
+                types::Datum::String(v.to_string())
+            })
+        }
+        (DataType::LargeUtf8, types::Primitive::String) => {
+            map(min_max(array.as_string::<i64>().iter().flatten()), |v| {
+                types::Datum::String(v.to_string())
+            })
+        }
+        (DataType::Binary, types::Primitive::Binary) => {
+            map(min_max(array.as_binary::<i32>().iter().flatten()), |v| {
+                types::Datum::Binary(v.to_vec())
+            })
+        }
+        (DataType::LargeBinary, types::Primitive::Binary) => {
+            map(min_max(array.as_binary::<i64>().iter().flatten()), |v| {
+                types::Datum::Binary(v.to_vec())
+            })
+        }
+        (DataType::LargeBinary, types::Primitive::Fixed(_)) => {
+            map(min_max(array.as_binary::<i64>().iter().flatten()), |v| {
+                types::Datum::Fixed(v.to_vec())
+            })
+        }
+        (DataType::FixedSizeBinary(_), types::Primitive::Fixed(_)) => {
+            let array = array.as_any().downcast_ref::<FixedSizeBinaryArray>()?;
+            map(min_max(array.iter().flatten()), |v| {
+                types::Datum::Fixed(v.to_vec())
+            })
+        }
+        (DataType::FixedSizeBinary(16), types::Primitive::Uuid) => {
+            let array = array.as_any().downcast_ref::<FixedSizeBinaryArray>()?;
+            let (min, max) = min_max(array.iter().flatten())?;
+            Some((
+                types::Datum::Uuid(uuid::Uuid::from_slice(min).ok()?),
+                types::Datum::Uuid(uuid::Uuid::from_slice(max).ok()?),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Get the value at `row` of array as a datum of `typ`.
+///
+/// `None` will be returned if the value is null or NaN, or the data type
+/// of array doesn't match `typ`.
+pub(crate) fn array_value(
+    array: &dyn Array,
+    row: usize,
+    typ: &types::Primitive,
+) -> Option<types::Datum> {
+    array_bounds(array.slice(row, 1).as_ref(), typ).map(|(v, _)| v)
+}
+
+impl DataFileMetrics {
+    /// Build metrics from the footer of an existing parquet file.
+    ///
+    /// Only top level primitive columns are tracked, which are matched
+    /// with fields of the table schema by field id stored in the file, or
+    /// by name if absent. Metrics are aggregated from statistics of all
+    /// row groups, bounds are absent if any row group lacks them, and NaN
+    /// counts are never filled since parquet doesn't record them.
+    pub fn from_parquet_metadata(metadata: &ParquetMetaData, schema: &types::Schema) -> Self {
+        let mut metrics = DataFileMetrics {
+            record_count: metadata.file_metadata().num_rows(),
+            ..Default::default()
+        };
+
+        for (idx, field_id, typ) in parquet_columns(metadata, schema) {
+            let chunks = metadata.row_groups().iter().map(|rg| rg.column(idx));
+            metrics
+                .value_counts
+                .insert(field_id, chunks.clone().map(|v| v.num_values()).sum());
+            metrics
+                .column_sizes
+                .insert(field_id, chunks.clone().map(|v| v.compressed_size()).sum());
+            let null_count: Option<i64> = chunks
+                .map(|v| v.statistics().map(|s| s.null_count() as i64))
+                .sum();
+            if let Some(v) = null_count {
+                metrics.null_value_counts.insert(field_id, v);
+            }
+            if let Some((lower, upper)) = parquet_bounds(metadata, idx, &typ) {
+                metrics
+                    .lower_bounds
+                    .insert(field_id, truncate_lower_bound(lower).to_bytes());
+                if let Some(v) = truncate_upper_bound(upper) {
+                    metrics.upper_bounds.insert(field_id, v.to_bytes());
+                }
+            }
+        }
+
+        metrics
+    }
+}
+
+/// Get the bounds of non-null values of tracked columns from the footer
+/// of parquet file, keyed by field id.
+///
+/// Unlike bounds in `DataFileMetrics`, the bounds are not truncated.
+pub(crate) fn parquet_column_bounds(
+    metadata: &ParquetMetaData,
+    schema: &types::Schema,
+) -> HashMap<i32, (types::Datum, types::Datum)> {
+    parquet_columns(metadata, schema)
+        .into_iter()
+        .filter_map(|(idx, field_id, typ)| Some((field_id, parquet_bounds(metadata, idx, &typ)?)))
+        .collect()
+}
+
+/// Get top level primitive columns of parquet file which are matched with
+/// fields of the table schema, as tuples of column index, field id and
+/// field type.
+fn parquet_columns(
+    metadata: &ParquetMetaData,
+    schema: &types::Schema,
+) -> Vec<(usize, i32, types::Primitive)> {
+    let descr = metadata.file_metadata().schema_descr();
+    (0..descr.num_columns())
+        .filter_map(|idx| {
+            let column = descr.column(idx);
+            if column.path().parts().len() != 1 {
+                return None;
+            }
+            let info = column.self_type().get_basic_info();
+            let field = if info.has_id() {
+                schema.fields.iter().find(|v| v.id == info.id())
+            } else {
+                schema.fields.iter().find(|v| v.name == column.name())
+            }?;
+            let types::Any::Primitive(typ) = &field.field_type else {
+                return None;
+            };
+            Some((idx, field.id, *typ))
+        })
+        .collect()
+}
+
+/// Get the bounds of non-null values of column `idx` from statistics of
+/// all row groups.
+fn parquet_bounds(
+    metadata: &ParquetMetaData,
+    idx: usize,
+    typ: &types::Primitive,
+) -> Option<(types::Datum, types::Datum)> {
+    let column = metadata.file_metadata().schema_descr().column(idx);
+
+    let mut bounds: Option<(types::Datum, types::Datum)> = None;
+    for rg in metadata.row_groups() {
+        let chunk = rg.column(idx);
+        let stats = chunk.statistics()?;
+        if !stats.has_min_max_set() {
+            // Row groups of all nulls have no bounds.
+            if stats.null_count() as i64 == chunk.num_values() {
+                continue;
+            }
+            return None;
+        }
+        let (lower, upper) = statistics_bounds(stats, &column, typ)?;
+        bounds = Some(match bounds {
+            None => (lower, upper),
+            Some((min, max)) => (
+                if lower < min { lower } else { min },
+                if upper > max { upper } else { max },
+            ),
+        });
+    }
+
+    bounds
+}
+
+/// Convert min and max of parquet statistics into datums of `typ`.
+///
+/// `None` will be returned if the physical or logical type of column
+/// doesn't match `typ`, or the bounds can't be trusted, for example, the
+/// deprecated bounds of byte arrays which are compared as signed bytes.
+fn statistics_bounds(
+    stats: &Statistics,
+    column: &ColumnDescriptor,
+    typ: &types::Primitive,
+) -> Option<(types::Datum, types::Datum)> {
+    fn map<T>(
+        min: &T,
+        max: &T,
+        f: impl Fn(&T) -> Option<types::Datum>,
+    ) -> Option<(types::Datum, types::Datum)> {
+        Some((f(min)?, f(max)?))
+    }
+
+    let is_micros = match column.logical_type() {
+        Some(LogicalType::Time { unit, .. }) | Some(LogicalType::Timestamp { unit, .. }) => {
+            matches!(unit, ParquetTimeUnit::MICROS(_))
+        }
+        _ => matches!(
+            column.converted_type(),
+            ConvertedType::TIME_MICROS | ConvertedType::TIMESTAMP_MICROS
+        ),
+    };
+    if stats.is_min_max_deprecated()
+        && matches!(
+            stats,
+            Statistics::ByteArray(_) | Statistics::FixedLenByteArray(_)
+        )
+    {
+        return None;
+    }
+
+    match (stats, typ) {
+        (Statistics::Boolean(s), types::Primitive::Boolean) => {
+            map(s.min(), s.max(), |v| Some(types::Datum::Boolean(*v)))
+        }
+        (Statistics::Int32(s), types::Primitive::Int) => {
+            map(s.min(), s.max(), |v| Some(types::Datum::Int(*v)))
+        }
+        (Statistics::Int32(s), types::Primitive::Date) => {
+            map(s.min(), s.max(), |v| Some(types::Datum::Date(*v)))
+        }
+        (Statistics::Int32(s), types::Primitive::Decimal { .. }) => map(s.min(), s.max(), |v| {
+            Some(types::Datum::Decimal(*v as i128))
+        }),
+        (Statistics::Int64(s), types::Primitive::Long) => {
+            map(s.min(), s.max(), |v| Some(types::Datum::Long(*v)))
+        }
+        (Statistics::Int64(s), types::Primitive::Time) if is_micros => {
+            map(s.min(), s.max(), |v| Some(types::Datum::Time(*v)))
+        }
+        (Statistics::Int64(s), types::Primitive::Timestamp) if is_micros => {
+            map(s.min(), s.max(), |v| Some(types::Datum::Timestamp(*v)))
+        }
+        (Statistics::Int64(s), types::Primitive::Timestampz) if is_micros => {
+            map(s.min(), s.max(), |v| Some(types::Datum::Timestamptz(*v)))
+        }
+        (Statistics::Int64(s), types::Primitive::Decimal { .. }) => map(s.min(), s.max(), |v| {
+            Some(types::Datum::Decimal(*v as i128))
+        }),
+        (Statistics::Float(s), types::Primitive::Float) => map(s.min(), s.max(), |v| {
+            (!v.is_nan()).then(|| types::Datum::Float((*v).into()))
+        }),
+        (Statistics::Double(s), types::Primitive::Double) => map(s.min(), s.max(), |v| {
+            (!v.is_nan()).then(|| types::Datum::Double((*v).into()))
+        }),
+        (Statistics::ByteArray(s), types::Primitive::String) => map(s.min(), s.max(), |v| {
+            let v = std::str::from_utf8(v.data()).ok()?;
+            Some(types::Datum::String(v.to_string()))
+        }),
+        (Statistics::ByteArray(s), types::Primitive::Binary) => map(s.min(), s.max(), |v| {
+            Some(types::Datum::Binary(v.data().to_vec()))
+        }),
+        (Statistics::ByteArray(s), types::Primitive::Decimal { .. }) => {
+            map(s.min(), s.max(), |v| {
+                Some(types::Datum::Decimal(decode_decimal(v.data())?))
+            })
+        }
+        (Statistics::FixedLenByteArray(s), types::Primitive::Decimal { .. }) => {
+            map(s.min(), s.max(), |v| {
+                Some(types::Datum::Decimal(decode_decimal(v.data())?))
+            })
+        }
+        (Statistics::FixedLenByteArray(s), types::Primitive::Fixed(_)) => {
+            map(s.min(), s.max(), |v| {
+                Some(types::Datum::Fixed(v.data().to_vec()))
+            })
+        }
+        (Statistics::FixedLenByteArray(s), types::Primitive::Uuid) => map(s.min(), s.max(), |v| {
+            Some(types::Datum::Uuid(uuid::Uuid::from_slice(v.data()).ok()?))
+        }),
+        _ => None,
+    }
+}
+
+/// Decode the big-endian two's-complement bytes of unscaled decimal.
+fn decode_decimal(bs: &[u8]) -> Option<i128> {
+    if bs.is_empty() || bs.len() > 16 {
+        return None;
+    }
+    let fill = if bs[0] & 0x80 != 0 { 0xFF } else { 0 };
+    let mut buf = [fill; 16];
+    buf[16 - bs.len()..].copy_from_slice(bs);
+    Some(i128::from_be_bytes(buf))
+}
+
+/// Truncate the lower bound, which is still a lower bound.
+fn truncate_lower_bound(v: types::Datum) -> types::Datum {
+    match v {
+        types::Datum::String(v) if v.chars().count() > TRUNCATE_LENGTH => {
+            types::Datum::String(v.chars().take(TRUNCATE_LENGTH).collect())
+        }
+        types::Datum::Binary(mut v) => {
+            v.truncate(TRUNCATE_LENGTH);
+            types::Datum::Binary(v)
+        }
+        v => v,
+    }
+}
+
+/// Truncate the upper bound and round it up, so that it's still an upper
+/// bound.
+///
+/// `None` will be returned if it can't be rounded up, for example, all
+/// bytes after truncated are `0xFF`.
+fn truncate_upper_bound(v: types::Datum) -> Option<types::Datum> {
+    let v = match v {
+        types::Datum::String(v) if v.chars().count() > TRUNCATE_LENGTH => {
+            let mut chars: Vec<char> = v.chars().take(TRUNCATE_LENGTH).collect();
+            loop {
+                let c = chars.pop()?;
+                // Skip surrogates which are not valid chars.
+                let next = match c as u32 + 1 {
+                    0xD800 => Some('\u{E000}'),
+                    v => char::from_u32(v),
+                };
+                if let Some(next) = next {
+                    chars.push(next);
+                    break types::Datum::String(chars.into_iter().collect());
+                }
+            }
+        }
+        types::Datum::Binary(mut v) if v.len() > TRUNCATE_LENGTH => {
+            v.truncate(TRUNCATE_LENGTH);
+            loop {
+                let b = v.pop()?;
+                if b != 0xFF {
+                    v.push(b + 1);
+                    break types::Datum::Binary(v);
+                }
+            }
+        }
+        v => v,
+    };
+
+    Some(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::ArrayRef;
+    use arrow_array::BinaryArray;
+    use arrow_array::Float64Array;
+    use arrow_array::Int64Array;
+    use arrow_array::StringArray;
+    use bytes::Bytes;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::reader::FileReader;
+    use parquet::file::reader::SerializedFileReader;
+
+    use super::*;
+
+    fn schema() -> types::Schema {
+        let field = |id: i32, name: &str, typ: types::Primitive| types::Field {
+            id,
+            name: name.to_string(),
+            required: false,
+            field_type: types::Any::Primitive(typ),
+            comment: None,
+        };
+
+        types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![
+                field(1, "id", types::Primitive::Long),
+                field(2, "name", types::Primitive::String),
+                field(3, "score", types::Primitive::Double),
+                field(4, "data", types::Primitive::Binary),
+            ],
+        }
+    }
+
+    fn batch() -> RecordBatch {
+        let long_name = "a".repeat(15) + "bc";
+        RecordBatch::try_from_iter([
+            (
+                "id",
+                Arc::new(Int64Array::from(vec![Some(3), None, Some(-1)])) as ArrayRef,
+            ),
+            (
+                "name",
+                Arc::new(StringArray::from(vec![
+                    Some("b"),
+                    Some(long_name.as_str()),
+                    None,
+                ])) as ArrayRef,
+            ),
+            (
+                "score",
+                Arc::new(Float64Array::from(vec![1.5, f64::NAN, -0.5])) as ArrayRef,
+            ),
+            (
+                "data",
+                Arc::new(BinaryArray::from(vec![
+                    &[0xFF; 20][..],
+                    &[0x01][..],
+                    &[0x00, 0xFF][..],
+                ])) as ArrayRef,
+            ),
+            (
+                "unknown",
+                Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collect_metrics() {
+        let batch = batch();
+
+        let schema = schema();
+        let mut collector = MetricsCollector::new(&batch.schema(), Some(&schema));
+        collector.update(&batch);
+        collector.update(&batch.slice(0, 1));
+        let metrics = collector.finish();
+
+        assert_eq!(metrics.record_count, 4);
+        assert_eq!(
+            metrics.value_counts,
+            HashMap::from([(1, 4), (2, 4), (3, 4), (4, 4)])
+        );
+        assert_eq!(
+            metrics.null_value_counts,
+            HashMap::from([(1, 1), (2, 1), (3, 0), (4, 0)])
+        );
+        // NaNs are only counted for float and double columns.
+        assert_eq!(metrics.nan_value_counts, HashMap::from([(3, 1)]));
+        // Column sizes are unknown until the file is written.
+        assert!(metrics.column_sizes.is_empty());
+
+        let bound = |bounds: &HashMap<i32, Vec<u8>>, id: i32| {
+            bounds.get(&id).map(|v| {
+                let typ = match &schema.fields[id as usize - 1].field_type {
+                    types::Any::Primitive(v) => v,
+                    _ => unreachable!(),
+                };
+                types::Datum::from_bytes(v, typ).unwrap()
+            })
+        };
+        assert_eq!(
+            bound(&metrics.lower_bounds, 1),
+            Some(types::Datum::Long(-1))
+        );
+        assert_eq!(bound(&metrics.upper_bounds, 1), Some(types::Datum::Long(3)));
+        // Strings are truncated, and upper bounds are rounded up.
+        assert_eq!(
+            bound(&metrics.lower_bounds, 2),
+            Some(types::Datum::String("a".repeat(15) + "b"))
+        );
+        assert_eq!(
+            bound(&metrics.upper_bounds, 2),
+            Some(types::Datum::String("b".to_string()))
+        );
+        // NaNs are not counted in bounds.
+        assert_eq!(
+            bound(&metrics.lower_bounds, 3),
+            Some(types::Datum::Double((-0.5).into()))
+        );
+        assert_eq!(
+            bound(&metrics.upper_bounds, 3),
+            Some(types::Datum::Double(1.5.into()))
+        );
+        // Upper bound of binaries is absent if it can't be rounded up.
+        assert_eq!(
+            bound(&metrics.lower_bounds, 4),
+            Some(types::Datum::Binary(vec![0x00, 0xFF]))
+        );
+        assert_eq!(bound(&metrics.upper_bounds, 4), None);
+
+        // Nothing is tracked without table schema.
+        let mut collector = MetricsCollector::new(&batch.schema(), None);
+        collector.update(&batch);
+        assert_eq!(
+            collector.finish(),
+            DataFileMetrics {
+                record_count: 3,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_truncate_bounds() {
+        let s = |v: &str| types::Datum::String(v.to_string());
+        let long = "x".repeat(TRUNCATE_LENGTH);
+
+        assert_eq!(truncate_lower_bound(s("abc")), s("abc"));
+        assert_eq!(truncate_upper_bound(s("abc")), Some(s("abc")));
+        assert_eq!(truncate_lower_bound(s(&(long.clone() + "z"))), s(&long));
+        assert_eq!(
+            truncate_upper_bound(s(&(long.clone() + "z"))),
+            Some(s(&("x".repeat(TRUNCATE_LENGTH - 1) + "y")))
+        );
+        // Chars which can't be incremented are dropped.
+        let max = "a".to_string() + &"\u{10FFFF}".repeat(TRUNCATE_LENGTH);
+        assert_eq!(truncate_upper_bound(s(&max)), Some(s("b")));
+        let max = "\u{10FFFF}".repeat(TRUNCATE_LENGTH + 1);
+        assert_eq!(truncate_upper_bound(s(&max)), None);
+        // Surrogates are skipped.
+        let v = "\u{D7FF}".repeat(TRUNCATE_LENGTH + 1);
+        assert_eq!(
+            truncate_upper_bound(s(&v)),
+            Some(s(&("\u{D7FF}".repeat(TRUNCATE_LENGTH - 1) + "\u{E000}")))
+        );
+
+        let b = |v: &[u8]| types::Datum::Binary(v.to_vec());
+        let mut long = vec![0x01; TRUNCATE_LENGTH + 1];
+        assert_eq!(truncate_lower_bound(b(&long)), b(&long[..TRUNCATE_LENGTH]));
+        long[TRUNCATE_LENGTH - 1] = 0xFF;
+        let mut expected = vec![0x01; TRUNCATE_LENGTH - 1];
+        expected[TRUNCATE_LENGTH - 2] = 0x02;
+        assert_eq!(truncate_upper_bound(b(&long)), Some(b(&expected)));
+    }
+
+    #[test]
+    fn test_metrics_from_parquet_metadata() {
+        let batch = batch();
+        let schema = schema();
+        let mut buf = vec![];
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.write(&batch.slice(0, 1)).unwrap();
+        writer.close().unwrap();
+        let reader = SerializedFileReader::new(Bytes::from(buf)).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.num_row_groups(), 2);
+
+        let mut collector = MetricsCollector::new(&batch.schema(), Some(&schema));
+        collector.update(&batch);
+        collector.update(&batch.slice(0, 1));
+        let expected = collector.finish();
+
+        // Metrics are aggregated from all row groups, except NaN counts
+        // which are not recorded by parquet.
+        let metrics = DataFileMetrics::from_parquet_metadata(metadata, &schema);
+        assert_eq!(metrics.record_count, expected.record_count);
+        assert_eq!(metrics.value_counts, expected.value_counts);
+        assert_eq!(metrics.null_value_counts, expected.null_value_counts);
+        assert!(metrics.nan_value_counts.is_empty());
+        assert_eq!(metrics.column_sizes.len(), 4);
+        assert_eq!(metrics.lower_bounds, expected.lower_bounds);
+        assert_eq!(metrics.upper_bounds, expected.upper_bounds);
+
+        // Bounds are not truncated.
+        let bounds = parquet_column_bounds(metadata, &schema);
+        assert_eq!(
+            bounds[&2],
+            (
+                types::Datum::String("a".repeat(15) + "bc"),
+                types::Datum::String("b".to_string())
+            )
+        );
+    }
+}
@@ -1,3 +1,14 @@
+mod reader;
+pub use reader::ParquetMetadataCache;
+pub use reader::ParquetReader;
+pub use reader::ParquetReaderBuilder;
+pub use reader::FIELD_ID_META_KEY;
+mod metrics;
+pub(crate) use metrics::array_value;
+pub(crate) use metrics::parquet_column_bounds;
+pub use metrics::DataFileMetrics;
 mod writer;
+pub(crate) use writer::cast_field_metadata;
+pub(crate) use writer::with_table_field_ids;
 pub use writer::ParquetWriter;
 pub use writer::ParquetWriterBuilder;
@@ -0,0 +1,486 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use arrow_array::RecordBatch;
+use arrow_schema::Schema as ArrowSchema;
+use arrow_schema::SchemaRef;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use futures::StreamExt;
+use opendal::Operator;
+use parquet::arrow::async_reader::AsyncFileReader;
+use parquet::arrow::async_reader::ParquetRecordBatchStream;
+use parquet::arrow::ParquetRecordBatchStreamBuilder;
+use parquet::arrow::ProjectionMask;
+use parquet::errors::ParquetError;
+use parquet::file::footer::decode_footer;
+use parquet::file::footer::decode_metadata;
+use parquet::file::metadata::ParquetMetaData;
+use parquet::file::FOOTER_SIZE;
+use parquet::schema::types::ColumnDescriptor;
+
+use crate::Error;
+use crate::ErrorKind;
+use crate::Result;
+
+/// The default size of the tail read to fetch parquet footer.
+///
+/// Most footers fit in it, so that the metadata can be fetched in one read.
+const DEFAULT_FOOTER_SIZE_HINT: usize = 64 * 1024;
+
+/// The metadata key of arrow fields to carry ids of parquet fields, which
+/// is the same as the one used by later versions of the parquet crate.
+pub const FIELD_ID_META_KEY: &str = "PARQUET:field_id";
+
+type ColumnFilter = Box<dyn Fn(&ColumnDescriptor) -> bool + Send + Sync>;
+
+/// ParquetMetadataCache caches decoded parquet metadata keyed by file
+/// path, so that the footer of a data file is fetched only once within a
+/// scan, for example, by split planning and then by the executor.
+///
+/// Paths are relative to the operator, so a cache should not be shared
+/// between tables. Clones of a cache share the same entries.
+#[derive(Clone, Default)]
+pub struct ParquetMetadataCache {
+    entries: Arc<Mutex<HashMap<String, Arc<ParquetMetaData>>>>,
+    fetch_count: Arc<AtomicUsize>,
+}
+
+impl ParquetMetadataCache {
+    /// Create a new empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the cached metadata of file.
+    pub fn get(&self, path: &str) -> Option<Arc<ParquetMetaData>> {
+        self.entries
+            .lock()
+            .expect("lock must succeed")
+            .get(path)
+            .cloned()
+    }
+
+    /// Insert the metadata of file into cache.
+    pub fn insert(&self, path: &str, metadata: Arc<ParquetMetaData>) {
+        self.entries
+            .lock()
+            .expect("lock must succeed")
+            .insert(path.to_string(), metadata);
+    }
+
+    /// Number of cached files.
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("lock must succeed").len()
+    }
+
+    /// Check if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of footers fetched from storage by readers using the cache,
+    /// which are not cached then.
+    pub fn fetch_count(&self) -> usize {
+        self.fetch_count.load(Ordering::Relaxed)
+    }
+}
+
+/// ParquetReaderBuilder is used to build a `ParquetReader` of a parquet file.
+pub struct ParquetReaderBuilder {
+    op: Operator,
+    path: String,
+
+    file_size: Option<u64>,
+    footer_size_hint: usize,
+    batch_size: Option<usize>,
+    metadata_cache: Option<ParquetMetadataCache>,
+    column_filter: Option<ColumnFilter>,
+}
+
+impl ParquetReaderBuilder {
+    /// Initiate a new builder to read the file at `path` of operator.
+    pub fn new(op: Operator, path: &str) -> Self {
+        Self {
+            op,
+            path: path.to_string(),
+
+            file_size: None,
+            footer_size_hint: DEFAULT_FOOTER_SIZE_HINT,
+            batch_size: None,
+            metadata_cache: None,
+            column_filter: None,
+        }
+    }
+
+    /// Configure the size of file.
+    ///
+    /// The size is usually known from `DataFile.file_size_in_bytes`, which
+    /// saves a `stat` call to the storage.
+    pub fn with_file_size(mut self, file_size: u64) -> Self {
+        self.file_size = Some(file_size);
+        self
+    }
+
+    /// Configure the size of the tail read to fetch parquet footer.
+    ///
+    /// If the footer is larger than it, the rest of footer will be fetched
+    /// in another read.
+    pub fn with_footer_size_hint(mut self, footer_size_hint: usize) -> Self {
+        self.footer_size_hint = footer_size_hint;
+        self
+    }
+
+    /// Configure the max number of rows in each record batch.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Configure the cache of parquet metadata.
+    ///
+    /// The footer of file will not be fetched if its metadata is cached,
+    /// and the fetched metadata will be inserted into the cache.
+    pub fn with_metadata_cache(mut self, cache: ParquetMetadataCache) -> Self {
+        self.metadata_cache = Some(cache);
+        self
+    }
+
+    /// Configure the leaf columns to read, only leaf columns on which
+    /// `filter` returns `true` are read.
+    ///
+    /// Nested columns are kept in the output with only selected leaves,
+    /// for example, selecting leaf `address.city` produces a struct column
+    /// `address` with the only child `city`. All columns are read by
+    /// default.
+    pub fn with_column_filter(
+        mut self,
+        filter: impl Fn(&ColumnDescriptor) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.column_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Consume the current builder to build a new reader.
+    ///
+    /// The footer of file will be fetched here if not cached.
+    pub async fn build(self) -> Result<ParquetReader> {
+        let cached = self
+            .metadata_cache
+            .as_ref()
+            .is_some_and(|v| v.get(&self.path).is_some());
+        let file_size = match self.file_size {
+            Some(v) => v,
+            // The size is only used to locate the footer.
+            None if cached => 0,
+            None => self.op.stat(&self.path).await?.content_length(),
+        };
+
+        let reader = FileReader {
+            op: self.op,
+            path: self.path,
+            file_size,
+            footer_size_hint: self.footer_size_hint.max(FOOTER_SIZE),
+            metadata_cache: self.metadata_cache,
+        };
+        let mut builder = ParquetRecordBatchStreamBuilder::new(reader).await?;
+        if let Some(batch_size) = self.batch_size {
+            builder = builder.with_batch_size(batch_size);
+        }
+        if let Some(filter) = self.column_filter {
+            let schema = builder.metadata().file_metadata().schema_descr();
+            let leaves: Vec<_> = (0..schema.num_columns())
+                .filter(|idx| filter(&schema.column(*idx)))
+                .collect();
+            let mask = ProjectionMask::leaves(schema, leaves);
+            builder = builder.with_projection(mask);
+        }
+
+        // Top level fields of the file, whose ids are attached to arrow
+        // fields so that columns can be matched with table schema by id.
+        let field_ids: HashMap<String, i32> = builder
+            .parquet_schema()
+            .root_schema()
+            .get_fields()
+            .iter()
+            .filter(|v| v.get_basic_info().has_id())
+            .map(|v| (v.name().to_string(), v.get_basic_info().id()))
+            .collect();
+        let stream = builder.build()?;
+        let schema = if field_ids.is_empty() {
+            None
+        } else {
+            Some(with_field_ids(stream.schema(), &field_ids))
+        };
+
+        Ok(ParquetReader { stream, schema })
+    }
+
+    /// Consume the current builder to fetch the metadata of file only,
+    /// without building a reader.
+    ///
+    /// The metadata cache is used if set. Options of reading record
+    /// batches are ignored.
+    pub async fn read_metadata(self) -> Result<Arc<ParquetMetaData>> {
+        if let Some(metadata) = self.metadata_cache.as_ref().and_then(|v| v.get(&self.path)) {
+            return Ok(metadata);
+        }
+        let file_size = match self.file_size {
+            Some(v) => v,
+            None => self.op.stat(&self.path).await?.content_length(),
+        };
+
+        let mut reader = FileReader {
+            op: self.op,
+            path: self.path,
+            file_size,
+            footer_size_hint: self.footer_size_hint.max(FOOTER_SIZE),
+            metadata_cache: self.metadata_cache,
+        };
+        Ok(reader.get_metadata().await?)
+    }
+}
+
+/// ParquetReader is used to read arrow data from parquet file on storage.
+///
+/// Only the footer will be fetched by a tail read at first, then each row
+/// group is fetched by ranged reads of its column chunks, instead of
+/// reading the whole file.
+///
+/// Initiate a new reader with `ParquetReaderBuilder::new()`.
+pub struct ParquetReader {
+    stream: ParquetRecordBatchStream<FileReader>,
+    /// Arrow schema with field ids attached, `None` if the file has no
+    /// field ids.
+    schema: Option<SchemaRef>,
+}
+
+impl ParquetReader {
+    /// Arrow schema of the file.
+    ///
+    /// Ids of top level fields in file are kept in field metadata keyed by
+    /// `FIELD_ID_META_KEY`.
+    pub fn schema(&self) -> SchemaRef {
+        match &self.schema {
+            Some(schema) => schema.clone(),
+            None => self.stream.schema().clone(),
+        }
+    }
+
+    /// Read the next record batch, `None` will be returned at the end of
+    /// file.
+    pub async fn next(&mut self) -> Result<Option<RecordBatch>> {
+        let batch = match self.stream.next().await {
+            None => return Ok(None),
+            Some(batch) => batch?,
+        };
+        match &self.schema {
+            Some(schema) => RecordBatch::try_new(schema.clone(), batch.columns().to_vec())
+                .map(Some)
+                .map_err(|err| {
+                    Error::new(ErrorKind::Unexpected, "attaching field ids failed").set_source(err)
+                }),
+            None => Ok(Some(batch)),
+        }
+    }
+}
+
+/// Attach ids of top level fields to the arrow schema.
+fn with_field_ids(schema: &SchemaRef, field_ids: &HashMap<String, i32>) -> SchemaRef {
+    let fields: Vec<_> = schema
+        .fields()
+        .iter()
+        .map(|field| match field_ids.get(field.name()) {
+            Some(id) => {
+                let mut metadata = field.metadata().clone();
+                metadata.insert(FIELD_ID_META_KEY.to_string(), id.to_string());
+                Arc::new(field.as_ref().clone().with_metadata(metadata))
+            }
+            None => field.clone(),
+        })
+        .collect();
+    Arc::new(ArrowSchema::new_with_metadata(
+        fields,
+        schema.metadata().clone(),
+    ))
+}
+
+/// FileReader fetches ranges of parquet file from operator.
+struct FileReader {
+    op: Operator,
+    path: String,
+
+    file_size: u64,
+    footer_size_hint: usize,
+    metadata_cache: Option<ParquetMetadataCache>,
+}
+
+impl FileReader {
+    async fn read_range(&self, range: Range<u64>) -> parquet::errors::Result<Bytes> {
+        let bs = self
+            .op
+            .range_read(&self.path, range)
+            .await
+            .map_err(|err| ParquetError::External(Box::new(err)))?;
+        Ok(Bytes::from(bs))
+    }
+
+    async fn read_metadata(&self) -> parquet::errors::Result<ParquetMetaData> {
+        if self.file_size < FOOTER_SIZE as u64 {
+            return Err(ParquetError::General(format!(
+                "file size {} is smaller than parquet footer",
+                self.file_size
+            )));
+        }
+
+        let tail_size = (self.footer_size_hint as u64).min(self.file_size);
+        let tail = self
+            .read_range(self.file_size - tail_size..self.file_size)
+            .await?;
+
+        let mut footer = [0; FOOTER_SIZE];
+        footer.copy_from_slice(&tail[tail.len() - FOOTER_SIZE..]);
+        let metadata_size = decode_footer(&footer)?;
+        let footer_size = (metadata_size + FOOTER_SIZE) as u64;
+        if footer_size > self.file_size {
+            return Err(ParquetError::General(format!(
+                "parquet footer size {} is larger than file size {}",
+                footer_size, self.file_size
+            )));
+        }
+
+        if footer_size <= tail_size {
+            let start = tail.len() - footer_size as usize;
+            decode_metadata(&tail[start..start + metadata_size])
+        } else {
+            // Fetch the rest of metadata which is not in the tail.
+            let rest = self
+                .read_range(self.file_size - footer_size..self.file_size - tail_size)
+                .await?;
+            let mut bs = Vec::with_capacity(metadata_size);
+            bs.extend_from_slice(&rest);
+            bs.extend_from_slice(&tail[..tail.len() - FOOTER_SIZE]);
+            decode_metadata(&bs)
+        }
+    }
+}
+
+impl AsyncFileReader for FileReader {
+    fn get_bytes(&mut self, range: Range<usize>) -> BoxFuture<'_, parquet::errors::Result<Bytes>> {
+        self.read_range(range.start as u64..range.end as u64)
+            .boxed()
+    }
+
+    fn get_metadata(&mut self) -> BoxFuture<'_, parquet::errors::Result<Arc<ParquetMetaData>>> {
+        async move {
+            if let Some(metadata) = self.metadata_cache.as_ref().and_then(|v| v.get(&self.path)) {
+                return Ok(metadata);
+            }
+
+            let metadata = Arc::new(self.read_metadata().await?);
+            if let Some(cache) = &self.metadata_cache {
+                cache.fetch_count.fetch_add(1, Ordering::Relaxed);
+                cache.insert(&self.path, metadata.clone());
+            }
+            Ok(metadata)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use arrow_array::{ArrayRef, Int64Array, RecordBatch};
+    use opendal::services::Memory;
+    use parquet::file::properties::WriterProperties;
+
+    use super::*;
+    use crate::io::parquet::ParquetWriterBuilder;
+
+    #[tokio::test]
+    async fn parquet_ranged_read_test() -> Result<()> {
+        let op: Operator = Operator::new(Memory::default())?.finish();
+
+        let col = Arc::new(Int64Array::from_iter_values(0..1024)) as ArrayRef;
+        let to_write = RecordBatch::try_from_iter([("col", col)])?;
+
+        let w = op.writer("test.parquet").await?;
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(256)
+            .build();
+        let mut pw = ParquetWriterBuilder::new(w, to_write.schema())
+            .with_properties(props)
+            .build()?;
+        pw.write(&to_write).await?;
+        pw.close().await?;
+        let file_size = op.stat("test.parquet").await?.content_length();
+
+        // The footer is fetched in one read or two reads.
+        for footer_size_hint in [DEFAULT_FOOTER_SIZE_HINT, 16] {
+            let mut reader = ParquetReaderBuilder::new(op.clone(), "test.parquet")
+                .with_file_size(file_size)
+                .with_footer_size_hint(footer_size_hint)
+                .with_batch_size(1024)
+                .build()
+                .await?;
+            assert_eq!(reader.schema(), to_write.schema());
+
+            let mut batches = vec![];
+            while let Some(batch) = reader.next().await? {
+                batches.push(batch);
+            }
+            // Each row group is read separately.
+            assert_eq!(batches.len(), 4);
+            let values: Vec<_> = batches
+                .iter()
+                .flat_map(|v| {
+                    v.column(0)
+                        .as_any()
+                        .downcast_ref::<Int64Array>()
+                        .unwrap()
+                        .values()
+                        .to_vec()
+                })
+                .collect();
+            assert_eq!(values, (0..1024).collect::<Vec<_>>());
+        }
+
+        // The footer is fetched only once with cache.
+        let cache = ParquetMetadataCache::new();
+        let mut reader = ParquetReaderBuilder::new(op.clone(), "test.parquet")
+            .with_metadata_cache(cache.clone())
+            .build()
+            .await?;
+        assert!(reader.next().await?.is_some());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.fetch_count(), 1);
+        // The file size is wrong, but the footer is not fetched again.
+        let mut reader = ParquetReaderBuilder::new(op.clone(), "test.parquet")
+            .with_file_size(0)
+            .with_metadata_cache(cache.clone())
+            .build()
+            .await?;
+        assert!(reader.next().await?.is_some());
+        assert_eq!(cache.fetch_count(), 1);
+        assert!(ParquetReaderBuilder::new(op.clone(), "test.parquet")
+            .with_file_size(0)
+            .build()
+            .await
+            .is_err());
+
+        // Reading a file which is not parquet must fail.
+        op.write("invalid.parquet", "not a parquet file").await?;
+        assert!(ParquetReaderBuilder::new(op.clone(), "invalid.parquet")
+            .build()
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -1,8 +1,15 @@
+use super::metrics::MetricsCollector;
+use super::DataFileMetrics;
+use super::FIELD_ID_META_KEY;
+use crate::types;
+use crate::Error;
+use crate::ErrorKind;
 use crate::Result;
-use arrow_array::RecordBatch;
-use arrow_schema::SchemaRef;
+use arrow_array::{make_array, Array, ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, SchemaRef};
 use opendal::Writer;
 use parquet::{arrow::AsyncArrowWriter, file::properties::WriterProperties};
+use std::sync::Arc;
 
 pub struct ParquetWriterBuilder {
     writer: Writer,
@@ -12,6 +19,7 @@ pub struct ParquetWriterBuilder {
     /// The intermediate buffer will automatically be resized if necessary
     buffer_size: usize,
     props: Option<WriterProperties>,
+    table_schema: Option<types::Schema>,
 }
 
 impl ParquetWriterBuilder {
@@ -23,6 +31,7 @@ impl ParquetWriterBuilder {
 
             buffer_size: 0,
             props: None,
+            table_schema: None,
         }
     }
 
@@ -46,8 +55,18 @@ impl ParquetWriterBuilder {
         self
     }
 
+    /// Configure the schema of table to write.
+    ///
+    /// Metrics of columns are collected for fields of the schema, which
+    /// are matched by name. Only the record count is collected without it.
+    pub fn with_table_schema(mut self, schema: types::Schema) -> Self {
+        self.table_schema = Some(schema);
+        self
+    }
+
     /// Consume the current builder to build a new writer.
     pub fn build(self) -> Result<ParquetWriter> {
+        let metrics = MetricsCollector::new(&self.arrow_schema, self.table_schema.as_ref());
         let writer = AsyncArrowWriter::try_new(
             self.writer,
             self.arrow_schema,
@@ -55,7 +74,7 @@ impl ParquetWriterBuilder {
             self.props,
         )?;
 
-        Ok(ParquetWriter { writer })
+        Ok(ParquetWriter { writer, metrics })
     }
 }
 
@@ -64,6 +83,7 @@ impl ParquetWriterBuilder {
 /// Initiate a new writer with `ParquetWriterBuilder::new()`.
 pub struct ParquetWriter {
     writer: AsyncArrowWriter<Writer>,
+    metrics: MetricsCollector,
 }
 
 impl ParquetWriter {
@@ -72,6 +92,7 @@ impl ParquetWriter {
     /// Note: It will not guarantee to take effect imediately.
     pub async fn write(&mut self, data: &RecordBatch) -> Result<()> {
         self.writer.write(data).await?;
+        self.metrics.update(data);
         Ok(())
     }
 
@@ -81,22 +102,130 @@ impl ParquetWriter {
     ///
     /// This function must be called before complete the write process.
     ///
-    /// # TODO
-    ///
-    /// Maybe we can return the `FileMetaData` to the user after close.
+    /// Use `close_with_metrics` instead to get metrics of written data.
     pub async fn close(self) -> Result<()> {
-        let _ = self.writer.close().await?;
+        self.close_with_metrics().await?;
         Ok(())
     }
+
+    /// Write footer, flush rest data and close file like `close`.
+    ///
+    /// Returns the metrics of written data, which should be filled into
+    /// the `DataFile` entry of the file.
+    pub async fn close_with_metrics(mut self) -> Result<DataFileMetrics> {
+        let file_metadata = self.writer.close().await?;
+        self.metrics.update_column_sizes(&file_metadata);
+        Ok(self.metrics.finish())
+    }
+}
+
+/// Attach the ids of table field to the arrow field and its nested fields
+/// with key `FIELD_ID_META_KEY`.
+///
+/// The arrow schema is embedded in written files, so the ids are restored
+/// by `ParquetReader`, and columns can be matched by ids after renames.
+pub(crate) fn with_table_field_ids(field: Field, table_field: &types::Field) -> Field {
+    with_field_id(field, table_field.id, &table_field.field_type)
+}
+
+fn with_field_id(field: Field, id: i32, typ: &types::Any) -> Field {
+    let data_type = match (field.data_type(), typ) {
+        (DataType::Struct(fields), types::Any::Struct(v)) => DataType::Struct(
+            fields
+                .iter()
+                .map(|f| match v.fields.iter().find(|v| &v.name == f.name()) {
+                    Some(v) => with_table_field_ids(f.as_ref().clone(), v),
+                    None => f.as_ref().clone(),
+                })
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        (DataType::List(item), types::Any::List(v)) => DataType::List(Arc::new(with_field_id(
+            item.as_ref().clone(),
+            v.element_id,
+            &v.element_type,
+        ))),
+        (DataType::LargeList(item), types::Any::List(v)) => DataType::LargeList(Arc::new(
+            with_field_id(item.as_ref().clone(), v.element_id, &v.element_type),
+        )),
+        (DataType::Map(entries, sorted), types::Any::Map(v)) => match entries.data_type() {
+            DataType::Struct(kv) if kv.len() == 2 => {
+                let kv = vec![
+                    with_field_id(kv[0].as_ref().clone(), v.key_id, &v.key_type),
+                    with_field_id(kv[1].as_ref().clone(), v.value_id, &v.value_type),
+                ];
+                let entries = entries
+                    .as_ref()
+                    .clone()
+                    .with_data_type(DataType::Struct(kv.into()));
+                DataType::Map(Arc::new(entries), *sorted)
+            }
+            _ => field.data_type().clone(),
+        },
+        (v, _) => v.clone(),
+    };
+
+    let mut metadata = field.metadata().clone();
+    metadata.insert(FIELD_ID_META_KEY.to_string(), id.to_string());
+    field.with_data_type(data_type).with_metadata(metadata)
+}
+
+/// Cast the array to the data type which only differs in metadata of
+/// nested fields, like the one built by `with_table_field_ids`.
+///
+/// Returns error if the types of values are different.
+pub(crate) fn cast_field_metadata(array: &ArrayRef, data_type: &DataType) -> Result<ArrayRef> {
+    if array.data_type() == data_type {
+        return Ok(array.clone());
+    }
+    let child_types: Vec<_> = match data_type {
+        DataType::Struct(fields) => fields.iter().map(|v| v.data_type()).collect(),
+        DataType::List(v) | DataType::LargeList(v) | DataType::Map(v, _) => vec![v.data_type()],
+        _ => vec![],
+    };
+    let data = array.to_data();
+    if child_types.is_empty() || child_types.len() != data.child_data().len() {
+        return Err(Error::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "array of type {} can't be cast to {}",
+                array.data_type(),
+                data_type
+            ),
+        ));
+    }
+
+    let mut children = Vec::with_capacity(child_types.len());
+    for (child, typ) in data.child_data().iter().zip(child_types) {
+        children.push(cast_field_metadata(&make_array(child.clone()), typ)?.to_data());
+    }
+    let data = data
+        .into_builder()
+        .data_type(data_type.clone())
+        .child_data(children)
+        .build()
+        .map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "array of type {} can't be cast to {}",
+                    array.data_type(),
+                    data_type
+                ),
+            )
+            .set_source(err)
+        })?;
+    Ok(make_array(data))
 }
 
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
-    use arrow_array::{ArrayRef, Int64Array, RecordBatch};
+    use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch};
     use bytes::Bytes;
     use opendal::{services::Memory, Operator};
     use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::collections::HashMap;
     use std::sync::Arc;
 
     use super::*;
@@ -129,4 +258,150 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn parquet_metrics_test() -> Result<()> {
+        let op: Operator = Operator::new(Memory::default())?.finish();
+
+        let col = Arc::new(Float64Array::from(vec![
+            Some(1.5),
+            Some(f64::NAN),
+            None,
+            Some(-2.0),
+            Some(f64::NAN),
+        ])) as ArrayRef;
+        let to_write = RecordBatch::try_from_iter([("score", col)]).unwrap();
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![types::Field {
+                id: 1,
+                name: "score".to_string(),
+                required: false,
+                field_type: types::Any::Primitive(types::Primitive::Double),
+                comment: None,
+            }],
+        };
+
+        let w = op.writer("test").await?;
+        let mut pw = ParquetWriterBuilder::new(w, to_write.schema())
+            .with_table_schema(schema)
+            .build()?;
+        pw.write(&to_write).await?;
+        let metrics = pw.close_with_metrics().await?;
+
+        assert_eq!(metrics.record_count, 5);
+        assert_eq!(metrics.null_value_counts, HashMap::from([(1, 1)]));
+        assert_eq!(metrics.nan_value_counts, HashMap::from([(1, 2)]));
+        assert!(metrics.column_sizes[&1] > 0);
+
+        let data_file = types::DataFile {
+            content: types::DataContentType::Data,
+            file_path: "test".to_string(),
+            file_format: types::DataFileFormat::Parquet,
+            partition: vec![],
+            record_count: metrics.record_count,
+            file_size_in_bytes: op.stat("test").await?.content_length() as i64,
+            column_sizes: Some(metrics.column_sizes),
+            value_counts: Some(metrics.value_counts),
+            null_value_counts: Some(metrics.null_value_counts),
+            nan_value_counts: Some(metrics.nan_value_counts),
+            distinct_counts: None,
+            lower_bounds: Some(metrics.lower_bounds),
+            upper_bounds: Some(metrics.upper_bounds),
+            key_metadata: None,
+            split_offsets: vec![],
+            equality_ids: None,
+            sort_order_id: None,
+        };
+        assert_eq!(data_file.nan_value_count(1), Some(2));
+        assert!(data_file.may_contain_nan(1));
+        assert!(data_file.column_size(1) <= Some(data_file.file_size_in_bytes));
+        // NaNs are not counted in bounds.
+        assert_eq!(
+            data_file.lower_bound(1, &types::Primitive::Double)?,
+            Some(types::Datum::Double((-2.0).into()))
+        );
+        assert_eq!(
+            data_file.upper_bound(1, &types::Primitive::Double)?,
+            Some(types::Datum::Double(1.5.into()))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parquet_nested_field_ids_test() -> Result<()> {
+        use arrow_array::{ListArray, StructArray};
+        use arrow_schema::Schema;
+
+        use crate::io::parquet::ParquetReaderBuilder;
+
+        let op: Operator = Operator::new(Memory::default())?.finish();
+        let table_field = types::Field {
+            id: 1,
+            name: "point".to_string(),
+            required: false,
+            field_type: types::Any::Struct(types::Struct {
+                fields: vec![types::Field {
+                    id: 2,
+                    name: "tags".to_string(),
+                    required: false,
+                    field_type: types::Any::List(types::List {
+                        element_id: 3,
+                        element_required: false,
+                        element_type: Box::new(types::Any::Primitive(types::Primitive::Long)),
+                    }),
+                    comment: None,
+                }],
+            }),
+            comment: None,
+        };
+
+        let tags = Arc::new(ListArray::from_iter_primitive::<
+            arrow_array::types::Int64Type,
+            _,
+            _,
+        >(vec![Some(vec![Some(1), Some(2)])])) as ArrayRef;
+        let point = Arc::new(StructArray::from(vec![(
+            Arc::new(Field::new("tags", tags.data_type().clone(), true)),
+            tags,
+        )])) as ArrayRef;
+        let field = with_table_field_ids(
+            Field::new("point", point.data_type().clone(), true),
+            &table_field,
+        );
+        let point = cast_field_metadata(&point, field.data_type())?;
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(vec![field])), vec![point])?;
+
+        // Ids of nested fields are attached.
+        let id_of = |field: &Field| field.metadata().get(FIELD_ID_META_KEY).cloned();
+        let schema = batch.schema();
+        let point = schema.field(0);
+        assert_eq!(id_of(point).as_deref(), Some("1"));
+        let DataType::Struct(children) = point.data_type() else {
+            panic!("point must be a struct")
+        };
+        assert_eq!(id_of(&children[0]).as_deref(), Some("2"));
+        let DataType::List(item) = children[0].data_type() else {
+            panic!("tags must be a list")
+        };
+        assert_eq!(id_of(item).as_deref(), Some("3"));
+
+        // And restored by reader.
+        let w = op.writer("test").await?;
+        let mut pw = ParquetWriterBuilder::new(w, batch.schema()).build()?;
+        pw.write(&batch).await?;
+        pw.close().await?;
+        let mut reader = ParquetReaderBuilder::new(op.clone(), "test")
+            .build()
+            .await?;
+        assert_eq!(reader.next().await?, Some(batch));
+
+        // Values of different types can't be cast.
+        let ids = Arc::new(Int64Array::from(vec![1])) as ArrayRef;
+        assert!(cast_field_metadata(&ids, &DataType::Int32).is_err());
+
+        Ok(())
+    }
 }
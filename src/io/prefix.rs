@@ -0,0 +1,261 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use opendal::raw::oio;
+use opendal::raw::*;
+use opendal::Result;
+
+/// PrefixLayer re-roots an operator at a sub directory of its root.
+///
+/// Paths of all operations are prefixed before passing to the inner
+/// accessor, and paths of listed entries are stripped back. Since the
+/// inner accessor is shared, operators layered from the same operator
+/// share the same http client and connection pool.
+#[derive(Debug, Clone)]
+pub(crate) struct PrefixLayer {
+    /// Normalized prefix like `db/tbl/`, or empty for the root.
+    prefix: String,
+}
+
+impl PrefixLayer {
+    /// Create a new layer with the prefix relative to the operator root.
+    pub fn new(prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        let prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{prefix}/")
+        };
+
+        Self { prefix }
+    }
+}
+
+impl<A: Accessor> Layer<A> for PrefixLayer {
+    type LayeredAccessor = PrefixAccessor<A>;
+
+    fn layer(&self, inner: A) -> Self::LayeredAccessor {
+        PrefixAccessor {
+            inner,
+            prefix: self.prefix.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PrefixAccessor<A: Accessor> {
+    inner: A,
+    prefix: String,
+}
+
+impl<A: Accessor> PrefixAccessor<A> {
+    /// Get the path of inner accessor.
+    fn path(&self, path: &str) -> String {
+        format!("{}{}", self.prefix, path.trim_start_matches('/'))
+    }
+
+    fn pager<P>(&self, pager: P) -> PrefixPager<P> {
+        PrefixPager {
+            inner: pager,
+            prefix: self.prefix.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<A: Accessor> LayeredAccessor for PrefixAccessor<A> {
+    type Inner = A;
+    type Reader = A::Reader;
+    type BlockingReader = A::BlockingReader;
+    type Writer = A::Writer;
+    type BlockingWriter = A::BlockingWriter;
+    type Appender = A::Appender;
+    type Pager = PrefixPager<A::Pager>;
+    type BlockingPager = PrefixPager<A::BlockingPager>;
+
+    fn inner(&self) -> &Self::Inner {
+        &self.inner
+    }
+
+    async fn create_dir(&self, path: &str, args: OpCreateDir) -> Result<RpCreateDir> {
+        self.inner.create_dir(&self.path(path), args).await
+    }
+
+    async fn read(&self, path: &str, args: OpRead) -> Result<(RpRead, Self::Reader)> {
+        self.inner.read(&self.path(path), args).await
+    }
+
+    async fn write(&self, path: &str, args: OpWrite) -> Result<(RpWrite, Self::Writer)> {
+        self.inner.write(&self.path(path), args).await
+    }
+
+    async fn append(&self, path: &str, args: OpAppend) -> Result<(RpAppend, Self::Appender)> {
+        self.inner.append(&self.path(path), args).await
+    }
+
+    async fn copy(&self, from: &str, to: &str, args: OpCopy) -> Result<RpCopy> {
+        self.inner
+            .copy(&self.path(from), &self.path(to), args)
+            .await
+    }
+
+    async fn rename(&self, from: &str, to: &str, args: OpRename) -> Result<RpRename> {
+        self.inner
+            .rename(&self.path(from), &self.path(to), args)
+            .await
+    }
+
+    async fn stat(&self, path: &str, args: OpStat) -> Result<RpStat> {
+        self.inner.stat(&self.path(path), args).await
+    }
+
+    async fn delete(&self, path: &str, args: OpDelete) -> Result<RpDelete> {
+        self.inner.delete(&self.path(path), args).await
+    }
+
+    async fn list(&self, path: &str, args: OpList) -> Result<(RpList, Self::Pager)> {
+        let (rp, pager) = self.inner.list(&self.path(path), args).await?;
+        Ok((rp, self.pager(pager)))
+    }
+
+    async fn batch(&self, args: OpBatch) -> Result<RpBatch> {
+        let ops = args
+            .into_operation()
+            .into_iter()
+            .map(|(path, op)| (self.path(&path), op))
+            .collect();
+        let results = self
+            .inner
+            .batch(OpBatch::new(ops))
+            .await?
+            .into_results()
+            .into_iter()
+            .map(|(path, result)| (strip_prefix(&self.prefix, path), result))
+            .collect();
+        Ok(RpBatch::new(results))
+    }
+
+    async fn presign(&self, path: &str, args: OpPresign) -> Result<RpPresign> {
+        self.inner.presign(&self.path(path), args).await
+    }
+
+    fn blocking_create_dir(&self, path: &str, args: OpCreateDir) -> Result<RpCreateDir> {
+        self.inner.blocking_create_dir(&self.path(path), args)
+    }
+
+    fn blocking_read(&self, path: &str, args: OpRead) -> Result<(RpRead, Self::BlockingReader)> {
+        self.inner.blocking_read(&self.path(path), args)
+    }
+
+    fn blocking_write(&self, path: &str, args: OpWrite) -> Result<(RpWrite, Self::BlockingWriter)> {
+        self.inner.blocking_write(&self.path(path), args)
+    }
+
+    fn blocking_copy(&self, from: &str, to: &str, args: OpCopy) -> Result<RpCopy> {
+        self.inner
+            .blocking_copy(&self.path(from), &self.path(to), args)
+    }
+
+    fn blocking_rename(&self, from: &str, to: &str, args: OpRename) -> Result<RpRename> {
+        self.inner
+            .blocking_rename(&self.path(from), &self.path(to), args)
+    }
+
+    fn blocking_stat(&self, path: &str, args: OpStat) -> Result<RpStat> {
+        self.inner.blocking_stat(&self.path(path), args)
+    }
+
+    fn blocking_delete(&self, path: &str, args: OpDelete) -> Result<RpDelete> {
+        self.inner.blocking_delete(&self.path(path), args)
+    }
+
+    fn blocking_list(&self, path: &str, args: OpList) -> Result<(RpList, Self::BlockingPager)> {
+        let (rp, pager) = self.inner.blocking_list(&self.path(path), args)?;
+        Ok((rp, self.pager(pager)))
+    }
+}
+
+/// Strip the prefix from path of inner accessor.
+fn strip_prefix(prefix: &str, path: String) -> String {
+    match path.strip_prefix(prefix) {
+        Some("") => "/".to_string(),
+        Some(v) => v.to_string(),
+        None => path,
+    }
+}
+
+/// PrefixPager strips the prefix from paths of listed entries.
+pub(crate) struct PrefixPager<P> {
+    inner: P,
+    prefix: String,
+}
+
+impl<P> PrefixPager<P> {
+    fn strip(&self, entries: Option<Vec<oio::Entry>>) -> Option<Vec<oio::Entry>> {
+        let mut entries = entries?;
+        for entry in &mut entries {
+            let path = strip_prefix(&self.prefix, entry.path().to_string());
+            entry.set_path(&path);
+        }
+        Some(entries)
+    }
+}
+
+#[async_trait]
+impl<P: oio::Page> oio::Page for PrefixPager<P> {
+    async fn next(&mut self) -> Result<Option<Vec<oio::Entry>>> {
+        let entries = self.inner.next().await?;
+        Ok(self.strip(entries))
+    }
+}
+
+impl<P: oio::BlockingPage> oio::BlockingPage for PrefixPager<P> {
+    fn next(&mut self) -> Result<Option<Vec<oio::Entry>>> {
+        let entries = self.inner.next()?;
+        Ok(self.strip(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use futures::TryStreamExt;
+    use opendal::services::Memory;
+    use opendal::Operator;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prefix_layer() -> Result<()> {
+        let op = Operator::new(Memory::default())?.finish();
+        let prefixed = op.clone().layer(PrefixLayer::new("/db/tbl/"));
+
+        prefixed.write("metadata/v1.metadata.json", "{}").await?;
+        assert!(op.is_exist("db/tbl/metadata/v1.metadata.json").await?);
+        assert_eq!(
+            prefixed.read("/metadata/v1.metadata.json").await?,
+            b"{}".to_vec()
+        );
+        assert_eq!(
+            prefixed
+                .stat("metadata/v1.metadata.json")
+                .await?
+                .content_length(),
+            2
+        );
+
+        op.write("db/other/metadata/v1.metadata.json", "{}").await?;
+        let mut paths = vec![];
+        let mut lister = prefixed.scan("/").await?;
+        while let Some(entry) = lister.try_next().await? {
+            paths.push(entry.path().to_string());
+        }
+        paths.sort();
+        assert_eq!(paths, vec!["metadata/v1.metadata.json"]);
+
+        prefixed.delete("metadata/v1.metadata.json").await?;
+        assert!(!op.is_exist("db/tbl/metadata/v1.metadata.json").await?);
+
+        Ok(())
+    }
+}
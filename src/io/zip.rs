@@ -0,0 +1,72 @@
+//! zip module provides the helpers to mount tables packaged in zip
+//! archives, which is handy for shipping self-contained fixtures.
+
+use std::io::Cursor;
+use std::io::Read;
+
+use opendal::services::Memory;
+use opendal::Operator;
+use zip::ZipArchive;
+
+use crate::Error;
+use crate::ErrorKind;
+use crate::Result;
+
+/// Build an in-memory operator rooted at the table packaged in the zip
+/// archive.
+///
+/// The table root is the parent of the `metadata` directory that contains
+/// table metadata files, so both `metadata/v1.metadata.json` and
+/// `path/to/tbl/metadata/v1.metadata.json` in the archive are supported.
+/// Only files under the table root are extracted.
+pub(crate) async fn build_zip_operator(bs: &[u8]) -> Result<Operator> {
+    let mut archive = ZipArchive::new(Cursor::new(bs)).map_err(|err| {
+        Error::new(ErrorKind::IcebergDataInvalid, "zip archive is invalid").set_source(err)
+    })?;
+
+    let root = archive
+        .file_names()
+        .filter_map(|name| {
+            let (dir, file) = name.rsplit_once('/')?;
+            if !file.ends_with(".metadata.json") {
+                return None;
+            }
+            match dir.strip_suffix("metadata") {
+                Some(root) if root.is_empty() || root.ends_with('/') => Some(root.to_string()),
+                _ => None,
+            }
+        })
+        .min_by_key(|root| root.len())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::IcebergDataInvalid,
+                "no table metadata found in zip archive",
+            )
+        })?;
+
+    let op = Operator::new(Memory::default())?.finish();
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx).map_err(|err| {
+            Error::new(ErrorKind::IcebergDataInvalid, "zip archive is invalid").set_source(err)
+        })?;
+        if !file.is_file() {
+            continue;
+        }
+        let Some(path) = file.name().strip_prefix(&root).map(|v| v.to_string()) else {
+            continue;
+        };
+
+        let mut content = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut content).map_err(|err| {
+            Error::new(
+                ErrorKind::IcebergDataInvalid,
+                "read file in zip archive failed",
+            )
+            .with_context("path", file.name())
+            .set_source(err)
+        })?;
+        op.write(&path, content).await?;
+    }
+
+    Ok(op)
+}
@@ -1,9 +1,22 @@
 mod table;
+pub use table::CheckIssue;
+pub use table::LoadReport;
+pub use table::ManifestSummary;
+pub use table::PropertyChanges;
 pub use table::Table;
+mod scan;
+pub use scan::MissingFileMode;
+pub use scan::ScanReport;
+pub use scan::TableScan;
+mod clock;
+pub use clock::Clock;
+pub use clock::SystemClock;
 mod error;
 pub use error::Error;
 pub use error::ErrorKind;
 pub use error::Result;
 
+pub mod catalog;
 pub mod io;
+pub mod operation;
 pub mod types;
@@ -0,0 +1,447 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use super::SnapshotProducer;
+use super::SnapshotUpdate;
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::parquet_column_bounds;
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::DataFileMetrics;
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::ParquetReaderBuilder;
+use crate::types;
+use crate::Table;
+
+/// AppendOperation adds data files to the table without touching existing
+/// files.
+///
+/// Data files are added as they are written by users, the format of table
+/// property `write.format.default` is not enforced.
+///
+/// Initiate a new operation with `Table::new_append()`.
+pub struct AppendOperation<'a> {
+    table: &'a mut Table,
+    data_files: Vec<types::DataFile>,
+    #[cfg(feature = "io_parquet")]
+    parquet_files: Vec<String>,
+    wap_id: Option<String>,
+}
+
+impl<'a> AppendOperation<'a> {
+    pub(crate) fn new(table: &'a mut Table) -> Self {
+        Self {
+            table,
+            data_files: vec![],
+            #[cfg(feature = "io_parquet")]
+            parquet_files: vec![],
+            wap_id: None,
+        }
+    }
+
+    /// Add a data file to the table.
+    ///
+    /// The partition values of data file must follow the default partition
+    /// spec of the table.
+    pub fn add_data_file(mut self, data_file: types::DataFile) -> Self {
+        self.data_files.push(data_file);
+        self
+    }
+
+    /// Add data files to the table.
+    pub fn add_data_files(mut self, data_files: impl IntoIterator<Item = types::DataFile>) -> Self {
+        self.data_files.extend(data_files);
+        self
+    }
+
+    /// Add an existing parquet file to the table by its full path.
+    ///
+    /// The footer of file is read while committing to fill the record
+    /// count, file size and column metrics of the data file. Partition
+    /// values are derived from bounds of the source columns, so all rows
+    /// of the file must belong to one partition of the default partition
+    /// spec.
+    #[cfg(feature = "io_parquet")]
+    pub fn add_parquet_file(mut self, path: impl Into<String>) -> Self {
+        self.parquet_files.push(path.into());
+        self
+    }
+
+    /// Commit the added data files as a new `append` snapshot.
+    pub async fn commit(self) -> Result<()> {
+        self.apply(false).await?;
+        Ok(())
+    }
+
+    /// Commit the new `append` snapshot as a staged snapshot with the given
+    /// write-audit-publish id, without changing the current snapshot.
+    ///
+    /// Returns the id of staged snapshot, publish it with
+    /// `Table::publish` after auditing.
+    pub async fn stage(mut self, wap_id: &str) -> Result<i64> {
+        self.wap_id = Some(wap_id.to_string());
+        let update = self.apply(false).await?;
+        let snapshot = update
+            .metadata
+            .snapshots
+            .iter()
+            .flatten()
+            .last()
+            .ok_or_else(|| anyhow!("staged snapshot is not found"))?;
+        Ok(snapshot.snapshot_id)
+    }
+
+    /// Produce the new `append` snapshot without writing anything.
+    ///
+    /// Returns the table metadata and manifests which would be committed.
+    pub async fn dry_run(self) -> Result<SnapshotUpdate> {
+        self.apply(true).await
+    }
+
+    async fn apply(self, dry_run: bool) -> Result<SnapshotUpdate> {
+        #[allow(unused_mut)]
+        let mut data_files = self.data_files;
+        #[cfg(feature = "io_parquet")]
+        for path in &self.parquet_files {
+            data_files.push(parquet_data_file(self.table, path).await?);
+        }
+
+        SnapshotProducer::new(self.table)?
+            .with_dry_run(dry_run)
+            .with_wap_id(self.wap_id)
+            .commit_files("append", data_files, |_, _| Ok(false))
+            .await
+    }
+}
+
+/// Build the data file entry of an existing parquet file from its footer.
+#[cfg(feature = "io_parquet")]
+async fn parquet_data_file(table: &Table, path: &str) -> Result<types::DataFile> {
+    let metadata = table.current_table_metadata()?;
+    let schema = metadata.current_schema()?;
+    let spec = metadata.current_partition_spec()?;
+    let op = table.operator();
+
+    let rel_path = table.rel_path(path)?;
+    let file_size = op.stat(&rel_path).await?.content_length();
+    let footer = ParquetReaderBuilder::new(op.clone(), &rel_path)
+        .with_file_size(file_size)
+        .read_metadata()
+        .await
+        .map_err(|err| anyhow!("read footer of parquet file {} failed: {}", path, err))?;
+    let metrics = DataFileMetrics::from_parquet_metadata(&footer, schema);
+    let bounds = parquet_column_bounds(&footer, schema);
+
+    let mut partition = Vec::with_capacity(spec.fields.len());
+    for field in &spec.fields {
+        let null_count = metrics.null_value_counts.get(&field.source_column_id);
+        if null_count == Some(&metrics.record_count) {
+            partition.push(None);
+            continue;
+        }
+        // Values between the bounds are only known to share the partition
+        // if the transform preserves order.
+        let value = match (null_count, bounds.get(&field.source_column_id)) {
+            (Some(0), Some((lower, upper))) if lower == upper => {
+                Some(field.transform.apply(lower)?)
+            }
+            (Some(0), Some((lower, upper)))
+                if !matches!(field.transform, types::Transform::Bucket(_)) =>
+            {
+                let lower = field.transform.apply(lower)?;
+                (lower == field.transform.apply(upper)?).then_some(lower)
+            }
+            _ => None,
+        }
+        .ok_or_else(|| {
+            anyhow!(
+                "partition field {} of parquet file {} can't be derived, rows may belong to multiple partitions",
+                field.name,
+                path
+            )
+        })?;
+        partition.push(value);
+    }
+
+    Ok(types::DataFile {
+        content: types::DataContentType::Data,
+        file_path: path.to_string(),
+        file_format: types::DataFileFormat::Parquet,
+        partition,
+        record_count: metrics.record_count,
+        file_size_in_bytes: file_size as i64,
+        column_sizes: Some(metrics.column_sizes),
+        value_counts: Some(metrics.value_counts),
+        null_value_counts: Some(metrics.null_value_counts),
+        nan_value_counts: None,
+        distinct_counts: None,
+        lower_bounds: Some(metrics.lower_bounds),
+        upper_bounds: Some(metrics.upper_bounds),
+        key_metadata: None,
+        split_offsets: footer
+            .row_groups()
+            .iter()
+            .filter_map(|rg| rg.columns().first().map(|c| c.byte_range().0 as i64))
+            .collect(),
+        equality_ids: None,
+        sort_order_id: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::test_utils::*;
+
+    #[tokio::test]
+    async fn test_append() -> Result<()> {
+        for format_version in ["1", "2"] {
+            let mut table = create_table(format_version).await?;
+
+            table
+                .new_append()
+                .add_data_file(data_file("a", Some("us"), 1))
+                .commit()
+                .await?;
+            table
+                .new_append()
+                .add_data_files([data_file("b", Some("eu"), 2), data_file("c", None, 3)])
+                .commit()
+                .await?;
+
+            assert_eq!(
+                current_data_file_paths(&table).await?,
+                vec![
+                    "memory:///tbl/data/a.parquet",
+                    "memory:///tbl/data/b.parquet",
+                    "memory:///tbl/data/c.parquet",
+                ]
+            );
+            let data_files = table.current_data_files().await?;
+            assert!(data_files.contains(&data_file("c", None, 3)));
+
+            let metadata = table.current_table_metadata()?;
+            assert_eq!(
+                table.current_metadata_path(),
+                Some("metadata/v3.metadata.json")
+            );
+            assert_eq!(metadata.snapshots.as_ref().map(|v| v.len()), Some(2));
+            assert_eq!(metadata.metadata_log.as_ref().map(|v| v.len()), Some(2));
+            let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+            assert_eq!(metadata.current_snapshot_id, Some(snapshot.snapshot_id));
+            assert_eq!(
+                metadata.refs.as_ref().unwrap()["main"].snapshot_id,
+                snapshot.snapshot_id
+            );
+            assert_eq!(snapshot.summary["operation"], "append");
+            assert_eq!(snapshot.summary["added-data-files"], "2");
+            assert_eq!(snapshot.summary["total-data-files"], "3");
+            assert_eq!(snapshot.summary["total-records"], "6");
+            if format_version == "2" {
+                assert_eq!(snapshot.sequence_number, 2);
+                assert_eq!(metadata.last_sequence_number, 2);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_append_partition_summaries() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_files([
+                data_file("a", Some("us"), 1),
+                data_file("b", Some("eu"), 1),
+                data_file("c", None, 1),
+            ])
+            .commit()
+            .await?;
+        table
+            .new_append()
+            .add_data_file(data_file("d", Some("cn"), 1))
+            .commit()
+            .await?;
+
+        let metadata = table.current_table_metadata()?;
+        let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+        let content = table
+            .operator()
+            .read(&table.rel_path(&snapshot.manifest_list)?)
+            .await?;
+        let manifests = types::parse_manifest_list(&content)?;
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(
+            manifests[0].partitions,
+            Some(vec![types::FieldSummary {
+                contains_null: false,
+                contains_nan: Some(false),
+                lower_bound: Some(b"cn".to_vec()),
+                upper_bound: Some(b"cn".to_vec()),
+            }])
+        );
+        assert_eq!(
+            manifests[1].partitions,
+            Some(vec![types::FieldSummary {
+                contains_null: true,
+                contains_nan: Some(false),
+                lower_bound: Some(b"eu".to_vec()),
+                upper_bound: Some(b"us".to_vec()),
+            }])
+        );
+
+        // Bounds are decoded by types of partition fields.
+        let summaries = manifests[1].partition_summaries(
+            metadata.current_partition_spec()?,
+            metadata.current_schema()?,
+        )?;
+        assert_eq!(
+            summaries,
+            Some(vec![types::PartitionFieldSummary {
+                contains_null: true,
+                contains_nan: Some(false),
+                lower_bound: Some(types::Datum::String("eu".to_string())),
+                upper_bound: Some(types::Datum::String("us".to_string())),
+            }])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_append_dry_run() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+        let files = list_files(&table).await?;
+
+        let update = table
+            .new_append()
+            .add_data_file(data_file("b", Some("eu"), 2))
+            .dry_run()
+            .await?;
+
+        // Nothing is written.
+        assert_eq!(list_files(&table).await?, files);
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v2.metadata.json")
+        );
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec!["memory:///tbl/data/a.parquet"]
+        );
+
+        let metadata = &update.metadata;
+        assert_eq!(metadata.snapshots.as_ref().map(|v| v.len()), Some(2));
+        assert_eq!(metadata.metadata_log.as_ref().map(|v| v.len()), Some(2));
+        assert_eq!(metadata.last_sequence_number, 2);
+        let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+        assert_eq!(metadata.current_snapshot_id, Some(snapshot.snapshot_id));
+        assert_eq!(snapshot.summary["added-data-files"], "1");
+        assert_eq!(snapshot.summary["total-records"], "3");
+        assert_eq!(update.manifests.len(), 2);
+        assert_eq!(update.manifests[0].added_files_count, 1);
+        assert_eq!(update.manifests[0].added_rows_count, 2);
+
+        // The table can still be committed after dry run.
+        table
+            .new_append()
+            .add_data_file(data_file("b", Some("eu"), 2))
+            .commit()
+            .await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v3.metadata.json")
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_append_parquet_file() -> Result<()> {
+        use std::sync::Arc;
+
+        use arrow_array::ArrayRef;
+        use arrow_array::Int64Array;
+        use arrow_array::RecordBatch;
+        use arrow_array::StringArray;
+
+        use crate::io::parquet::ParquetWriterBuilder;
+
+        let mut table = create_table("2").await?;
+        let op = table.operator().clone();
+        let schema = table.current_table_metadata()?.current_schema()?.clone();
+        let write = |path: &'static str, regions: Vec<Option<&'static str>>| {
+            let op = op.clone();
+            let schema = schema.clone();
+            async move {
+                let ids: Vec<i64> = (0..regions.len() as i64).collect();
+                let batch = RecordBatch::try_from_iter([
+                    ("id", Arc::new(Int64Array::from(ids)) as ArrayRef),
+                    ("region", Arc::new(StringArray::from(regions)) as ArrayRef),
+                ])?;
+                let mut writer = ParquetWriterBuilder::new(op.writer(path).await?, batch.schema())
+                    .with_table_schema(schema)
+                    .build()?;
+                writer.write(&batch).await?;
+                writer.close().await?;
+                Result::<()>::Ok(())
+            }
+        };
+        write("data/us.parquet", vec![Some("us"); 3]).await?;
+        write("data/null.parquet", vec![None, None]).await?;
+        write("data/mixed.parquet", vec![Some("us"), Some("eu")]).await?;
+
+        table
+            .new_append()
+            .add_parquet_file("memory:///tbl/data/us.parquet")
+            .add_parquet_file("memory:///tbl/data/null.parquet")
+            .commit()
+            .await?;
+
+        let data_files = table.current_data_files().await?;
+        assert_eq!(data_files.len(), 2);
+        let us = data_files
+            .iter()
+            .find(|v| v.file_path == "memory:///tbl/data/us.parquet")
+            .unwrap();
+        assert_eq!(us.partition, vec![Some(types::Datum::String("us".into()))]);
+        assert_eq!(us.record_count, 3);
+        assert_eq!(
+            us.file_size_in_bytes as u64,
+            op.stat("data/us.parquet").await?.content_length()
+        );
+        assert_eq!(us.value_counts.as_ref().unwrap()[&1], 3);
+        assert_eq!(us.null_value_counts.as_ref().unwrap()[&2], 0);
+        assert_eq!(
+            us.lower_bounds.as_ref().unwrap()[&1],
+            types::Datum::Long(0).to_bytes()
+        );
+        assert_eq!(
+            us.upper_bounds.as_ref().unwrap()[&1],
+            types::Datum::Long(2).to_bytes()
+        );
+        let null = data_files
+            .iter()
+            .find(|v| v.file_path == "memory:///tbl/data/null.parquet")
+            .unwrap();
+        assert_eq!(null.partition, vec![None]);
+        assert_eq!(null.record_count, 2);
+
+        // Files with rows of multiple partitions are rejected.
+        let result = table
+            .new_append()
+            .add_parquet_file("memory:///tbl/data/mixed.parquet")
+            .commit()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(table.current_data_files().await?.len(), 2);
+
+        Ok(())
+    }
+}
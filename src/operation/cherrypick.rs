@@ -0,0 +1,410 @@
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+use super::SnapshotProducer;
+use super::SnapshotUpdate;
+use crate::types;
+use crate::Table;
+
+/// CherrypickOperation applies changes of a snapshot which is not an
+/// ancestor of the current snapshot, like a snapshot staged by
+/// write-audit-publish, as a new snapshot on top of the current one.
+///
+/// Files added by the picked snapshot are added again, and files deleted
+/// by it are deleted from the current snapshot. The operation fails if
+/// the changes conflict with the current snapshot: a deleted data file
+/// is no longer live, an added data file is live already, or data files
+/// have been added to partitions of a picked overwrite since its parent.
+///
+/// The new snapshot has the same operation as the picked one, and records
+/// its id in summary as `source-snapshot-id`. If the picked snapshot is
+/// staged with `wap.id`, it's recorded as `published-wap-id`.
+///
+/// Initiate a new operation with `Table::new_cherrypick()`.
+pub struct CherrypickOperation<'a> {
+    table: &'a mut Table,
+    snapshot_id: i64,
+}
+
+impl<'a> CherrypickOperation<'a> {
+    pub(crate) fn new(table: &'a mut Table, snapshot_id: i64) -> Self {
+        Self { table, snapshot_id }
+    }
+
+    /// Commit changes of the picked snapshot as a new snapshot.
+    pub async fn commit(self) -> Result<()> {
+        self.apply(false).await?;
+        Ok(())
+    }
+
+    /// Produce the new snapshot without writing anything.
+    ///
+    /// Returns the table metadata and manifests which would be committed.
+    pub async fn dry_run(self) -> Result<SnapshotUpdate> {
+        self.apply(true).await
+    }
+
+    async fn apply(self, dry_run: bool) -> Result<SnapshotUpdate> {
+        let snapshot_id = self.snapshot_id;
+        let parent_id = self
+            .table
+            .current_table_metadata()?
+            .snapshots
+            .iter()
+            .flatten()
+            .find(|v| v.snapshot_id == snapshot_id)
+            .and_then(|v| v.parent_snapshot_id);
+        // Snapshots committed after the parent of the picked snapshot, which
+        // are all ancestors if the parent is not an ancestor.
+        let mut newer_ids = vec![];
+        let mut reached_parent = false;
+        for ancestor in self.table.ancestors()? {
+            let ancestor = ancestor?;
+            let source = ancestor.summary.get("source-snapshot-id");
+            if ancestor.snapshot_id == snapshot_id
+                || source.is_some_and(|v| *v == snapshot_id.to_string())
+            {
+                return Err(anyhow!(
+                    "snapshot {} has been applied to the current snapshot already",
+                    snapshot_id
+                ));
+            }
+            if Some(ancestor.snapshot_id) == parent_id {
+                reached_parent = true;
+            }
+            if !reached_parent {
+                newer_ids.push(ancestor.snapshot_id);
+            }
+        }
+
+        let mut producer = SnapshotProducer::new(self.table)?.with_dry_run(dry_run);
+        let snapshot = producer.snapshot(snapshot_id)?.clone();
+        let operation = snapshot
+            .summary
+            .get("operation")
+            .cloned()
+            .ok_or_else(|| anyhow!("operation of snapshot {} is unknown", snapshot_id))?;
+        if operation == "replace" {
+            return Err(anyhow!(
+                "snapshot {} only rewrites files, which can't be cherry-picked",
+                snapshot_id
+            ));
+        }
+
+        // Collect changes made by the picked snapshot itself.
+        let mut added = vec![];
+        let mut deleted_paths = HashSet::new();
+        let mut partitions = HashSet::new();
+        for manifest in producer.manifests_of(&snapshot).await? {
+            if manifest.added_snapshot_id != snapshot_id {
+                continue;
+            }
+            for entry in producer.read_manifest(&manifest).await? {
+                if entry.snapshot_id != Some(snapshot_id) {
+                    continue;
+                }
+                if entry.status != types::ManifestStatus::Existing {
+                    partitions.insert((
+                        manifest.partition_spec_id,
+                        entry.data_file.partition.clone(),
+                    ));
+                }
+                match entry.status {
+                    types::ManifestStatus::Added => added.push(entry.data_file),
+                    types::ManifestStatus::Deleted => {
+                        deleted_paths.insert(entry.data_file.file_path);
+                    }
+                    types::ManifestStatus::Existing => {}
+                }
+            }
+        }
+
+        // An overwrite replaces data of its partitions as they were at its
+        // parent, so data added to them since then would be lost silently.
+        if operation == "overwrite" {
+            for id in newer_ids {
+                let newer = producer.snapshot(id)?.clone();
+                for manifest in producer.manifests_of(&newer).await? {
+                    if manifest.added_snapshot_id != id
+                        || manifest.content != types::ManifestContentType::Data
+                    {
+                        continue;
+                    }
+                    for entry in producer.read_manifest(&manifest).await? {
+                        if entry.status == types::ManifestStatus::Added
+                            && entry.snapshot_id == Some(id)
+                            && partitions.contains(&(
+                                manifest.partition_spec_id,
+                                entry.data_file.partition.clone(),
+                            ))
+                        {
+                            return Err(anyhow!(
+                                "cherry-pick conflict: data file {} is added by snapshot {} to partitions overwritten by snapshot {}",
+                                entry.data_file.file_path,
+                                id,
+                                snapshot_id
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut live_paths = HashSet::new();
+        let (manifests, deleted) = producer
+            .write_manifests(&added, |_, data_file| {
+                live_paths.insert(data_file.file_path.clone());
+                Ok(deleted_paths.contains(&data_file.file_path))
+            })
+            .await?;
+
+        // The same path could be live in several entries, so paths are
+        // compared instead of counts.
+        let found: HashSet<_> = deleted.iter().map(|v| v.file_path.as_str()).collect();
+        if let Some(path) = deleted_paths.iter().find(|v| !found.contains(v.as_str())) {
+            return Err(anyhow!(
+                "cherry-pick conflict: data file {} deleted by snapshot {} is not live",
+                path,
+                snapshot_id
+            ));
+        }
+        if let Some(data_file) = added.iter().find(|v| live_paths.contains(&v.file_path)) {
+            return Err(anyhow!(
+                "cherry-pick conflict: data file {} added by snapshot {} is live already",
+                data_file.file_path,
+                snapshot_id
+            ));
+        }
+
+        let mut summary = producer.summary(&operation, &added, &deleted)?;
+        summary.insert("source-snapshot-id".to_string(), snapshot_id.to_string());
+        if let Some(wap_id) = snapshot.summary.get("wap.id") {
+            summary.insert("published-wap-id".to_string(), wap_id.clone());
+        }
+        producer.commit(manifests, summary).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::operation::test_utils::create_table;
+    use crate::operation::test_utils::current_data_file_paths;
+    use crate::operation::test_utils::data_file;
+    use crate::Table;
+
+    /// Move the current snapshot back to its parent, leaving the current
+    /// one out of history like a staged snapshot.
+    async fn stage_current(table: &mut Table) -> Result<i64> {
+        let mut metadata = table.current_table_metadata()?.clone();
+        let staged = metadata.current_snapshot_id.unwrap();
+        let parent = table.ancestors()?.nth(1).unwrap()?.snapshot_id;
+        metadata.current_snapshot_id = Some(parent);
+        metadata
+            .refs
+            .as_mut()
+            .unwrap()
+            .get_mut("main")
+            .unwrap()
+            .snapshot_id = parent;
+        table.commit(metadata).await?;
+        Ok(staged)
+    }
+
+    #[tokio::test]
+    async fn test_cherrypick_append() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 10))
+            .commit()
+            .await?;
+        table
+            .new_append()
+            .add_data_file(data_file("b", Some("eu"), 20))
+            .commit()
+            .await?;
+        let staged = stage_current(&mut table).await?;
+        table
+            .new_append()
+            .add_data_file(data_file("c", Some("us"), 30))
+            .commit()
+            .await?;
+
+        table.cherrypick(staged).await?;
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec![
+                "memory:///tbl/data/a.parquet",
+                "memory:///tbl/data/b.parquet",
+                "memory:///tbl/data/c.parquet",
+            ]
+        );
+        let snapshot = table.ancestors()?.next().unwrap()?;
+        assert_eq!(snapshot.summary["operation"], "append");
+        assert_eq!(snapshot.summary["source-snapshot-id"], staged.to_string());
+        assert_eq!(snapshot.summary["added-data-files"], "1");
+        assert_eq!(snapshot.summary["total-records"], "60");
+
+        // Picking the same snapshot twice is rejected.
+        assert!(table.cherrypick(staged).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cherrypick_conflict() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 10))
+            .add_data_file(data_file("b", Some("eu"), 20))
+            .commit()
+            .await?;
+        table
+            .new_overwrite()
+            .add_data_file(data_file("c", Some("us"), 30))
+            .commit()
+            .await?;
+        let staged = stage_current(&mut table).await?;
+
+        // a has been deleted by others.
+        table
+            .new_delete()
+            .delete_file("memory:///tbl/data/a.parquet")
+            .commit()
+            .await?;
+        let err = table.cherrypick(staged).await.unwrap_err();
+        assert!(err.to_string().contains("is not live"), "{err}");
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec!["memory:///tbl/data/b.parquet"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cherrypick_overwrite_conflict() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 10))
+            .add_data_file(data_file("b", Some("eu"), 20))
+            .commit()
+            .await?;
+        table
+            .new_overwrite()
+            .add_data_file(data_file("c", Some("us"), 30))
+            .commit()
+            .await?;
+        let staged = stage_current(&mut table).await?;
+
+        // Data added to other partitions doesn't conflict.
+        table
+            .new_append()
+            .add_data_file(data_file("d", Some("cn"), 40))
+            .commit()
+            .await?;
+        let update = table.new_cherrypick(staged).dry_run().await?;
+        assert_ne!(&update.metadata, table.current_table_metadata()?);
+
+        // Data added to the overwritten partition would be lost.
+        table
+            .new_append()
+            .add_data_file(data_file("e", Some("us"), 50))
+            .commit()
+            .await?;
+        let err = table.cherrypick(staged).await.unwrap_err();
+        assert!(err.to_string().contains("overwritten"), "{err}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cherrypick_duplicated_entries() -> Result<()> {
+        let mut table = create_table("2").await?;
+        // The same file is live in two entries.
+        for _ in 0..2 {
+            table
+                .new_append()
+                .add_data_file(data_file("a", Some("us"), 10))
+                .commit()
+                .await?;
+        }
+        table
+            .new_delete()
+            .delete_file("memory:///tbl/data/a.parquet")
+            .commit()
+            .await?;
+        let staged = stage_current(&mut table).await?;
+
+        table.cherrypick(staged).await?;
+        assert!(current_data_file_paths(&table).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stage_and_publish() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 10))
+            .commit()
+            .await?;
+
+        let staged = table
+            .new_append()
+            .add_data_file(data_file("b", Some("eu"), 20))
+            .stage("w1")
+            .await?;
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec!["memory:///tbl/data/a.parquet"]
+        );
+        let metadata = table.current_table_metadata()?;
+        let snapshot = metadata.snapshots.iter().flatten().last().unwrap();
+        assert_eq!(snapshot.snapshot_id, staged);
+        assert_eq!(snapshot.summary["wap.id"], "w1");
+
+        // Staged on top of the current snapshot, which is fast-forwarded.
+        table.publish("w1").await?;
+        assert_eq!(
+            table.current_table_metadata()?.current_snapshot_id,
+            Some(staged)
+        );
+        assert!(table.publish("w1").await.is_err());
+
+        // Staged on a stale snapshot, which is cherry-picked.
+        table
+            .new_overwrite()
+            .add_data_file(data_file("c", Some("us"), 30))
+            .stage("w2")
+            .await?;
+        table
+            .new_append()
+            .add_data_file(data_file("d", Some("cn"), 40))
+            .commit()
+            .await?;
+        table.publish("w2").await?;
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec![
+                "memory:///tbl/data/b.parquet",
+                "memory:///tbl/data/c.parquet",
+                "memory:///tbl/data/d.parquet",
+            ]
+        );
+        let snapshot = table.ancestors()?.next().unwrap()?;
+        assert_eq!(snapshot.summary["operation"], "overwrite");
+        assert_eq!(snapshot.summary["published-wap-id"], "w2");
+        assert!(table.publish("w2").await.is_err());
+        assert!(table.publish("w3").await.is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,644 @@
+use std::collections::HashSet;
+#[cfg(feature = "io_parquet")]
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+#[cfg(feature = "io_parquet")]
+use arrow_array::Array;
+#[cfg(feature = "io_parquet")]
+use arrow_array::RecordBatch;
+#[cfg(feature = "io_parquet")]
+use arrow_schema::DataType;
+#[cfg(feature = "io_parquet")]
+use arrow_schema::Field as ArrowField;
+#[cfg(feature = "io_parquet")]
+use arrow_schema::Schema as ArrowSchema;
+#[cfg(feature = "io_parquet")]
+use uuid::Uuid;
+
+use super::SnapshotProducer;
+use super::SnapshotUpdate;
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::array_value;
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::with_table_field_ids;
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::ParquetWriterBuilder;
+#[cfg(feature = "io_parquet")]
+use crate::scan::promote_types;
+use crate::types;
+use crate::Table;
+
+type PartitionFilter<'a> = Box<dyn Fn(&[Option<types::Datum>]) -> bool + Send + Sync + 'a>;
+
+/// DeleteOperation deletes whole data files from the table.
+///
+/// Data files could be deleted by their paths, or by a partition
+/// predicate which deletes all live data files whose partition values
+/// match it. Like overwrite, partition values passed to the predicate are
+/// in the order of fields of the default partition spec.
+///
+/// Rows could be deleted by equality delete files, which are written
+/// along with the snapshot. Rows are never rewritten and no position
+/// delete files are written.
+///
+/// Initiate a new operation with `Table::new_delete()`.
+pub struct DeleteOperation<'a> {
+    table: &'a mut Table,
+    filter: Option<PartitionFilter<'a>>,
+    paths: HashSet<String>,
+    /// Columns and rows of equality deletes to write.
+    #[cfg(feature = "io_parquet")]
+    equality_deletes: Vec<(Vec<String>, RecordBatch)>,
+}
+
+impl<'a> DeleteOperation<'a> {
+    pub(crate) fn new(table: &'a mut Table) -> Self {
+        Self {
+            table,
+            filter: None,
+            paths: HashSet::new(),
+            #[cfg(feature = "io_parquet")]
+            equality_deletes: vec![],
+        }
+    }
+
+    /// Delete the data file with the given path.
+    ///
+    /// The path must be the same as the `file_path` recorded in manifest,
+    /// and the commit fails if it's not a live data file of the table.
+    pub fn delete_file(mut self, path: impl Into<String>) -> Self {
+        self.paths.insert(path.into());
+        self
+    }
+
+    /// Delete all data files whose partition values match the predicate.
+    pub fn delete_where(
+        mut self,
+        filter: impl Fn(&[Option<types::Datum>]) -> bool + Send + Sync + 'a,
+    ) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Delete rows whose values of `columns` equal one of `rows`.
+    ///
+    /// Equality delete files are written with ids of `columns` as their
+    /// equality ids, one for each partition of `rows`. So `rows` must
+    /// contain `columns`, which must contain source columns of the default
+    /// partition spec. Only v2 tables support equality deletes.
+    ///
+    /// Delete files share the sequence number of the new snapshot, so they
+    /// apply to rows committed before, but not to rows committed after.
+    #[cfg(feature = "io_parquet")]
+    pub fn equality_delete(mut self, columns: &[&str], rows: RecordBatch) -> Self {
+        self.equality_deletes
+            .push((columns.iter().map(|v| v.to_string()).collect(), rows));
+        self
+    }
+
+    /// Commit the deletion as a new `delete` snapshot.
+    pub async fn commit(self) -> Result<()> {
+        self.apply(false).await?;
+        Ok(())
+    }
+
+    /// Produce the new `delete` snapshot without writing anything.
+    ///
+    /// Returns the table metadata and manifests which would be committed.
+    pub async fn dry_run(self) -> Result<SnapshotUpdate> {
+        self.apply(true).await
+    }
+
+    async fn apply(self, dry_run: bool) -> Result<SnapshotUpdate> {
+        let op = self.table.operator().clone();
+        let mut written = vec![];
+        let result = self.produce(dry_run, &mut written).await;
+        // Written delete files are not referenced by any snapshot if the
+        // commit fails, remove them instead of leaving orphans.
+        if result.is_err() {
+            for path in written {
+                if let Err(err) = op.delete(&path).await {
+                    log::warn!("remove equality delete file {} failed: {}", path, err);
+                }
+            }
+        }
+        result
+    }
+
+    /// Produce the new `delete` snapshot, paths of written delete files are
+    /// pushed into `written`.
+    async fn produce(
+        self,
+        dry_run: bool,
+        #[cfg_attr(
+            not(feature = "io_parquet"),
+            allow(unused_variables, clippy::ptr_arg)
+        )]
+        written: &mut Vec<String>,
+    ) -> Result<SnapshotUpdate> {
+        let default_spec_id = self.table.current_table_metadata()?.default_spec_id;
+        #[cfg(feature = "io_parquet")]
+        let added = self.write_equality_deletes(dry_run, written).await?;
+        #[cfg(not(feature = "io_parquet"))]
+        let added = vec![];
+        let paths = self.paths;
+        let filter = self.filter;
+
+        let mut producer = SnapshotProducer::new(self.table)?.with_dry_run(dry_run);
+        let (manifests, deleted) = producer
+            .write_manifests(&added, |manifest, data_file| {
+                if paths.contains(&data_file.file_path) {
+                    return Ok(true);
+                }
+                let Some(filter) = &filter else {
+                    return Ok(false);
+                };
+                if manifest.partition_spec_id != default_spec_id {
+                    return Err(anyhow!(
+                        "delete can't evaluate data file {} of partition spec {}",
+                        data_file.file_path,
+                        manifest.partition_spec_id
+                    ));
+                }
+                Ok(filter(&data_file.partition))
+            })
+            .await?;
+
+        let deleted_paths: HashSet<_> = deleted.iter().map(|v| v.file_path.as_str()).collect();
+        if let Some(path) = paths.iter().find(|v| !deleted_paths.contains(v.as_str())) {
+            return Err(anyhow!("data file {} is not found in table", path));
+        }
+
+        let summary = producer.summary("delete", &added, &deleted)?;
+        producer.commit(manifests, summary).await
+    }
+
+    /// Write equality delete files of the default partition spec, paths of
+    /// written files are pushed into `written`.
+    ///
+    /// Columns are written with names and field ids of the table schema.
+    /// In dry-run mode, files are not written and their sizes are estimated
+    /// by the memory size of rows.
+    #[cfg(feature = "io_parquet")]
+    async fn write_equality_deletes(
+        &self,
+        dry_run: bool,
+        written: &mut Vec<String>,
+    ) -> Result<Vec<types::DataFile>> {
+        if self.equality_deletes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let metadata = self.table.current_table_metadata()?;
+        if metadata.format_version != types::TableFormatVersion::V2 {
+            return Err(anyhow!("equality deletes are only supported by v2 tables"));
+        }
+        let schema = metadata.current_schema()?;
+        let spec = metadata
+            .partition_specs
+            .iter()
+            .find(|v| v.spec_id == metadata.default_spec_id)
+            .ok_or_else(|| anyhow!("partition spec {} is not found", metadata.default_spec_id))?;
+        let op = self.table.operator();
+
+        let commit_uuid = Uuid::new_v4();
+        let mut added = vec![];
+        for (columns, rows) in &self.equality_deletes {
+            let mut equality_ids = Vec::with_capacity(columns.len());
+            let mut types = Vec::with_capacity(columns.len());
+            let mut indices = Vec::with_capacity(columns.len());
+            let mut fields = Vec::with_capacity(columns.len());
+            for column in columns {
+                let field = schema
+                    .fields
+                    .iter()
+                    .find(|v| v.name == *column)
+                    .ok_or_else(|| anyhow!("column {} is not found in schema", column))?;
+                let types::Any::Primitive(typ) = &field.field_type else {
+                    return Err(anyhow!(
+                        "column {} of equality deletes must be primitive",
+                        column
+                    ));
+                };
+                let idx = rows.schema().index_of(column).map_err(|_| {
+                    anyhow!("column {} is not found in equality delete rows", column)
+                })?;
+                equality_ids.push(field.id);
+                types.push(typ);
+                indices.push(idx);
+                fields.push(field.clone());
+            }
+            let rows = equality_delete_rows(rows.project(&indices)?, fields)?;
+
+            // Columns of rows to compute partition values from.
+            let mut sources = Vec::with_capacity(spec.fields.len());
+            for field in &spec.fields {
+                let idx = equality_ids
+                    .iter()
+                    .position(|v| *v == field.source_column_id)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "columns of equality deletes must contain the source column of partition field {}",
+                            field.name
+                        )
+                    })?;
+                sources.push((idx, &field.transform));
+            }
+            let mut partitions = Vec::with_capacity(rows.num_rows());
+            for row in 0..rows.num_rows() {
+                let mut partition = Vec::with_capacity(sources.len());
+                for (idx, transform) in &sources {
+                    let array = rows.column(*idx);
+                    if array.is_null(row) {
+                        partition.push(None);
+                        continue;
+                    }
+                    let value = array_value(array.as_ref(), row, types[*idx]).ok_or_else(|| {
+                        anyhow!(
+                            "value of column {} can't be converted to {:?}",
+                            columns[*idx],
+                            types[*idx]
+                        )
+                    })?;
+                    partition.push(transform.apply(&value)?);
+                }
+                partitions.push(partition);
+            }
+
+            // Group runs of rows with the same partition values by slicing.
+            let mut groups: Vec<(Vec<Option<types::Datum>>, Vec<RecordBatch>)> = vec![];
+            let mut start = 0;
+            for row in 1..=partitions.len() {
+                if row < partitions.len() && partitions[row] == partitions[start] {
+                    continue;
+                }
+                let batch = rows.slice(start, row - start);
+                match groups.iter_mut().find(|(v, _)| *v == partitions[start]) {
+                    Some((_, batches)) => batches.push(batch),
+                    None => groups.push((partitions[start].clone(), vec![batch])),
+                }
+                start = row;
+            }
+
+            for (partition, batches) in groups {
+                let path = format!("data/{commit_uuid}-eq-{}.parquet", added.len());
+                let mut data_file = types::DataFile {
+                    content: types::DataContentType::EqualityDeletes,
+                    file_path: format!("{}/{path}", metadata.location),
+                    file_format: types::DataFileFormat::Parquet,
+                    partition,
+                    record_count: batches.iter().map(|v| v.num_rows() as i64).sum(),
+                    file_size_in_bytes: batches
+                        .iter()
+                        .map(|v| v.get_array_memory_size() as i64)
+                        .sum(),
+                    column_sizes: None,
+                    value_counts: None,
+                    null_value_counts: None,
+                    nan_value_counts: None,
+                    distinct_counts: None,
+                    lower_bounds: None,
+                    upper_bounds: None,
+                    key_metadata: None,
+                    split_offsets: vec![],
+                    equality_ids: Some(equality_ids.clone()),
+                    sort_order_id: None,
+                };
+                if !dry_run {
+                    written.push(path.clone());
+                    let mut writer =
+                        ParquetWriterBuilder::new(op.writer(&path).await?, rows.schema())
+                            .with_table_schema(schema.clone())
+                            .build()?;
+                    for batch in &batches {
+                        writer.write(batch).await?;
+                    }
+                    let metrics = writer.close_with_metrics().await?;
+
+                    data_file.record_count = metrics.record_count;
+                    data_file.file_size_in_bytes = op.stat(&path).await?.content_length() as i64;
+                    data_file.column_sizes = Some(metrics.column_sizes);
+                    data_file.value_counts = Some(metrics.value_counts);
+                    data_file.null_value_counts = Some(metrics.null_value_counts);
+                    data_file.nan_value_counts = Some(metrics.nan_value_counts);
+                    data_file.lower_bounds = Some(metrics.lower_bounds);
+                    data_file.upper_bounds = Some(metrics.upper_bounds);
+                }
+                added.push(data_file);
+            }
+        }
+
+        Ok(added)
+    }
+}
+
+/// Build rows of equality delete file from the given rows, whose columns
+/// are the ones of fields in order.
+///
+/// Columns are renamed to names of fields with field ids attached, so that
+/// readers can resolve them by `equality_ids`. Their types are promoted to
+/// the ones of fields, and other types are rejected.
+#[cfg(feature = "io_parquet")]
+fn equality_delete_rows(rows: RecordBatch, fields: Vec<types::Field>) -> Result<RecordBatch> {
+    let mut arrow_fields = Vec::with_capacity(fields.len());
+    for (field, column) in fields.iter().zip(rows.columns()) {
+        arrow_fields.push(with_table_field_ids(
+            ArrowField::new(&field.name, column.data_type().clone(), !field.required),
+            field,
+        ));
+    }
+    let rows = RecordBatch::try_new(
+        Arc::new(ArrowSchema::new(arrow_fields)),
+        rows.columns().to_vec(),
+    )?;
+    let schema = types::Schema {
+        schema_id: 0,
+        identifier_field_ids: None,
+        fields,
+    };
+    let rows = promote_types(rows, &schema)?;
+
+    let mut arrow_fields = Vec::with_capacity(schema.fields.len());
+    for (field, column) in schema.fields.iter().zip(rows.columns()) {
+        let data_type = DataType::try_from(field.field_type.clone())?;
+        if column.data_type() != &data_type {
+            return Err(anyhow!(
+                "column {} of type {} can't be written as {}",
+                field.name,
+                column.data_type(),
+                data_type
+            ));
+        }
+        arrow_fields.push(with_table_field_ids(
+            ArrowField::new(&field.name, data_type, !field.required),
+            field,
+        ));
+    }
+    Ok(RecordBatch::try_new(
+        Arc::new(ArrowSchema::new(arrow_fields)),
+        rows.columns().to_vec(),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::test_utils::*;
+
+    #[tokio::test]
+    async fn test_delete() -> Result<()> {
+        for format_version in ["1", "2"] {
+            let mut table = create_table(format_version).await?;
+            table
+                .new_append()
+                .add_data_files([
+                    data_file("a", Some("us"), 1),
+                    data_file("b", Some("eu"), 2),
+                    data_file("c", Some("us"), 3),
+                    data_file("d", None, 4),
+                ])
+                .commit()
+                .await?;
+
+            table
+                .new_delete()
+                .delete_where(|v| v[0].is_none())
+                .delete_file("memory:///tbl/data/a.parquet")
+                .commit()
+                .await?;
+
+            assert_eq!(
+                current_data_file_paths(&table).await?,
+                vec![
+                    "memory:///tbl/data/b.parquet",
+                    "memory:///tbl/data/c.parquet",
+                ]
+            );
+
+            let metadata = table.current_table_metadata()?;
+            let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+            assert_eq!(snapshot.summary["operation"], "delete");
+            assert_eq!(snapshot.summary["deleted-data-files"], "2");
+            assert_eq!(snapshot.summary["deleted-records"], "5");
+            assert_eq!(snapshot.summary["total-records"], "5");
+            assert!(!snapshot.summary.contains_key("added-data-files"));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_delete_equality_delete() -> Result<()> {
+        use std::sync::Arc;
+
+        use arrow_array::ArrayRef;
+        use arrow_array::Int64Array;
+        use arrow_array::StringArray;
+
+        let rows = RecordBatch::try_from_iter([
+            (
+                "region",
+                Arc::new(StringArray::from(vec!["us", "eu", "us"])) as ArrayRef,
+            ),
+            ("id", Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef),
+        ])?;
+
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_files([data_file("a", Some("us"), 2), data_file("b", Some("eu"), 1)])
+            .commit()
+            .await?;
+        table
+            .new_delete()
+            .equality_delete(&["id", "region"], rows.clone())
+            .commit()
+            .await?;
+
+        let metadata = table.current_table_metadata()?;
+        let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+        assert_eq!(snapshot.sequence_number, 2);
+        assert_eq!(snapshot.summary["added-equality-delete-files"], "2");
+        assert_eq!(snapshot.summary["added-equality-deletes"], "3");
+        assert_eq!(snapshot.summary["total-delete-files"], "2");
+        assert_eq!(snapshot.summary["total-equality-deletes"], "3");
+        assert_eq!(snapshot.summary["total-records"], "3");
+
+        // Deletes are committed with the sequence number of the snapshot,
+        // which is larger than the one of data files.
+        let manifests =
+            types::parse_manifest_list(&table.read_raw(&snapshot.manifest_list).await?)?;
+        assert_eq!(manifests.len(), 2);
+        for manifest in &manifests {
+            let expected = match manifest.content {
+                types::ManifestContentType::Data => 1,
+                types::ManifestContentType::Deletes => 2,
+            };
+            assert_eq!(manifest.sequence_number, expected);
+        }
+
+        let mut deletes: Vec<_> = table
+            .current_data_files()
+            .await?
+            .into_iter()
+            .filter(|v| v.content == types::DataContentType::EqualityDeletes)
+            .collect();
+        deletes.sort_by_key(|v| v.record_count);
+        assert_eq!(deletes.len(), 2);
+        assert_eq!(
+            deletes[0].partition,
+            vec![Some(types::Datum::String("eu".to_string()))]
+        );
+        assert_eq!(deletes[0].record_count, 1);
+        assert_eq!(
+            deletes[1].partition,
+            vec![Some(types::Datum::String("us".to_string()))]
+        );
+        assert_eq!(deletes[1].record_count, 2);
+        assert_eq!(deletes[1].equality_ids, Some(vec![1, 2]));
+        assert_eq!(
+            deletes[1].lower_bound(1, &types::Primitive::Long)?,
+            Some(types::Datum::Long(1))
+        );
+        assert_eq!(
+            deletes[1].upper_bound(1, &types::Primitive::Long)?,
+            Some(types::Datum::Long(3))
+        );
+
+        // Columns are written with field ids of table schema.
+        let mut reader = crate::io::parquet::ParquetReaderBuilder::new(
+            table.operator().clone(),
+            &table.rel_path(&deletes[1].file_path)?,
+        )
+        .build()
+        .await?;
+        let batch = reader.next().await?.unwrap();
+        let columns: Vec<_> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|v| {
+                (
+                    v.name().clone(),
+                    v.metadata()
+                        .get(crate::io::parquet::FIELD_ID_META_KEY)
+                        .cloned(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("id".to_string(), Some("1".to_string())),
+                ("region".to_string(), Some("2".to_string()))
+            ]
+        );
+
+        // Partition source columns must be part of the equality columns.
+        let result = table
+            .new_delete()
+            .equality_delete(&["id"], rows.clone())
+            .commit()
+            .await;
+        assert!(result.is_err());
+
+        let mut table = create_table("1").await?;
+        let result = table
+            .new_delete()
+            .equality_delete(&["id", "region"], rows)
+            .commit()
+            .await;
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_delete_equality_delete_failed() -> Result<()> {
+        use std::sync::Arc;
+
+        use arrow_array::ArrayRef;
+        use arrow_array::Int32Array;
+        use arrow_array::StringArray;
+
+        // Ids are promoted to long of the table schema.
+        let rows = RecordBatch::try_from_iter([
+            ("id", Arc::new(Int32Array::from(vec![1])) as ArrayRef),
+            (
+                "region",
+                Arc::new(StringArray::from(vec!["us"])) as ArrayRef,
+            ),
+        ])?;
+
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+        let data_files = |files: Vec<String>| {
+            files
+                .into_iter()
+                .filter(|v| v.starts_with("data/"))
+                .collect::<Vec<_>>()
+        };
+        let files = data_files(list_files(&table).await?);
+
+        // Written delete files are removed if the commit fails.
+        let result = table
+            .new_delete()
+            .equality_delete(&["id", "region"], rows.clone())
+            .delete_file("memory:///tbl/data/x.parquet")
+            .commit()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(data_files(list_files(&table).await?), files);
+
+        table
+            .new_delete()
+            .equality_delete(&["id", "region"], rows)
+            .commit()
+            .await?;
+        let deletes: Vec<_> = table
+            .current_data_files()
+            .await?
+            .into_iter()
+            .filter(|v| v.content == types::DataContentType::EqualityDeletes)
+            .collect();
+        assert_eq!(deletes.len(), 1);
+        assert_eq!(
+            deletes[0].lower_bound(1, &types::Primitive::Long)?,
+            Some(types::Datum::Long(1))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_file_not_found() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+
+        let result = table
+            .new_delete()
+            .delete_file("memory:///tbl/data/a.parquet")
+            .delete_file("memory:///tbl/data/x.parquet")
+            .commit()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec!["memory:///tbl/data/a.parquet"]
+        );
+
+        Ok(())
+    }
+}
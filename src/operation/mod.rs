@@ -0,0 +1,166 @@
+//! operation module provides the operations to update iceberg tables by
+//! committing new snapshots.
+
+use crate::types;
+
+/// SnapshotUpdate is the result of a write operation: the new table
+/// metadata and manifests of its current snapshot.
+///
+/// It's returned by `dry_run()` of operations to inspect what would be
+/// committed, in which case the manifests and manifest list it refers to
+/// are not written.
+#[derive(Debug, Clone)]
+pub struct SnapshotUpdate {
+    /// The new table metadata.
+    pub metadata: types::TableMetadata,
+    /// Manifests of the current snapshot in the new metadata.
+    pub manifests: Vec<types::ManifestList>,
+}
+
+mod snapshot_producer;
+pub(crate) use snapshot_producer::set_current_snapshot;
+pub(crate) use snapshot_producer::SnapshotProducer;
+
+mod append;
+pub use append::AppendOperation;
+
+mod overwrite;
+pub use overwrite::OverwriteOperation;
+
+mod delete;
+pub use delete::DeleteOperation;
+
+mod rewrite_manifests;
+pub use rewrite_manifests::RewriteManifestsOperation;
+
+mod cherrypick;
+pub use cherrypick::CherrypickOperation;
+
+mod update_schema;
+pub use update_schema::UpdateSchemaOperation;
+
+#[cfg(feature = "io_parquet")]
+mod rewrite;
+#[cfg(feature = "io_parquet")]
+pub use rewrite::RewriteOperation;
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use futures::TryStreamExt;
+    use opendal::services::Memory;
+    use opendal::Operator;
+
+    use crate::types;
+    use crate::Table;
+
+    /// Create an empty table in memory, which is partitioned by the
+    /// identity of `region` column.
+    pub async fn create_table(format_version: &str) -> Result<Table> {
+        let op = Operator::new(Memory::default())?.finish();
+
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![
+                types::Field {
+                    id: 1,
+                    name: "id".to_string(),
+                    required: true,
+                    field_type: types::Any::Primitive(types::Primitive::Long),
+                    comment: None,
+                },
+                types::Field {
+                    id: 2,
+                    name: "region".to_string(),
+                    required: false,
+                    field_type: types::Any::Primitive(types::Primitive::String),
+                    comment: None,
+                },
+            ],
+        };
+        let partition_spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: vec![types::PartitionField {
+                source_column_id: 2,
+                partition_field_id: 1000,
+                transform: types::Transform::Identity,
+                name: "region".to_string(),
+            }],
+        };
+        let properties =
+            HashMap::from([("format-version".to_string(), format_version.to_string())]);
+
+        Table::create(op, "memory:///tbl", schema, partition_spec, properties).await
+    }
+
+    /// Build a data file of the given region.
+    pub fn data_file(name: &str, region: Option<&str>, record_count: i64) -> types::DataFile {
+        types::DataFile {
+            content: types::DataContentType::Data,
+            file_path: format!("memory:///tbl/data/{name}.parquet"),
+            file_format: types::DataFileFormat::Parquet,
+            partition: vec![region.map(|v| types::Datum::String(v.to_string()))],
+            record_count,
+            file_size_in_bytes: record_count * 10,
+            column_sizes: None,
+            value_counts: None,
+            null_value_counts: None,
+            nan_value_counts: None,
+            distinct_counts: None,
+            lower_bounds: None,
+            upper_bounds: None,
+            key_metadata: None,
+            split_offsets: vec![],
+            equality_ids: None,
+            sort_order_id: None,
+        }
+    }
+
+    /// Write the batch into a parquet data file of the given region.
+    #[cfg(feature = "io_parquet")]
+    pub async fn write_file(
+        table: &Table,
+        name: &str,
+        region: Option<&str>,
+        batch: arrow_array::RecordBatch,
+    ) -> Result<types::DataFile> {
+        let path = format!("data/{name}.parquet");
+        let mut writer = crate::io::parquet::ParquetWriterBuilder::new(
+            table.operator().writer(&path).await?,
+            batch.schema(),
+        )
+        .build()?;
+        writer.write(&batch).await?;
+        writer.close().await?;
+
+        let mut data_file = data_file(name, region, batch.num_rows() as i64);
+        data_file.file_size_in_bytes = table.operator().stat(&path).await?.content_length() as i64;
+        Ok(data_file)
+    }
+
+    /// Get the sorted paths of current data files.
+    pub async fn current_data_file_paths(table: &Table) -> Result<Vec<String>> {
+        let mut paths: Vec<_> = table
+            .current_data_files()
+            .await?
+            .into_iter()
+            .map(|v| v.file_path)
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// List the sorted paths of all files in table location.
+    pub async fn list_files(table: &Table) -> Result<Vec<String>> {
+        let mut paths = vec![];
+        let mut lister = table.operator().scan("/").await?;
+        while let Some(entry) = lister.try_next().await? {
+            paths.push(entry.path().to_string());
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
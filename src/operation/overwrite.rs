@@ -0,0 +1,284 @@
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+use super::SnapshotProducer;
+use super::SnapshotUpdate;
+use crate::types;
+use crate::Table;
+
+type PartitionFilter<'a> = Box<dyn Fn(&[Option<types::Datum>]) -> bool + Send + Sync + 'a>;
+
+/// OverwriteOperation replaces data files of the matching partitions with
+/// new data files in a single snapshot.
+///
+/// - With an overwrite filter, all live data files whose partition values
+///   match the filter are deleted.
+/// - Without an overwrite filter, all partitions of the new data files are
+///   replaced, which is known as dynamic partition overwrite.
+///
+/// Partition values passed to the filter are in the order of fields of
+/// the default partition spec. Data files written with other partition
+/// specs can't be evaluated, the commit fails if any of them is live.
+///
+/// Initiate a new operation with `Table::new_overwrite()`.
+pub struct OverwriteOperation<'a> {
+    table: &'a mut Table,
+    filter: Option<PartitionFilter<'a>>,
+    data_files: Vec<types::DataFile>,
+    wap_id: Option<String>,
+}
+
+impl<'a> OverwriteOperation<'a> {
+    pub(crate) fn new(table: &'a mut Table) -> Self {
+        Self {
+            table,
+            filter: None,
+            data_files: vec![],
+            wap_id: None,
+        }
+    }
+
+    /// Set the partition predicate to decide which data files to delete.
+    ///
+    /// All added data files must match the filter too.
+    pub fn overwrite_filter(
+        mut self,
+        filter: impl Fn(&[Option<types::Datum>]) -> bool + Send + Sync + 'a,
+    ) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Add a data file to the table.
+    ///
+    /// The partition values of data file must follow the default partition
+    /// spec of the table.
+    pub fn add_data_file(mut self, data_file: types::DataFile) -> Self {
+        self.data_files.push(data_file);
+        self
+    }
+
+    /// Add data files to the table.
+    pub fn add_data_files(mut self, data_files: impl IntoIterator<Item = types::DataFile>) -> Self {
+        self.data_files.extend(data_files);
+        self
+    }
+
+    /// Commit the overwrite as a new `overwrite` snapshot.
+    pub async fn commit(self) -> Result<()> {
+        self.apply(false).await?;
+        Ok(())
+    }
+
+    /// Commit the new `overwrite` snapshot as a staged snapshot with the given
+    /// write-audit-publish id, without changing the current snapshot.
+    ///
+    /// Returns the id of staged snapshot, publish it with
+    /// `Table::publish` after auditing.
+    pub async fn stage(mut self, wap_id: &str) -> Result<i64> {
+        self.wap_id = Some(wap_id.to_string());
+        let update = self.apply(false).await?;
+        let snapshot = update
+            .metadata
+            .snapshots
+            .iter()
+            .flatten()
+            .last()
+            .ok_or_else(|| anyhow!("staged snapshot is not found"))?;
+        Ok(snapshot.snapshot_id)
+    }
+
+    /// Produce the new `overwrite` snapshot without writing anything.
+    ///
+    /// Returns the table metadata and manifests which would be committed.
+    pub async fn dry_run(self) -> Result<SnapshotUpdate> {
+        self.apply(true).await
+    }
+
+    async fn apply(self, dry_run: bool) -> Result<SnapshotUpdate> {
+        let default_spec_id = self.table.current_table_metadata()?.default_spec_id;
+
+        let filter: PartitionFilter<'_> = match self.filter {
+            Some(filter) => {
+                if let Some(v) = self.data_files.iter().find(|v| !filter(&v.partition)) {
+                    return Err(anyhow!(
+                        "data file {} doesn't match the overwrite filter",
+                        v.file_path
+                    ));
+                }
+                filter
+            }
+            None => {
+                let partitions: HashSet<_> = self
+                    .data_files
+                    .iter()
+                    .map(|v| v.partition.clone())
+                    .collect();
+                Box::new(move |v| partitions.contains(v))
+            }
+        };
+
+        SnapshotProducer::new(self.table)?
+            .with_dry_run(dry_run)
+            .with_wap_id(self.wap_id)
+            .commit_files("overwrite", self.data_files, |manifest, data_file| {
+                if manifest.partition_spec_id != default_spec_id {
+                    return Err(anyhow!(
+                        "overwrite can't evaluate data file {} of partition spec {}",
+                        data_file.file_path,
+                        manifest.partition_spec_id
+                    ));
+                }
+                Ok(filter(&data_file.partition))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::test_utils::*;
+
+    #[tokio::test]
+    async fn test_overwrite_with_filter() -> Result<()> {
+        for format_version in ["1", "2"] {
+            let mut table = create_table(format_version).await?;
+            table
+                .new_append()
+                .add_data_files([
+                    data_file("a", Some("us"), 1),
+                    data_file("b", Some("eu"), 2),
+                    data_file("c", Some("us"), 3),
+                ])
+                .commit()
+                .await?;
+
+            let us = Some(types::Datum::String("us".to_string()));
+            table
+                .new_overwrite()
+                .overwrite_filter(|v| v[0] == us)
+                .add_data_file(data_file("d", Some("us"), 4))
+                .commit()
+                .await?;
+
+            assert_eq!(
+                current_data_file_paths(&table).await?,
+                vec![
+                    "memory:///tbl/data/b.parquet",
+                    "memory:///tbl/data/d.parquet",
+                ]
+            );
+
+            let metadata = table.current_table_metadata()?;
+            let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+            assert_eq!(snapshot.summary["operation"], "overwrite");
+            assert_eq!(snapshot.summary["deleted-data-files"], "2");
+            assert_eq!(snapshot.summary["deleted-records"], "4");
+            assert_eq!(snapshot.summary["total-data-files"], "2");
+            assert_eq!(snapshot.summary["total-records"], "6");
+
+            // Overwritten files are marked as deleted in the rewritten manifest.
+            let content = table
+                .operator()
+                .read(&table.rel_path(&snapshot.manifest_list)?)
+                .await?;
+            let manifests = types::parse_manifest_list(&content)?;
+            assert_eq!(manifests.len(), 2);
+            let rewritten = &manifests[1];
+            assert_eq!(rewritten.added_snapshot_id, snapshot.snapshot_id);
+            assert_eq!(rewritten.existing_files_count, 1);
+            assert_eq!(rewritten.deleted_files_count, 2);
+
+            let content = table
+                .operator()
+                .read(&table.rel_path(&rewritten.manifest_path)?)
+                .await?;
+            let (_, entries) = types::parse_manifest_file(&content)?;
+            let mut statuses: Vec<_> = entries
+                .iter()
+                .map(|v| (v.data_file.file_path.as_str(), v.status))
+                .collect();
+            statuses.sort_by_key(|v| v.0);
+            assert_eq!(
+                statuses,
+                vec![
+                    (
+                        "memory:///tbl/data/a.parquet",
+                        types::ManifestStatus::Deleted
+                    ),
+                    (
+                        "memory:///tbl/data/b.parquet",
+                        types::ManifestStatus::Existing
+                    ),
+                    (
+                        "memory:///tbl/data/c.parquet",
+                        types::ManifestStatus::Deleted
+                    ),
+                ]
+            );
+            for entry in entries {
+                if entry.status == types::ManifestStatus::Deleted {
+                    assert_eq!(entry.snapshot_id, Some(snapshot.snapshot_id));
+                } else {
+                    assert_ne!(entry.snapshot_id, Some(snapshot.snapshot_id));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_dynamic_partitions() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_files([
+                data_file("a", Some("us"), 1),
+                data_file("b", Some("eu"), 2),
+                data_file("c", None, 3),
+            ])
+            .commit()
+            .await?;
+
+        table
+            .new_overwrite()
+            .add_data_files([data_file("d", Some("eu"), 4), data_file("e", None, 5)])
+            .commit()
+            .await?;
+
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec![
+                "memory:///tbl/data/a.parquet",
+                "memory:///tbl/data/d.parquet",
+                "memory:///tbl/data/e.parquet",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_file_not_match_filter() -> Result<()> {
+        let mut table = create_table("2").await?;
+
+        let us = Some(types::Datum::String("us".to_string()));
+        let result = table
+            .new_overwrite()
+            .overwrite_filter(|v| v[0] == us)
+            .add_data_file(data_file("a", Some("eu"), 1))
+            .commit()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v1.metadata.json")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1051 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use arrow_array::new_null_array;
+use arrow_array::RecordBatch;
+use arrow_schema::DataType;
+use arrow_schema::Field;
+use arrow_schema::Schema as ArrowSchema;
+use arrow_schema::SchemaRef;
+use futures::TryStreamExt;
+use parquet::basic::Compression;
+use parquet::basic::GzipLevel;
+use parquet::basic::ZstdLevel;
+use parquet::file::properties::WriterProperties;
+use uuid::Uuid;
+
+use super::SnapshotProducer;
+use super::SnapshotUpdate;
+use crate::io::parquet::cast_field_metadata;
+use crate::io::parquet::with_table_field_ids;
+use crate::io::parquet::ParquetReaderBuilder;
+use crate::io::parquet::ParquetWriter;
+use crate::io::parquet::ParquetWriterBuilder;
+use crate::io::parquet::FIELD_ID_META_KEY;
+use crate::scan::promote_types;
+use crate::types;
+use crate::Table;
+
+type PartitionFilter<'a> = Box<dyn Fn(&[Option<types::Datum>]) -> bool + Send + Sync + 'a>;
+
+/// A candidate data file along with its data sequence number, which is
+/// `None` in v1 tables.
+type Candidate = (types::DataFile, Option<i64>);
+
+/// The default value of table property `write.target-file-size-bytes`.
+const DEFAULT_TARGET_FILE_SIZE_BYTES: i64 = 512 * 1024 * 1024;
+
+/// The default value of table property `write.parquet.compression-codec`.
+const DEFAULT_PARQUET_COMPRESSION: Compression = Compression::SNAPPY;
+
+/// RewriteOperation compacts small data files into larger ones without
+/// changing the data of table.
+///
+/// Parquet data files of the default partition spec whose partition values
+/// match the filter and whose sizes are smaller than the target file size
+/// are candidates. Candidates of the same partition are packed into groups
+/// no larger than the target file size, and each group with more than one
+/// file is rewritten into a new data file.
+///
+/// Data files with pending position or equality deletes are never
+/// rewritten, and new data files keep the data sequence numbers of
+/// rewritten ones, so that deleted rows stay deleted. Rows are written in
+/// the current schema, columns of older files are promoted and missing
+/// optional columns are filled with nulls.
+///
+/// New data files are always written in parquet, compressed by table
+/// property `write.parquet.compression-codec`, which is `snappy` by
+/// default. Tables whose `write.format.default` is another format are
+/// rejected instead of being rewritten in parquet.
+///
+/// Before committing, the table is refreshed to make sure all rewritten
+/// files are still live, otherwise the commit fails without changing the
+/// table.
+///
+/// Initiate a new operation with `Table::rewrite_data_files()`.
+pub struct RewriteOperation<'a> {
+    table: &'a mut Table,
+    filter: PartitionFilter<'a>,
+    target_file_size_bytes: Option<i64>,
+}
+
+impl<'a> RewriteOperation<'a> {
+    pub(crate) fn new(
+        table: &'a mut Table,
+        filter: impl Fn(&[Option<types::Datum>]) -> bool + Send + Sync + 'a,
+    ) -> Self {
+        Self {
+            table,
+            filter: Box::new(filter),
+            target_file_size_bytes: None,
+        }
+    }
+
+    /// Set the target size of rewritten files.
+    ///
+    /// Defaults to table property `write.target-file-size-bytes`.
+    pub fn target_file_size_bytes(mut self, size: i64) -> Self {
+        self.target_file_size_bytes = Some(size);
+        self
+    }
+
+    /// Rewrite data files and commit them as a new `replace` snapshot.
+    pub async fn commit(self) -> Result<()> {
+        self.apply(false).await?;
+        Ok(())
+    }
+
+    /// Plan the rewrite without writing anything.
+    ///
+    /// Data files are not rewritten, so sizes of new data files are
+    /// estimated by the sum of rewritten ones. Returns the table metadata
+    /// and manifests which would be committed, or the current ones if there
+    /// is nothing to rewrite.
+    pub async fn dry_run(self) -> Result<SnapshotUpdate> {
+        self.apply(true).await
+    }
+
+    async fn apply(self, dry_run: bool) -> Result<SnapshotUpdate> {
+        let metadata = self.table.current_table_metadata()?;
+        let location = metadata.location.clone();
+        let default_spec_id = metadata.default_spec_id;
+        let target_file_size_bytes = match self.target_file_size_bytes {
+            Some(v) => v,
+            None => match metadata
+                .properties
+                .as_ref()
+                .and_then(|v| v.get("write.target-file-size-bytes"))
+            {
+                None => DEFAULT_TARGET_FILE_SIZE_BYTES,
+                Some(v) => v
+                    .parse()
+                    .map_err(|err| anyhow!("write.target-file-size-bytes is invalid: {}", err))?,
+            },
+        };
+        // Check the format before reading anything, so that tables of other
+        // formats never get parquet files silently.
+        if let Some(v) = metadata
+            .properties
+            .as_ref()
+            .and_then(|v| v.get("write.format.default"))
+        {
+            let format: types::DataFileFormat = v
+                .parse()
+                .map_err(|err| anyhow!("write.format.default is invalid: {}", err))?;
+            if format != types::DataFileFormat::Parquet {
+                return Err(anyhow!(
+                    "rewrite only writes parquet data files, but write.format.default is {}",
+                    v
+                ));
+            }
+        }
+        let compression = match metadata
+            .properties
+            .as_ref()
+            .and_then(|v| v.get("write.parquet.compression-codec"))
+        {
+            None => DEFAULT_PARQUET_COMPRESSION,
+            Some(v) => parse_parquet_compression(v)?,
+        };
+
+        // Files with pending deletes are never rewritten, otherwise deleted
+        // rows could be revived.
+        let planned_snapshot_id = metadata.current_snapshot_id;
+        let deletes = PendingDeletes::load(self.table).await?;
+
+        // Collect candidates and group them by partition.
+        let mut partitions: Vec<(Vec<Option<types::Datum>>, Vec<Candidate>)> = vec![];
+        {
+            let producer = SnapshotProducer::new(self.table)?;
+            for manifest in producer.current_manifests().await? {
+                if manifest.content != types::ManifestContentType::Data
+                    || manifest.partition_spec_id != default_spec_id
+                {
+                    continue;
+                }
+
+                for entry in producer.read_manifest(&manifest).await? {
+                    let data_file = entry.data_file;
+                    if entry.status == types::ManifestStatus::Deleted
+                        || data_file.content != types::DataContentType::Data
+                        || data_file.file_format != types::DataFileFormat::Parquet
+                        || data_file.file_size_in_bytes >= target_file_size_bytes
+                        || !(self.filter)(&data_file.partition)
+                        || deletes.applies_to(
+                            manifest.partition_spec_id,
+                            &data_file,
+                            entry.sequence_number,
+                        )
+                    {
+                        continue;
+                    }
+
+                    let candidate = (data_file, entry.sequence_number);
+                    match partitions
+                        .iter_mut()
+                        .find(|(v, _)| *v == candidate.0.partition)
+                    {
+                        Some((_, files)) => files.push(candidate),
+                        None => partitions.push((candidate.0.partition.clone(), vec![candidate])),
+                    }
+                }
+            }
+        }
+
+        let mut groups = vec![];
+        for (_, mut files) in partitions {
+            files.sort_by(|(a, _), (b, _)| a.file_path.cmp(&b.file_path));
+
+            let mut group: Vec<Candidate> = vec![];
+            let mut group_size = 0;
+            for file in files {
+                if !group.is_empty()
+                    && group_size + file.0.file_size_in_bytes > target_file_size_bytes
+                {
+                    groups.push(std::mem::take(&mut group));
+                    group_size = 0;
+                }
+                group_size += file.0.file_size_in_bytes;
+                group.push(file);
+            }
+            groups.push(group);
+        }
+        groups.retain(|v| v.len() > 1);
+        if groups.is_empty() {
+            return SnapshotProducer::new(self.table)?.unchanged().await;
+        }
+
+        let commit_uuid = Uuid::new_v4();
+        let mut added = Vec::with_capacity(groups.len());
+        let mut rewritten = vec![];
+        for (idx, group) in groups.into_iter().enumerate() {
+            let path = format!("data/{commit_uuid}-{idx}.parquet");
+            let files: Vec<_> = group.iter().map(|(v, _)| v.clone()).collect();
+            let data_file = if dry_run {
+                plan_group(&location, &path, &files)
+            } else {
+                self.rewrite_group(&location, &path, &files, compression)
+                    .await?
+            };
+            // The new file keeps the largest data sequence number of the
+            // group, so that no delete applies to it differently.
+            let sequence_number = group.iter().filter_map(|(_, v)| *v).max();
+            added.push((data_file, sequence_number));
+            rewritten.extend(group);
+        }
+
+        // Refresh the table to validate against the latest snapshot.
+        self.table.load().await?;
+        if self.table.current_table_metadata()?.current_snapshot_id != planned_snapshot_id {
+            let deletes = PendingDeletes::load(self.table).await?;
+            for (data_file, sequence_number) in &rewritten {
+                if deletes.applies_to(default_spec_id, data_file, *sequence_number) {
+                    return Err(anyhow!(
+                        "rows of rewritten data file {} have been deleted by others",
+                        data_file.file_path
+                    ));
+                }
+            }
+        }
+        let rewritten: HashSet<_> = rewritten.into_iter().map(|(v, _)| v.file_path).collect();
+
+        let mut producer = SnapshotProducer::new(self.table)?.with_dry_run(dry_run);
+        let (manifests, deleted) = producer
+            .write_manifests_with_sequence_numbers(&added, |_, data_file| {
+                Ok(rewritten.contains(&data_file.file_path))
+            })
+            .await?;
+        if deleted.len() != rewritten.len() {
+            return Err(anyhow!(
+                "rewritten data files have been changed by others, {} of {} files are still live",
+                deleted.len(),
+                rewritten.len()
+            ));
+        }
+
+        let added: Vec<_> = added.into_iter().map(|(v, _)| v).collect();
+        let summary = producer.summary("replace", &added, &deleted)?;
+        producer.commit(manifests, summary).await
+    }
+
+    /// Concatenate rows of data files into a new data file at `path`.
+    async fn rewrite_group(
+        &self,
+        location: &str,
+        path: &str,
+        group: &[types::DataFile],
+        compression: Compression,
+    ) -> Result<types::DataFile> {
+        let op = self.table.operator();
+        let schema = self
+            .table
+            .current_table_metadata()?
+            .current_schema()?
+            .clone();
+
+        let mut writer: Option<(ParquetWriter, SchemaRef)> = None;
+        for data_file in group {
+            let mut reader =
+                ParquetReaderBuilder::new(op.clone(), &self.table.rel_path(&data_file.file_path)?)
+                    .with_file_size(data_file.file_size_in_bytes as u64)
+                    .build()
+                    .await?;
+
+            while let Some(batch) = reader.next().await? {
+                let batch = project_batch(batch, &schema, writer.as_ref().map(|(_, v)| v))?;
+                let (w, _) = match &mut writer {
+                    Some(w) => w,
+                    None => writer.insert((
+                        ParquetWriterBuilder::new(op.writer(path).await?, batch.schema())
+                            .with_table_schema(schema.clone())
+                            .with_properties(
+                                WriterProperties::builder()
+                                    .set_compression(compression)
+                                    .build(),
+                            )
+                            .build()?,
+                        batch.schema(),
+                    )),
+                };
+                w.write(&batch).await.map_err(|err| {
+                    anyhow!(
+                        "write rows of data file {} failed: {}",
+                        data_file.file_path,
+                        err
+                    )
+                })?;
+            }
+        }
+
+        let Some((writer, _)) = writer else {
+            return Err(anyhow!("data files to rewrite are all empty"));
+        };
+        let metrics = writer.close_with_metrics().await?;
+
+        Ok(types::DataFile {
+            content: types::DataContentType::Data,
+            file_path: format!("{location}/{path}"),
+            file_format: types::DataFileFormat::Parquet,
+            partition: group[0].partition.clone(),
+            record_count: metrics.record_count,
+            file_size_in_bytes: op.stat(path).await?.content_length() as i64,
+            column_sizes: Some(metrics.column_sizes),
+            value_counts: Some(metrics.value_counts),
+            null_value_counts: Some(metrics.null_value_counts),
+            nan_value_counts: Some(metrics.nan_value_counts),
+            distinct_counts: None,
+            lower_bounds: Some(metrics.lower_bounds),
+            upper_bounds: Some(metrics.upper_bounds),
+            key_metadata: None,
+            split_offsets: vec![],
+            equality_ids: None,
+            sort_order_id: None,
+        })
+    }
+}
+
+/// PendingDeletes are live delete files of the current snapshot, which
+/// decide whether rows of a data file have been deleted.
+struct PendingDeletes {
+    /// Paths of data files referenced by position deletes, along with the
+    /// largest sequence number of deletes referencing them.
+    positions: HashMap<String, i64>,
+    /// Partition spec ids, partition values and sequence numbers of
+    /// equality delete files.
+    equalities: Vec<(i32, Vec<Option<types::Datum>>, i64)>,
+}
+
+impl PendingDeletes {
+    /// Load delete files of the current snapshot, position delete files
+    /// are read to find data files they reference.
+    async fn load(table: &mut Table) -> Result<Self> {
+        let mut position_files = vec![];
+        let mut equalities = vec![];
+        {
+            let producer = SnapshotProducer::new(table)?;
+            for manifest in producer.current_manifests().await? {
+                if manifest.content != types::ManifestContentType::Deletes {
+                    continue;
+                }
+                for entry in producer.read_manifest(&manifest).await? {
+                    if entry.status == types::ManifestStatus::Deleted {
+                        continue;
+                    }
+                    // Delete files only exist in v2 tables, whose sequence
+                    // numbers are filled by `read_manifest`.
+                    let sequence_number = entry.sequence_number.unwrap_or_default();
+                    let data_file = entry.data_file;
+                    match data_file.content {
+                        types::DataContentType::Data => {}
+                        types::DataContentType::PostionDeletes => {
+                            position_files.push((data_file, sequence_number))
+                        }
+                        types::DataContentType::EqualityDeletes => equalities.push((
+                            manifest.partition_spec_id,
+                            data_file.partition,
+                            sequence_number,
+                        )),
+                    }
+                }
+            }
+        }
+
+        let mut positions: HashMap<String, i64> = HashMap::new();
+        for (delete_file, sequence_number) in position_files {
+            let deletes: Vec<_> = table
+                .read_position_deletes(&delete_file)
+                .await?
+                .try_collect()
+                .await?;
+            for (path, _) in deletes {
+                let v = positions.entry(path).or_insert(sequence_number);
+                *v = (*v).max(sequence_number);
+            }
+        }
+
+        Ok(Self {
+            positions,
+            equalities,
+        })
+    }
+
+    /// Check whether any delete applies to the data file of the partition
+    /// spec.
+    ///
+    /// Equality deletes of other partition specs are assumed to apply,
+    /// since their partitions can't be compared.
+    fn applies_to(
+        &self,
+        partition_spec_id: i32,
+        data_file: &types::DataFile,
+        sequence_number: Option<i64>,
+    ) -> bool {
+        let sequence_number = sequence_number.unwrap_or_default();
+        // Position deletes apply to data files of the same sequence number,
+        // while equality deletes only apply to older ones.
+        if self
+            .positions
+            .get(&data_file.file_path)
+            .is_some_and(|v| *v >= sequence_number)
+        {
+            return true;
+        }
+        self.equalities.iter().any(|(spec_id, partition, v)| {
+            *v > sequence_number
+                && (partition.is_empty()
+                    || *spec_id != partition_spec_id
+                    || *partition == data_file.partition)
+        })
+    }
+}
+
+/// Project a batch read from data file to the current schema.
+///
+/// Columns are matched with fields by ids, or by names if the data file
+/// has no field ids, and promoted to the types of fields. Missing optional
+/// fields, for example, added after the file is written, are filled with
+/// nulls. Ids of fields are attached to the output, so that rewritten files
+/// are still matched by ids after renames.
+///
+/// Types of columns follow the given arrow schema if any, which is the one
+/// of batches written before, so that batches of files written by
+/// different writers share the same schema.
+fn project_batch(
+    batch: RecordBatch,
+    schema: &types::Schema,
+    arrow_schema: Option<&SchemaRef>,
+) -> Result<RecordBatch> {
+    let batch = promote_types(batch, schema)?;
+    let batch_schema = batch.schema();
+    let mut fields = Vec::with_capacity(schema.fields.len());
+    let mut columns = Vec::with_capacity(schema.fields.len());
+    for (idx, field) in schema.fields.iter().enumerate() {
+        let column = batch_schema
+            .fields()
+            .iter()
+            .position(|v| match v.metadata().get(FIELD_ID_META_KEY) {
+                Some(id) => *id == field.id.to_string(),
+                None => v.name() == &field.name,
+            })
+            .map(|v| batch.column(v).clone());
+        let data_type = match (arrow_schema, &column) {
+            (Some(arrow_schema), _) => arrow_schema.field(idx).data_type().clone(),
+            (None, Some(column)) => column.data_type().clone(),
+            (None, None) => DataType::try_from(field.field_type.clone())?,
+        };
+        let arrow_field =
+            with_table_field_ids(Field::new(&field.name, data_type, !field.required), field);
+        let data_type = arrow_field.data_type();
+        let column = match column {
+            Some(column) => cast_field_metadata(&column, data_type).map_err(|err| {
+                anyhow!(
+                    "column {} of type {} can't be written as {}: {}",
+                    field.name,
+                    column.data_type(),
+                    data_type,
+                    err
+                )
+            })?,
+            None if field.required => {
+                return Err(anyhow!("required column {} is missing", field.name))
+            }
+            None => new_null_array(data_type, batch.num_rows()),
+        };
+        fields.push(arrow_field);
+        columns.push(column);
+    }
+
+    Ok(RecordBatch::try_new(
+        Arc::new(ArrowSchema::new(fields)),
+        columns,
+    )?)
+}
+
+/// Parse the codec of table property `write.parquet.compression-codec`.
+fn parse_parquet_compression(codec: &str) -> Result<Compression> {
+    match codec.to_lowercase().as_str() {
+        "snappy" => Ok(Compression::SNAPPY),
+        "zstd" => Ok(Compression::ZSTD(ZstdLevel::default())),
+        "gzip" => Ok(Compression::GZIP(GzipLevel::default())),
+        "none" | "uncompressed" => Ok(Compression::UNCOMPRESSED),
+        v => Err(anyhow!(
+            "write.parquet.compression-codec {:?} is not supported",
+            v
+        )),
+    }
+}
+
+/// Plan the new data file of group without rewriting it.
+fn plan_group(location: &str, path: &str, group: &[types::DataFile]) -> types::DataFile {
+    types::DataFile {
+        content: types::DataContentType::Data,
+        file_path: format!("{location}/{path}"),
+        file_format: types::DataFileFormat::Parquet,
+        partition: group[0].partition.clone(),
+        record_count: group.iter().map(|v| v.record_count).sum(),
+        file_size_in_bytes: group.iter().map(|v| v.file_size_in_bytes).sum(),
+        column_sizes: None,
+        value_counts: None,
+        null_value_counts: None,
+        nan_value_counts: None,
+        distinct_counts: None,
+        lower_bounds: None,
+        upper_bounds: None,
+        key_metadata: None,
+        split_offsets: vec![],
+        equality_ids: None,
+        sort_order_id: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::Array;
+    use arrow_array::ArrayRef;
+    use arrow_array::Int64Array;
+    use arrow_array::StringArray;
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::operation::test_utils::*;
+
+    /// Write a parquet data file with the given ids into table.
+    async fn write_data_file(
+        table: &Table,
+        name: &str,
+        region: &str,
+        ids: Vec<i64>,
+    ) -> Result<types::DataFile> {
+        let col = Arc::new(Int64Array::from(ids.clone())) as ArrayRef;
+        let batch = RecordBatch::try_from_iter([("id", col)])?;
+
+        let path = format!("data/{name}.parquet");
+        let w = table.operator().writer(&path).await?;
+        let mut writer = ParquetWriterBuilder::new(w, batch.schema()).build()?;
+        writer.write(&batch).await?;
+        writer.close().await?;
+
+        let mut data_file = data_file(name, Some(region), ids.len() as i64);
+        data_file.file_size_in_bytes = table.operator().stat(&path).await?.content_length() as i64;
+        Ok(data_file)
+    }
+
+    /// Read all ids of the given data file.
+    async fn read_ids(table: &Table, data_file: &types::DataFile) -> Result<Vec<i64>> {
+        let content = table
+            .operator()
+            .read(&table.rel_path(&data_file.file_path)?)
+            .await?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(content))?.build()?;
+
+        let mut ids = vec![];
+        for batch in reader {
+            let batch = batch?;
+            let col = batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap();
+            ids.extend(col.values().iter().copied());
+        }
+        Ok(ids)
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let files = vec![
+            write_data_file(&table, "a", "us", vec![1, 2, 3]).await?,
+            write_data_file(&table, "b", "us", vec![4, 5]).await?,
+            write_data_file(&table, "c", "eu", vec![6]).await?,
+        ];
+        table.new_append().add_data_files(files).commit().await?;
+
+        table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await?;
+
+        let data_files = table.current_data_files().await?;
+        assert_eq!(data_files.len(), 2);
+        let rewritten = data_files
+            .iter()
+            .find(|v| v.file_path != "memory:///tbl/data/c.parquet")
+            .unwrap();
+        assert_eq!(
+            rewritten.partition,
+            vec![Some(types::Datum::String("us".to_string()))]
+        );
+        assert_eq!(rewritten.record_count, 5);
+        // Missing `region` column is written in nulls.
+        assert_eq!(
+            rewritten.value_counts,
+            Some(HashMap::from([(1, 5), (2, 5)]))
+        );
+        assert_eq!(
+            rewritten.null_value_counts,
+            Some(HashMap::from([(1, 0), (2, 5)]))
+        );
+        assert_eq!(
+            rewritten.lower_bounds,
+            Some(HashMap::from([(1, types::Datum::Long(1).to_bytes())]))
+        );
+        assert_eq!(
+            rewritten.upper_bounds,
+            Some(HashMap::from([(1, types::Datum::Long(5).to_bytes())]))
+        );
+        let mut ids = read_ids(&table, rewritten).await?;
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+
+        let metadata = table.current_table_metadata()?;
+        let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+        assert_eq!(snapshot.summary["operation"], "replace");
+        assert_eq!(snapshot.summary["added-data-files"], "1");
+        assert_eq!(snapshot.summary["deleted-data-files"], "2");
+        assert_eq!(snapshot.summary["total-records"], "6");
+
+        // Nothing to rewrite if each partition has only one small file.
+        table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v3.metadata.json")
+        );
+
+        Ok(())
+    }
+
+    /// Get data sequence numbers of current data files keyed by path.
+    async fn data_sequence_numbers(table: &mut Table) -> Result<HashMap<String, i64>> {
+        let producer = SnapshotProducer::new(table)?;
+        let mut sequence_numbers = HashMap::new();
+        for manifest in producer.current_manifests().await? {
+            for entry in producer.read_manifest(&manifest).await? {
+                if entry.status != types::ManifestStatus::Deleted
+                    && entry.data_file.content == types::DataContentType::Data
+                {
+                    sequence_numbers
+                        .insert(entry.data_file.file_path, entry.sequence_number.unwrap());
+                }
+            }
+        }
+        Ok(sequence_numbers)
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files_with_position_deletes() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let a = write_data_file(&table, "a", "us", vec![1, 2, 3]).await?;
+        table.new_append().add_data_file(a).commit().await?;
+        let b = write_data_file(&table, "b", "us", vec![4, 5]).await?;
+        table.new_append().add_data_file(b).commit().await?;
+        let c = write_data_file(&table, "c", "us", vec![6]).await?;
+        table.new_append().add_data_file(c).commit().await?;
+
+        // Delete the 1st row of `a`.
+        let paths = Arc::new(StringArray::from(vec!["memory:///tbl/data/a.parquet"])) as ArrayRef;
+        let positions = Arc::new(Int64Array::from(vec![0])) as ArrayRef;
+        let batch = RecordBatch::try_from_iter([("file_path", paths), ("pos", positions)])?;
+        let w = table.operator().writer("data/deletes.parquet").await?;
+        let mut writer = ParquetWriterBuilder::new(w, batch.schema()).build()?;
+        writer.write(&batch).await?;
+        writer.close().await?;
+        let mut deletes = data_file("deletes", Some("us"), 1);
+        deletes.content = types::DataContentType::PostionDeletes;
+        deletes.file_size_in_bytes = table
+            .operator()
+            .stat("data/deletes.parquet")
+            .await?
+            .content_length() as i64;
+        table.new_append().add_data_file(deletes).commit().await?;
+
+        let before = data_sequence_numbers(&mut table).await?;
+        table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await?;
+
+        // `a` is kept with its deletes, only `b` and `c` are rewritten.
+        let after = data_sequence_numbers(&mut table).await?;
+        assert_eq!(after.len(), 2);
+        assert_eq!(
+            after["memory:///tbl/data/a.parquet"],
+            before["memory:///tbl/data/a.parquet"]
+        );
+        let (_, sequence_number) = after
+            .iter()
+            .find(|(k, _)| *k != "memory:///tbl/data/a.parquet")
+            .unwrap();
+        assert_eq!(*sequence_number, before["memory:///tbl/data/c.parquet"]);
+
+        // The deleted row stays deleted.
+        let mut ids = vec![];
+        for batch in table.collect().await? {
+            let col = batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap();
+            ids.extend(col.values().iter().copied());
+        }
+        ids.sort();
+        assert_eq!(ids, vec![2, 3, 4, 5, 6]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files_with_equality_deletes() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let files = vec![
+            write_data_file(&table, "a", "us", vec![1]).await?,
+            write_data_file(&table, "b", "eu", vec![2]).await?,
+        ];
+        table.new_append().add_data_files(files).commit().await?;
+        let rows = RecordBatch::try_from_iter([
+            ("id", Arc::new(Int64Array::from(vec![1])) as ArrayRef),
+            (
+                "region",
+                Arc::new(StringArray::from(vec!["us"])) as ArrayRef,
+            ),
+        ])?;
+        table
+            .new_delete()
+            .equality_delete(&["id", "region"], rows)
+            .commit()
+            .await?;
+        // Files committed after the deletes are not affected.
+        let files = vec![
+            write_data_file(&table, "c", "us", vec![3]).await?,
+            write_data_file(&table, "d", "us", vec![4]).await?,
+            write_data_file(&table, "e", "eu", vec![5]).await?,
+        ];
+        table.new_append().add_data_files(files).commit().await?;
+
+        table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await?;
+
+        // The equality delete file, `a` and two rewritten files.
+        let paths = current_data_file_paths(&table).await?;
+        assert_eq!(paths.len(), 4);
+        assert!(paths.contains(&"memory:///tbl/data/a.parquet".to_string()));
+        // Equality deletes of `us` don't apply to `b`.
+        assert!(!paths.contains(&"memory:///tbl/data/b.parquet".to_string()));
+        assert!(!paths.contains(&"memory:///tbl/data/c.parquet".to_string()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files_schema_evolution() -> Result<()> {
+        let mut table = create_table("2").await?;
+        // `a` is written before `id` is promoted from int to long.
+        let col = Arc::new(arrow_array::Int32Array::from(vec![1, 2])) as ArrayRef;
+        let batch = RecordBatch::try_from_iter([("id", col)])?;
+        let w = table.operator().writer("data/a.parquet").await?;
+        let mut writer = ParquetWriterBuilder::new(w, batch.schema()).build()?;
+        writer.write(&batch).await?;
+        writer.close().await?;
+        let mut a = data_file("a", Some("us"), 2);
+        a.file_size_in_bytes = table
+            .operator()
+            .stat("data/a.parquet")
+            .await?
+            .content_length() as i64;
+        let b = write_data_file(&table, "b", "us", vec![3]).await?;
+        table.new_append().add_data_files([a, b]).commit().await?;
+
+        table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await?;
+        let data_files = table.current_data_files().await?;
+        assert_eq!(data_files.len(), 1);
+        let mut ids = read_ids(&table, &data_files[0]).await?;
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files_then_rename() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let files = vec![
+            write_data_file(&table, "a", "us", vec![1, 2]).await?,
+            write_data_file(&table, "b", "us", vec![3]).await?,
+        ];
+        table.new_append().add_data_files(files).commit().await?;
+        table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await?;
+
+        // Rewritten files carry field ids, so they are still matched by the
+        // next rewrite after renames.
+        table
+            .update_schema()
+            .rename_column("id", "key")
+            .commit()
+            .await?;
+        let col = Arc::new(Int64Array::from(vec![4])) as ArrayRef;
+        let batch = RecordBatch::try_from_iter([("key", col)])?;
+        let w = table.operator().writer("data/c.parquet").await?;
+        let mut writer = ParquetWriterBuilder::new(w, batch.schema()).build()?;
+        writer.write(&batch).await?;
+        writer.close().await?;
+        let mut c = data_file("c", Some("us"), 1);
+        c.file_size_in_bytes = table
+            .operator()
+            .stat("data/c.parquet")
+            .await?
+            .content_length() as i64;
+        table.new_append().add_data_file(c).commit().await?;
+
+        table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await?;
+        let data_files = table.current_data_files().await?;
+        assert_eq!(data_files.len(), 1);
+        let mut keys = read_ids(&table, &data_files[0]).await?;
+        keys.sort();
+        assert_eq!(keys, vec![1, 2, 3, 4]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files_write_format() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let files = vec![
+            write_data_file(&table, "a", "us", vec![1]).await?,
+            write_data_file(&table, "b", "us", vec![2]).await?,
+        ];
+        table.new_append().add_data_files(files).commit().await?;
+
+        let set_write_format = |table: &Table, format: &str| -> Result<types::TableMetadata> {
+            let mut metadata = table.current_table_metadata()?.clone();
+            metadata
+                .properties
+                .get_or_insert_with(HashMap::new)
+                .insert("write.format.default".to_string(), format.to_string());
+            Ok(metadata)
+        };
+
+        // Tables of other formats are rejected instead of getting parquet.
+        for format in ["orc", "avro", "csv"] {
+            let metadata = set_write_format(&table, format)?;
+            table.commit(metadata).await?;
+            let err = table
+                .rewrite_data_files(|_| true)
+                .target_file_size_bytes(1024 * 1024)
+                .commit()
+                .await
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("write.format.default"), "{err}");
+            assert_eq!(table.current_data_files().await?.len(), 2);
+        }
+
+        let metadata = set_write_format(&table, "PARQUET")?;
+        table.commit(metadata).await?;
+        table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await?;
+        let data_files = table.current_data_files().await?;
+        assert_eq!(data_files.len(), 1);
+        assert_eq!(data_files[0].file_format, types::DataFileFormat::Parquet);
+        assert!(data_files[0].file_path.ends_with(".parquet"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files_compression() -> Result<()> {
+        /// Get the compression of the first column of the data file.
+        async fn compression_of(table: &Table, data_file: &types::DataFile) -> Result<Compression> {
+            let content = table
+                .operator()
+                .read(&table.rel_path(&data_file.file_path)?)
+                .await?;
+            let builder = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(content))?;
+            Ok(builder.metadata().row_group(0).column(0).compression())
+        }
+
+        let cases = [
+            (None, Compression::SNAPPY),
+            (Some("zstd"), Compression::ZSTD(ZstdLevel::default())),
+            (Some("GZIP"), Compression::GZIP(GzipLevel::default())),
+            (Some("none"), Compression::UNCOMPRESSED),
+        ];
+        for (codec, expected) in cases {
+            let mut table = create_table("2").await?;
+            let files = vec![
+                write_data_file(&table, "a", "us", vec![1]).await?,
+                write_data_file(&table, "b", "us", vec![2]).await?,
+            ];
+            table.new_append().add_data_files(files).commit().await?;
+            if let Some(codec) = codec {
+                let mut metadata = table.current_table_metadata()?.clone();
+                metadata.properties.get_or_insert_with(HashMap::new).insert(
+                    "write.parquet.compression-codec".to_string(),
+                    codec.to_string(),
+                );
+                table.commit(metadata).await?;
+            }
+
+            table
+                .rewrite_data_files(|_| true)
+                .target_file_size_bytes(1024 * 1024)
+                .commit()
+                .await?;
+            let data_files = table.current_data_files().await?;
+            assert_eq!(data_files.len(), 1);
+            // Levels are not recorded in the file.
+            let actual = compression_of(&table, &data_files[0]).await?;
+            assert_eq!(
+                std::mem::discriminant(&actual),
+                std::mem::discriminant(&expected),
+                "compression of codec {codec:?} is not match"
+            );
+        }
+
+        assert!(parse_parquet_compression("lzo").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files_conflict() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let files = vec![
+            write_data_file(&table, "a", "us", vec![1]).await?,
+            write_data_file(&table, "b", "us", vec![2]).await?,
+        ];
+        table.new_append().add_data_files(files).commit().await?;
+
+        // Delete a rewritten file via another table instance.
+        let mut other = Table::new(table.operator().clone());
+        other.load().await?;
+        other
+            .new_delete()
+            .delete_file("memory:///tbl/data/a.parquet")
+            .commit()
+            .await?;
+
+        let result = table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .commit()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec!["memory:///tbl/data/b.parquet"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_data_files_dry_run() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let files = vec![
+            write_data_file(&table, "a", "us", vec![1, 2]).await?,
+            write_data_file(&table, "b", "us", vec![3]).await?,
+        ];
+        let total_size: i64 = files.iter().map(|v| v.file_size_in_bytes).sum();
+        table.new_append().add_data_files(files).commit().await?;
+        let paths = list_files(&table).await?;
+
+        let update = table
+            .rewrite_data_files(|_| true)
+            .target_file_size_bytes(1024 * 1024)
+            .dry_run()
+            .await?;
+
+        // Nothing is written.
+        assert_eq!(list_files(&table).await?, paths);
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v2.metadata.json")
+        );
+
+        let snapshot = update.metadata.snapshots.as_ref().unwrap().last().unwrap();
+        assert_eq!(
+            update.metadata.current_snapshot_id,
+            Some(snapshot.snapshot_id)
+        );
+        assert_eq!(snapshot.summary["operation"], "replace");
+        assert_eq!(snapshot.summary["added-data-files"], "1");
+        assert_eq!(snapshot.summary["added-records"], "3");
+        assert_eq!(snapshot.summary["added-files-size"], total_size.to_string());
+        assert_eq!(snapshot.summary["deleted-data-files"], "2");
+
+        // Current metadata is returned if there is nothing to rewrite.
+        let update = table.rewrite_data_files(|_| false).dry_run().await?;
+        assert_eq!(&update.metadata, table.current_table_metadata()?);
+        assert_eq!(update.manifests.len(), 1);
+
+        Ok(())
+    }
+}
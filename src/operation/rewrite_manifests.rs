@@ -0,0 +1,266 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use super::SnapshotProducer;
+use super::SnapshotUpdate;
+use crate::types;
+use crate::Table;
+
+/// The default value of table property `commit.manifest.target-size-bytes`.
+const DEFAULT_TARGET_SIZE_BYTES: i64 = 8 * 1024 * 1024;
+
+/// RewriteManifestsOperation merges data manifests into fewer larger ones.
+///
+/// Manifests are rewritten per partition spec: live entries are clustered
+/// by partition values and packed into manifests no larger than the target
+/// size, entries of the same partition are never split unless they exceed
+/// the target size alone. The size of entries is estimated by the sizes of
+/// existing manifests.
+///
+/// All live entries are kept with status `EXISTING`, while deleted entries
+/// are dropped. Delete manifests and partition specs with only one data
+/// manifest are kept as is.
+///
+/// Initiate a new operation with `Table::rewrite_manifests()`.
+pub struct RewriteManifestsOperation<'a> {
+    table: &'a mut Table,
+    target_size_bytes: Option<i64>,
+}
+
+impl<'a> RewriteManifestsOperation<'a> {
+    pub(crate) fn new(table: &'a mut Table) -> Self {
+        Self {
+            table,
+            target_size_bytes: None,
+        }
+    }
+
+    /// Set the target size of rewritten manifests.
+    ///
+    /// Defaults to table property `commit.manifest.target-size-bytes`.
+    pub fn target_size_bytes(mut self, size: i64) -> Self {
+        self.target_size_bytes = Some(size);
+        self
+    }
+
+    /// Rewrite manifests and commit them as a new `replace` snapshot.
+    pub async fn commit(self) -> Result<()> {
+        self.apply(false).await?;
+        Ok(())
+    }
+
+    /// Plan the rewrite without writing anything.
+    ///
+    /// Returns the table metadata and manifests which would be committed,
+    /// or the current ones if there is nothing to rewrite.
+    pub async fn dry_run(self) -> Result<SnapshotUpdate> {
+        self.apply(true).await
+    }
+
+    async fn apply(self, dry_run: bool) -> Result<SnapshotUpdate> {
+        let metadata = self.table.current_table_metadata()?;
+        let target_size_bytes = match self.target_size_bytes {
+            Some(v) => v,
+            None => match metadata
+                .properties
+                .as_ref()
+                .and_then(|v| v.get("commit.manifest.target-size-bytes"))
+            {
+                None => DEFAULT_TARGET_SIZE_BYTES,
+                Some(v) => v.parse().map_err(|err| {
+                    anyhow!("commit.manifest.target-size-bytes is invalid: {}", err)
+                })?,
+            },
+        };
+
+        let mut producer = SnapshotProducer::new(self.table)?.with_dry_run(dry_run);
+
+        let mut kept = vec![];
+        let mut specs: Vec<(i32, Vec<types::ManifestList>)> = vec![];
+        for manifest in producer.current_manifests().await? {
+            if manifest.content != types::ManifestContentType::Data {
+                kept.push(manifest);
+                continue;
+            }
+            match specs
+                .iter_mut()
+                .find(|(v, _)| *v == manifest.partition_spec_id)
+            {
+                Some((_, manifests)) => manifests.push(manifest),
+                None => specs.push((manifest.partition_spec_id, vec![manifest])),
+            }
+        }
+
+        let mut manifests = vec![];
+        let mut replaced_count = 0;
+        let mut entries_count = 0;
+        for (spec_id, spec_manifests) in specs {
+            if spec_manifests.len() <= 1 {
+                kept.extend(spec_manifests);
+                continue;
+            }
+
+            let mut total_size = 0;
+            let mut total_entries = 0;
+            let mut partitions: Vec<(Vec<Option<types::Datum>>, Vec<types::ManifestFile>)> = vec![];
+            for manifest in &spec_manifests {
+                let entries = producer.read_manifest(manifest).await?;
+                total_size += manifest.manifest_length;
+                total_entries += entries.len() as i64;
+
+                for mut entry in entries {
+                    if entry.status == types::ManifestStatus::Deleted {
+                        continue;
+                    }
+                    entry.status = types::ManifestStatus::Existing;
+
+                    match partitions
+                        .iter_mut()
+                        .find(|(v, _)| *v == entry.data_file.partition)
+                    {
+                        Some((_, entries)) => entries.push(entry),
+                        None => partitions.push((entry.data_file.partition.clone(), vec![entry])),
+                    }
+                }
+            }
+            replaced_count += spec_manifests.len();
+
+            let entry_size = (total_size / total_entries.max(1)).max(1);
+            let mut group: Vec<types::ManifestFile> = vec![];
+            for (_, entries) in partitions {
+                entries_count += entries.len();
+                if !group.is_empty()
+                    && (group.len() + entries.len()) as i64 * entry_size > target_size_bytes
+                {
+                    manifests.push(
+                        producer
+                            .write_manifest(spec_id, std::mem::take(&mut group))
+                            .await?,
+                    );
+                }
+                group.extend(entries);
+            }
+            if !group.is_empty() {
+                manifests.push(producer.write_manifest(spec_id, group).await?);
+            }
+        }
+
+        if replaced_count == 0 {
+            return producer.unchanged().await;
+        }
+
+        let mut summary = producer.summary("replace", &[], &[])?;
+        for (key, value) in [
+            ("manifests-created", manifests.len()),
+            ("manifests-kept", kept.len()),
+            ("manifests-replaced", replaced_count),
+            ("entries-processed", entries_count),
+        ] {
+            summary.insert(key.to_string(), value.to_string());
+        }
+
+        manifests.extend(kept);
+        producer.commit(manifests, summary).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::test_utils::*;
+
+    /// Read manifests of the current snapshot.
+    async fn current_manifests(table: &Table) -> Result<Vec<types::ManifestList>> {
+        let metadata = table.current_table_metadata()?;
+        let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+        let content = table
+            .operator()
+            .read(&table.rel_path(&snapshot.manifest_list)?)
+            .await?;
+        Ok(types::parse_manifest_list(&content)?)
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_manifests() -> Result<()> {
+        for format_version in ["1", "2"] {
+            let mut table = create_table(format_version).await?;
+            for (name, region) in [("a", "us"), ("b", "eu"), ("c", "us"), ("d", "eu")] {
+                table
+                    .new_append()
+                    .add_data_file(data_file(name, Some(region), 1))
+                    .commit()
+                    .await?;
+            }
+            table
+                .new_delete()
+                .delete_file("memory:///tbl/data/d.parquet")
+                .commit()
+                .await?;
+            let data_files = current_data_file_paths(&table).await?;
+            assert_eq!(current_manifests(&table).await?.len(), 4);
+
+            table.rewrite_manifests().commit().await?;
+
+            let manifests = current_manifests(&table).await?;
+            assert_eq!(manifests.len(), 1);
+            assert_eq!(manifests[0].existing_files_count, 3);
+            assert_eq!(manifests[0].added_files_count, 0);
+            assert_eq!(manifests[0].deleted_files_count, 0);
+
+            let content = table
+                .operator()
+                .read(&table.rel_path(&manifests[0].manifest_path)?)
+                .await?;
+            let (_, entries) = types::parse_manifest_file(&content)?;
+            assert!(entries
+                .iter()
+                .all(|v| v.status == types::ManifestStatus::Existing));
+            assert_eq!(current_data_file_paths(&table).await?, data_files);
+
+            let metadata = table.current_table_metadata()?;
+            let snapshot = metadata.snapshots.as_ref().unwrap().last().unwrap();
+            assert_eq!(snapshot.summary["operation"], "replace");
+            assert_eq!(snapshot.summary["manifests-replaced"], "4");
+            assert_eq!(snapshot.summary["manifests-created"], "1");
+            assert_eq!(snapshot.summary["total-data-files"], "3");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_manifests_group_by_partition() -> Result<()> {
+        let mut table = create_table("2").await?;
+        for (name, region) in [("a", "us"), ("b", "eu"), ("c", "us")] {
+            table
+                .new_append()
+                .add_data_file(data_file(name, Some(region), 1))
+                .commit()
+                .await?;
+        }
+
+        table
+            .rewrite_manifests()
+            .target_size_bytes(1)
+            .commit()
+            .await?;
+
+        let manifests = current_manifests(&table).await?;
+        assert_eq!(manifests.len(), 2);
+        for manifest in manifests {
+            let content = table
+                .operator()
+                .read(&table.rel_path(&manifest.manifest_path)?)
+                .await?;
+            let (_, entries) = types::parse_manifest_file(&content)?;
+            assert!(entries
+                .iter()
+                .all(|v| v.data_file.partition == entries[0].data_file.partition));
+            // Sequence numbers of existing entries are kept.
+            assert!(entries.iter().all(|v| v.sequence_number.is_some()));
+            assert!(manifest.min_sequence_number < 4);
+        }
+
+        Ok(())
+    }
+}
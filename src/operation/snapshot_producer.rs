@@ -0,0 +1,636 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use uuid::Uuid;
+
+use super::SnapshotUpdate;
+use crate::types;
+use crate::Table;
+
+/// SnapshotProducer produces a new snapshot on top of the current
+/// snapshot of table.
+///
+/// It's shared by all operations: operations decide which data files to
+/// add and which to delete, while the producer writes manifests, manifest
+/// list and the new table metadata.
+///
+/// In dry-run mode, nothing is written to storage: manifests are only
+/// serialized to compute their lengths, and the new table metadata is
+/// returned instead of committed.
+pub(crate) struct SnapshotProducer<'a> {
+    table: &'a mut Table,
+    metadata: types::TableMetadata,
+
+    snapshot_id: i64,
+    sequence_number: i64,
+    commit_uuid: Uuid,
+    manifest_count: usize,
+    dry_run: bool,
+    /// Stage the new snapshot with the write-audit-publish id instead of
+    /// making it current.
+    wap_id: Option<String>,
+}
+
+impl<'a> SnapshotProducer<'a> {
+    /// Create a new snapshot producer for the current version of table.
+    pub fn new(table: &'a mut Table) -> Result<Self> {
+        let metadata = table.current_table_metadata()?.clone();
+
+        let snapshot_id = loop {
+            let (hi, lo) = Uuid::new_v4().as_u64_pair();
+            let id = ((hi ^ lo) & i64::MAX as u64) as i64;
+            if !metadata
+                .snapshots
+                .iter()
+                .flatten()
+                .any(|v| v.snapshot_id == id)
+            {
+                break id;
+            }
+        };
+        let sequence_number = match metadata.format_version {
+            types::TableFormatVersion::V1 => 0,
+            types::TableFormatVersion::V2 => metadata.last_sequence_number + 1,
+            types::TableFormatVersion::V3 => {
+                return Err(anyhow!("writing v3 tables is not supported yet"))
+            }
+        };
+
+        Ok(Self {
+            table,
+            metadata,
+
+            snapshot_id,
+            sequence_number,
+            commit_uuid: Uuid::new_v4(),
+            manifest_count: 0,
+            dry_run: false,
+            wap_id: None,
+        })
+    }
+
+    /// Set whether to run in dry-run mode.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Stage the new snapshot with the given write-audit-publish id.
+    ///
+    /// The staged snapshot is added to metadata with `wap.id` in its
+    /// summary, but the current snapshot is not changed. Publish it later
+    /// with `Table::publish`.
+    pub fn with_wap_id(mut self, wap_id: Option<String>) -> Self {
+        self.wap_id = wap_id;
+        self
+    }
+
+    /// Get the current snapshot of table, `None` if the table is empty.
+    fn current_snapshot(&self) -> Result<Option<&types::Snapshot>> {
+        let Some(id) = self.metadata.current_snapshot_id else {
+            return Ok(None);
+        };
+
+        self.snapshot(id).map(Some)
+    }
+
+    /// Get the snapshot with the given id.
+    pub fn snapshot(&self, snapshot_id: i64) -> Result<&types::Snapshot> {
+        self.metadata
+            .snapshots
+            .iter()
+            .flatten()
+            .find(|v| v.snapshot_id == snapshot_id)
+            .ok_or_else(|| anyhow!("snapshot with id {} is not found", snapshot_id))
+    }
+
+    /// Read all manifests of the current snapshot.
+    pub async fn current_manifests(&self) -> Result<Vec<types::ManifestList>> {
+        let Some(snapshot) = self.current_snapshot()? else {
+            return Ok(vec![]);
+        };
+
+        self.manifests_of(snapshot).await
+    }
+
+    /// Read all manifests of the given snapshot.
+    pub async fn manifests_of(
+        &self,
+        snapshot: &types::Snapshot,
+    ) -> Result<Vec<types::ManifestList>> {
+        let path = self.table.rel_path(&snapshot.manifest_list)?;
+        let content = self.table.operator().read(&path).await?;
+        Ok(types::parse_manifest_list(&content)?)
+    }
+
+    /// Read entries of the given manifest.
+    ///
+    /// Snapshot id and sequence numbers inherited from the manifest are
+    /// filled, so that entries can be written into other manifests.
+    pub async fn read_manifest(
+        &self,
+        manifest: &types::ManifestList,
+    ) -> Result<Vec<types::ManifestFile>> {
+        let path = self.table.rel_path(&manifest.manifest_path)?;
+        let content = self.table.operator().read(&path).await?;
+        let (_, mut entries) = types::parse_manifest_file_with_partition_specs(
+            &content,
+            &self.metadata.partition_specs,
+        )?;
+
+        for entry in &mut entries {
+            entry.snapshot_id.get_or_insert(manifest.added_snapshot_id);
+            if self.metadata.format_version == types::TableFormatVersion::V2 {
+                entry.sequence_number = Some(entry.data_sequence_number(manifest)?);
+                entry
+                    .file_sequence_number
+                    .get_or_insert(manifest.sequence_number);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Write entries into a new manifest of the given partition spec.
+    ///
+    /// Entries of delete files are written into a delete manifest, they
+    /// can't be mixed with entries of data files. Added entries will be
+    /// committed in the new snapshot, their sequence numbers are usually
+    /// left empty to inherit from the manifest.
+    pub async fn write_manifest(
+        &mut self,
+        partition_spec_id: i32,
+        entries: Vec<types::ManifestFile>,
+    ) -> Result<types::ManifestList> {
+        let is_data = |v: &types::ManifestFile| v.data_file.content == types::DataContentType::Data;
+        let content = match entries.first() {
+            Some(v) if !is_data(v) => types::ManifestContentType::Deletes,
+            _ => types::ManifestContentType::Data,
+        };
+        if entries
+            .iter()
+            .any(|v| is_data(v) != (content == types::ManifestContentType::Data))
+        {
+            return Err(anyhow!(
+                "data files and delete files can't be written into the same manifest"
+            ));
+        }
+
+        let partition_spec = self
+            .metadata
+            .partition_specs
+            .iter()
+            .find(|v| v.spec_id == partition_spec_id)
+            .ok_or_else(|| anyhow!("partition spec {} is not found", partition_spec_id))?;
+        let schema = self.metadata.current_schema()?;
+
+        let manifest_metadata = types::ManifestMetadata {
+            schema: schema.clone(),
+            schema_id: schema.schema_id,
+            partition_spec: partition_spec.clone(),
+            partition_spec_id,
+            format_version: match self.metadata.format_version {
+                types::TableFormatVersion::V1 => 1,
+                types::TableFormatVersion::V2 => 2,
+                types::TableFormatVersion::V3 => unreachable!("v3 tables are rejected by new"),
+            },
+            content,
+        };
+        let bs = types::serialize_manifest_file(&manifest_metadata, &entries)?;
+
+        let path = format!(
+            "metadata/{}-m{}.avro",
+            self.commit_uuid, self.manifest_count
+        );
+        self.manifest_count += 1;
+        let manifest_length = bs.len() as i64;
+        if !self.dry_run {
+            self.table.operator().write(&path, bs).await?;
+        }
+
+        let mut manifest = types::ManifestList {
+            manifest_path: format!("{}/{}", self.metadata.location, path),
+            manifest_length,
+            partition_spec_id,
+            content,
+            sequence_number: self.sequence_number,
+            min_sequence_number: self.sequence_number,
+            added_snapshot_id: self.snapshot_id,
+            added_files_count: 0,
+            existing_files_count: 0,
+            deleted_files_count: 0,
+            added_rows_count: 0,
+            existing_rows_count: 0,
+            deleted_rows_count: 0,
+            partitions: Some(partition_summaries(
+                manifest_metadata.partition_spec.fields.len(),
+                &entries,
+            )),
+            key_metadata: None,
+        };
+        for entry in &entries {
+            let rows = entry.data_file.record_count;
+            match entry.status {
+                types::ManifestStatus::Added => {
+                    manifest.added_files_count += 1;
+                    manifest.added_rows_count += rows;
+                    if let Some(v) = entry.sequence_number {
+                        manifest.min_sequence_number = manifest.min_sequence_number.min(v);
+                    }
+                }
+                types::ManifestStatus::Existing => {
+                    manifest.existing_files_count += 1;
+                    manifest.existing_rows_count += rows;
+                    if let Some(v) = entry.sequence_number {
+                        manifest.min_sequence_number = manifest.min_sequence_number.min(v);
+                    }
+                }
+                types::ManifestStatus::Deleted => {
+                    manifest.deleted_files_count += 1;
+                    manifest.deleted_rows_count += rows;
+                }
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Commit a new snapshot that adds `added` data files and deletes live
+    /// data files matched by `is_deleted`.
+    ///
+    /// Returns the committed table metadata and manifests.
+    pub async fn commit_files<F>(
+        mut self,
+        operation: &str,
+        added: Vec<types::DataFile>,
+        is_deleted: F,
+    ) -> Result<SnapshotUpdate>
+    where
+        F: FnMut(&types::ManifestList, &types::DataFile) -> Result<bool>,
+    {
+        let (manifests, deleted) = self.write_manifests(&added, is_deleted).await?;
+
+        let summary = self.summary(operation, &added, &deleted)?;
+        self.commit(manifests, summary).await
+    }
+
+    /// Write manifests of the new snapshot that adds `added` data files and
+    /// deletes live data files matched by `is_deleted`.
+    ///
+    /// Added delete files are written into a separate delete manifest. They
+    /// share the sequence number of the new snapshot, so that they only
+    /// apply to data files committed before.
+    ///
+    /// Manifests without deleted files are kept as is, others are rewritten
+    /// with deleted files marked as `DELETED` and remaining files marked as
+    /// `EXISTING`. Delete manifests are always kept.
+    ///
+    /// Returns all manifests of the new snapshot and the deleted data files.
+    pub async fn write_manifests<F>(
+        &mut self,
+        added: &[types::DataFile],
+        is_deleted: F,
+    ) -> Result<(Vec<types::ManifestList>, Vec<types::DataFile>)>
+    where
+        F: FnMut(&types::ManifestList, &types::DataFile) -> Result<bool>,
+    {
+        let added: Vec<_> = added.iter().map(|v| (v.clone(), None)).collect();
+        self.write_manifests_with_sequence_numbers(&added, is_deleted)
+            .await
+    }
+
+    /// Write manifests like `write_manifests`, but added files could keep
+    /// the given data sequence numbers instead of inheriting the one of the
+    /// new snapshot.
+    ///
+    /// This is used by rewrites, whose new files must not be newer than the
+    /// rewritten ones, otherwise deletes committed later than the rewritten
+    /// files would no longer apply.
+    pub async fn write_manifests_with_sequence_numbers<F>(
+        &mut self,
+        added: &[(types::DataFile, Option<i64>)],
+        mut is_deleted: F,
+    ) -> Result<(Vec<types::ManifestList>, Vec<types::DataFile>)>
+    where
+        F: FnMut(&types::ManifestList, &types::DataFile) -> Result<bool>,
+    {
+        let mut manifests = vec![];
+        let (added_data, added_deletes): (Vec<_>, Vec<_>) = added
+            .iter()
+            .cloned()
+            .partition(|(v, _)| v.content == types::DataContentType::Data);
+        for added in [added_data, added_deletes] {
+            if added.is_empty() {
+                continue;
+            }
+            let entries = added
+                .into_iter()
+                .map(|(data_file, sequence_number)| types::ManifestFile {
+                    status: types::ManifestStatus::Added,
+                    snapshot_id: Some(self.snapshot_id),
+                    sequence_number,
+                    file_sequence_number: None,
+                    data_file,
+                })
+                .collect();
+            let spec_id = self.metadata.default_spec_id;
+            manifests.push(self.write_manifest(spec_id, entries).await?);
+        }
+
+        let mut deleted = vec![];
+        for manifest in self.current_manifests().await? {
+            if manifest.content == types::ManifestContentType::Deletes {
+                manifests.push(manifest);
+                continue;
+            }
+
+            let mut entries = vec![];
+            let mut has_deleted = false;
+            for mut entry in self.read_manifest(&manifest).await? {
+                // Deleted entries are only kept in the snapshot deleting them.
+                if entry.status == types::ManifestStatus::Deleted {
+                    continue;
+                }
+
+                if is_deleted(&manifest, &entry.data_file)? {
+                    has_deleted = true;
+                    entry.status = types::ManifestStatus::Deleted;
+                    entry.snapshot_id = Some(self.snapshot_id);
+                    deleted.push(entry.data_file.clone());
+                } else {
+                    entry.status = types::ManifestStatus::Existing;
+                }
+                entries.push(entry);
+            }
+
+            if has_deleted {
+                manifests.push(
+                    self.write_manifest(manifest.partition_spec_id, entries)
+                        .await?,
+                );
+            } else {
+                manifests.push(manifest);
+            }
+        }
+
+        Ok((manifests, deleted))
+    }
+
+    /// Build the snapshot summary with totals from the current snapshot.
+    ///
+    /// `added` could contain delete files, whose records are counted as
+    /// deletes instead of records.
+    pub fn summary(
+        &self,
+        operation: &str,
+        added: &[types::DataFile],
+        deleted: &[types::DataFile],
+    ) -> Result<HashMap<String, String>> {
+        let mut summary = HashMap::new();
+        summary.insert("operation".to_string(), operation.to_string());
+
+        let (added, added_deletes): (Vec<_>, Vec<_>) = added
+            .iter()
+            .partition(|v| v.content == types::DataContentType::Data);
+        let deletes_of = |content: types::DataContentType| -> (i64, i64) {
+            let files = added_deletes.iter().filter(|v| v.content == content);
+            (
+                files.clone().count() as i64,
+                files.map(|v| v.record_count).sum(),
+            )
+        };
+        let (added_position_delete_files, added_position_deletes) =
+            deletes_of(types::DataContentType::PostionDeletes);
+        let (added_equality_delete_files, added_equality_deletes) =
+            deletes_of(types::DataContentType::EqualityDeletes);
+
+        let added_records: i64 = added.iter().map(|v| v.record_count).sum();
+        let added_size: i64 = added
+            .iter()
+            .chain(&added_deletes)
+            .map(|v| v.file_size_in_bytes)
+            .sum();
+        let deleted_records: i64 = deleted.iter().map(|v| v.record_count).sum();
+        let deleted_size: i64 = deleted.iter().map(|v| v.file_size_in_bytes).sum();
+        for (key, value) in [
+            ("added-data-files", added.len() as i64),
+            ("added-records", added_records),
+            ("added-files-size", added_size),
+            ("added-delete-files", added_deletes.len() as i64),
+            ("added-position-delete-files", added_position_delete_files),
+            ("added-position-deletes", added_position_deletes),
+            ("added-equality-delete-files", added_equality_delete_files),
+            ("added-equality-deletes", added_equality_deletes),
+            ("deleted-data-files", deleted.len() as i64),
+            ("deleted-records", deleted_records),
+            ("removed-files-size", deleted_size),
+        ] {
+            if value != 0 {
+                summary.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        let previous = self.current_snapshot()?.map(|v| &v.summary);
+        let total = |key: &str| -> Result<i64> {
+            match previous.and_then(|v| v.get(key)) {
+                None => Ok(0),
+                Some(v) => v
+                    .parse()
+                    .map_err(|err| anyhow!("parse summary {} failed: {}", key, err)),
+            }
+        };
+        for (key, value) in [
+            (
+                "total-data-files",
+                total("total-data-files")? + added.len() as i64 - deleted.len() as i64,
+            ),
+            (
+                "total-records",
+                total("total-records")? + added_records - deleted_records,
+            ),
+            (
+                "total-files-size",
+                total("total-files-size")? + added_size - deleted_size,
+            ),
+            (
+                "total-delete-files",
+                total("total-delete-files")? + added_deletes.len() as i64,
+            ),
+            (
+                "total-position-deletes",
+                total("total-position-deletes")? + added_position_deletes,
+            ),
+            (
+                "total-equality-deletes",
+                total("total-equality-deletes")? + added_equality_deletes,
+            ),
+        ] {
+            summary.insert(key.to_string(), value.to_string());
+        }
+
+        Ok(summary)
+    }
+
+    /// Keep the table unchanged.
+    ///
+    /// Returns the current table metadata and manifests, for operations
+    /// with nothing to commit.
+    pub async fn unchanged(self) -> Result<SnapshotUpdate> {
+        let manifests = self.current_manifests().await?;
+        Ok(SnapshotUpdate {
+            metadata: self.metadata,
+            manifests,
+        })
+    }
+
+    /// Write the manifest list and commit the new snapshot as the current
+    /// snapshot of table, or as a staged snapshot if the write-audit-publish
+    /// id is set.
+    ///
+    /// Returns the committed table metadata and manifests. In dry-run mode,
+    /// the metadata which would be committed is returned instead.
+    pub async fn commit(
+        mut self,
+        manifests: Vec<types::ManifestList>,
+        summary: HashMap<String, String>,
+    ) -> Result<SnapshotUpdate> {
+        let metadata = self.build_metadata(&manifests, summary).await?;
+
+        if self.dry_run {
+            let (_, metadata) = self.table.prepare_commit(metadata)?;
+            return Ok(SnapshotUpdate {
+                metadata,
+                manifests,
+            });
+        }
+
+        self.table.commit(metadata).await?;
+        Ok(SnapshotUpdate {
+            metadata: self.table.current_table_metadata()?.clone(),
+            manifests,
+        })
+    }
+
+    /// Write the manifest list and build the table metadata with the new
+    /// snapshot, without committing it.
+    ///
+    /// The manifest list is not written in dry-run mode.
+    pub async fn build_metadata(
+        &mut self,
+        manifests: &[types::ManifestList],
+        mut summary: HashMap<String, String>,
+    ) -> Result<types::TableMetadata> {
+        let mut metadata = self.metadata.clone();
+        if let Some(wap_id) = &self.wap_id {
+            summary.insert("wap.id".to_string(), wap_id.clone());
+        }
+
+        let path = format!(
+            "metadata/snap-{}-1-{}.avro",
+            self.snapshot_id, self.commit_uuid
+        );
+        let snapshot = types::Snapshot {
+            snapshot_id: self.snapshot_id,
+            parent_snapshot_id: metadata.current_snapshot_id,
+            sequence_number: self.sequence_number,
+            timestamp_ms: self.table.clock().now_ms(),
+            manifest_list: format!("{}/{}", metadata.location, path),
+            summary,
+            schema_id: Some(metadata.current_schema_id as i64),
+        };
+        let content =
+            types::serialize_manifest_list(metadata.format_version, &snapshot, manifests)?;
+        if !self.dry_run {
+            self.table.operator().write(&path, content).await?;
+        }
+
+        if metadata.format_version == types::TableFormatVersion::V2 {
+            metadata.last_sequence_number = self.sequence_number;
+        }
+        if self.wap_id.is_none() {
+            set_current_snapshot(&mut metadata, snapshot.snapshot_id, snapshot.timestamp_ms);
+        }
+        metadata
+            .snapshots
+            .get_or_insert_with(Vec::new)
+            .push(snapshot);
+
+        Ok(metadata)
+    }
+}
+
+/// Set the snapshot as the current snapshot of metadata, which is
+/// referenced by the `main` branch.
+pub(crate) fn set_current_snapshot(
+    metadata: &mut types::TableMetadata,
+    snapshot_id: i64,
+    timestamp_ms: i64,
+) {
+    metadata.current_snapshot_id = Some(snapshot_id);
+    metadata
+        .snapshot_log
+        .get_or_insert_with(Vec::new)
+        .push(types::SnapshotLog {
+            timestamp_ms,
+            snapshot_id,
+        });
+    metadata
+        .refs
+        .get_or_insert_with(HashMap::new)
+        .entry("main".to_string())
+        .and_modify(|v| v.snapshot_id = snapshot_id)
+        .or_insert(types::SnapshotReference {
+            snapshot_id,
+            typ: types::SnapshotReferenceType::Branch,
+            min_snapshots_to_keep: None,
+            max_snapshot_age_ms: None,
+            max_ref_age_ms: None,
+        });
+}
+
+/// Summarize partition values of entries for each partition field.
+///
+/// NaN values are not counted in bounds, and `contains_nan` is always
+/// set, which is false for non-floating fields.
+fn partition_summaries(
+    field_count: usize,
+    entries: &[types::ManifestFile],
+) -> Vec<types::FieldSummary> {
+    (0..field_count)
+        .map(|idx| {
+            let mut contains_null = false;
+            let mut contains_nan = false;
+            let mut lower: Option<&types::Datum> = None;
+            let mut upper: Option<&types::Datum> = None;
+            for entry in entries {
+                let value = match entry.data_file.partition.get(idx) {
+                    Some(Some(v)) => v,
+                    _ => {
+                        contains_null = true;
+                        continue;
+                    }
+                };
+                match value {
+                    types::Datum::Float(v) if v.is_nan() => contains_nan = true,
+                    types::Datum::Double(v) if v.is_nan() => contains_nan = true,
+                    _ => {
+                        if lower.is_none_or(|v| value < v) {
+                            lower = Some(value);
+                        }
+                        if upper.is_none_or(|v| value > v) {
+                            upper = Some(value);
+                        }
+                    }
+                }
+            }
+
+            types::FieldSummary {
+                contains_null,
+                contains_nan: Some(contains_nan),
+                lower_bound: lower.map(|v| v.to_bytes()),
+                upper_bound: upper.map(|v| v.to_bytes()),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,367 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::types;
+use crate::Table;
+
+/// A change of schema.
+enum SchemaChange {
+    Add {
+        name: String,
+        typ: types::Any,
+        doc: Option<String>,
+    },
+    Delete {
+        name: String,
+    },
+    Rename {
+        name: String,
+        new_name: String,
+    },
+}
+
+/// UpdateSchemaOperation evolves the schema of table.
+///
+/// Changes are applied to the current schema in order, and the result is
+/// committed as a new schema which becomes the current schema. Field ids
+/// of new columns are allocated from `last-column-id`, so ids are never
+/// reused, even for dropped columns. Ids of renamed columns are kept, so
+/// that existing data files remain readable.
+///
+/// Initiate a new operation with `Table::update_schema()`.
+pub struct UpdateSchemaOperation<'a> {
+    table: &'a mut Table,
+    changes: Vec<SchemaChange>,
+}
+
+impl<'a> UpdateSchemaOperation<'a> {
+    pub(crate) fn new(table: &'a mut Table) -> Self {
+        Self {
+            table,
+            changes: vec![],
+        }
+    }
+
+    /// Add an optional top level column.
+    ///
+    /// Ids of `typ` are ignored, new ids will be allocated for the column
+    /// and the fields nested in it.
+    pub fn add_column(mut self, name: &str, typ: types::Any, doc: Option<&str>) -> Self {
+        self.changes.push(SchemaChange::Add {
+            name: name.to_string(),
+            typ,
+            doc: doc.map(|v| v.to_string()),
+        });
+        self
+    }
+
+    /// Drop a top level column.
+    ///
+    /// Columns used by the default partition spec or identifier fields
+    /// can't be dropped.
+    pub fn drop_column(mut self, name: &str) -> Self {
+        self.changes.push(SchemaChange::Delete {
+            name: name.to_string(),
+        });
+        self
+    }
+
+    /// Rename a top level column, the field id is not changed.
+    pub fn rename_column(mut self, name: &str, new_name: &str) -> Self {
+        self.changes.push(SchemaChange::Rename {
+            name: name.to_string(),
+            new_name: new_name.to_string(),
+        });
+        self
+    }
+
+    /// Commit the new schema.
+    pub async fn commit(self) -> Result<()> {
+        let mut metadata = self.table.current_table_metadata()?.clone();
+        let partition_spec = metadata.current_partition_spec()?.clone();
+        let mut schema = metadata.current_schema()?.clone();
+        let mut last_column_id = metadata.last_column_id;
+
+        for change in self.changes {
+            match change {
+                SchemaChange::Add { name, typ, doc } => {
+                    if schema.fields.iter().any(|v| v.name == name) {
+                        return Err(anyhow!("column {} already exists", name));
+                    }
+
+                    last_column_id += 1;
+                    let id = last_column_id;
+                    let field_type = assign_ids(typ, &mut last_column_id);
+                    schema.fields.push(types::Field {
+                        id,
+                        name,
+                        required: false,
+                        field_type,
+                        comment: doc,
+                    });
+                }
+                SchemaChange::Delete { name } => {
+                    let idx = schema
+                        .fields
+                        .iter()
+                        .position(|v| v.name == name)
+                        .ok_or_else(|| anyhow!("column {} is not found", name))?;
+                    let id = schema.fields[idx].id;
+                    if partition_spec
+                        .fields
+                        .iter()
+                        .any(|v| v.source_column_id == id)
+                    {
+                        return Err(anyhow!(
+                            "column {} is used by the default partition spec",
+                            name
+                        ));
+                    }
+                    if schema
+                        .identifier_field_ids
+                        .as_ref()
+                        .is_some_and(|v| v.contains(&id))
+                    {
+                        return Err(anyhow!("column {} is an identifier field", name));
+                    }
+
+                    schema.fields.remove(idx);
+                }
+                SchemaChange::Rename { name, new_name } => {
+                    if schema.fields.iter().any(|v| v.name == new_name) {
+                        return Err(anyhow!("column {} already exists", new_name));
+                    }
+                    let field = schema
+                        .fields
+                        .iter_mut()
+                        .find(|v| v.name == name)
+                        .ok_or_else(|| anyhow!("column {} is not found", name))?;
+
+                    field.name = new_name;
+                }
+            }
+        }
+
+        schema.schema_id = metadata
+            .schemas
+            .iter()
+            .map(|v| v.schema_id)
+            .max()
+            .unwrap_or_default()
+            + 1;
+        metadata.current_schema_id = schema.schema_id;
+        metadata.last_column_id = last_column_id;
+        metadata.schemas.push(schema);
+
+        self.table.commit(metadata).await
+    }
+}
+
+/// Assign new ids to the fields nested in type.
+fn assign_ids(typ: types::Any, last_column_id: &mut i32) -> types::Any {
+    let mut next_id = || {
+        *last_column_id += 1;
+        *last_column_id
+    };
+
+    match typ {
+        types::Any::Primitive(v) => types::Any::Primitive(v),
+        types::Any::Unknown(v) => types::Any::Unknown(v),
+        types::Any::Struct(v) => {
+            // Allocate ids of fields first, so that they are continuous.
+            let ids: Vec<_> = v.fields.iter().map(|_| next_id()).collect();
+            let fields = v
+                .fields
+                .into_iter()
+                .zip(ids)
+                .map(|(field, id)| types::Field {
+                    id,
+                    field_type: assign_ids(field.field_type, last_column_id),
+                    ..field
+                })
+                .collect();
+            types::Any::Struct(types::Struct { fields })
+        }
+        types::Any::List(v) => {
+            let element_id = next_id();
+            types::Any::List(types::List {
+                element_id,
+                element_required: v.element_required,
+                element_type: Box::new(assign_ids(*v.element_type, last_column_id)),
+            })
+        }
+        types::Any::Map(v) => {
+            let key_id = next_id();
+            let value_id = next_id();
+            types::Any::Map(types::Map {
+                key_id,
+                key_type: Box::new(assign_ids(*v.key_type, last_column_id)),
+                value_id,
+                value_required: v.value_required,
+                value_type: Box::new(assign_ids(*v.value_type, last_column_id)),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::test_utils::*;
+
+    #[tokio::test]
+    async fn test_add_column() -> Result<()> {
+        let mut table = create_table("2").await?;
+
+        table
+            .add_column(
+                "score",
+                types::Any::Primitive(types::Primitive::Double),
+                Some("score of row"),
+            )
+            .await?;
+
+        let metadata = table.current_table_metadata()?;
+        assert_eq!(metadata.last_column_id, 3);
+        assert_eq!(metadata.current_schema_id, 1);
+        assert_eq!(metadata.schemas.len(), 2);
+        let schema = metadata.current_schema()?;
+        assert_eq!(schema.fields[2].id, 3);
+        assert_eq!(schema.fields[2].name, "score");
+        assert!(!schema.fields[2].required);
+        assert_eq!(schema.fields[2].comment.as_deref(), Some("score of row"));
+        // Previous schema is kept.
+        assert_eq!(metadata.schemas[0].fields.len(), 2);
+
+        // Nested fields get new ids.
+        let typ = types::Any::Struct(types::Struct {
+            fields: vec![
+                types::Field {
+                    id: 0,
+                    name: "tags".to_string(),
+                    required: false,
+                    field_type: types::Any::List(types::List {
+                        element_id: 0,
+                        element_required: true,
+                        element_type: Box::new(types::Any::Primitive(types::Primitive::String)),
+                    }),
+                    comment: None,
+                },
+                types::Field {
+                    id: 0,
+                    name: "city".to_string(),
+                    required: false,
+                    field_type: types::Any::Primitive(types::Primitive::String),
+                    comment: None,
+                },
+            ],
+        });
+        table.add_column("address", typ, None).await?;
+
+        let metadata = table.current_table_metadata()?;
+        assert_eq!(metadata.last_column_id, 7);
+        assert_eq!(metadata.current_schema_id, 2);
+        let field = &metadata.current_schema()?.fields[3];
+        assert_eq!(field.id, 4);
+        let types::Any::Struct(s) = &field.field_type else {
+            panic!("address must be a struct");
+        };
+        assert_eq!((s.fields[0].id, s.fields[1].id), (5, 6));
+        let types::Any::List(l) = &s.fields[0].field_type else {
+            panic!("tags must be a list");
+        };
+        assert_eq!(l.element_id, 7);
+
+        // Metadata is committed.
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v3.metadata.json")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_drop_and_rename_column() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .add_column(
+                "score",
+                types::Any::Primitive(types::Primitive::Double),
+                None,
+            )
+            .await?;
+
+        table.rename_column("score", "points").await?;
+        let schema = table.current_table_metadata()?.current_schema()?;
+        assert_eq!(schema.fields[2].id, 3);
+        assert_eq!(schema.fields[2].name, "points");
+
+        table.drop_column("points").await?;
+        let metadata = table.current_table_metadata()?;
+        let schema = metadata.current_schema()?;
+        assert_eq!(
+            schema
+                .fields
+                .iter()
+                .map(|v| (v.id, v.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "id"), (2, "region")]
+        );
+        assert_eq!(metadata.last_column_id, 3);
+
+        // Ids of dropped columns are not reused.
+        table
+            .add_column(
+                "points",
+                types::Any::Primitive(types::Primitive::Long),
+                None,
+            )
+            .await?;
+        let schema = table.current_table_metadata()?.current_schema()?;
+        assert_eq!(schema.fields[2].id, 4);
+
+        // Renaming a column to an existing name is not allowed.
+        assert!(table.rename_column("id", "region").await.is_err());
+        assert!(table.rename_column("unknown", "other").await.is_err());
+        // Partition source columns can't be dropped.
+        assert!(table.drop_column("region").await.is_err());
+        assert!(table.drop_column("unknown").await.is_err());
+
+        // Changes are applied in order.
+        table
+            .update_schema()
+            .rename_column("id", "uid")
+            .rename_column("points", "id")
+            .commit()
+            .await?;
+        let schema = table.current_table_metadata()?.current_schema()?;
+        assert_eq!(
+            schema
+                .fields
+                .iter()
+                .map(|v| (v.id, v.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "uid"), (2, "region"), (4, "id")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_column_exists() -> Result<()> {
+        let mut table = create_table("2").await?;
+
+        let result = table
+            .add_column(
+                "region",
+                types::Any::Primitive(types::Primitive::String),
+                None,
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(table.current_table_metadata()?.last_column_id, 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1730 @@
+//! scan module provides the planning of table scans.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+#[cfg(feature = "io_parquet")]
+use std::collections::HashSet;
+#[cfg(feature = "io_parquet")]
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+#[cfg(feature = "io_parquet")]
+use arrow_array::cast::AsArray;
+#[cfg(feature = "io_parquet")]
+use arrow_array::types::Decimal128Type;
+#[cfg(feature = "io_parquet")]
+use arrow_array::types::Float32Type;
+#[cfg(feature = "io_parquet")]
+use arrow_array::types::Int32Type;
+#[cfg(feature = "io_parquet")]
+use arrow_array::Array;
+#[cfg(feature = "io_parquet")]
+use arrow_array::ArrayRef;
+#[cfg(feature = "io_parquet")]
+use arrow_array::Decimal128Array;
+#[cfg(feature = "io_parquet")]
+use arrow_array::Float64Array;
+#[cfg(feature = "io_parquet")]
+use arrow_array::Int64Array;
+#[cfg(feature = "io_parquet")]
+use arrow_array::RecordBatch;
+#[cfg(feature = "io_parquet")]
+use arrow_array::StringArray;
+#[cfg(feature = "io_parquet")]
+use arrow_schema::ArrowError;
+#[cfg(feature = "io_parquet")]
+use arrow_schema::DataType;
+#[cfg(feature = "io_parquet")]
+use arrow_schema::Schema as ArrowSchema;
+#[cfg(feature = "io_parquet")]
+use arrow_schema::DECIMAL128_MAX_PRECISION;
+use futures::StreamExt;
+use futures::TryStreamExt;
+#[cfg(feature = "io_parquet")]
+use parquet::schema::types::ColumnDescriptor;
+
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::ParquetMetadataCache;
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::ParquetReader;
+#[cfg(feature = "io_parquet")]
+use crate::io::parquet::ParquetReaderBuilder;
+use crate::types;
+use crate::types::Expression;
+use crate::Table;
+
+/// The max number of data files checked at the same time by
+/// `TableScan::verify_files`.
+const VERIFY_FILES_CONCURRENCY: usize = 16;
+
+/// Number of rows collected by `TableScan::collect` to warn about.
+#[cfg(feature = "io_parquet")]
+const LARGE_COLLECT_ROWS: usize = 1_000_000;
+
+/// ScanReport records what a scan planning consulted, so that results can
+/// be traced back to the exact version of data.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScanReport {
+    /// Id of the snapshot planned, `None` if the table has no snapshot.
+    pub snapshot_id: Option<i64>,
+    /// Location of the manifest list of the snapshot.
+    pub manifest_list_path: Option<String>,
+    /// Locations of manifests read while planning, in reading order.
+    pub manifest_paths: Vec<String>,
+    /// Locations of missing data files skipped by `verify_files`.
+    pub missing_files: Vec<String>,
+    /// Locations of missing data manifests skipped by the load policy of
+    /// table.
+    pub skipped_manifest_paths: Vec<String>,
+}
+
+/// MissingFileMode decides how missing files found by
+/// `TableScan::verify_files` are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingFileMode {
+    /// Fail the planning, which is the default.
+    #[default]
+    Error,
+    /// Drop missing data files from the plan with a warning.
+    ///
+    /// Missing delete files still fail the planning, since skipping them
+    /// would bring deleted rows back.
+    Skip,
+}
+
+/// TableScan plans data files of the current snapshot to read.
+///
+/// Initiate a new scan with `Table::scan()`.
+pub struct TableScan<'a> {
+    table: &'a Table,
+    filter: Expression,
+    /// `None` means all columns are selected.
+    columns: Option<Vec<String>>,
+    /// `None` means all rows are read.
+    limit: Option<usize>,
+    case_sensitive: bool,
+    verify_files: bool,
+    missing_file_mode: MissingFileMode,
+    /// Shared by all parquet readers of the scan.
+    #[cfg(feature = "io_parquet")]
+    metadata_cache: ParquetMetadataCache,
+}
+
+impl<'a> TableScan<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        Self {
+            table,
+            filter: Expression::AlwaysTrue,
+            columns: None,
+            limit: None,
+            case_sensitive: true,
+            verify_files: false,
+            missing_file_mode: MissingFileMode::Error,
+            #[cfg(feature = "io_parquet")]
+            metadata_cache: ParquetMetadataCache::new(),
+        }
+    }
+
+    /// Use the cache of parquet metadata for readers of the scan, so that
+    /// footers can be shared with other scans of the same table.
+    ///
+    /// Each scan has its own cache by default.
+    #[cfg(feature = "io_parquet")]
+    pub fn with_metadata_cache(mut self, cache: ParquetMetadataCache) -> Self {
+        self.metadata_cache = cache;
+        self
+    }
+
+    /// The cache of parquet metadata used by readers of the scan.
+    #[cfg(feature = "io_parquet")]
+    pub fn metadata_cache(&self) -> &ParquetMetadataCache {
+        &self.metadata_cache
+    }
+
+    /// Select columns to read by names, all columns are read by default.
+    ///
+    /// Subfields of struct columns are selected by dotted paths like
+    /// `address.city`, the nested structure is kept in the output with
+    /// only selected subfields.
+    pub fn select(mut self, columns: &[&str]) -> Self {
+        self.columns = Some(columns.iter().map(|v| v.to_string()).collect());
+        self
+    }
+
+    /// Set whether column names are resolved case-sensitively, which is
+    /// `true` by default.
+    ///
+    /// Names of selected columns are resolved case-insensitively if
+    /// `false`, which fails if a name matches more than one field. Filters
+    /// refer to columns by field ids, which are not affected.
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Stop reading after `n` live rows are read, so that previewing a
+    /// huge table doesn't read all data files.
+    ///
+    /// The limit is applied after rows removed by delete files are
+    /// skipped.
+    pub fn limit(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Set whether planned files are checked to exist in storage, which is
+    /// `false` by default.
+    ///
+    /// Files deleted out-of-band are found by stat before being read, and
+    /// handled by `with_missing_file_mode`. Files are checked
+    /// concurrently, but it still costs a request per planned file.
+    pub fn verify_files(mut self, verify: bool) -> Self {
+        self.verify_files = verify;
+        self
+    }
+
+    /// Set how missing files found by `verify_files` are handled,
+    /// `MissingFileMode::Error` by default.
+    pub fn with_missing_file_mode(mut self, mode: MissingFileMode) -> Self {
+        self.missing_file_mode = mode;
+        self
+    }
+
+    /// Set the filter on columns of the table, which is used to prune data
+    /// files that can't contain matched rows.
+    ///
+    /// Pruning is inclusive: rows of planned files still need to be
+    /// filtered by readers.
+    pub fn filter(mut self, filter: Expression) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Plan data files and delete files of the current snapshot which may
+    /// contain rows matching the filter.
+    ///
+    /// Entries are filtered while being decoded from manifests: the filter
+    /// projected by the partition spec of manifest is evaluated against
+    /// partition values of each entry, and the filter itself is evaluated
+    /// against statistics of each data file. So that only matched entries
+    /// are buffered, even for huge manifests.
+    pub async fn plan_files(&self) -> Result<Vec<types::DataFile>> {
+        let (data_files, _) = self.plan_files_with_report().await?;
+        Ok(data_files)
+    }
+
+    /// Plan files like `plan_files`, along with a report of the snapshot
+    /// and manifests consulted.
+    pub async fn plan_files_with_report(&self) -> Result<(Vec<types::DataFile>, ScanReport)> {
+        let (entries, report) = self.plan_entries().await?;
+        Ok((entries.into_iter().map(|(v, _)| v).collect(), report))
+    }
+
+    /// Plan files along with their data sequence numbers, which decide
+    /// whether a delete file applies to a data file.
+    async fn plan_entries(&self) -> Result<(Vec<(types::DataFile, i64)>, ScanReport)> {
+        let mut report = ScanReport::default();
+        let meta = self.table.current_table_metadata()?;
+        let Some(current_snapshot_id) = meta.current_snapshot_id else {
+            // An empty table has no snapshot yet.
+            return Ok((vec![], report));
+        };
+        let current_snapshot = meta
+            .snapshots
+            .iter()
+            .flatten()
+            .find(|v| v.snapshot_id == current_snapshot_id)
+            .ok_or_else(|| anyhow!("snapshot with id {} is not found", current_snapshot_id))?;
+        let schema = meta.current_schema()?;
+
+        let op = self.table.operator();
+        let manifest_list_path = self.table.rel_path(&current_snapshot.manifest_list)?;
+        let manifest_list = types::parse_manifest_list(&op.read(&manifest_list_path).await?)?;
+        report.snapshot_id = Some(current_snapshot_id);
+        report.manifest_list_path = Some(current_snapshot.manifest_list.clone());
+
+        // Filters projected by partition specs keyed by spec id.
+        let mut partition_filters = HashMap::new();
+        let mut data_files = vec![];
+        for manifest in manifest_list {
+            let spec = meta
+                .partition_specs
+                .iter()
+                .find(|v| v.spec_id == manifest.partition_spec_id)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "partition spec {} of manifest {} is not found",
+                        manifest.partition_spec_id,
+                        manifest.manifest_path
+                    )
+                })?;
+            let partition_filter = match partition_filters.entry(spec.spec_id) {
+                Entry::Occupied(v) => v.into_mut(),
+                Entry::Vacant(v) => v.insert(self.filter.project(spec)?),
+            };
+
+            let Some(content) = self.table.read_manifest(&manifest).await? else {
+                report.skipped_manifest_paths.push(manifest.manifest_path);
+                continue;
+            };
+            report.manifest_paths.push(manifest.manifest_path.clone());
+            let manifest_meta = types::visit_manifest_file(
+                &content,
+                &meta.partition_specs,
+                |manifest_meta, entry| {
+                    check_manifest_format_version(meta.format_version, &manifest, manifest_meta)?;
+                    if entry.status == types::ManifestStatus::Deleted {
+                        return Ok(());
+                    }
+                    let sequence_number = entry.data_sequence_number(&manifest)?;
+                    let data_file = entry.data_file;
+                    if !partition_filter.can_match_partition(spec, &data_file.partition) {
+                        return Ok(());
+                    }
+                    // Statistics of delete files are about deleted rows, which
+                    // can't be used to prune.
+                    if data_file.content == types::DataContentType::Data {
+                        // Bounds that can't be decoded by current schema, for
+                        // example, written before type promotion, are not used
+                        // to prune.
+                        if let Ok(stats) = types::DataFileStats::try_new(&data_file, schema) {
+                            if !self.filter.can_match_file(&stats) {
+                                return Ok(());
+                            }
+                        }
+                    }
+
+                    data_files.push((data_file, sequence_number));
+                    Ok(())
+                },
+            )?;
+            // Empty manifests are not visited.
+            check_manifest_format_version(meta.format_version, &manifest, &manifest_meta)?;
+        }
+
+        if self.verify_files {
+            data_files = self.verify_data_files(data_files, &mut report).await?;
+        }
+        Ok((data_files, report))
+    }
+
+    /// Check planned files exist by stat, and handle missing ones by
+    /// `missing_file_mode`.
+    async fn verify_data_files(
+        &self,
+        data_files: Vec<(types::DataFile, i64)>,
+        report: &mut ScanReport,
+    ) -> Result<Vec<(types::DataFile, i64)>> {
+        // Data files may be written outside table location.
+        let op = self.table.root_operator();
+        let exists: Vec<bool> = futures::stream::iter(&data_files)
+            .map(|(data_file, _)| async move {
+                let path = self.table.root_path(&data_file.file_path)?;
+                match op.stat(&path).await {
+                    Ok(_) => Ok(true),
+                    Err(err) if err.kind() == opendal::ErrorKind::NotFound => Ok(false),
+                    Err(err) => Err(anyhow!(
+                        "stat data file {} failed: {}",
+                        data_file.file_path,
+                        err
+                    )),
+                }
+            })
+            .buffered(VERIFY_FILES_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut verified = Vec::with_capacity(data_files.len());
+        for ((data_file, sequence_number), exist) in data_files.into_iter().zip(exists) {
+            if exist {
+                verified.push((data_file, sequence_number));
+                continue;
+            }
+            if self.missing_file_mode == MissingFileMode::Error
+                || data_file.content != types::DataContentType::Data
+            {
+                return Err(anyhow!(
+                    "{:?} file {} is missing in storage",
+                    data_file.content,
+                    data_file.file_path
+                ));
+            }
+            log::warn!("data file {} is missing, skipped", data_file.file_path);
+            report.missing_files.push(data_file.file_path);
+        }
+
+        Ok(verified)
+    }
+
+    /// Read all rows of planned data files into memory.
+    ///
+    /// Rows removed by position delete files are skipped. Rows are not
+    /// filtered by the filter, which is only used to prune files.
+    ///
+    /// # Notes
+    ///
+    /// Top level columns written in types promoted later, like `int`
+    /// columns of `long` fields, are read in the types of current schema.
+    ///
+    /// Equality deletes apply to data files of the same partition, or all
+    /// data files if they are unpartitioned, whose data sequence numbers
+    /// are smaller than theirs. Columns of equality deletes must be top
+    /// level primitive columns.
+    ///
+    /// Only parquet files are supported. This is meant for notebooks and
+    /// small tables, a warning will be logged if too many rows are
+    /// collected.
+    #[cfg(feature = "io_parquet")]
+    pub async fn collect(&self) -> Result<Vec<RecordBatch>> {
+        let schema = self.table.current_table_metadata()?.current_schema()?;
+        let mut data_files = vec![];
+        let mut deletes: HashMap<String, HashMap<i64, i64>> = HashMap::new();
+        let mut equality_deletes = vec![];
+        let (entries, _) = self.plan_entries().await?;
+        for (data_file, sequence_number) in entries {
+            match data_file.content {
+                types::DataContentType::Data => data_files.push((data_file, sequence_number)),
+                types::DataContentType::PostionDeletes => {
+                    self.read_position_deletes(&data_file, sequence_number, &mut deletes)
+                        .await?
+                }
+                types::DataContentType::EqualityDeletes => equality_deletes.push(
+                    self.read_equality_deletes(data_file, sequence_number, schema)
+                        .await?,
+                ),
+            }
+        }
+
+        // Columns of equality deletes are read along with selected ones,
+        // and removed once deletes are applied.
+        let mut extra_columns: Vec<&types::Field> = vec![];
+        let selection = match &self.columns {
+            None => None,
+            Some(columns) => {
+                let mut columns = columns.clone();
+                for field in equality_deletes.iter().flat_map(|v| &v.fields) {
+                    let selected = columns.iter().any(|v| {
+                        let top = v.split('.').next().unwrap_or_default();
+                        if self.case_sensitive {
+                            top == field.name
+                        } else {
+                            top.eq_ignore_ascii_case(&field.name)
+                        }
+                    });
+                    if !selected {
+                        columns.push(field.name.clone());
+                        extra_columns.push(field);
+                    }
+                }
+                Some(Arc::new(ColumnSelection::try_new(
+                    schema,
+                    &columns,
+                    self.case_sensitive,
+                )?))
+            }
+        };
+
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let mut batches = vec![];
+        let mut rows = 0;
+        // Push live rows of batch until the limit is reached.
+        fn push(
+            batches: &mut Vec<RecordBatch>,
+            rows: &mut usize,
+            limit: usize,
+            batch: RecordBatch,
+        ) {
+            let len = batch.num_rows().min(limit - *rows);
+            if len > 0 {
+                *rows += len;
+                batches.push(batch.slice(0, len));
+            }
+        }
+        for (data_file, sequence_number) in data_files {
+            if rows >= limit {
+                break;
+            }
+            // Position deletes only apply to data files whose sequence
+            // numbers are not larger than theirs.
+            let deleted: Option<HashSet<i64>> = deletes
+                .get(&data_file.file_path)
+                .map(|v| {
+                    v.iter()
+                        .filter(|(_, v)| **v >= sequence_number)
+                        .map(|(pos, _)| *pos)
+                        .collect::<HashSet<_>>()
+                })
+                .filter(|v| !v.is_empty());
+            let equality_deletes: Vec<_> = equality_deletes
+                .iter()
+                .filter(|v| v.applies_to(&data_file, sequence_number))
+                .collect();
+            let mut reader = self.parquet_reader(&data_file, selection.clone()).await?;
+            let mut pos = 0;
+            while let Some(batch) = reader.next().await? {
+                if rows >= limit {
+                    break;
+                }
+                let batch = promote_types(batch, schema)?;
+                let start = pos;
+                pos += batch.num_rows() as i64;
+                if deleted.is_none() && equality_deletes.is_empty() {
+                    push(
+                        &mut batches,
+                        &mut rows,
+                        limit,
+                        remove_columns(batch, &extra_columns)?,
+                    );
+                    continue;
+                }
+
+                let mut is_deleted = vec![false; batch.num_rows()];
+                if let Some(deleted) = &deleted {
+                    for (idx, v) in is_deleted.iter_mut().enumerate() {
+                        *v = deleted.contains(&(start + idx as i64));
+                    }
+                }
+                for deletes in &equality_deletes {
+                    deletes.mark_deleted(&batch, &mut is_deleted)?;
+                }
+                let batch = remove_columns(batch, &extra_columns)?;
+
+                // Keep runs of live rows by slicing, so that rows don't
+                // need to be copied.
+                let mut offset = 0;
+                for idx in 0..=batch.num_rows() {
+                    // The end of batch is treated as a deleted row.
+                    if is_deleted.get(idx).copied().unwrap_or(true) {
+                        if idx > offset {
+                            push(
+                                &mut batches,
+                                &mut rows,
+                                limit,
+                                batch.slice(offset, idx - offset),
+                            );
+                        }
+                        offset = idx + 1;
+                    }
+                }
+            }
+        }
+
+        if rows > LARGE_COLLECT_ROWS {
+            log::warn!(
+                "{} rows are collected into memory, consider reading data files in streaming",
+                rows
+            );
+        }
+        Ok(batches)
+    }
+
+    /// Read positions of deleted rows keyed by path of data file, along
+    /// with the largest sequence number of delete files deleting them.
+    #[cfg(feature = "io_parquet")]
+    async fn read_position_deletes(
+        &self,
+        delete_file: &types::DataFile,
+        sequence_number: i64,
+        deletes: &mut HashMap<String, HashMap<i64, i64>>,
+    ) -> Result<()> {
+        let mut reader = self.parquet_reader(delete_file, None).await?;
+        while let Some(batch) = reader.next().await? {
+            for (path, pos) in parse_position_deletes(&delete_file.file_path, &batch)? {
+                let v = deletes
+                    .entry(path)
+                    .or_default()
+                    .entry(pos)
+                    .or_insert(sequence_number);
+                *v = (*v).max(sequence_number);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read rows of the equality delete file, which are keyed by values of
+    /// its equality columns.
+    #[cfg(feature = "io_parquet")]
+    async fn read_equality_deletes(
+        &self,
+        delete_file: types::DataFile,
+        sequence_number: i64,
+        schema: &types::Schema,
+    ) -> Result<EqualityDeletes> {
+        let mut fields = vec![];
+        for id in delete_file.equality_ids.iter().flatten() {
+            let field = schema.fields.iter().find(|v| v.id == *id).ok_or_else(|| {
+                anyhow!(
+                    "equality field {} of delete file {} is not a top level field",
+                    id,
+                    delete_file.file_path
+                )
+            })?;
+            if !matches!(field.field_type, types::Any::Primitive(_)) {
+                return Err(anyhow!(
+                    "equality field {} of delete file {} must be primitive",
+                    field.name,
+                    delete_file.file_path
+                ));
+            }
+            fields.push(field.clone());
+        }
+        if fields.is_empty() {
+            return Err(anyhow!(
+                "equality delete file {} has no equality ids",
+                delete_file.file_path
+            ));
+        }
+
+        let mut deletes = EqualityDeletes {
+            partition: delete_file.partition.clone(),
+            sequence_number,
+            fields,
+            keys: HashSet::new(),
+        };
+        let mut reader = self.parquet_reader(&delete_file, None).await?;
+        while let Some(batch) = reader.next().await? {
+            let batch = promote_types(batch, schema)?;
+            let columns = deletes.key_columns(&batch)?;
+            for row in 0..batch.num_rows() {
+                deletes.keys.insert(EqualityDeletes::key(&columns, row));
+            }
+        }
+
+        Ok(deletes)
+    }
+
+    /// Open the reader of data file by its format.
+    #[cfg(feature = "io_parquet")]
+    pub(crate) async fn parquet_reader(
+        &self,
+        data_file: &types::DataFile,
+        selection: Option<Arc<ColumnSelection>>,
+    ) -> Result<ParquetReader> {
+        if data_file.file_format != types::DataFileFormat::Parquet {
+            return Err(anyhow!(
+                "format {:?} of data file {} is not supported yet",
+                data_file.file_format,
+                data_file.file_path
+            ));
+        }
+
+        // Data files may be written outside table location.
+        let mut builder = ParquetReaderBuilder::new(
+            self.table.root_operator().clone(),
+            &self.table.root_path(&data_file.file_path)?,
+        )
+        .with_file_size(data_file.file_size_in_bytes as u64)
+        .with_metadata_cache(self.metadata_cache.clone());
+        if let Some(selection) = selection {
+            builder = builder.with_column_filter(move |column| selection.contains(column));
+        }
+
+        Ok(builder.build().await?)
+    }
+}
+
+/// Promote top level columns of batch read from data file to the types of
+/// fields in current schema.
+///
+/// Columns are matched with fields by the ids kept in arrow field metadata,
+/// or by name if the file has no field ids, so that renamed columns are
+/// still promoted.
+///
+/// Only promotions allowed by the spec are done: `int` to `long`, `float`
+/// to `double` and widening of decimals, others are left as is.
+#[cfg(feature = "io_parquet")]
+pub(crate) fn promote_types(batch: RecordBatch, schema: &types::Schema) -> Result<RecordBatch> {
+    let arrow_schema = batch.schema();
+    let mut fields = Vec::with_capacity(batch.num_columns());
+    let mut columns = Vec::with_capacity(batch.num_columns());
+    let mut promoted = false;
+    for (field, column) in arrow_schema.fields().iter().zip(batch.columns()) {
+        let field_id = field
+            .metadata()
+            .get(crate::io::parquet::FIELD_ID_META_KEY)
+            .map(|v| {
+                v.parse::<i32>()
+                    .map_err(|_| anyhow!("column {} has invalid field id {}", field.name(), v))
+            })
+            .transpose()?;
+        let typ = schema
+            .fields
+            .iter()
+            .find(|v| match field_id {
+                Some(id) => v.id == id,
+                None => &v.name == field.name(),
+            })
+            .and_then(|v| match &v.field_type {
+                types::Any::Primitive(typ) => Some(typ),
+                _ => None,
+            });
+        let column = match (column.data_type(), typ) {
+            (DataType::Int32, Some(types::Primitive::Long)) => {
+                let array: Int64Array = column.as_primitive::<Int32Type>().unary(|v| v as i64);
+                Arc::new(array) as ArrayRef
+            }
+            (DataType::Float32, Some(types::Primitive::Double)) => {
+                let array: Float64Array = column.as_primitive::<Float32Type>().unary(|v| v as f64);
+                Arc::new(array) as ArrayRef
+            }
+            (
+                DataType::Decimal128(from_precision, from_scale),
+                Some(types::Primitive::Decimal { precision, scale }),
+            ) if (*from_precision, *from_scale as u8) != (*precision, *scale) => {
+                // Both scale and digits before the point must not shrink.
+                let from_integral = *from_precision as i16 - *from_scale as i16;
+                if *precision < *from_precision
+                    || (*scale as i8) < *from_scale
+                    || (*precision as i16 - *scale as i16) < from_integral
+                    || *precision > DECIMAL128_MAX_PRECISION
+                {
+                    return Err(anyhow!(
+                        "column {} of decimal({}, {}) can't be promoted to decimal({}, {})",
+                        field.name(),
+                        from_precision,
+                        from_scale,
+                        precision,
+                        scale
+                    ));
+                }
+                let factor = 10_i128.pow((*scale as i8 - *from_scale) as u32);
+                let array: Decimal128Array =
+                    column.as_primitive::<Decimal128Type>().try_unary(|v| {
+                        v.checked_mul(factor).ok_or_else(|| {
+                            ArrowError::ComputeError(format!("decimal {v} overflows"))
+                        })
+                    })?;
+                let array = array.with_precision_and_scale(*precision, *scale as i8)?;
+                array.validate_decimal_precision(*precision)?;
+                Arc::new(array) as ArrayRef
+            }
+            _ => {
+                fields.push(field.clone());
+                columns.push(column.clone());
+                continue;
+            }
+        };
+        promoted = true;
+        fields.push(Arc::new(
+            field
+                .as_ref()
+                .clone()
+                .with_data_type(column.data_type().clone()),
+        ));
+        columns.push(column);
+    }
+
+    if !promoted {
+        return Ok(batch);
+    }
+    let schema = ArrowSchema::new_with_metadata(fields, arrow_schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Find the index of column of the top level field in batch, by the field
+/// id if any, or by the name.
+#[cfg(feature = "io_parquet")]
+fn column_index_of_field(batch: &RecordBatch, field: &types::Field) -> Option<usize> {
+    let id = field.id.to_string();
+    batch.schema().fields().iter().position(|v| {
+        match v.metadata().get(crate::io::parquet::FIELD_ID_META_KEY) {
+            Some(v) => *v == id,
+            None => *v.name() == field.name,
+        }
+    })
+}
+
+/// Remove top level columns of the fields from batch.
+#[cfg(feature = "io_parquet")]
+fn remove_columns(batch: RecordBatch, fields: &[&types::Field]) -> Result<RecordBatch> {
+    if fields.is_empty() {
+        return Ok(batch);
+    }
+    let removed: HashSet<_> = fields
+        .iter()
+        .filter_map(|v| column_index_of_field(&batch, v))
+        .collect();
+    let indices: Vec<_> = (0..batch.num_columns())
+        .filter(|idx| !removed.contains(idx))
+        .collect();
+    Ok(batch.project(&indices)?)
+}
+
+/// EqualityDeletes are rows of an equality delete file keyed by values of
+/// its equality columns.
+#[cfg(feature = "io_parquet")]
+struct EqualityDeletes {
+    partition: Vec<Option<types::Datum>>,
+    sequence_number: i64,
+    /// Top level primitive fields of equality ids.
+    fields: Vec<types::Field>,
+    keys: HashSet<Vec<Option<types::Datum>>>,
+}
+
+#[cfg(feature = "io_parquet")]
+impl EqualityDeletes {
+    /// Equality deletes only apply to data files committed before them.
+    fn applies_to(&self, data_file: &types::DataFile, sequence_number: i64) -> bool {
+        sequence_number < self.sequence_number
+            && (self.partition.is_empty() || self.partition == data_file.partition)
+    }
+
+    /// Columns of equality fields in batch, missing columns are all nulls.
+    fn key_columns<'b>(
+        &self,
+        batch: &'b RecordBatch,
+    ) -> Result<Vec<Option<(&'b ArrayRef, types::Primitive)>>> {
+        let mut columns = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let types::Any::Primitive(typ) = &field.field_type else {
+                return Err(anyhow!("equality field {} must be primitive", field.name));
+            };
+            columns.push(column_index_of_field(batch, field).map(|v| (batch.column(v), *typ)));
+        }
+        Ok(columns)
+    }
+
+    fn key(
+        columns: &[Option<(&ArrayRef, types::Primitive)>],
+        row: usize,
+    ) -> Vec<Option<types::Datum>> {
+        columns
+            .iter()
+            .map(|v| match v {
+                Some((array, _)) if array.is_null(row) => None,
+                Some((array, typ)) => crate::io::parquet::array_value(array.as_ref(), row, typ),
+                None => None,
+            })
+            .collect()
+    }
+
+    /// Mark rows of batch deleted if their keys are deleted.
+    fn mark_deleted(&self, batch: &RecordBatch, is_deleted: &mut [bool]) -> Result<()> {
+        let columns = self.key_columns(batch)?;
+        for (row, v) in is_deleted.iter_mut().enumerate() {
+            if !*v && self.keys.contains(&Self::key(&columns, row)) {
+                *v = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ColumnSelection decides which leaf columns of parquet files to read.
+#[cfg(feature = "io_parquet")]
+pub(crate) struct ColumnSelection {
+    /// Ids of selected fields and their nested fields.
+    field_ids: HashSet<i32>,
+    /// Dotted paths of selected fields, used for files without field ids.
+    paths: Vec<String>,
+    case_sensitive: bool,
+}
+
+#[cfg(feature = "io_parquet")]
+impl ColumnSelection {
+    fn try_new(schema: &types::Schema, columns: &[String], case_sensitive: bool) -> Result<Self> {
+        let mut field_ids = HashSet::new();
+        for column in columns {
+            let field = if case_sensitive {
+                schema.field_by_path(column)
+            } else {
+                schema.field_by_name_case_insensitive(column)?
+            };
+            let field = field.ok_or_else(|| anyhow!("column {} is not found in schema", column))?;
+            field_ids.insert(field.id);
+            collect_nested_field_ids(&field.field_type, &mut field_ids);
+        }
+
+        let paths = if case_sensitive {
+            columns.to_vec()
+        } else {
+            columns.iter().map(|v| v.to_lowercase()).collect()
+        };
+        Ok(Self {
+            field_ids,
+            paths,
+            case_sensitive,
+        })
+    }
+
+    fn contains(&self, column: &ColumnDescriptor) -> bool {
+        let info = column.self_type().get_basic_info();
+        if info.has_id() {
+            return self.field_ids.contains(&info.id());
+        }
+
+        let mut path = column.path().string();
+        if !self.case_sensitive {
+            path = path.to_lowercase();
+        }
+        self.paths
+            .iter()
+            .any(|v| path == *v || path.starts_with(&format!("{v}.")))
+    }
+}
+
+/// Collect ids of fields nested in the type.
+#[cfg(feature = "io_parquet")]
+fn collect_nested_field_ids(typ: &types::Any, ids: &mut HashSet<i32>) {
+    match typ {
+        types::Any::Primitive(_) | types::Any::Unknown(_) => {}
+        types::Any::Struct(v) => {
+            for field in &v.fields {
+                ids.insert(field.id);
+                collect_nested_field_ids(&field.field_type, ids);
+            }
+        }
+        types::Any::List(v) => {
+            ids.insert(v.element_id);
+            collect_nested_field_ids(&v.element_type, ids);
+        }
+        types::Any::Map(v) => {
+            ids.insert(v.key_id);
+            ids.insert(v.value_id);
+            collect_nested_field_ids(&v.key_type, ids);
+            collect_nested_field_ids(&v.value_type, ids);
+        }
+    }
+}
+
+/// Check the format version declared by manifest against the format
+/// version of table metadata, manifests written by buggy writers with
+/// inconsistent versions are reported as corrupted.
+///
+/// V1 manifests are valid in v2 and v3 tables only if they are written
+/// before the table is upgraded, whose sequence numbers are 0. V2
+/// manifests are valid in v3 tables, which are upgraded from v2.
+fn check_manifest_format_version(
+    table_version: types::TableFormatVersion,
+    manifest: &types::ManifestList,
+    manifest_meta: &types::ManifestMetadata,
+) -> crate::Result<()> {
+    let (version, valid) = match (table_version, manifest_meta.format_version) {
+        (types::TableFormatVersion::V1, v) => (1, v == 1),
+        (types::TableFormatVersion::V2, 1) => (2, manifest.sequence_number == 0),
+        (types::TableFormatVersion::V2, v) => (2, v == 2),
+        (types::TableFormatVersion::V3, 1) => (3, manifest.sequence_number == 0),
+        (types::TableFormatVersion::V3, v) => (3, v == 2 || v == 3),
+    };
+    if valid {
+        return Ok(());
+    }
+
+    Err(crate::Error::new(
+        crate::ErrorKind::Corrupt,
+        format!(
+            "manifest {} declares format version {}, which is inconsistent with format version {} of table metadata",
+            manifest.manifest_path,
+            manifest_meta.format_version,
+            version,
+        ),
+    )
+    .with_context("manifest", &manifest.manifest_path)
+    .with_context("sequence_number", manifest.sequence_number.to_string()))
+}
+
+/// Parse `(file_path, pos)` pairs from a batch of position delete file.
+#[cfg(feature = "io_parquet")]
+pub(crate) fn parse_position_deletes(
+    delete_file_path: &str,
+    batch: &RecordBatch,
+) -> Result<Vec<(String, i64)>> {
+    let invalid = || {
+        anyhow!(
+            "position delete file {} must have columns file_path and pos",
+            delete_file_path
+        )
+    };
+
+    let paths = batch
+        .column_by_name("file_path")
+        .and_then(|v| v.as_any().downcast_ref::<StringArray>())
+        .ok_or_else(invalid)?;
+    let positions = batch
+        .column_by_name("pos")
+        .and_then(|v| v.as_any().downcast_ref::<Int64Array>())
+        .ok_or_else(invalid)?;
+    if paths.null_count() > 0 || positions.null_count() > 0 {
+        return Err(invalid());
+    }
+
+    Ok(paths
+        .iter()
+        .zip(positions.iter())
+        .filter_map(|(path, pos)| Some((path?.to_string(), pos?)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::operation::test_utils::*;
+
+    /// Build a data file whose `id` column is in `[lower, upper]`.
+    fn data_file_with_ids(
+        name: &str,
+        region: Option<&str>,
+        lower: i64,
+        upper: i64,
+    ) -> types::DataFile {
+        let mut data_file = data_file(name, region, 10);
+        data_file.lower_bounds = Some(HashMap::from([(1, lower.to_le_bytes().to_vec())]));
+        data_file.upper_bounds = Some(HashMap::from([(1, upper.to_le_bytes().to_vec())]));
+        data_file
+    }
+
+    async fn plan_file_names(table: &Table, filter: Expression) -> Result<Vec<String>> {
+        let mut names: Vec<_> = table
+            .scan()
+            .filter(filter)
+            .plan_files()
+            .await?
+            .into_iter()
+            .map(|v| {
+                v.file_path
+                    .trim_start_matches("memory:///tbl/data/")
+                    .to_string()
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    #[tokio::test]
+    async fn test_plan_files() -> Result<()> {
+        let mut table = create_table("2").await?;
+        assert!(table.scan().plan_files().await?.is_empty());
+
+        table
+            .new_append()
+            .add_data_files([
+                data_file_with_ids("a", Some("us"), 1, 10),
+                data_file_with_ids("b", Some("eu"), 11, 20),
+            ])
+            .commit()
+            .await?;
+        table
+            .new_append()
+            .add_data_file(data_file("c", None, 10))
+            .commit()
+            .await?;
+
+        let region = |v: &str| types::Datum::String(v.to_string());
+        assert_eq!(
+            plan_file_names(&table, Expression::AlwaysTrue).await?,
+            vec!["a.parquet", "b.parquet", "c.parquet"]
+        );
+        // Pruned by partition values.
+        assert_eq!(
+            plan_file_names(&table, Expression::Eq(2, region("us"))).await?,
+            vec!["a.parquet"]
+        );
+        assert_eq!(
+            plan_file_names(&table, Expression::IsNull(2)).await?,
+            vec!["c.parquet"]
+        );
+        // Pruned by statistics, files without bounds are kept.
+        assert_eq!(
+            plan_file_names(&table, Expression::Gt(1, types::Datum::Long(15))).await?,
+            vec!["b.parquet", "c.parquet"]
+        );
+        // Pruned by both.
+        assert_eq!(
+            plan_file_names(
+                &table,
+                Expression::NotNull(2).and(Expression::Lt(1, types::Datum::Long(5)))
+            )
+            .await?,
+            vec!["a.parquet"]
+        );
+
+        // Deleted entries are not planned.
+        table
+            .new_delete()
+            .delete_file("memory:///tbl/data/b.parquet")
+            .commit()
+            .await?;
+        assert_eq!(
+            plan_file_names(&table, Expression::AlwaysTrue).await?,
+            vec!["a.parquet", "c.parquet"]
+        );
+
+        // Literals of mismatched types can't prune non-null values.
+        assert_eq!(
+            plan_file_names(&table, Expression::Eq(2, types::Datum::Long(1))).await?,
+            vec!["a.parquet"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plan_files_with_report() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let (_, report) = table.scan().plan_files_with_report().await?;
+        assert_eq!(report, ScanReport::default());
+
+        for name in ["a", "b"] {
+            table
+                .new_append()
+                .add_data_file(data_file(name, Some("us"), 1))
+                .commit()
+                .await?;
+        }
+
+        let (data_files, report) = table.scan().plan_files_with_report().await?;
+        assert_eq!(data_files.len(), 2);
+        let meta = table.current_table_metadata()?;
+        let snapshot = meta
+            .snapshots
+            .iter()
+            .flatten()
+            .find(|v| Some(v.snapshot_id) == meta.current_snapshot_id)
+            .expect("current snapshot must exist");
+        assert_eq!(report.snapshot_id, meta.current_snapshot_id);
+        assert_eq!(
+            report.manifest_list_path.as_deref(),
+            Some(snapshot.manifest_list.as_str())
+        );
+        let manifests = types::parse_manifest_list(
+            &table
+                .operator()
+                .read(&table.rel_path(&snapshot.manifest_list)?)
+                .await?,
+        )?;
+        assert_eq!(
+            report.manifest_paths,
+            manifests
+                .into_iter()
+                .map(|v| v.manifest_path)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(report.manifest_paths.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plan_files_verify_files() -> Result<()> {
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .add_data_file(data_file("b", Some("us"), 1))
+            .commit()
+            .await?;
+        // Only a exists in storage.
+        table
+            .operator()
+            .write("data/a.parquet", vec![0; 10])
+            .await?;
+
+        // Files are not checked by default.
+        assert_eq!(table.scan().plan_files().await?.len(), 2);
+
+        let err = table
+            .scan()
+            .verify_files(true)
+            .plan_files()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("b.parquet is missing"), "{err}");
+
+        let (data_files, report) = table
+            .scan()
+            .verify_files(true)
+            .with_missing_file_mode(MissingFileMode::Skip)
+            .plan_files_with_report()
+            .await?;
+        assert_eq!(
+            data_files
+                .into_iter()
+                .map(|v| v.file_path)
+                .collect::<Vec<_>>(),
+            vec!["memory:///tbl/data/a.parquet"]
+        );
+        assert_eq!(report.missing_files, vec!["memory:///tbl/data/b.parquet"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plan_files_format_version_mismatch() -> Result<()> {
+        // V1 manifests written before upgrade are valid in v2 tables.
+        let mut table = create_table("1").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+        let mut metadata = table.current_table_metadata()?.clone();
+        metadata.format_version = types::TableFormatVersion::V2;
+        table.commit(metadata).await?;
+        table
+            .new_append()
+            .add_data_file(data_file("b", Some("us"), 1))
+            .commit()
+            .await?;
+        assert_eq!(table.scan().plan_files().await?.len(), 2);
+
+        // V2 manifests are never valid in v1 tables.
+        let mut metadata = table.current_table_metadata()?.clone();
+        metadata.format_version = types::TableFormatVersion::V1;
+        table.commit(metadata).await?;
+        let err = table.scan().plan_files().await.unwrap_err();
+        let err = err
+            .downcast_ref::<crate::Error>()
+            .expect("must be icelake error");
+        assert_eq!(err.kind(), crate::ErrorKind::Corrupt);
+        assert!(
+            err.to_string().contains("declares format version 2"),
+            "{err}"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_collect() -> Result<()> {
+        use std::sync::Arc;
+
+        use arrow_array::ArrayRef;
+
+        let ids = |v: Vec<i64>| -> Result<RecordBatch> {
+            let col = Arc::new(Int64Array::from(v)) as ArrayRef;
+            Ok(RecordBatch::try_from_iter([("id", col)])?)
+        };
+        let collect_ids = |batches: Vec<RecordBatch>| -> Vec<i64> {
+            batches
+                .iter()
+                .flat_map(|v| {
+                    let col = v.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+                    col.values().to_vec()
+                })
+                .collect()
+        };
+
+        let mut table = create_table("2").await?;
+        assert!(table.collect().await?.is_empty());
+
+        let a = write_file(&table, "a", Some("us"), ids(vec![1, 2, 3, 4])?).await?;
+        let b = write_file(&table, "b", Some("us"), ids(vec![5, 6])?).await?;
+        table.new_append().add_data_files([a, b]).commit().await?;
+        let mut all = collect_ids(table.collect().await?);
+        all.sort();
+        assert_eq!(all, vec![1, 2, 3, 4, 5, 6]);
+
+        // Delete the 1st and 3rd rows of `a`, and all rows of `b`.
+        let paths = Arc::new(StringArray::from(vec![
+            "memory:///tbl/data/a.parquet",
+            "memory:///tbl/data/a.parquet",
+            "memory:///tbl/data/b.parquet",
+            "memory:///tbl/data/b.parquet",
+        ])) as ArrayRef;
+        let positions = Arc::new(Int64Array::from(vec![0, 2, 0, 1])) as ArrayRef;
+        let batch = RecordBatch::try_from_iter([("file_path", paths), ("pos", positions)])?;
+        let mut deletes = write_file(&table, "deletes", Some("us"), batch).await?;
+        deletes.content = types::DataContentType::PostionDeletes;
+        table
+            .new_append()
+            .add_data_file(deletes.clone())
+            .commit()
+            .await?;
+
+        let mut live = collect_ids(table.collect().await?);
+        live.sort();
+        assert_eq!(live, vec![2, 4]);
+
+        // Position deletes can be read directly.
+        let pairs: Vec<_> = table
+            .read_position_deletes(&deletes)
+            .await?
+            .try_collect()
+            .await?;
+        assert_eq!(
+            pairs,
+            vec![
+                ("memory:///tbl/data/a.parquet".to_string(), 0),
+                ("memory:///tbl/data/a.parquet".to_string(), 2),
+                ("memory:///tbl/data/b.parquet".to_string(), 0),
+                ("memory:///tbl/data/b.parquet".to_string(), 1),
+            ]
+        );
+        deletes.content = types::DataContentType::Data;
+        assert!(table.read_position_deletes(&deletes).await.is_err());
+
+        // Limit is applied on live rows.
+        let limited = collect_ids(table.scan().limit(1).collect().await?);
+        assert_eq!(limited.len(), 1);
+        assert!(live.contains(&limited[0]));
+        assert_eq!(
+            collect_ids(table.scan().limit(10).collect().await?).len(),
+            2
+        );
+        assert!(table.scan().limit(0).collect().await?.is_empty());
+
+        // Equality deletes apply to rows committed before.
+        let mut deletes = write_file(&table, "eq_deletes", Some("us"), ids(vec![2])?).await?;
+        deletes.content = types::DataContentType::EqualityDeletes;
+        deletes.equality_ids = Some(vec![1]);
+        table.new_append().add_data_file(deletes).commit().await?;
+        assert_eq!(collect_ids(table.collect().await?), vec![4]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_collect_upgraded_from_v1() -> Result<()> {
+        let ids = |v: Vec<i64>| -> Result<RecordBatch> {
+            let col = Arc::new(Int64Array::from(v)) as ArrayRef;
+            Ok(RecordBatch::try_from_iter([("id", col)])?)
+        };
+
+        // `a` is written by v1, whose manifest has no sequence numbers.
+        let mut table = create_table("1").await?;
+        let a = write_file(&table, "a", Some("us"), ids(vec![1, 2, 3])?).await?;
+        table.new_append().add_data_file(a).commit().await?;
+
+        let mut metadata = table.current_table_metadata()?.clone();
+        metadata.format_version = types::TableFormatVersion::V2;
+        table.commit(metadata).await?;
+
+        // Deletes of sequence number 1 apply to `a` of sequence number 0,
+        // but not to `b` added later with sequence number 2.
+        let paths = Arc::new(StringArray::from(vec![
+            "memory:///tbl/data/a.parquet",
+            "memory:///tbl/data/b.parquet",
+        ])) as ArrayRef;
+        let positions = Arc::new(Int64Array::from(vec![0, 0])) as ArrayRef;
+        let batch = RecordBatch::try_from_iter([("file_path", paths), ("pos", positions)])?;
+        let mut deletes = write_file(&table, "deletes", Some("us"), batch).await?;
+        deletes.content = types::DataContentType::PostionDeletes;
+        table.new_append().add_data_file(deletes).commit().await?;
+        let b = write_file(&table, "b", Some("us"), ids(vec![10, 11])?).await?;
+        table.new_append().add_data_file(b).commit().await?;
+
+        let (entries, _) = table.scan().plan_entries().await?;
+        let mut sequence_numbers: Vec<_> = entries
+            .iter()
+            .map(|(v, seq)| (v.file_path.trim_start_matches("memory:///tbl/data/"), *seq))
+            .collect();
+        sequence_numbers.sort();
+        assert_eq!(
+            sequence_numbers,
+            vec![("a.parquet", 0), ("b.parquet", 2), ("deletes.parquet", 1)]
+        );
+
+        let mut live: Vec<i64> = table
+            .collect()
+            .await?
+            .iter()
+            .flat_map(|v| {
+                v.column(0)
+                    .as_primitive::<arrow_array::types::Int64Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect();
+        live.sort();
+        assert_eq!(live, vec![2, 3, 10, 11]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_collect_metadata_cache() -> Result<()> {
+        let mut table = create_table("2").await?;
+        let ids = |v: Vec<i64>| Arc::new(Int64Array::from(v)) as ArrayRef;
+        let a = write_file(
+            &table,
+            "a",
+            Some("us"),
+            RecordBatch::try_from_iter([("id", ids(vec![1, 2]))])?,
+        )
+        .await?;
+        let b = write_file(
+            &table,
+            "b",
+            Some("us"),
+            RecordBatch::try_from_iter([("id", ids(vec![3]))])?,
+        )
+        .await?;
+        table.new_append().add_data_files([a, b]).commit().await?;
+        let paths = Arc::new(StringArray::from(vec!["memory:///tbl/data/a.parquet"])) as ArrayRef;
+        let batch = RecordBatch::try_from_iter([("file_path", paths), ("pos", ids(vec![0]))])?;
+        let mut deletes = write_file(&table, "deletes", Some("us"), batch).await?;
+        deletes.content = types::DataContentType::PostionDeletes;
+        table.new_append().add_data_file(deletes).commit().await?;
+
+        // Each footer is fetched once, including the one of delete file.
+        let scan = table.scan();
+        assert_eq!(scan.collect().await?.len(), 2);
+        assert_eq!(scan.metadata_cache().fetch_count(), 3);
+        assert_eq!(scan.collect().await?.len(), 2);
+        assert_eq!(scan.metadata_cache().fetch_count(), 3);
+
+        // The cache could be shared by other scans.
+        let cache = scan.metadata_cache().clone();
+        let scan = table.scan().with_metadata_cache(cache.clone());
+        scan.collect().await?;
+        assert_eq!(cache.fetch_count(), 3);
+        assert_eq!(table.scan().metadata_cache().fetch_count(), 0);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_collect_equality_deletes() -> Result<()> {
+        let region_ids = |region: &str, ids: Vec<i64>| -> Result<RecordBatch> {
+            let regions = vec![region; ids.len()];
+            Ok(RecordBatch::try_from_iter([
+                ("id", Arc::new(Int64Array::from(ids)) as ArrayRef),
+                ("region", Arc::new(StringArray::from(regions)) as ArrayRef),
+            ])?)
+        };
+        let collect_ids = |batches: Vec<RecordBatch>| -> Vec<i64> {
+            let mut ids: Vec<i64> = batches
+                .iter()
+                .flat_map(|v| {
+                    v.column(0)
+                        .as_primitive::<arrow_array::types::Int64Type>()
+                        .values()
+                        .to_vec()
+                })
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        let mut table = create_table("2").await?;
+        let a = write_file(&table, "a", Some("us"), region_ids("us", vec![1, 2, 3])?).await?;
+        let b = write_file(&table, "b", Some("eu"), region_ids("eu", vec![2, 4])?).await?;
+        table.new_append().add_data_files([a, b]).commit().await?;
+
+        let rows = RecordBatch::try_from_iter([
+            ("id", Arc::new(Int64Array::from(vec![2, 3])) as ArrayRef),
+            (
+                "region",
+                Arc::new(StringArray::from(vec!["us", "eu"])) as ArrayRef,
+            ),
+        ])?;
+        table
+            .new_delete()
+            .equality_delete(&["id", "region"], rows)
+            .commit()
+            .await?;
+        // Rows committed after deletes are not deleted.
+        let c = write_file(&table, "c", Some("us"), region_ids("us", vec![2])?).await?;
+        table.new_append().add_data_file(c).commit().await?;
+
+        assert_eq!(collect_ids(table.collect().await?), vec![1, 2, 2, 3, 4]);
+
+        // Equality columns are read to apply deletes, but not returned.
+        let batches = table.scan().select(&["id"]).collect().await?;
+        assert!(batches.iter().all(|v| v.num_columns() == 1));
+        assert_eq!(collect_ids(batches), vec![1, 2, 2, 3, 4]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_collect_promoted_types() -> Result<()> {
+        use crate::io::parquet::ParquetWriterBuilder;
+        use arrow_array::Int32Array;
+
+        // The file is written when `id` was still an int.
+        let mut table = create_table("2").await?;
+        let batch = RecordBatch::try_from_iter([
+            ("id", Arc::new(Int32Array::from(vec![1, -2, 3])) as ArrayRef),
+            (
+                "region",
+                Arc::new(StringArray::from(vec!["us"; 3])) as ArrayRef,
+            ),
+        ])?;
+        let path = "data/a.parquet";
+        let mut writer =
+            ParquetWriterBuilder::new(table.operator().writer(path).await?, batch.schema())
+                .build()?;
+        writer.write(&batch).await?;
+        writer.close().await?;
+        let mut data_file = data_file("a", Some("us"), 3);
+        data_file.file_size_in_bytes = table.operator().stat(path).await?.content_length() as i64;
+        table.new_append().add_data_file(data_file).commit().await?;
+
+        let batches = table.collect().await?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].schema().field(0).data_type(), &DataType::Int64);
+        assert_eq!(
+            batches[0]
+                .column(0)
+                .as_primitive::<arrow_array::types::Int64Type>()
+                .values(),
+            &[1, -2, 3]
+        );
+        assert_eq!(batches[0].schema().field(1).data_type(), &DataType::Utf8);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[test]
+    fn test_promote_types() -> Result<()> {
+        let field = |id, name: &str, typ| types::Field {
+            id,
+            name: name.to_string(),
+            required: false,
+            field_type: types::Any::Primitive(typ),
+            comment: None,
+        };
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![
+                field(1, "score", types::Primitive::Double),
+                field(
+                    2,
+                    "amount",
+                    types::Primitive::Decimal {
+                        precision: 12,
+                        scale: 3,
+                    },
+                ),
+            ],
+        };
+
+        let amount =
+            Decimal128Array::from(vec![Some(1234), None]).with_precision_and_scale(9, 2)?;
+        let batch = RecordBatch::try_from_iter([
+            (
+                "score",
+                Arc::new(arrow_array::Float32Array::from(vec![1.5, -0.25])) as ArrayRef,
+            ),
+            ("amount", Arc::new(amount) as ArrayRef),
+            (
+                "unknown",
+                Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+            ),
+        ])?;
+
+        let promoted = promote_types(batch.clone(), &schema)?;
+        assert_eq!(
+            promoted
+                .column(0)
+                .as_primitive::<arrow_array::types::Float64Type>()
+                .values(),
+            &[1.5, -0.25]
+        );
+        let amount = promoted.column(1).as_primitive::<Decimal128Type>();
+        assert_eq!(amount.data_type(), &DataType::Decimal128(12, 3));
+        assert_eq!(amount.value(0), 12340);
+        assert!(amount.is_null(1));
+        assert_eq!(promoted.column(2), batch.column(2));
+
+        // Decimals can't be narrowed.
+        let schema = types::Schema {
+            fields: vec![field(
+                2,
+                "amount",
+                types::Primitive::Decimal {
+                    precision: 9,
+                    scale: 1,
+                },
+            )],
+            ..schema
+        };
+        assert!(promote_types(batch.clone(), &schema).is_err());
+
+        // Digits before the point can't be reduced.
+        let schema = types::Schema {
+            fields: vec![field(
+                2,
+                "amount",
+                types::Primitive::Decimal {
+                    precision: 10,
+                    scale: 4,
+                },
+            )],
+            ..schema
+        };
+        assert!(promote_types(batch, &schema).is_err());
+
+        // Overflowed values are reported instead of wrapping around.
+        let amount = Decimal128Array::from(vec![i128::MAX / 2]).with_precision_and_scale(37, 0)?;
+        let batch = RecordBatch::try_from_iter([("amount", Arc::new(amount) as ArrayRef)])?;
+        let schema = types::Schema {
+            fields: vec![field(
+                2,
+                "amount",
+                types::Primitive::Decimal {
+                    precision: 38,
+                    scale: 1,
+                },
+            )],
+            ..schema
+        };
+        assert!(promote_types(batch, &schema).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[test]
+    fn test_promote_types_by_field_id() -> Result<()> {
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![types::Field {
+                id: 1,
+                name: "score".to_string(),
+                required: false,
+                field_type: types::Any::Primitive(types::Primitive::Double),
+                comment: None,
+            }],
+        };
+        let with_id = |name: &str, id: i32| {
+            arrow_schema::Field::new(name, DataType::Float32, true).with_metadata(HashMap::from([
+                (
+                    crate::io::parquet::FIELD_ID_META_KEY.to_string(),
+                    id.to_string(),
+                ),
+            ]))
+        };
+        let column = Arc::new(arrow_array::Float32Array::from(vec![1.5])) as ArrayRef;
+        let batch = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![
+                // Renamed after the file is written.
+                with_id("old_score", 1),
+                // Dropped and then added again with the same name.
+                with_id("score", 2),
+            ])),
+            vec![column.clone(), column],
+        )?;
+
+        let promoted = promote_types(batch, &schema)?;
+        assert_eq!(promoted.column(0).data_type(), &DataType::Float64);
+        assert_eq!(promoted.column(1).data_type(), &DataType::Float32);
+        Ok(())
+    }
+
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_collect_nested_columns() -> Result<()> {
+        use std::sync::Arc;
+
+        use arrow_array::ArrayRef;
+        use arrow_array::StructArray;
+        use opendal::services::Memory;
+        use opendal::Operator;
+
+        use crate::io::parquet::ParquetWriterBuilder;
+
+        let field = |id, name: &str, field_type| types::Field {
+            id,
+            name: name.to_string(),
+            required: false,
+            field_type,
+            comment: None,
+        };
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![
+                field(1, "id", types::Any::Primitive(types::Primitive::Long)),
+                field(
+                    2,
+                    "address",
+                    types::Any::Struct(types::Struct {
+                        fields: vec![
+                            field(3, "city", types::Any::Primitive(types::Primitive::String)),
+                            field(4, "zip", types::Any::Primitive(types::Primitive::Long)),
+                        ],
+                    }),
+                ),
+            ],
+        };
+        let spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+        let op = Operator::new(Memory::default())?.finish();
+        let mut table = Table::create(op, "memory:///tbl", schema, spec, HashMap::new()).await?;
+
+        let address = StructArray::try_from(vec![
+            (
+                "city",
+                Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef,
+            ),
+            (
+                "zip",
+                Arc::new(Int64Array::from(vec![100, 200])) as ArrayRef,
+            ),
+        ])?;
+        let batch = RecordBatch::try_from_iter([
+            ("id", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+            ("address", Arc::new(address) as ArrayRef),
+        ])?;
+        let path = "data/a.parquet";
+        let mut writer =
+            ParquetWriterBuilder::new(table.operator().writer(path).await?, batch.schema())
+                .build()?;
+        writer.write(&batch).await?;
+        writer.close().await?;
+        let mut data_file = data_file("a", None, 2);
+        data_file.partition = vec![];
+        data_file.file_size_in_bytes = table.operator().stat(path).await?.content_length() as i64;
+        table.new_append().add_data_file(data_file).commit().await?;
+
+        let batches = table.scan().select(&["address.city"]).collect().await?;
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_columns(), 1);
+        let address = batch
+            .column_by_name("address")
+            .and_then(|v| v.as_any().downcast_ref::<StructArray>())
+            .expect("address must be a struct");
+        assert_eq!(address.num_columns(), 1);
+        let city = address
+            .column_by_name("city")
+            .and_then(|v| v.as_any().downcast_ref::<StringArray>())
+            .expect("city must be a string");
+        assert_eq!(city.iter().collect::<Vec<_>>(), vec![Some("a"), Some("b")]);
+
+        // The whole struct is selected by its name.
+        let batches = table.scan().select(&["id", "address"]).collect().await?;
+        assert_eq!(batches[0].schema(), table.collect().await?[0].schema());
+
+        assert!(table
+            .scan()
+            .select(&["address.country"])
+            .collect()
+            .await
+            .is_err());
+
+        // Names are resolved case-insensitively if asked.
+        assert!(table
+            .scan()
+            .select(&["Address.City"])
+            .collect()
+            .await
+            .is_err());
+        let batches = table
+            .scan()
+            .select(&["Address.City"])
+            .with_case_sensitive(false)
+            .collect()
+            .await?;
+        assert_eq!(batches[0].num_columns(), 1);
+        assert!(batches[0].column_by_name("address").is_some());
+
+        Ok(())
+    }
+}
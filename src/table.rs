@@ -1,237 +1,4022 @@
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
 
 use anyhow::anyhow;
 use anyhow::Result;
 use futures::StreamExt;
-use opendal::layers::LoggingLayer;
-use opendal::services::Fs;
+use futures::TryStreamExt;
 use opendal::Operator;
 
+use crate::io;
+use crate::io::LoadPolicy;
+use crate::io::MetadataIo;
+use crate::operation::set_current_snapshot;
+use crate::operation::AppendOperation;
+use crate::operation::CherrypickOperation;
+use crate::operation::DeleteOperation;
+use crate::operation::OverwriteOperation;
+use crate::operation::RewriteManifestsOperation;
+#[cfg(feature = "io_parquet")]
+use crate::operation::RewriteOperation;
+use crate::operation::SnapshotProducer;
+use crate::operation::UpdateSchemaOperation;
 use crate::types;
+use crate::Clock;
+use crate::SystemClock;
+use crate::TableScan;
+
+/// The default directory of metadata files.
+const DEFAULT_METADATA_DIR: &str = "metadata/";
+
+/// The default name of version hint file in metadata directory.
+const DEFAULT_VERSION_HINT_FILE: &str = "version-hint.text";
+
+/// The default value of table property `write.metadata.previous-versions-max`.
+const DEFAULT_PREVIOUS_VERSIONS_MAX: usize = 100;
+
+/// The max number of manifest lists or manifests read at the same time
+/// while planning several snapshots.
+const DEFAULT_PLAN_CONCURRENCY: usize = 16;
+
+/// Old and new values of changed table properties keyed by name, `None`
+/// means the property is absent.
+pub type PropertyChanges = HashMap<String, (Option<String>, Option<String>)>;
+
+/// ManifestSummary summarizes manifests of the current snapshot for
+/// diagnosing table health.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ManifestSummary {
+    /// Number of all manifests.
+    pub manifest_count: usize,
+    /// Number of manifests tracking data files.
+    pub data_manifest_count: usize,
+    /// Number of manifests tracking delete files.
+    pub delete_manifest_count: usize,
+    /// Number of manifests keyed by `partition-spec-id`.
+    pub manifest_count_by_spec: HashMap<i32, usize>,
+    /// Total length of all manifests in bytes.
+    pub total_manifest_bytes: u64,
+}
+
+/// LoadReport records how `Table::load` resolved the latest metadata file,
+/// so that a missing or stale version hint can be noticed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LoadReport {
+    /// Path of the metadata file loaded, relative to the operator root.
+    pub metadata_path: String,
+    /// Whether the version hint was missing, and metadata directories were
+    /// listed to find the latest metadata file instead.
+    pub version_hint_missing: bool,
+    /// The metadata path in version hint, if it was stale and replaced by
+    /// the latest listed one.
+    pub stale_version_hint: Option<String>,
+}
+
+/// CheckIssue is a problem of table found by `Table::check`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckIssue {
+    /// The snapshot of `current-snapshot-id` is not found in snapshots.
+    MissingCurrentSnapshot(i64),
+    /// The schema id referenced by metadata or a snapshot is not found.
+    MissingSchema(i64),
+    /// Parents of snapshots form a cycle, ids of snapshots in the cycle
+    /// are recorded.
+    SnapshotCycle(Vec<i64>),
+    /// A manifest list, manifest or data file referenced by metadata
+    /// doesn't exist.
+    MissingFile(String),
+    /// A referenced file exists but can't be read or parsed.
+    InvalidFile {
+        /// Location of the file.
+        path: String,
+        /// Why the file is invalid.
+        reason: String,
+    },
+}
 
 /// Table is the main entry point for the IceLake.
 pub struct Table {
+    /// The operator rooted at table root, which is `root_op` prefixed by
+    /// `table_prefix`.
     op: Operator,
+    /// The operator passed by users, which may serve many tables.
+    root_op: Operator,
+    /// The path of table root relative to the root of `root_op` like
+    /// `db/tbl/`, empty if the operator is rooted at table root.
+    table_prefix: String,
+    /// The storage of metadata files and version hint, `op` if not set.
+    metadata_io: Option<Arc<dyn MetadataIo>>,
 
-    table_metadata: HashMap<i64, types::TableMetadata>,
+    /// Loaded table metadata keyed by version.
+    table_metadata: HashMap<i32, types::TableMetadata>,
 
-    /// `0` means the version is not loaded yet.
+    /// `None` means the version is not loaded yet.
     ///
-    /// We use table's `last-updated-ms` to represent the version.
-    current_version: i64,
+    /// We use the version parsed from name of metadata file like
+    /// `v2.metadata.json`, since `last-updated-ms` of different versions
+    /// may be the same.
+    current_version: Option<i32>,
     current_location: Option<String>,
+    /// The path of metadata file used by current version, relative to the
+    /// operator root.
+    current_metadata_path: Option<String>,
+    /// Candidate directories of metadata files in priority order, used
+    /// when version hint is absent.
+    metadata_dirs: Vec<String>,
+    /// The path of version hint file relative to the operator root.
+    version_hint_path: String,
+    /// Only metadata files whose names start with the prefix are listed.
+    metadata_file_prefix: Option<String>,
+    /// The tag which the current snapshot is pinned to by `load_tag`,
+    /// commits are rejected while it's set.
+    loaded_tag: Option<String>,
+    /// The source of timestamps written by commits.
+    clock: Arc<dyn Clock>,
+    /// Whether to keep the raw json of loaded metadata file.
+    keep_metadata_json: bool,
+    /// The raw json of metadata file used by current version, only kept
+    /// if `keep_metadata_json` is set.
+    current_metadata_json: Option<serde_json::Value>,
+    /// `None` means the version hint is trusted without listing, and
+    /// missing manifests fail reads.
+    load_policy: Option<LoadPolicy>,
+    /// How the last `load` resolved the metadata file.
+    load_report: Option<LoadReport>,
 }
 
-impl Table {
-    /// Create a new table via the given operator.
-    pub fn new(op: Operator) -> Self {
-        Self {
-            op,
+impl Table {
+    /// Create a new table via the given operator.
+    pub fn new(op: Operator) -> Self {
+        Self {
+            root_op: op.clone(),
+            op,
+            table_prefix: String::new(),
+            metadata_io: None,
+
+            table_metadata: HashMap::new(),
+
+            current_version: None,
+            current_location: None,
+            current_metadata_path: None,
+            metadata_dirs: vec![DEFAULT_METADATA_DIR.to_string()],
+            version_hint_path: format!("{DEFAULT_METADATA_DIR}{DEFAULT_VERSION_HINT_FILE}"),
+            metadata_file_prefix: None,
+            loaded_tag: None,
+            clock: Arc::new(SystemClock),
+            keep_metadata_json: false,
+            current_metadata_json: None,
+            load_policy: None,
+            load_report: None,
+        }
+    }
+
+    /// Set the clock of timestamps written by commits, the system clock
+    /// by default.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set the storage of metadata files and version hint, the operator
+    /// of table by default.
+    ///
+    /// Manifests and data files are still accessed via the operator.
+    pub fn with_metadata_io(mut self, metadata_io: Arc<dyn MetadataIo>) -> Self {
+        self.metadata_io = Some(metadata_io);
+        self
+    }
+
+    /// Keep the raw json of loaded metadata file, which is exposed by
+    /// `current_metadata_json`.
+    ///
+    /// It's disabled by default to avoid holding the metadata twice in
+    /// memory.
+    pub fn with_metadata_json(mut self, keep: bool) -> Self {
+        self.keep_metadata_json = keep;
+        self
+    }
+
+    /// Set the policy of loading the table with missing or inconsistent
+    /// metadata.
+    ///
+    /// The version hint is verified by listing metadata directories if
+    /// set, so that a stale hint is detected at the cost of listing. Not
+    /// set by default, which trusts the hint, falls back to listing with a
+    /// warning only if the hint is missing, and fails on missing
+    /// manifests.
+    pub fn with_load_policy(mut self, policy: LoadPolicy) -> Self {
+        self.load_policy = Some(policy);
+        self
+    }
+
+    /// Set the path of table root relative to the operator root, like
+    /// `db/tbl`, for operators serving many tables.
+    ///
+    /// Paths relative to table location, like those returned by
+    /// `rel_path`, are read via an operator prefixed by it, while the
+    /// operator passed by users is kept as `root_operator`.
+    pub fn with_table_prefix(mut self, table_prefix: &str) -> Self {
+        let prefix = table_prefix.trim_matches('/');
+        self.table_prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{prefix}/")
+        };
+        self.op = self.root_op.clone().layer(io::PrefixLayer::new(prefix));
+        self
+    }
+
+    /// Set candidate directories of metadata files in priority order.
+    ///
+    /// Directories are relative to the operator root, `metadata` by
+    /// default. When version hint is absent, the highest version found
+    /// across all directories will be loaded, and the earlier directory
+    /// wins if the same version exists in several of them.
+    pub fn with_metadata_dirs(mut self, dirs: &[&str]) -> Self {
+        self.metadata_dirs = dirs
+            .iter()
+            .map(|v| format!("{}/", v.trim_matches('/')))
+            .collect();
+        self
+    }
+
+    /// Set the name of version hint file in metadata directory,
+    /// `version-hint.text` by default.
+    ///
+    /// Some tools keep the pointer of current metadata in a nonstandard
+    /// file, whose content can be either a version number or a path of
+    /// metadata file.
+    pub fn with_version_hint_file(mut self, name: &str) -> Self {
+        self.version_hint_path = format!("{DEFAULT_METADATA_DIR}{}", name.trim_matches('/'));
+        self
+    }
+
+    /// Only consider metadata files whose names start with the given
+    /// prefix when listing metadata directories, like `v` for files
+    /// named `v1.metadata.json`.
+    ///
+    /// This avoids picking up unrelated files ending with
+    /// `.metadata.json` in directories shared with other tools.
+    pub fn with_metadata_file_prefix(mut self, prefix: &str) -> Self {
+        self.metadata_file_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Load metadata and manifest from storage.
+    ///
+    /// How the metadata file is resolved is recorded in `load_report`.
+    pub async fn load(&mut self) -> Result<()> {
+        let report = self.resolve_latest_metadata().await?;
+        self.load_metadata(&report.metadata_path).await?;
+        self.load_report = Some(report);
+        Ok(())
+    }
+
+    /// Get the report of the last successful `load`, `None` if the table
+    /// is not loaded by `load`.
+    pub fn load_report(&self) -> Option<&LoadReport> {
+        self.load_report.as_ref()
+    }
+
+    /// Resolve the path of latest metadata file, see
+    /// `resolve_latest_metadata`.
+    async fn latest_metadata_path(&self) -> Result<String> {
+        Ok(self.resolve_latest_metadata().await?.metadata_path)
+    }
+
+    /// Resolve the path of latest metadata file from version hint, or by
+    /// listing metadata directories if the hint is missing.
+    ///
+    /// The hint is verified against the listed metadata files if a load
+    /// policy is set, see `with_load_policy`.
+    async fn resolve_latest_metadata(&self) -> Result<LoadReport> {
+        let hint = if self.is_version_hint_exist().await? {
+            Some(self.read_version_hint().await?)
+        } else {
+            None
+        };
+
+        let info = self.op.info();
+        let can_list = self.metadata_io().can_list();
+        let Some(hint) = hint else {
+            if !can_list {
+                return Err(anyhow!(
+                    "version hint {} is not found in table at {}://{}, which is required since the storage can't list metadata files",
+                    self.version_hint_path,
+                    info.scheme(),
+                    info.root()
+                ));
+            }
+            if self.load_policy == Some(LoadPolicy::Strict) {
+                return Err(anyhow!(
+                    "version hint {} is not found in table at {}://{}, which is required by strict load policy",
+                    self.version_hint_path,
+                    info.scheme(),
+                    info.root()
+                ));
+            }
+
+            // Missing hint often signals a broken writer.
+            log::warn!(
+                "version hint {} is not found in table at {}://{}, fallback to list metadata directories",
+                self.version_hint_path,
+                info.scheme(),
+                info.root()
+            );
+
+            let latest = self
+                .latest_listed_metadata_path()
+                .await?
+                .ok_or_else(|| anyhow!("no table metadata found"))?;
+            self.repair_version_hint(&latest).await?;
+            return Ok(LoadReport {
+                metadata_path: latest,
+                version_hint_missing: true,
+                stale_version_hint: None,
+            });
+        };
+        // The hint is trusted by default, and can't be verified if the
+        // storage can't list.
+        if self.load_policy.is_none() || !can_list {
+            return Ok(LoadReport {
+                metadata_path: hint,
+                ..Default::default()
+            });
+        }
+
+        let latest = self.latest_listed_metadata_path().await?;
+        let stale = match &latest {
+            None => false,
+            Some(latest) => {
+                !self.metadata_io().is_exist(&hint).await?
+                    || parse_metadata_version(latest) > parse_metadata_version(&hint)
+            }
+        };
+        let Some(latest) = latest.filter(|_| stale) else {
+            return Ok(LoadReport {
+                metadata_path: hint,
+                ..Default::default()
+            });
+        };
+        if self.load_policy == Some(LoadPolicy::Strict) {
+            return Err(anyhow!(
+                "version hint {} points to {}, which is stale since {} is found",
+                self.version_hint_path,
+                hint,
+                latest
+            ));
+        }
+
+        log::warn!(
+            "version hint {} points to {}, which is stale since {} is found, fallback to the latter",
+            self.version_hint_path,
+            hint,
+            latest
+        );
+        self.repair_version_hint(&latest).await?;
+        Ok(LoadReport {
+            metadata_path: latest,
+            version_hint_missing: false,
+            stale_version_hint: Some(hint),
+        })
+    }
+
+    /// Find the metadata file of the highest version by listing metadata
+    /// directories, `None` if there is no metadata file.
+    ///
+    /// Within a directory, the name order decides if the version can't be
+    /// parsed. The earlier directory wins if the same version exists in
+    /// several of them, which is ambiguous and rejected by strict load
+    /// policy.
+    async fn latest_listed_metadata_path(&self) -> Result<Option<String>> {
+        let mut latest: Option<String> = None;
+        let mut candidates = vec![];
+        for dir in &self.metadata_dirs {
+            let paths = self.list_table_metadata_paths_in(dir).await?;
+            // Pick the latest one in directory, fallback to name order
+            // if the version can't be parsed.
+            let Some(path) = paths
+                .iter()
+                .max_by_key(|v| (parse_metadata_version(v), v.as_str()))
+                .cloned()
+            else {
+                continue;
+            };
+            if latest
+                .as_deref()
+                .is_none_or(|v| parse_metadata_version(&path) > parse_metadata_version(v))
+            {
+                latest = Some(path);
+            }
+            candidates.extend(paths);
+        }
+
+        let Some(latest) = latest else {
+            return Ok(None);
+        };
+        let version = parse_metadata_version(&latest);
+        let ambiguous: Vec<_> = candidates
+            .iter()
+            .filter(|v| parse_metadata_version(v) == version)
+            .collect();
+        if version.is_some() && ambiguous.len() > 1 {
+            match self.load_policy {
+                None => {}
+                Some(LoadPolicy::Strict) => {
+                    return Err(anyhow!(
+                        "latest metadata version is ambiguous between {:?}",
+                        ambiguous
+                    ))
+                }
+                Some(_) => log::warn!(
+                    "latest metadata version is ambiguous between {:?}, pick {}",
+                    ambiguous,
+                    latest
+                ),
+            }
+        }
+
+        Ok(Some(latest))
+    }
+
+    /// Rewrite the version hint to point to the given metadata file, if
+    /// required by load policy.
+    async fn repair_version_hint(&self, path: &str) -> Result<()> {
+        if self.load_policy != Some(LoadPolicy::RepairHint) {
+            return Ok(());
+        }
+
+        let content = match parse_metadata_version(path) {
+            Some(version) if path == format!("metadata/v{version}.metadata.json") => {
+                version.to_string()
+            }
+            _ => path.to_string(),
+        };
+        self.metadata_io()
+            .write(&self.version_hint_path, content.into_bytes())
+            .await?;
+        log::info!(
+            "version hint {} is repaired to point to {}",
+            self.version_hint_path,
+            path
+        );
+
+        Ok(())
+    }
+
+    /// Fetch the manifest list location of current snapshot, as written in
+    /// the latest metadata file.
+    ///
+    /// Only the current snapshot id and manifest lists of snapshots are
+    /// parsed from metadata, which is much cheaper than `load` for quick
+    /// checks on tables with long history. The table doesn't need to be
+    /// loaded.
+    pub async fn current_manifest_list_path(&self) -> Result<String> {
+        let path = self.latest_metadata_path().await?;
+        let content = self.metadata_io().read(&path).await?;
+
+        types::parse_current_manifest_list(&content)
+            .map_err(|err| err.with_context("path", &path))?
+            .ok_or_else(|| anyhow!("table has no current snapshot"))
+    }
+
+    /// Load metadata of all versions for inspection.
+    ///
+    /// All metadata files found in candidate metadata directories are
+    /// loaded, which can be listed by `metadata_versions`. The current
+    /// version will be loaded too if the table is not loaded yet.
+    pub async fn load_all_versions(&mut self) -> Result<()> {
+        for path in self.list_table_metadata_paths().await? {
+            let version = metadata_version_of(&path)?;
+            let metadata = self.read_table_metadata(&path).await?;
+            self.table_metadata.insert(version, metadata);
+        }
+
+        if self.current_version.is_none() {
+            self.load().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Load metadata from the given path which is relative to the
+    /// operator root.
+    async fn load_metadata(&mut self, path: &str) -> Result<()> {
+        let version = metadata_version_of(path)?;
+        let content = self.metadata_io().read(path).await?;
+        let metadata =
+            types::parse_table_metadata(&content).map_err(|err| err.with_context("path", path))?;
+        // TODO: check if the metadata is out of date.
+        self.set_current_metadata(version, Some(path.to_string()), metadata);
+        if self.keep_metadata_json {
+            let json = serde_json::from_slice(&content)
+                .map_err(|err| anyhow!("parse metadata {} failed: {}", path, err))?;
+            self.current_metadata_json = Some(json);
+        }
+
+        Ok(())
+    }
+
+    /// Set the given metadata as the current version.
+    fn set_current_metadata(
+        &mut self,
+        version: i32,
+        path: Option<String>,
+        metadata: types::TableMetadata,
+    ) {
+        self.current_version = Some(version);
+        self.current_location = Some(metadata.location.clone());
+        self.current_metadata_path = path;
+        self.loaded_tag = None;
+        self.current_metadata_json = None;
+        self.table_metadata.insert(version, metadata);
+    }
+
+    /// Load the latest metadata and pin the current snapshot to the one
+    /// referenced by the given tag, so that scans and
+    /// `current_data_files` read the data as of the tag.
+    ///
+    /// The pinned table is read-only, commits are rejected until the
+    /// table is loaded again by `load`.
+    pub async fn load_tag(&mut self, tag_name: &str) -> Result<()> {
+        self.load().await?;
+
+        let snapshot_id = self.tag_snapshot(tag_name)?.snapshot_id;
+        let version = self
+            .current_version
+            .ok_or_else(|| anyhow!("table metadata not loaded yet"))?;
+        if let Some(metadata) = self.table_metadata.get_mut(&version) {
+            metadata.current_snapshot_id = Some(snapshot_id);
+        }
+        self.loaded_tag = Some(tag_name.to_string());
+
+        Ok(())
+    }
+
+    /// Open an iceberg table by uri
+    ///
+    /// The storage type is inferred by the scheme of uri, for example
+    /// `s3://bucket/path/to/table` or `/path/to/table`.
+    ///
+    /// Tables served over `http(s)://` can't be listed, so they must
+    /// have a version hint.
+    pub async fn open(uri: &str) -> Result<Table> {
+        let op = io::build_operator(uri)?;
+
+        let mut table = Table::new(op);
+        table.load().await?;
+        Ok(table)
+    }
+
+    /// Open an iceberg table by catalog uri like
+    /// `<catalog>[+<storage>]://<location>#<namespace>.<table>`.
+    ///
+    /// The catalog implementation is selected by the scheme, for example
+    /// `hadoop+s3://bucket/warehouse#db.tbl` loads `db.tbl` from a hadoop
+    /// catalog whose warehouse is `s3://bucket/warehouse`, and
+    /// `rest://host/v1#db.tbl` loads it from a rest catalog.
+    pub async fn open_catalog(uri: &str) -> Result<Table> {
+        let (catalog, table) = crate::catalog::parse_catalog_uri(uri)?;
+        catalog.load_table(&table).await
+    }
+
+    /// Open an iceberg table packaged in a zip archive.
+    ///
+    /// The archive is extracted into memory, and the table will be loaded
+    /// from the parent of the `metadata` directory in the archive. This is
+    /// useful for distributing reproducible fixtures.
+    #[cfg(feature = "zip")]
+    pub async fn open_zip(bs: &[u8]) -> Result<Table> {
+        let op = io::build_zip_operator(bs).await?;
+
+        let mut table = Table::new(op);
+        table.load().await?;
+        Ok(table)
+    }
+
+    /// Open an iceberg table by uri with options of storage services.
+    ///
+    /// For example, a custom endpoint is required to open tables stored in
+    /// MinIO or LocalStack.
+    pub async fn open_with_options(uri: &str, options: &io::OpenOptions) -> Result<Table> {
+        let op = io::build_operator_with_options(uri, options)?;
+
+        let mut table = Table::new(op);
+        if let Some(name) = &options.version_hint_file {
+            table = table.with_version_hint_file(name);
+        }
+        if let Some(prefix) = &options.metadata_file_prefix {
+            table = table.with_metadata_file_prefix(prefix);
+        }
+        if let Some(policy) = options.load_policy {
+            table = table.with_load_policy(policy);
+        }
+        table = table.with_metadata_json(options.keep_metadata_json);
+        table.load().await?;
+        Ok(table)
+    }
+
+    /// Open an iceberg table under the given prefix of operator.
+    ///
+    /// `table_prefix` is the path of table root relative to the operator
+    /// root, like `db/tbl`. All reads and writes of the table are prefixed
+    /// with it, so that tables of the same warehouse can share one operator
+    /// and its connection pool instead of building an operator per table.
+    pub async fn open_in(op: Operator, table_prefix: &str) -> Result<Table> {
+        let mut table = Table::new(op).with_table_prefix(table_prefix);
+        table.load().await?;
+        Ok(table)
+    }
+
+    /// Open many iceberg tables under the given prefixes of operator
+    /// concurrently, like `open_in`.
+    ///
+    /// At most `concurrency` tables are loaded at the same time, and each
+    /// result is yielded with its prefix as soon as it completes, so the
+    /// order of results may differ from `prefixes`. A failed table doesn't
+    /// stop loading others.
+    pub fn load_many(
+        op: Operator,
+        prefixes: Vec<String>,
+        concurrency: usize,
+    ) -> impl futures::Stream<Item = (String, Result<Table>)> {
+        futures::stream::iter(prefixes)
+            .map(move |prefix| {
+                let op = op.clone();
+                async move {
+                    let table = Table::open_in(op, &prefix).await;
+                    (prefix, table)
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+    }
+
+    /// Open an iceberg table by the absolute location of its metadata file.
+    ///
+    /// Catalogs like REST catalog return the `metadata-location` of table
+    /// as an absolute uri like `s3://bucket/db/tbl/metadata/v5.metadata.json`.
+    /// We split it into an operator rooted at the table location and the
+    /// metadata path relative to it, so that the location can be loaded
+    /// directly.
+    pub async fn open_with_metadata_location(metadata_location: &str) -> Result<Table> {
+        let (root, path) = io::split_metadata_location(metadata_location)?;
+        let op = io::build_operator(root)?;
+
+        let mut table = Table::new(op);
+        table.load_metadata(path).await?;
+        Ok(table)
+    }
+
+    /// Open an iceberg table with metadata bytes fetched out-of-band, like
+    /// the response body of a catalog.
+    ///
+    /// The operator must be rooted at the table location, which is used
+    /// to read manifests. Since the path of metadata is unknown, the
+    /// version is inferred from the metadata log, and the table can't be
+    /// committed.
+    pub fn from_metadata_bytes(op: Operator, bs: &[u8]) -> Result<Table> {
+        let metadata = types::parse_table_metadata(bs)?;
+        // The previous version is always the last one of metadata log.
+        let version = match metadata.metadata_log.iter().flatten().last() {
+            None => 0,
+            Some(v) => parse_metadata_version(&v.metadata_file)
+                .map(|v| v + 1)
+                .unwrap_or(0),
+        };
+
+        let mut table = Table::new(op);
+        table.set_current_metadata(version, None, metadata);
+        Ok(table)
+    }
+
+    /// Create a new table at the root of the given operator.
+    ///
+    /// The initial metadata `metadata/v1.metadata.json` with the given
+    /// schema and partition spec will be written along with the version
+    /// hint, then the created table will be loaded and returned.
+    ///
+    /// `location` is the table's base location like `s3://bucket/db/tbl`
+    /// which must point to the root of the operator.
+    ///
+    /// The table will be created in format version 2 unless the property
+    /// `format-version` is set to `1`.
+    pub async fn create(
+        op: Operator,
+        location: &str,
+        schema: types::Schema,
+        partition_spec: types::PartitionSpec,
+        properties: HashMap<String, String>,
+    ) -> Result<Table> {
+        Self::create_with_clock(
+            op,
+            location,
+            schema,
+            partition_spec,
+            properties,
+            Arc::new(SystemClock),
+        )
+        .await
+    }
+
+    /// Create a new table like `create`, and commit the given data files as
+    /// its first snapshot, which registers files written by others into a
+    /// fresh table.
+    ///
+    /// The table is created with default properties. The snapshot is
+    /// written in the first version of metadata, so the table is never
+    /// visible without it. No snapshot is added if `data_files` is empty.
+    /// If writing the snapshot fails, the table is not created.
+    pub async fn create_as(
+        op: Operator,
+        location: &str,
+        schema: types::Schema,
+        partition_spec: types::PartitionSpec,
+        data_files: Vec<types::DataFile>,
+    ) -> Result<Table> {
+        let mut table = Table::new(op);
+        let mut metadata = table
+            .new_table_metadata(location, schema, partition_spec, HashMap::new())
+            .await?;
+        if !data_files.is_empty() {
+            // Build the snapshot against the initial metadata kept in
+            // memory, which is replaced by the written one below.
+            table.set_current_metadata(0, None, metadata);
+            let mut producer = SnapshotProducer::new(&mut table)?;
+            let (manifests, _) = producer
+                .write_manifests(&data_files, |_, _| Ok(false))
+                .await?;
+            let summary = producer.summary("append", &data_files, &[])?;
+            metadata = producer.build_metadata(&manifests, summary).await?;
+            metadata.last_updated_ms = table.clock.now_ms().max(metadata.last_updated_ms);
+        }
+
+        table.write_initial_metadata(metadata).await?;
+        Ok(table)
+    }
+
+    /// Create a new table like `create`, with timestamps of the created
+    /// and following commits provided by the clock.
+    pub async fn create_with_clock(
+        op: Operator,
+        location: &str,
+        schema: types::Schema,
+        partition_spec: types::PartitionSpec,
+        properties: HashMap<String, String>,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Table> {
+        let mut table = Table::new(op).with_clock(clock);
+        let metadata = table
+            .new_table_metadata(location, schema, partition_spec, properties)
+            .await?;
+        table.write_initial_metadata(metadata).await?;
+        Ok(table)
+    }
+
+    /// Build the metadata of a new table without snapshots, returns error
+    /// if the table already exists.
+    async fn new_table_metadata(
+        &self,
+        location: &str,
+        schema: types::Schema,
+        partition_spec: types::PartitionSpec,
+        mut properties: HashMap<String, String>,
+    ) -> Result<types::TableMetadata> {
+        if self.is_version_hint_exist().await?
+            || !self.list_table_metadata_paths().await?.is_empty()
+        {
+            return Err(anyhow!("table at {} already exists", location));
+        }
+
+        let format_version = match properties.remove("format-version").as_deref() {
+            None | Some("2") => types::TableFormatVersion::V2,
+            Some("1") => types::TableFormatVersion::V1,
+            Some(v) => return Err(anyhow!("format version {} is not supported", v)),
+        };
+
+        Ok(types::TableMetadata {
+            format_version,
+            table_uuid: uuid::Uuid::new_v4().to_string(),
+            location: location.trim_end_matches('/').to_string(),
+            last_sequence_number: 0,
+            last_updated_ms: self.clock.now_ms(),
+            last_column_id: highest_field_id(&schema.fields),
+            current_schema_id: schema.schema_id,
+            schemas: vec![schema],
+            // Partition field ids start from 1000, so the initial value is 999.
+            last_partition_id: partition_spec
+                .fields
+                .iter()
+                .map(|v| v.partition_field_id)
+                .max()
+                .unwrap_or(999),
+            default_spec_id: partition_spec.spec_id,
+            partition_specs: vec![partition_spec],
+            properties: Some(properties),
+            current_snapshot_id: None,
+            snapshots: Some(vec![]),
+            snapshot_log: Some(vec![]),
+            metadata_log: Some(vec![]),
+            sort_orders: vec![types::SortOrder {
+                order_id: 0,
+                fields: vec![],
+            }],
+            default_sort_order_id: 0,
+            refs: Some(HashMap::new()),
+            unknown_fields: BTreeMap::new(),
+            extensions: BTreeMap::new(),
+        })
+    }
+
+    /// Write the metadata as the first version of a new table along with
+    /// the version hint, and load it as the only version.
+    async fn write_initial_metadata(&mut self, metadata: types::TableMetadata) -> Result<()> {
+        let path = "metadata/v1.metadata.json";
+        self.metadata_io()
+            .write(path, types::serialize_table_metadata(&metadata)?)
+            .await?;
+        self.metadata_io()
+            .write(&self.version_hint_path, b"1".to_vec())
+            .await?;
+
+        self.table_metadata.clear();
+        self.load_metadata(path).await
+    }
+
+    /// Start a new scan to plan data files of the current snapshot.
+    pub fn scan(&self) -> TableScan<'_> {
+        TableScan::new(self)
+    }
+
+    /// Read all rows of the current snapshot into memory.
+    ///
+    /// See `TableScan::collect` for details.
+    #[cfg(feature = "io_parquet")]
+    pub async fn collect(&self) -> Result<Vec<arrow_array::RecordBatch>> {
+        self.scan().collect().await
+    }
+
+    /// Read rows of the given data file as a stream of record batches.
+    ///
+    /// The file is read as is without planning a scan, rows removed by
+    /// delete files are not skipped. This is useful to investigate a
+    /// suspected bad file in manifests.
+    ///
+    /// Columns of data files are promoted to the types of current schema
+    /// like `TableScan::collect`.
+    #[cfg(feature = "io_parquet")]
+    pub async fn read_data_file(
+        &self,
+        data_file: &types::DataFile,
+    ) -> Result<impl futures::Stream<Item = Result<arrow_array::RecordBatch>>> {
+        // Delete files have their own schemas, which are not promoted.
+        let schema = match data_file.content {
+            types::DataContentType::Data => {
+                Some(self.current_table_metadata()?.current_schema()?.clone())
+            }
+            _ => None,
+        };
+        let reader = self.scan().parquet_reader(data_file, None).await?;
+
+        Ok(futures::stream::try_unfold(
+            (reader, schema),
+            |(mut reader, schema)| async move {
+                let Some(batch) = reader.next().await? else {
+                    return Ok(None);
+                };
+                let batch = match &schema {
+                    Some(schema) => crate::scan::promote_types(batch, schema)?,
+                    None => batch,
+                };
+                Ok(Some((batch, (reader, schema))))
+            },
+        ))
+    }
+
+    /// Read `(file_path, pos)` pairs of the given position delete file as
+    /// a stream, in the order they are written.
+    ///
+    /// Like `read_data_file`, the file is read as is, whether the deletes
+    /// apply to data files is not checked.
+    #[cfg(feature = "io_parquet")]
+    pub async fn read_position_deletes(
+        &self,
+        delete_file: &types::DataFile,
+    ) -> Result<impl futures::Stream<Item = Result<(String, i64)>>> {
+        if delete_file.content != types::DataContentType::PostionDeletes {
+            return Err(anyhow!(
+                "file {} is not a position delete file",
+                delete_file.file_path
+            ));
+        }
+
+        let path = delete_file.file_path.clone();
+        let batches = self.read_data_file(delete_file).await?;
+        Ok(batches
+            .and_then(move |batch| {
+                let deletes = crate::scan::parse_position_deletes(&path, &batch);
+                async move { deletes }
+            })
+            .map_ok(|deletes| futures::stream::iter(deletes.into_iter().map(Ok)))
+            .try_flatten())
+    }
+
+    /// Start a new append operation which adds data files to the table.
+    pub fn new_append(&mut self) -> AppendOperation<'_> {
+        AppendOperation::new(self)
+    }
+
+    /// Start a new overwrite operation which replaces data files of the
+    /// matching partitions with new data files.
+    pub fn new_overwrite(&mut self) -> OverwriteOperation<'_> {
+        OverwriteOperation::new(self)
+    }
+
+    /// Start a new delete operation which deletes data files from the
+    /// table.
+    pub fn new_delete(&mut self) -> DeleteOperation<'_> {
+        DeleteOperation::new(self)
+    }
+
+    /// Start a new rewrite operation which compacts small data files whose
+    /// partition values match the filter into larger ones.
+    #[cfg(feature = "io_parquet")]
+    pub fn rewrite_data_files<'a>(
+        &'a mut self,
+        filter: impl Fn(&[Option<types::Datum>]) -> bool + Send + Sync + 'a,
+    ) -> RewriteOperation<'a> {
+        RewriteOperation::new(self, filter)
+    }
+
+    /// Start a new rewrite operation which merges small data manifests
+    /// into larger ones grouped by partition.
+    pub fn rewrite_manifests(&mut self) -> RewriteManifestsOperation<'_> {
+        RewriteManifestsOperation::new(self)
+    }
+
+    /// Start a new operation which evolves the schema of table.
+    pub fn update_schema(&mut self) -> UpdateSchemaOperation<'_> {
+        UpdateSchemaOperation::new(self)
+    }
+
+    /// Start a new cherry-pick operation which applies changes of the
+    /// given snapshot on top of the current snapshot.
+    pub fn new_cherrypick(&mut self, snapshot_id: i64) -> CherrypickOperation<'_> {
+        CherrypickOperation::new(self, snapshot_id)
+    }
+
+    /// Apply changes of the given snapshot, which is typically staged by
+    /// write-audit-publish, as a new snapshot on top of current and commit.
+    ///
+    /// The commit fails if the changes conflict with the current snapshot.
+    pub async fn cherrypick(&mut self, snapshot_id: i64) -> Result<()> {
+        self.new_cherrypick(snapshot_id).commit().await
+    }
+
+    /// Publish the snapshot staged with the given write-audit-publish id
+    /// and commit.
+    ///
+    /// If the staged snapshot is based on the current snapshot, it becomes
+    /// the current snapshot as is. Otherwise its changes are cherry-picked
+    /// on top of the current snapshot. The latest staged snapshot wins if
+    /// several ones share the same id.
+    pub async fn publish(&mut self, wap_id: &str) -> Result<()> {
+        for ancestor in self.ancestors()? {
+            let summary = &ancestor?.summary;
+            if [summary.get("wap.id"), summary.get("published-wap-id")]
+                .contains(&Some(&wap_id.to_string()))
+            {
+                return Err(anyhow!("wap id {} has been published already", wap_id));
+            }
+        }
+
+        let meta = self.current_table_metadata()?;
+        let staged = meta
+            .snapshots
+            .iter()
+            .flatten()
+            .rev()
+            .find(|v| v.summary.get("wap.id").map(String::as_str) == Some(wap_id))
+            .ok_or_else(|| anyhow!("no snapshot is staged with wap id {}", wap_id))?;
+        if staged.parent_snapshot_id != meta.current_snapshot_id {
+            let snapshot_id = staged.snapshot_id;
+            return self.cherrypick(snapshot_id).await;
+        }
+
+        let mut metadata = meta.clone();
+        set_current_snapshot(&mut metadata, staged.snapshot_id, self.clock.now_ms());
+        self.commit(metadata).await
+    }
+
+    /// Add an optional top level column to the schema of table and commit.
+    ///
+    /// A new field id is allocated from `last-column-id`.
+    pub async fn add_column(
+        &mut self,
+        name: &str,
+        typ: types::Any,
+        doc: Option<&str>,
+    ) -> Result<()> {
+        self.update_schema()
+            .add_column(name, typ, doc)
+            .commit()
+            .await
+    }
+
+    /// Drop a top level column from the schema of table and commit.
+    ///
+    /// The field id of dropped column will not be reused.
+    pub async fn drop_column(&mut self, name: &str) -> Result<()> {
+        self.update_schema().drop_column(name).commit().await
+    }
+
+    /// Rename a top level column of the schema of table and commit.
+    ///
+    /// The field id of column is not changed.
+    pub async fn rename_column(&mut self, name: &str, new_name: &str) -> Result<()> {
+        self.update_schema()
+            .rename_column(name, new_name)
+            .commit()
+            .await
+    }
+
+    /// Commit the given metadata as the next version of table.
+    ///
+    /// The new metadata will be written to `metadata/v{N+1}.metadata.json`
+    /// where `N` is the version of current metadata, then the version hint
+    /// will be updated and the new version will be loaded. Unknown top level
+    /// fields parsed from the current metadata are written as is, so that
+    /// tables shared with newer engines are not degraded.
+    ///
+    /// The commit fails if the next version already exists, which means
+    /// the table has been updated by others. Since the storage doesn't
+    /// provide atomic create, this check is best-effort.
+    ///
+    /// Metadata files trimmed from the metadata log will be deleted after
+    /// commit if `write.metadata.delete-after-commit.enabled` is true.
+    pub(crate) async fn commit(&mut self, metadata: types::TableMetadata) -> Result<()> {
+        let (version, metadata) = self.prepare_commit(metadata)?;
+
+        let mut trimmed = vec![];
+        let delete_after_commit = match metadata
+            .properties
+            .as_ref()
+            .and_then(|v| v.get("write.metadata.delete-after-commit.enabled"))
+        {
+            None => false,
+            Some(v) => v.parse().map_err(|err| {
+                anyhow!(
+                    "write.metadata.delete-after-commit.enabled is invalid: {}",
+                    err
+                )
+            })?,
+        };
+        if delete_after_commit {
+            let current = self.current_table_metadata()?;
+            let kept: HashSet<_> = metadata
+                .metadata_log
+                .iter()
+                .flatten()
+                .map(|v| v.metadata_file.as_str())
+                .collect();
+            trimmed = current
+                .metadata_log
+                .iter()
+                .flatten()
+                .filter(|v| !kept.contains(v.metadata_file.as_str()))
+                .map(|v| v.metadata_file.clone())
+                .collect();
+        }
+        let path = format!("metadata/v{}.metadata.json", version);
+        if self.metadata_io().is_exist(&path).await? {
+            return Err(anyhow!(
+                "commit conflict: metadata {} has been written by others",
+                path
+            ));
+        }
+
+        self.metadata_io()
+            .write(&path, types::serialize_table_metadata(&metadata)?)
+            .await?;
+        self.metadata_io()
+            .write(&self.version_hint_path, version.to_string().into_bytes())
+            .await?;
+        self.load_metadata(&path).await?;
+
+        // The commit has succeeded, failures of cleanup are only logged.
+        for path in trimmed {
+            if let Some(path) = self.try_rel_path(&path) {
+                // Paths of metadata io have no leading slash.
+                let path = path.trim_start_matches('/');
+                if let Err(err) = self.metadata_io().delete(path).await {
+                    log::warn!("delete trimmed metadata {} failed: {}", path, err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prepare the metadata to commit as the next version of table without
+    /// touching the storage.
+    ///
+    /// Returns the next version and the metadata with
+    /// `last-updated-ms` and metadata log updated. The metadata log is
+    /// trimmed to at most `write.metadata.previous-versions-max` entries.
+    pub(crate) fn prepare_commit(
+        &self,
+        mut metadata: types::TableMetadata,
+    ) -> Result<(i32, types::TableMetadata)> {
+        if let Some(tag) = &self.loaded_tag {
+            return Err(anyhow!(
+                "table is loaded at tag {}, which is read-only, load the table again to commit",
+                tag
+            ));
+        }
+        let current = self.current_table_metadata()?;
+        let current_path = self.current_metadata_path.clone().ok_or_else(|| {
+            anyhow!("path of current metadata is unknown, maybe loaded from bytes?")
+        })?;
+        let version = self
+            .current_version
+            .ok_or_else(|| anyhow!("table metadata not loaded yet"))?;
+
+        // Make sure `last-updated-ms` is increasing.
+        metadata.last_updated_ms = self.clock.now_ms().max(current.last_updated_ms + 1);
+        metadata
+            .metadata_log
+            .get_or_insert_with(Vec::new)
+            .push(types::MetadataLog {
+                timestamp_ms: current.last_updated_ms,
+                metadata_file: format!("{}/{}", current.location, current_path),
+            });
+
+        let previous_versions_max = match metadata
+            .properties
+            .as_ref()
+            .and_then(|v| v.get("write.metadata.previous-versions-max"))
+        {
+            None => DEFAULT_PREVIOUS_VERSIONS_MAX,
+            Some(v) => v.parse().map_err(|err| {
+                anyhow!("write.metadata.previous-versions-max is invalid: {}", err)
+            })?,
+        };
+        if let Some(log) = &mut metadata.metadata_log {
+            let trimmed = log.len().saturating_sub(previous_versions_max.max(1));
+            log.drain(..trimmed);
+        }
+
+        Ok((version + 1, metadata))
+    }
+
+    /// Get the operator of table which is rooted at table location.
+    ///
+    /// Paths recorded in metadata are absolute, use `rel_path` to convert
+    /// them into paths of the operator.
+    pub fn operator(&self) -> &Operator {
+        &self.op
+    }
+
+    /// Get the operator passed by users, which is rooted at
+    /// `table_prefix` above the table location.
+    ///
+    /// Use `root_path` to convert paths recorded in metadata into paths of
+    /// the operator.
+    pub fn root_operator(&self) -> &Operator {
+        &self.root_op
+    }
+
+    /// Get the path of table root relative to the root operator like
+    /// `db/tbl/`, empty if the operator is rooted at table location.
+    pub fn table_prefix(&self) -> &str {
+        &self.table_prefix
+    }
+
+    /// Get the storage of metadata files and version hint.
+    fn metadata_io(&self) -> &dyn MetadataIo {
+        match &self.metadata_io {
+            Some(v) => v.as_ref(),
+            None => &self.op,
+        }
+    }
+
+    /// Get the clock of timestamps written by commits.
+    pub(crate) fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Fetch current table metadata.
+    pub fn current_table_metadata(&self) -> Result<&types::TableMetadata> {
+        let version = self
+            .current_version
+            .ok_or_else(|| anyhow!("table metadata not loaded yet"))?;
+
+        self.table_metadata
+            .get(&version)
+            .ok_or_else(|| anyhow!("table metadata not found"))
+    }
+
+    /// Fetch the format version of current table metadata.
+    pub fn format_version(&self) -> Result<types::TableFormatVersion> {
+        Ok(self.current_table_metadata()?.format_version)
+    }
+
+    /// List all loaded versions in ascending order.
+    ///
+    /// Versions are parsed from names of metadata files, like `2` for
+    /// `metadata/v2.metadata.json`.
+    pub fn metadata_versions(&self) -> Vec<i32> {
+        let mut versions: Vec<_> = self.table_metadata.keys().copied().collect();
+        versions.sort();
+        versions
+    }
+
+    /// Fetch table metadata of the loaded version.
+    pub fn table_metadata(&self, version: i32) -> Option<&types::TableMetadata> {
+        self.table_metadata.get(&version)
+    }
+
+    /// Diff table properties between two loaded versions.
+    ///
+    /// Returns the old and new values of properties which are added,
+    /// removed or changed, keyed by name. Both versions must be loaded,
+    /// see `load_all_versions`.
+    pub fn properties_changed_between(&self, v_old: i32, v_new: i32) -> Result<PropertyChanges> {
+        let properties = |version| {
+            self.table_metadata(version)
+                .map(|v| v.properties.clone().unwrap_or_default())
+                .ok_or_else(|| anyhow!("table metadata of version {} is not loaded", version))
+        };
+        let mut old = properties(v_old)?;
+        let new = properties(v_new)?;
+
+        let mut changed = HashMap::new();
+        for (key, value) in new {
+            let old_value = old.remove(&key);
+            if old_value.as_ref() != Some(&value) {
+                changed.insert(key, (old_value, Some(value)));
+            }
+        }
+        for (key, value) in old {
+            changed.insert(key, (Some(value), None));
+        }
+
+        Ok(changed)
+    }
+
+    /// Fetch the path of metadata file used by current version.
+    ///
+    /// The path is relative to the operator root, and will be `None` if
+    /// the table is not loaded yet or loaded from bytes.
+    pub fn current_metadata_path(&self) -> Option<&str> {
+        self.current_metadata_path.as_deref()
+    }
+
+    /// Fetch the raw json of metadata file used by current version, which
+    /// keeps fields not modeled by `TableMetadata` like vendor extensions.
+    ///
+    /// `None` will be returned unless enabled by `with_metadata_json`, or
+    /// if the table is not loaded yet or loaded from bytes.
+    pub fn current_metadata_json(&self) -> Option<&serde_json::Value> {
+        self.current_metadata_json.as_ref()
+    }
+
+    /// Fetch the snapshot log of current version, which records when each
+    /// snapshot became current, sorted by timestamp as the spec requires.
+    ///
+    /// An empty slice will be returned if the table is not loaded yet or
+    /// has no snapshot log.
+    pub fn snapshot_log(&self) -> &[types::SnapshotLog] {
+        self.current_table_metadata()
+            .ok()
+            .and_then(|v| v.snapshot_log.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Fetch the version of metadata file used by current version, like
+    /// `2` for `metadata/v2.metadata.json`.
+    ///
+    /// `None` will be returned if the table is not loaded yet.
+    pub fn current_metadata_version(&self) -> Option<i32> {
+        self.current_version
+    }
+
+    /// Walk the snapshot lineage from the current snapshot to the root by
+    /// following `parent-snapshot-id`.
+    ///
+    /// The walk stops at the root, or at a parent that has been expired
+    /// from metadata. Nothing is yielded if the table has no snapshot
+    /// yet. An error is yielded if the parents form a cycle.
+    pub fn ancestors(&self) -> Result<impl Iterator<Item = Result<&types::Snapshot>> + '_> {
+        let meta = self.current_table_metadata()?;
+        let snapshots: HashMap<i64, &types::Snapshot> = meta
+            .snapshots
+            .iter()
+            .flatten()
+            .map(|v| (v.snapshot_id, v))
+            .collect();
+
+        if let Some(id) = meta.current_snapshot_id {
+            if !snapshots.contains_key(&id) {
+                return Err(anyhow!("snapshot with id {} is not found", id));
+            }
+        }
+
+        let mut next = meta.current_snapshot_id;
+        let mut visited = HashSet::new();
+        Ok(std::iter::from_fn(move || {
+            let id = next.take()?;
+            let snapshot = *snapshots.get(&id)?;
+            if !visited.insert(id) {
+                return Some(Err(anyhow!(
+                    "snapshot {} is its own ancestor, parents form a cycle",
+                    id
+                )));
+            }
+            next = snapshot.parent_snapshot_id;
+            Some(Ok(snapshot))
+        }))
+    }
+
+    /// # TODO
+    ///
+    /// we will have better API to play with snapshots and partitions.
+    ///
+    /// Currently, we just return all data files of the current version.
+    ///
+    /// `file_path`s are returned exactly as recorded in the manifests, so
+    /// that they can be passed back to operations like `delete_file` and
+    /// read by the engine which wrote them. They are not normalized, since
+    /// percent-decoding is not reversible for names containing a literal
+    /// `%`. Use `normalized_path` to compare paths written by different
+    /// engines, or `current_data_file_paths` for deduplicated paths
+    /// relative to the operator.
+    pub async fn current_data_files(&self) -> Result<Vec<types::DataFile>> {
+        let meta = self.current_table_metadata()?;
+
+        let Some(current_snapshot_id) = meta.current_snapshot_id else {
+            // An empty table has no snapshot yet.
+            return Ok(vec![]);
+        };
+        let current_snapshot = meta
+            .snapshots
+            .as_ref()
+            .ok_or_else(|| anyhow!("snapshots is emppty"))?
+            .iter()
+            .find(|v| v.snapshot_id == current_snapshot_id)
+            .ok_or_else(|| anyhow!("snapshot with id {} is not found", current_snapshot_id))?;
+
+        self.data_files_of_snapshot(current_snapshot).await
+    }
+
+    /// Get all data files of the snapshot referenced by the given tag,
+    /// without changing the current snapshot of table.
+    pub async fn data_files_at_tag(&self, tag_name: &str) -> Result<Vec<types::DataFile>> {
+        let snapshot = self.tag_snapshot(tag_name)?;
+        self.data_files_of_snapshot(snapshot).await
+    }
+
+    /// Resolve the snapshot referenced by the given tag.
+    fn tag_snapshot(&self, tag_name: &str) -> Result<&types::Snapshot> {
+        let meta = self.current_table_metadata()?;
+
+        let reference = meta
+            .refs
+            .as_ref()
+            .and_then(|v| v.get(tag_name))
+            .ok_or_else(|| anyhow!("tag {} is not found", tag_name))?;
+        if reference.typ != types::SnapshotReferenceType::Tag {
+            return Err(anyhow!("ref {} is not a tag", tag_name));
+        }
+
+        meta.snapshots
+            .iter()
+            .flatten()
+            .find(|v| v.snapshot_id == reference.snapshot_id)
+            .ok_or_else(|| {
+                anyhow!(
+                    "snapshot with id {} of tag {} is not found",
+                    reference.snapshot_id,
+                    tag_name
+                )
+            })
+    }
+
+    /// Get all data files of the given snapshot.
+    async fn data_files_of_snapshot(
+        &self,
+        snapshot: &types::Snapshot,
+    ) -> Result<Vec<types::DataFile>> {
+        let manifest_list = self.read_manifest_list(snapshot).await?;
+
+        let mut data_files = vec![];
+        for manifest in manifest_list {
+            data_files.extend(self.live_data_files_of_manifest(&manifest).await?);
+        }
+
+        Ok(data_files)
+    }
+
+    /// Get all data files of the given snapshots in one pass, each tagged
+    /// with the id of snapshot it's live in.
+    ///
+    /// Manifest lists and manifests are read concurrently, and manifests
+    /// shared by several snapshots, like heads of branches forked from the
+    /// same history, are read only once. Files live in several snapshots
+    /// are returned once per snapshot, following the order of `ids`.
+    pub async fn data_files_for_snapshots(
+        &self,
+        ids: &[i64],
+    ) -> Result<Vec<(i64, types::DataFile)>> {
+        let meta = self.current_table_metadata()?;
+        let snapshots = ids
+            .iter()
+            .map(|id| {
+                meta.snapshots
+                    .iter()
+                    .flatten()
+                    .find(|v| v.snapshot_id == *id)
+                    .ok_or_else(|| anyhow!("snapshot with id {} is not found", id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let manifest_lists: Vec<_> = futures::stream::iter(snapshots)
+            .map(|snapshot| self.read_manifest_list(snapshot))
+            .buffered(DEFAULT_PLAN_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let unique_manifests: HashMap<_, _> = manifest_lists
+            .iter()
+            .flatten()
+            .map(|v| (v.manifest_path.as_str(), v))
+            .collect();
+        let manifests: HashMap<_, _> = futures::stream::iter(unique_manifests)
+            .map(|(path, manifest)| async move {
+                let data_files = self.live_data_files_of_manifest(manifest).await?;
+                Ok::<_, anyhow::Error>((path, data_files))
+            })
+            .buffer_unordered(DEFAULT_PLAN_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut data_files = vec![];
+        for (id, manifest_list) in ids.iter().zip(&manifest_lists) {
+            for manifest in manifest_list {
+                data_files.extend(
+                    manifests[manifest.manifest_path.as_str()]
+                        .iter()
+                        .map(|v| (*id, v.clone())),
+                );
+            }
+        }
+
+        Ok(data_files)
+    }
+
+    /// Read the manifest list of the given snapshot.
+    async fn read_manifest_list(
+        &self,
+        snapshot: &types::Snapshot,
+    ) -> Result<Vec<types::ManifestList>> {
+        let manifest_list_path = self.rel_path(&snapshot.manifest_list)?;
+        let manifest_list_content = self.op.read(&manifest_list_path).await?;
+        Ok(types::parse_manifest_list(&manifest_list_content)?)
+    }
+
+    /// Read the content of the manifest, `None` if it's missing and
+    /// skipped by load policy.
+    ///
+    /// Only data manifests could be skipped, skipping delete manifests
+    /// would bring deleted rows back.
+    pub(crate) async fn read_manifest(
+        &self,
+        manifest: &types::ManifestList,
+    ) -> Result<Option<Vec<u8>>> {
+        let manifest_path = &manifest.manifest_path;
+        match self.op.read(&self.rel_path(manifest_path)?).await {
+            Ok(content) => Ok(Some(content)),
+            Err(err)
+                if err.kind() == opendal::ErrorKind::NotFound
+                    && manifest.content == types::ManifestContentType::Data
+                    && matches!(
+                        self.load_policy,
+                        Some(LoadPolicy::Lenient | LoadPolicy::RepairHint)
+                    ) =>
+            {
+                log::warn!("manifest {} is missing, skipped", manifest_path);
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Read data files of the given manifest which are not deleted.
+    async fn live_data_files_of_manifest(
+        &self,
+        manifest: &types::ManifestList,
+    ) -> Result<Vec<types::DataFile>> {
+        let meta = self.current_table_metadata()?;
+
+        let Some(manifest_content) = self.read_manifest(manifest).await? else {
+            return Ok(vec![]);
+        };
+        let (_, manifest_files) = types::parse_manifest_file_with_partition_specs(
+            &manifest_content,
+            &meta.partition_specs,
+        )?;
+
+        Ok(manifest_files
+            .into_iter()
+            .filter(|v| v.status != types::ManifestStatus::Deleted)
+            .map(|v| v.data_file)
+            .collect())
+    }
+
+    /// Estimate the number of rows in the current version without reading
+    /// data files.
+    ///
+    /// Record counts of live data files in manifests are summed up, and
+    /// rows removed by position delete files are subtracted. Equality
+    /// deletes can't be counted without reading data, so the estimation
+    /// may be larger than the exact count.
+    pub async fn estimated_row_count(&self) -> Result<u64> {
+        // An empty table has no snapshot yet.
+        if self.current_table_metadata()?.current_snapshot_id.is_none() {
+            return Ok(0);
+        }
+
+        let mut count: i64 = 0;
+        for data_file in self.current_data_files().await? {
+            match data_file.content {
+                types::DataContentType::Data => count += data_file.record_count,
+                types::DataContentType::PostionDeletes => count -= data_file.record_count,
+                types::DataContentType::EqualityDeletes => {}
+            }
+        }
+
+        Ok(count.max(0) as u64)
+    }
+
+    /// Get the global range of the column by combining lower and upper
+    /// bounds of all live data files, without reading data.
+    ///
+    /// The column is found by dotted path like `address.city` in the
+    /// current schema, and must be primitive. Files whose values are all
+    /// null are skipped. `None` is returned if the table is empty, the
+    /// column has only nulls, or any file lacks bounds of the column. The
+    /// range may be wider than the actual values, since bounds of strings
+    /// and binaries can be truncated and deleted rows are still counted.
+    pub async fn column_range(
+        &self,
+        field_name: &str,
+    ) -> Result<Option<(types::Datum, types::Datum)>> {
+        let meta = self.current_table_metadata()?;
+        let field = meta
+            .current_schema()?
+            .field_by_path(field_name)
+            .ok_or_else(|| anyhow!("column {} is not found in schema", field_name))?;
+        let types::Any::Primitive(typ) = &field.field_type else {
+            return Err(anyhow!("column {} is not primitive", field_name));
+        };
+        // An empty table has no snapshot yet.
+        if meta.current_snapshot_id.is_none() {
+            return Ok(None);
+        }
+
+        let mut range: Option<(types::Datum, types::Datum)> = None;
+        for data_file in self.current_data_files().await? {
+            if data_file.content != types::DataContentType::Data
+                || data_file.null_value_count(field.id) == Some(data_file.record_count)
+            {
+                continue;
+            }
+            let (Some(lower), Some(upper)) = (
+                data_file.lower_bound(field.id, typ)?,
+                data_file.upper_bound(field.id, typ)?,
+            ) else {
+                return Ok(None);
+            };
+
+            range = Some(match range {
+                None => (lower, upper),
+                Some((min, max)) => (
+                    if lower < min { lower } else { min },
+                    if upper > max { upper } else { max },
+                ),
+            });
+        }
+
+        Ok(range)
+    }
+
+    /// Summarize manifests of the current snapshot by content type and
+    /// partition spec, without reading manifests themselves.
+    ///
+    /// Too many small manifests, or manifests written by old partition
+    /// specs, slow down planning and are worth rewriting.
+    pub async fn manifest_summary(&self) -> Result<ManifestSummary> {
+        let meta = self.current_table_metadata()?;
+        let mut summary = ManifestSummary::default();
+        let Some(current_snapshot_id) = meta.current_snapshot_id else {
+            // An empty table has no snapshot yet.
+            return Ok(summary);
+        };
+        let current_snapshot = meta
+            .snapshots
+            .iter()
+            .flatten()
+            .find(|v| v.snapshot_id == current_snapshot_id)
+            .ok_or_else(|| anyhow!("snapshot with id {} is not found", current_snapshot_id))?;
+
+        let manifest_list_path = self.rel_path(&current_snapshot.manifest_list)?;
+        let manifest_list = types::parse_manifest_list(&self.op.read(&manifest_list_path).await?)?;
+        for manifest in manifest_list {
+            summary.manifest_count += 1;
+            match manifest.content {
+                types::ManifestContentType::Data => summary.data_manifest_count += 1,
+                types::ManifestContentType::Deletes => summary.delete_manifest_count += 1,
+            }
+            *summary
+                .manifest_count_by_spec
+                .entry(manifest.partition_spec_id)
+                .or_default() += 1;
+            summary.total_manifest_bytes += manifest.manifest_length.max(0) as u64;
+        }
+
+        Ok(summary)
+    }
+
+    /// Compute the ratio of records in delete files to records in data
+    /// files for each partition of the current version.
+    ///
+    /// Only record counts in manifests are used, no data file is read. The
+    /// ratio is infinite for partitions with deletes but no data, and
+    /// partitions without any file are absent.
+    pub async fn delete_ratio_by_partition(&self) -> Result<HashMap<types::PartitionValues, f64>> {
+        let meta = self.current_table_metadata()?;
+        let Some(current_snapshot_id) = meta.current_snapshot_id else {
+            // An empty table has no snapshot yet.
+            return Ok(HashMap::new());
+        };
+        let current_snapshot = meta
+            .snapshots
+            .iter()
+            .flatten()
+            .find(|v| v.snapshot_id == current_snapshot_id)
+            .ok_or_else(|| anyhow!("snapshot with id {} is not found", current_snapshot_id))?;
+
+        // Records of data files and delete files keyed by partition.
+        let mut records: HashMap<types::PartitionValues, (i64, i64)> = HashMap::new();
+        let manifest_list_path = self.rel_path(&current_snapshot.manifest_list)?;
+        let manifest_list = types::parse_manifest_list(&self.op.read(&manifest_list_path).await?)?;
+        for manifest in manifest_list {
+            let content = self
+                .op
+                .read(&self.rel_path(&manifest.manifest_path)?)
+                .await?;
+            types::visit_manifest_file(&content, &meta.partition_specs, |_, entry| {
+                if entry.status == types::ManifestStatus::Deleted {
+                    return Ok(());
+                }
+                let data_file = entry.data_file;
+                let (data, deletes) = records
+                    .entry(types::PartitionValues {
+                        spec_id: manifest.partition_spec_id,
+                        values: data_file.partition,
+                    })
+                    .or_default();
+                match data_file.content {
+                    types::DataContentType::Data => *data += data_file.record_count,
+                    _ => *deletes += data_file.record_count,
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(records
+            .into_iter()
+            .map(|(partition, (data, deletes))| {
+                let ratio = if deletes == 0 {
+                    0.0
+                } else {
+                    deletes as f64 / data as f64
+                };
+                (partition, ratio)
+            })
+            .collect())
+    }
+
+    /// Get paths of all data files of the current version.
+    ///
+    /// The paths are relative to the operator root, which can be read by
+    /// the operator of table directly. Mixed schemes like `s3a` and `s3`
+    /// of table location are accepted, and the same file written in
+    /// different encodings is returned only once, at the path of its first
+    /// occurrence.
+    ///
+    /// Paths keep the encoding written in the manifests, so that they can
+    /// be read as is. Duplicates are found by `normalized_path`, which is
+    /// lossy and only used as the comparison key.
+    pub async fn current_data_file_paths(&self) -> Result<Vec<String>> {
+        let mut seen = HashSet::new();
+        let mut paths = vec![];
+        for data_file in self.current_data_files().await? {
+            let path = self.rel_path(&data_file.file_path)?;
+            if seen.insert(self.normalized_path(&path)?) {
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Get the normalized form of a data file path, which is stable across
+    /// engines and can be compared as string.
+    ///
+    /// The path could be either recorded in manifests or relative to the
+    /// table location. It's stripped to the path relative to the operator
+    /// root like `rel_path`, then percent-encoded bytes are decoded, like
+    /// `s3a://bucket/tbl/data/a%20b.parquet` to `/data/a b.parquet`.
+    ///
+    /// Decoding is not reversible for names containing a literal `%`, so
+    /// the normalized path is a comparison key and should not be used to
+    /// read the file.
+    pub fn normalized_path(&self, path: &str) -> Result<String> {
+        let path = match path.starts_with('/') {
+            true => path.to_string(),
+            false => self.rel_path(path)?,
+        };
+        Ok(normalize_path(&path))
+    }
+
+    /// List all files reachable from the current table metadata.
+    ///
+    /// This includes the current and previous metadata files recorded in
+    /// metadata log, the version hint, and all manifest lists, manifests
+    /// and data files referenced by any snapshot.
+    ///
+    /// The returned paths are relative to the operator root. To avoid
+    /// deleting files shared with other tables, files outside the table
+    /// location and previous metadata files with a different table uuid
+    /// are not included. Files already removed are skipped instead of
+    /// failing the listing.
+    pub async fn reachable_files(&self) -> Result<Vec<String>> {
+        let meta = self.current_table_metadata()?;
+        // Paths in the same table could be recorded with or without the
+        // leading `/`, normalize them to avoid duplicates.
+        let rel_path = |path: &str| {
+            self.try_rel_path(path)
+                .map(|v| v.trim_start_matches('/').to_string())
+        };
+        let mut files = HashSet::new();
+        // The path is unknown if metadata is loaded from bytes.
+        if let Some(path) = &self.current_metadata_path {
+            files.insert(path.clone());
+        }
+        if self.is_version_hint_exist().await? {
+            files.insert(self.version_hint_path.clone());
+        }
+
+        let mut metadatas = vec![meta.clone()];
+        for log in meta.metadata_log.iter().flatten() {
+            let Some(path) = rel_path(&log.metadata_file) else {
+                continue;
+            };
+            // Previous metadata could have been removed already.
+            if !self.metadata_io().is_exist(&path).await? {
+                continue;
+            }
+
+            let metadata = self.read_table_metadata(&path).await?;
+            if metadata.table_uuid != meta.table_uuid {
+                continue;
+            }
+            files.insert(path);
+            metadatas.push(metadata);
+        }
+
+        let mut manifest_lists = HashSet::new();
+        for snapshot in metadatas.iter().flat_map(|v| v.snapshots.iter().flatten()) {
+            if let Some(path) = rel_path(&snapshot.manifest_list) {
+                manifest_lists.insert(path);
+            }
+        }
+
+        // Manifest lists and manifests of old snapshots could have been
+        // removed already, files referenced only by them are not reachable.
+        let mut manifests = HashSet::new();
+        for path in manifest_lists {
+            let Some(content) = self.read_if_exist(&path).await? else {
+                continue;
+            };
+            for manifest in types::parse_manifest_list(&content)? {
+                if let Some(path) = rel_path(&manifest.manifest_path) {
+                    manifests.insert(path);
+                }
+            }
+            files.insert(path);
+        }
+
+        for path in manifests {
+            let Some(content) = self.read_if_exist(&path).await? else {
+                continue;
+            };
+            let (_, entries) = types::parse_manifest_file(&content)?;
+            for entry in entries {
+                if let Some(path) = rel_path(&entry.data_file.file_path) {
+                    files.insert(path);
+                }
+            }
+            files.insert(path);
+        }
+
+        let mut files: Vec<_> = files.into_iter().collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Read the file in table, `None` will be returned if it's not found.
+    async fn read_if_exist(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        match self.op.read(path).await {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.kind() == opendal::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Check the current metadata and files it references, like `fsck`.
+    ///
+    /// The following are verified:
+    ///
+    /// - `current-snapshot-id` is present in snapshots.
+    /// - Schema ids of metadata and snapshots resolve.
+    /// - Parents of snapshots are acyclic.
+    /// - Manifest lists, manifests and live data files referenced by all
+    ///   snapshots exist.
+    ///
+    /// All found issues are returned instead of failing on the first one,
+    /// errors are only returned if the storage can't be accessed.
+    pub async fn check(&self) -> Result<Vec<CheckIssue>> {
+        let meta = self.current_table_metadata()?;
+        let snapshots: Vec<_> = meta.snapshots.iter().flatten().collect();
+        let mut issues = vec![];
+
+        if let Some(id) = meta.current_snapshot_id {
+            if !snapshots.iter().any(|v| v.snapshot_id == id) {
+                issues.push(CheckIssue::MissingCurrentSnapshot(id));
+            }
+        }
+
+        let schema_ids: HashSet<i64> = meta.schemas.iter().map(|v| v.schema_id as i64).collect();
+        let mut missing_schemas = vec![meta.current_schema_id as i64];
+        missing_schemas.extend(snapshots.iter().filter_map(|v| v.schema_id));
+        missing_schemas.retain(|v| !schema_ids.contains(v));
+        missing_schemas.sort();
+        missing_schemas.dedup();
+        issues.extend(missing_schemas.into_iter().map(CheckIssue::MissingSchema));
+
+        // Walk parents of every snapshot, the walk stops at snapshots
+        // already walked, so that each cycle is reported only once.
+        let parents: HashMap<i64, Option<i64>> = snapshots
+            .iter()
+            .map(|v| (v.snapshot_id, v.parent_snapshot_id))
+            .collect();
+        let mut walked = HashSet::new();
+        for snapshot in &snapshots {
+            let mut chain = vec![];
+            let mut next = Some(snapshot.snapshot_id);
+            while let Some(id) = next {
+                if walked.contains(&id) {
+                    break;
+                }
+                if let Some(pos) = chain.iter().position(|v| *v == id) {
+                    issues.push(CheckIssue::SnapshotCycle(chain[pos..].to_vec()));
+                    break;
+                }
+                chain.push(id);
+                // Parents expired from metadata end the walk.
+                next = parents.get(&id).copied().flatten();
+            }
+            walked.extend(chain);
+        }
+
+        // Check files in order of manifest lists, manifests and data
+        // files, only existing files are read.
+        let mut checked = HashSet::new();
+        let mut manifest_lists = vec![];
+        for snapshot in &snapshots {
+            if let Some(path) = self
+                .check_file(&snapshot.manifest_list, &mut checked, &mut issues)
+                .await?
+            {
+                manifest_lists.push((snapshot.manifest_list.clone(), path));
+            }
+        }
+
+        let mut manifests = vec![];
+        for (location, path) in manifest_lists {
+            let list = match types::parse_manifest_list(&self.op.read(&path).await?) {
+                Ok(v) => v,
+                Err(err) => {
+                    issues.push(CheckIssue::InvalidFile {
+                        path: location,
+                        reason: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+            for manifest in list {
+                if let Some(path) = self
+                    .check_file(&manifest.manifest_path, &mut checked, &mut issues)
+                    .await?
+                {
+                    manifests.push((manifest.manifest_path, path));
+                }
+            }
+        }
+
+        for (location, path) in manifests {
+            let entries = match types::parse_manifest_file_with_partition_specs(
+                &self.op.read(&path).await?,
+                &meta.partition_specs,
+            ) {
+                Ok((_, v)) => v,
+                Err(err) => {
+                    issues.push(CheckIssue::InvalidFile {
+                        path: location,
+                        reason: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+            for entry in entries {
+                // Deleted files could have been removed legally.
+                if entry.status == types::ManifestStatus::Deleted {
+                    continue;
+                }
+                self.check_file(&entry.data_file.file_path, &mut checked, &mut issues)
+                    .await?;
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Check if the file at location exists for `check`, returns the path
+    /// relative to the operator root if it exists and hasn't been checked.
+    async fn check_file(
+        &self,
+        location: &str,
+        checked: &mut HashSet<String>,
+        issues: &mut Vec<CheckIssue>,
+    ) -> Result<Option<String>> {
+        if !checked.insert(location.to_string()) {
+            return Ok(None);
+        }
+        let Some(path) = self.try_rel_path(location) else {
+            issues.push(CheckIssue::InvalidFile {
+                path: location.to_string(),
+                reason: "file is not under the table location".to_string(),
+            });
+            return Ok(None);
+        };
+        if !self.op.is_exist(&path).await? {
+            issues.push(CheckIssue::MissingFile(location.to_string()));
+            return Ok(None);
+        }
+
+        Ok(Some(path))
+    }
+
+    /// Read raw bytes of a file in table, like a manifest list or manifest
+    /// that fails to parse, for offline analysis.
+    ///
+    /// The path could be either a location recorded in metadata like
+    /// `s3://bucket/tbl/metadata/snap-1.avro`, or a path relative to the
+    /// table location like `metadata/snap-1.avro`.
+    pub async fn read_raw(&self, path: &str) -> Result<Vec<u8>> {
+        let path = self.try_rel_path(path).unwrap_or_else(|| path.to_string());
+        self.op
+            .read(&path)
+            .await
+            .map_err(|err| anyhow!("read file {} failed: {}", path, err))
+    }
+
+    /// Get the relpath related to the base of table location.
+    pub fn rel_path(&self, path: &str) -> Result<String> {
+        let location = self
+            .current_location
+            .as_ref()
+            .ok_or_else(|| anyhow!("table location is empty, maybe it's not loaded?"))?;
+
+        strip_location(location, path).ok_or_else(|| {
+            anyhow!(
+                "path {} is not starts with table location {}",
+                path,
+                location
+            )
+        })
+    }
+
+    /// Get the relpath related to the base of table location, `None` will
+    /// be returned if the path is not in table location or the table is
+    /// not loaded.
+    ///
+    /// Use it instead of `rel_path` to skip paths outside the table.
+    pub fn try_rel_path(&self, path: &str) -> Option<String> {
+        let location = self.current_location.as_ref()?;
+
+        strip_location(location, path)
+    }
+
+    /// Get the path relative to the root of `root_operator`.
+    ///
+    /// Paths in table location are prefixed by `table_prefix`. Paths
+    /// outside table location but under the operator root, like data files
+    /// written to a shared directory of warehouse, are resolved too, since
+    /// the root location is known by stripping `table_prefix` from table
+    /// location.
+    pub fn root_path(&self, path: &str) -> Result<String> {
+        if let Some(rel) = self.try_rel_path(path) {
+            return Ok(format!(
+                "/{}{}",
+                self.table_prefix,
+                rel.trim_start_matches('/')
+            ));
+        }
+
+        let location = self
+            .current_location
+            .as_ref()
+            .ok_or_else(|| anyhow!("table location is empty, maybe it's not loaded?"))?;
+        location
+            .trim_end_matches('/')
+            .strip_suffix(self.table_prefix.trim_end_matches('/'))
+            .filter(|root| !self.table_prefix.is_empty() && root.ends_with('/'))
+            .and_then(|root| strip_location(root, path))
+            .ok_or_else(|| {
+                anyhow!(
+                    "path {} is not under the operator root of table location {}",
+                    path,
+                    location
+                )
+            })
+    }
+
+    /// Check if version hint file exist.
+    async fn is_version_hint_exist(&self) -> Result<bool> {
+        self.metadata_io()
+            .is_exist(&self.version_hint_path)
+            .await
+            .map_err(|e| anyhow!("check if version hint exist failed: {}", e))
+    }
+
+    /// Read version hint of table, returns the path of metadata file it
+    /// points to.
+    ///
+    /// The content of hint is either a version number like `2`, or a path
+    /// of metadata file which could be absolute or relative to the table
+    /// root.
+    async fn read_version_hint(&self) -> Result<String> {
+        let content = self.metadata_io().read(&self.version_hint_path).await?;
+        let version_hint = String::from_utf8(content)?;
+        let version_hint = version_hint.trim();
+
+        if let Ok(version) = version_hint.parse::<i32>() {
+            return Ok(format!("metadata/v{}.metadata.json", version));
+        }
+        if !version_hint.ends_with(".metadata.json") {
+            return Err(anyhow!("parse version hint failed: {}", version_hint));
+        }
+
+        if version_hint.starts_with('/') || version_hint.contains("://") {
+            // Table location is unknown before loading, assume the hint
+            // points to metadata under the table root.
+            let (_, path) = io::split_metadata_location(version_hint)?;
+            Ok(path.to_string())
+        } else {
+            Ok(version_hint.to_string())
+        }
+    }
+
+    /// Read table metadata of the given version.
+    async fn read_table_metadata(&self, path: &str) -> Result<types::TableMetadata> {
+        let content = self.metadata_io().read(path).await?;
+
+        let metadata =
+            types::parse_table_metadata(&content).map_err(|err| err.with_context("path", path))?;
+
+        Ok(metadata)
+    }
+
+    /// List all paths of table metadata files in candidate directories.
+    ///
+    /// The returned paths are sorted by name in each directory.
+    async fn list_table_metadata_paths(&self) -> Result<Vec<String>> {
+        let mut paths = vec![];
+        for dir in &self.metadata_dirs {
+            paths.extend(self.list_table_metadata_paths_in(dir).await?);
+        }
+
+        Ok(paths)
+    }
+
+    /// List all paths of table metadata files in the given directory.
+    ///
+    /// Directories and files not matching the metadata file prefix are
+    /// skipped. The returned paths are sorted by name.
+    ///
+    /// TODO: we can imporve this by only fetch the latest metadata.
+    async fn list_table_metadata_paths_in(&self, dir: &str) -> Result<Vec<String>> {
+        let mut paths = vec![];
+        for path in self
+            .metadata_io()
+            .list(dir)
+            .await
+            .map_err(|err| anyhow!("list metadata in {} failed: {}", dir, err))?
+        {
+            // Only push into paths if the entry is a metadata file.
+            let name = path.rsplit('/').next().unwrap_or_default();
+            if !name.ends_with(".metadata.json") {
+                continue;
+            }
+            if let Some(prefix) = &self.metadata_file_prefix {
+                if !name.starts_with(prefix.as_str()) {
+                    continue;
+                }
+            }
+            paths.push(path);
+        }
+
+        // Make the returned paths sorted by name.
+        paths.sort();
+
+        Ok(paths)
+    }
+}
+
+/// Parse the version from metadata path like `metadata/v1.metadata.json`
+/// or `metadata/00001-<uuid>.metadata.json`.
+fn parse_metadata_version(path: &str) -> Option<i32> {
+    let name = path.rsplit('/').next()?.strip_suffix(".metadata.json")?;
+
+    match name.strip_prefix('v') {
+        Some(v) => v.parse().ok(),
+        None => name.split_once('-')?.0.parse().ok(),
+    }
+}
+
+/// Strip the table location from path, returns the rest starting with `/`.
+///
+/// Engines don't agree on whether location ends with `/`, so trailing
+/// slashes of location and duplicated slashes after it are ignored. If the
+/// path doesn't start with location as is, the location is compared again
+/// with prefixes of path in the normalized form by `normalize_path`, like
+/// `s3a://bucket/my%20tbl/a` against `s3://bucket/my tbl`. The rest of
+/// path is always returned as is.
+fn strip_location(location: &str, path: &str) -> Option<String> {
+    fn rest_of(rest: &str) -> Option<String> {
+        // Make sure `s3://bucket/tbl` doesn't match `s3://bucket/tbl2/a`.
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+
+        Some(format!("/{}", rest.trim_start_matches('/')))
+    }
+
+    let location = location.trim_end_matches('/');
+    if let Some(rest) = path.strip_prefix(location) {
+        return rest_of(rest);
+    }
+
+    let normalized = normalize_path(location);
+    path.match_indices('/')
+        .map(|(idx, _)| idx)
+        .chain([path.len()])
+        .find(|idx| normalize_path(&path[..*idx]).trim_end_matches('/') == normalized)
+        .and_then(|idx| rest_of(&path[idx..]))
+}
+
+/// Normalize the path written by different engines, so that the same file
+/// always has the same path.
+///
+/// Schemes are lowercased and aliases of s3 like `s3a` and `s3n` are
+/// replaced by `s3`. Percent-encoded bytes are decoded, unless the decoded
+/// bytes are not valid UTF-8, in which case the path is kept as is.
+fn normalize_path(path: &str) -> String {
+    let path = match path.split_once("://") {
+        Some((scheme, rest)) => {
+            let scheme = match scheme.to_ascii_lowercase().as_str() {
+                "s3a" | "s3n" => "s3".to_string(),
+                v => v.to_string(),
+            };
+            format!("{scheme}://{rest}")
+        }
+        None => path.to_string(),
+    };
+    if !path.contains('%') {
+        return path;
+    }
+
+    let bs = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bs.len());
+    let mut idx = 0;
+    while idx < bs.len() {
+        let hex = bs
+            .get(idx + 1..idx + 3)
+            .and_then(|v| std::str::from_utf8(v).ok())
+            .and_then(|v| u8::from_str_radix(v, 16).ok());
+        match (bs[idx], hex) {
+            (b'%', Some(v)) => {
+                decoded.push(v);
+                idx += 3;
+            }
+            (v, _) => {
+                decoded.push(v);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).unwrap_or(path)
+}
+
+/// Parse the version from metadata path, which is required to load it.
+fn metadata_version_of(path: &str) -> Result<i32> {
+    parse_metadata_version(path)
+        .ok_or_else(|| anyhow!("can't decide the version of metadata {}", path))
+}
+
+/// Get the highest field id of the fields including nested fields.
+fn highest_field_id(fields: &[types::Field]) -> i32 {
+    fn highest_type_id(typ: &types::Any) -> i32 {
+        match typ {
+            types::Any::Primitive(_) | types::Any::Unknown(_) => 0,
+            types::Any::Struct(v) => highest_field_id(&v.fields),
+            types::Any::List(v) => v.element_id.max(highest_type_id(&v.element_type)),
+            types::Any::Map(v) => v
+                .key_id
+                .max(v.value_id)
+                .max(highest_type_id(&v.key_type))
+                .max(highest_type_id(&v.value_type)),
+        }
+    }
+
+    fields
+        .iter()
+        .map(|v| v.id.max(highest_type_id(&v.field_type)))
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use opendal::{
+        layers::LoggingLayer,
+        services::{Fs, Memory},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_strip_location() {
+        let cases = [
+            (
+                "s3://bucket/tbl",
+                "s3://bucket/tbl/data/a.parquet",
+                Some("/data/a.parquet"),
+            ),
+            (
+                "s3://bucket/tbl/",
+                "s3://bucket/tbl/data/a.parquet",
+                Some("/data/a.parquet"),
+            ),
+            (
+                "s3://bucket/tbl",
+                "s3://bucket/tbl//data/a.parquet",
+                Some("/data/a.parquet"),
+            ),
+            (
+                "s3://bucket/tbl/",
+                "s3://bucket/tbl//data/a.parquet",
+                Some("/data/a.parquet"),
+            ),
+            (
+                "s3://bucket/tbl//",
+                "s3://bucket/tbl/data/a.parquet",
+                Some("/data/a.parquet"),
+            ),
+            ("s3://bucket/tbl", "s3://bucket/tbl2/data/a.parquet", None),
+            ("s3://bucket/tbl/", "s3://other/tbl/data/a.parquet", None),
+            // Paths written by other engines are normalized.
+            (
+                "s3://bucket/tbl",
+                "s3a://bucket/tbl/data/a.parquet",
+                Some("/data/a.parquet"),
+            ),
+            (
+                "s3://bucket/my tbl",
+                "S3N://bucket/my%20tbl/data/a%20b.parquet",
+                Some("/data/a%20b.parquet"),
+            ),
+            // Paths matching as is are not decoded.
+            (
+                "s3://bucket/tbl",
+                "s3://bucket/tbl/data/a%20b.parquet",
+                Some("/data/a%20b.parquet"),
+            ),
+        ];
+        for (location, path, expected) in cases {
+            assert_eq!(
+                strip_location(location, path).as_deref(),
+                expected,
+                "{location} {path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path("s3a://bucket/tbl/a.parquet"),
+            "s3://bucket/tbl/a.parquet"
+        );
+        assert_eq!(normalize_path("HDFS://nn/tbl"), "hdfs://nn/tbl");
+        assert_eq!(
+            normalize_path("s3://bucket/region=a%20b/%E4%B8%AD.parquet"),
+            "s3://bucket/region=a b/中.parquet"
+        );
+        // Invalid escapes are kept.
+        assert_eq!(normalize_path("/data/100%.parquet"), "/data/100%.parquet");
+        assert_eq!(normalize_path("/data/%zz%2"), "/data/%zz%2");
+        // Decoded bytes must be valid UTF-8.
+        assert_eq!(normalize_path("/data/%FF.parquet"), "/data/%FF.parquet");
+    }
+
+    #[tokio::test]
+    async fn test_table_version_hint() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+
+        let op = Operator::new(builder)?
+            .layer(LoggingLayer::default())
+            .finish();
+
+        let table = Table::new(op);
+
+        let version_hint = table.read_version_hint().await?;
+
+        assert_eq!(version_hint, "metadata/v2.metadata.json");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_read_table_metadata() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+
+        let op = Operator::new(builder)?
+            .layer(LoggingLayer::default())
+            .finish();
+
+        let table = Table::new(op);
+
+        let table_v1 = table
+            .read_table_metadata("metadata/v1.metadata.json")
+            .await?;
+
+        assert_eq!(table_v1.format_version, types::TableFormatVersion::V1);
+        assert_eq!(table_v1.last_updated_ms, 1686911664577);
+
+        let table_v2 = table
+            .read_table_metadata("metadata/v2.metadata.json")
+            .await?;
+        assert_eq!(table_v2.format_version, types::TableFormatVersion::V1);
+        assert_eq!(table_v2.last_updated_ms, 1686911671713);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_current_metadata_json() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+        let op = Operator::new(builder)?.finish();
+
+        let mut table = Table::new(op.clone());
+        table.load().await?;
+        assert!(table.current_metadata_json().is_none());
+
+        let mut table = Table::new(op).with_metadata_json(true);
+        table.load().await?;
+        let json = table.current_metadata_json().expect("json must be kept");
+        assert_eq!(json["format-version"], 1);
+        assert_eq!(json["last-updated-ms"], 1686911671713i64);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_current_data_files_with_evolved_spec() -> Result<()> {
+        let path = format!(
+            "{}/testdata/spec_evolution_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+
+        let op = Operator::new(builder)?
+            .layer(LoggingLayer::default())
+            .finish();
+
+        let mut table = Table::new(op);
+        table.load().await?;
+
+        let table_metadata = table.current_table_metadata()?;
+        assert_eq!(table_metadata.default_spec_id, 1);
+        assert_eq!(table_metadata.partition_specs.len(), 2);
+
+        // Data files are written under different partition specs.
+        let mut data_files = table.current_data_files().await?;
+        data_files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        assert_eq!(data_files.len(), 2);
+        assert_eq!(
+            data_files[0].partition,
+            vec![Some(types::Datum::String("us".to_string()))]
+        );
+        assert_eq!(
+            data_files[1].partition,
+            vec![Some(types::Datum::Date(19524))]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_with_avro_logical_type_partitions() -> Result<()> {
+        let path = format!(
+            "{}/testdata/timestamp_partition_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+
+        let op = Operator::new(builder)?
+            .layer(LoggingLayer::default())
+            .finish();
+
+        let mut table = Table::new(op);
+        table.load().await?;
+
+        // Partitions are written with java style avro logical types.
+        let mut data_files = table.current_data_files().await?;
+        data_files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        assert_eq!(data_files.len(), 2);
+        assert_eq!(
+            data_files[0].partition,
+            vec![
+                Some(types::Datum::Timestamp(1686825000000000)),
+                Some(types::Datum::Date(19523)),
+                Some(types::Datum::Decimal(1234)),
+            ]
+        );
+        assert_eq!(
+            data_files[1].partition,
+            vec![
+                Some(types::Datum::Timestamp(1686902400000000)),
+                Some(types::Datum::Date(19524)),
+                Some(types::Datum::Decimal(-500)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "zip")]
+    #[tokio::test]
+    async fn test_table_open_zip() -> Result<()> {
+        use std::io::Write;
+
+        let root = env::current_dir()?.join("testdata");
+
+        // Package files of simple_table in a nested directory.
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let mut dirs = vec![root.join("simple_table")];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let name = path.strip_prefix(&root)?.to_string_lossy().to_string();
+                writer.start_file(name, zip::write::FileOptions::default())?;
+                writer.write_all(&std::fs::read(&path)?)?;
+            }
+        }
+        let bs = writer.finish()?.into_inner();
+
+        let table = Table::open_zip(&bs).await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v2.metadata.json")
+        );
+        assert_eq!(table.current_data_files().await?.len(), 3);
+
+        assert!(Table::open_zip(b"not a zip").await.is_err());
+
+        Ok(())
+    }
+
+    /// Test against a local MinIO, which is skipped unless
+    /// `ICELAKE_TEST_MINIO_ENDPOINT` is set, like `http://127.0.0.1:9000`.
+    ///
+    /// The bucket `ICELAKE_TEST_MINIO_BUCKET` (`icelake` by default) must
+    /// exist.
+    #[tokio::test]
+    async fn test_table_open_with_options_minio() -> Result<()> {
+        let Ok(endpoint) = env::var("ICELAKE_TEST_MINIO_ENDPOINT") else {
+            return Ok(());
+        };
+        let bucket = env::var("ICELAKE_TEST_MINIO_BUCKET").unwrap_or("icelake".to_string());
+        let options = io::OpenOptions::new()
+            .endpoint(&endpoint)
+            .region("us-east-1")
+            .access_key_id(
+                &env::var("ICELAKE_TEST_MINIO_ACCESS_KEY").unwrap_or("minioadmin".to_string()),
+            )
+            .secret_access_key(
+                &env::var("ICELAKE_TEST_MINIO_SECRET_KEY").unwrap_or("minioadmin".to_string()),
+            );
+
+        let location = format!("s3://{bucket}/{}", uuid::Uuid::new_v4());
+        let op = io::build_operator_with_options(&location, &options)?;
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![types::Field {
+                id: 1,
+                name: "id".to_string(),
+                required: true,
+                field_type: types::Any::Primitive(types::Primitive::Long),
+                comment: None,
+            }],
+        };
+        let partition_spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+        Table::create(op, &location, schema, partition_spec, HashMap::new()).await?;
+
+        let table = Table::open_with_options(&location, &options).await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v1.metadata.json")
+        );
+        assert_eq!(table.current_table_metadata()?.location, location);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+
+        let op = Operator::new(builder)?
+            .layer(LoggingLayer::default())
+            .finish();
+
+        let mut table = Table::new(op);
+        assert_eq!(table.try_rel_path("/opt/a.parquet"), None);
+        assert!(table.format_version().is_err());
+        table.load().await?;
+        assert_eq!(table.format_version()?, types::TableFormatVersion::V1);
+        assert_eq!(table.estimated_row_count().await?, 3);
+
+        let table_metadata = table.current_table_metadata()?;
+        assert_eq!(table_metadata.format_version, types::TableFormatVersion::V1);
+        assert_eq!(table_metadata.last_updated_ms, 1686911671713);
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v2.metadata.json")
+        );
+
+        let path = format!("{}/data/a.parquet", table_metadata.location);
+        assert_eq!(
+            table.try_rel_path(&path).as_deref(),
+            Some("/data/a.parquet")
+        );
+        assert_eq!(table.rel_path(&path)?, "/data/a.parquet");
+        assert_eq!(table.try_rel_path("s3://other/data/a.parquet"), None);
+        assert!(table.rel_path("s3://other/data/a.parquet").is_err());
+
+        let mut paths = table.current_data_file_paths().await?;
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "/data/00000-0-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet",
+                "/data/00001-1-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet",
+                "/data/00002-2-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet",
+            ]
+        );
+        for path in &paths {
+            assert!(table.operator().is_exist(path).await?);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_open_in() -> Result<()> {
+        let path = format!(
+            "{}/testdata",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+        let op = Operator::new(builder)?.finish();
+
+        let table = Table::open_in(op.clone(), "simple_table").await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v2.metadata.json")
+        );
+        assert_eq!(table.current_data_files().await?.len(), 3);
+
+        // Tables of the same operator are opened by their own prefixes.
+        let table = Table::open_in(op, "/spec_evolution_table/").await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v4.metadata.json")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_root_path() -> Result<()> {
+        let root = Operator::new(Memory::default())?.finish();
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![types::Field {
+                id: 1,
+                name: "id".to_string(),
+                required: true,
+                field_type: types::Any::Primitive(types::Primitive::Long),
+                comment: None,
+            }],
+        };
+        Table::create(
+            root.clone().layer(io::PrefixLayer::new("db/tbl")),
+            "memory:///db/tbl",
+            schema,
+            types::PartitionSpec {
+                spec_id: 0,
+                fields: vec![],
+            },
+            HashMap::new(),
+        )
+        .await?;
+
+        let table = Table::open_in(root.clone(), "/db/tbl").await?;
+        assert_eq!(table.table_prefix(), "db/tbl/");
+        let metadata_path = table.current_metadata_path().unwrap();
+        assert!(table.operator().is_exist(metadata_path).await?);
+        assert!(
+            table
+                .root_operator()
+                .is_exist(&format!("db/tbl/{metadata_path}"))
+                .await?
+        );
+
+        assert_eq!(
+            table.rel_path("memory:///db/tbl/data/a.parquet")?,
+            "/data/a.parquet"
+        );
+        assert_eq!(
+            table.root_path("memory:///db/tbl/data/a.parquet")?,
+            "/db/tbl/data/a.parquet"
+        );
+        // Files outside table location are resolved by the operator root.
+        assert_eq!(
+            table.root_path("memory:///db/shared/b.parquet")?,
+            "/db/shared/b.parquet"
+        );
+        assert!(table
+            .root_path("s3://bucket/db/tbl/data/a.parquet")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_many() -> Result<()> {
+        let path = format!(
+            "{}/testdata",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+        let op = Operator::new(builder)?.finish();
+
+        let prefixes = vec![
+            "simple_table".to_string(),
+            "missing_table".to_string(),
+            "spec_evolution_table".to_string(),
+        ];
+        let mut results: Vec<_> = Table::load_many(op, prefixes, 2).collect().await;
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0, "missing_table");
+        assert!(results[0].1.is_err());
+        assert_eq!(results[1].0, "simple_table");
+        assert_eq!(
+            results[1].1.as_ref().unwrap().current_metadata_version(),
+            Some(2)
+        );
+        assert_eq!(results[2].0, "spec_evolution_table");
+        assert_eq!(
+            results[2].1.as_ref().unwrap().current_metadata_version(),
+            Some(4)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_without_version_hint() -> Result<()> {
+        let path = format!(
+            "{}/testdata/no_hint_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+
+        let op = Operator::new(builder)?
+            .layer(LoggingLayer::default())
+            .finish();
+
+        let mut table = Table::new(op);
+        table.load().await?;
+
+        let table_metadata = table.current_table_metadata()?;
+        assert_eq!(table_metadata.format_version, types::TableFormatVersion::V1);
+        assert_eq!(table_metadata.last_updated_ms, 1672981042425);
+        assert_eq!(
+            table_metadata.location,
+            "s3://testbucket/iceberg_data/iceberg_ctl/iceberg_db/iceberg_tbl"
+        );
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/00005-032145b7-6a0c-4a53-bc3d-b7b571ccab3b.metadata.json")
+        );
+        assert_eq!(
+            table.load_report(),
+            Some(&LoadReport {
+                metadata_path: "metadata/00005-032145b7-6a0c-4a53-bc3d-b7b571ccab3b.metadata.json"
+                    .to_string(),
+                version_hint_missing: true,
+                stale_version_hint: None,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_all_versions() -> Result<()> {
+        let path = format!(
+            "{}/testdata/spec_evolution_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let mut builder = Fs::default();
+        builder.root(&path);
+
+        let op = Operator::new(builder)?
+            .layer(LoggingLayer::default())
+            .finish();
+
+        let mut table = Table::new(op);
+        table.load_all_versions().await?;
+
+        assert_eq!(table.metadata_versions(), vec![1, 2, 3, 4]);
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v4.metadata.json")
+        );
+        assert_eq!(table.current_metadata_version(), Some(4));
+
+        // Partition spec is evolved in a later version.
+        let first = table.table_metadata(1).unwrap();
+        assert_eq!(first.partition_specs.len(), 1);
+        assert_eq!(first.current_snapshot_id, None);
+        let last = table.table_metadata(4).unwrap();
+        assert_eq!(last.partition_specs.len(), 2);
+        assert_eq!(table.table_metadata(0), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_versions_with_same_timestamp() -> Result<()> {
+        let testdata = env::current_dir()?.join("testdata/simple_table/metadata");
+        let v1 = std::fs::read(testdata.join("v1.metadata.json"))?;
+        let mut v2 =
+            types::parse_table_metadata(&std::fs::read(testdata.join("v2.metadata.json"))?)?;
+        v2.last_updated_ms = types::parse_table_metadata(&v1)?.last_updated_ms;
+
+        let op = Operator::new(Memory::default())?.finish();
+        op.write("metadata/v1.metadata.json", v1).await?;
+        op.write(
+            "metadata/v2.metadata.json",
+            types::serialize_table_metadata(&v2)?,
+        )
+        .await?;
+
+        let mut table = Table::new(op);
+        table.load_all_versions().await?;
+
+        // Versions sharing the same timestamp don't collide.
+        assert_eq!(table.metadata_versions(), vec![1, 2]);
+        assert_eq!(table.current_metadata_version(), Some(2));
+        assert_eq!(table.current_table_metadata()?, &v2);
+        assert_ne!(table.table_metadata(1), Some(&v2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_with_metadata_dirs() -> Result<()> {
+        let testdata = env::current_dir()?.join("testdata/simple_table/metadata");
+        let v1 = std::fs::read(testdata.join("v1.metadata.json"))?;
+        let v2 = std::fs::read(testdata.join("v2.metadata.json"))?;
+
+        let op = Operator::new(Memory::default())?.finish();
+        op.write("metadata/v1.metadata.json", v1).await?;
+        op.write("catalog/v2.metadata.json", v2.clone()).await?;
+
+        // Only `metadata` is searched by default.
+        let mut table = Table::new(op.clone());
+        table.load().await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v1.metadata.json")
+        );
+        assert_eq!(table.current_metadata_version(), Some(1));
+
+        // The highest version across directories is picked.
+        let mut table = Table::new(op.clone()).with_metadata_dirs(&["metadata", "/catalog/"]);
+        table.load().await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("catalog/v2.metadata.json")
+        );
+        assert_eq!(table.current_metadata_version(), Some(2));
+
+        // The earlier directory wins for the same version.
+        op.write("metadata/v2.metadata.json", v2).await?;
+        let mut table = Table::new(op.clone()).with_metadata_dirs(&["metadata", "catalog"]);
+        table.load().await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v2.metadata.json")
+        );
+
+        // Missing directories are skipped.
+        let mut table = Table::new(op).with_metadata_dirs(&["missing", "catalog"]);
+        table.load().await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("catalog/v2.metadata.json")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_with_metadata_file_prefix() -> Result<()> {
+        let testdata = env::current_dir()?.join("testdata/simple_table/metadata");
+        let v1 = std::fs::read(testdata.join("v1.metadata.json"))?;
+        let v2 = std::fs::read(testdata.join("v2.metadata.json"))?;
+
+        let op = Operator::new(Memory::default())?.finish();
+        op.write("metadata/v1.metadata.json", v1).await?;
+        op.write("metadata/v2.metadata.json", v2).await?;
+        // Unrelated files and directories of other tools.
+        op.write("metadata/backup-v9.metadata.json", "not metadata")
+            .await?;
+        op.write("metadata/v10.metadata.json/v1.metadata.json", "nested")
+            .await?;
+
+        let mut table = Table::new(op.clone());
+        assert!(table.load_all_versions().await.is_err());
+
+        let mut table = Table::new(op).with_metadata_file_prefix("v");
+        table.load_all_versions().await?;
+        assert_eq!(table.metadata_versions(), vec![1, 2]);
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v2.metadata.json")
+        );
+
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct MemoryMetadataIo {
+        files: std::sync::Mutex<BTreeMap<String, Vec<u8>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl MetadataIo for MemoryMetadataIo {
+        async fn read(&self, path: &str) -> crate::Result<Vec<u8>> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| {
+                    crate::Error::new(crate::ErrorKind::Unexpected, "file not found")
+                        .with_context("path", path)
+                })
+        }
+
+        async fn list(&self, prefix: &str) -> crate::Result<Vec<String>> {
+            Ok(self
+                .files
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|v| v.strip_prefix(prefix).is_some_and(|v| !v.contains('/')))
+                .cloned()
+                .collect())
+        }
+
+        async fn is_exist(&self, path: &str) -> crate::Result<bool> {
+            Ok(self.files.lock().unwrap().contains_key(path))
+        }
+
+        async fn write(&self, path: &str, bs: Vec<u8>) -> crate::Result<()> {
+            self.files.lock().unwrap().insert(path.to_string(), bs);
+            Ok(())
+        }
+
+        async fn delete(&self, path: &str) -> crate::Result<()> {
+            self.files.lock().unwrap().remove(path);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_table_with_metadata_io() -> Result<()> {
+        let testdata = env::current_dir()?.join("testdata/simple_table/metadata");
+        let metadata_io = Arc::new(MemoryMetadataIo::default());
+        for name in ["v1.metadata.json", "v2.metadata.json"] {
+            metadata_io
+                .write(
+                    &format!("metadata/{name}"),
+                    std::fs::read(testdata.join(name))?,
+                )
+                .await?;
+        }
+
+        // Metadata is never touched via the operator.
+        let op = Operator::new(Memory::default())?.finish();
+        let mut table = Table::new(op.clone()).with_metadata_io(metadata_io.clone());
+        table.load().await?;
+        assert_eq!(table.current_metadata_version(), Some(2));
+
+        table
+            .commit(table.current_table_metadata()?.clone())
+            .await?;
+        assert_eq!(table.current_metadata_version(), Some(3));
+        assert_eq!(
+            metadata_io.files.lock().unwrap().keys().collect::<Vec<_>>(),
+            vec![
+                "metadata/v1.metadata.json",
+                "metadata/v2.metadata.json",
+                "metadata/v3.metadata.json",
+                "metadata/version-hint.text",
+            ]
+        );
+        assert!(!op.is_exist("metadata/v3.metadata.json").await?);
+        assert!(!op.is_exist("metadata/version-hint.text").await?);
+
+        // Trimmed metadata files are deleted via metadata io too.
+        let created = crate::operation::test_utils::create_table("2").await?;
+        let metadata_io = Arc::new(MemoryMetadataIo::default());
+        for path in ["metadata/v1.metadata.json", "metadata/version-hint.text"] {
+            metadata_io
+                .write(path, created.operator().read(path).await?)
+                .await?;
+        }
+        let mut table = Table::new(op.clone()).with_metadata_io(metadata_io.clone());
+        table.load().await?;
+        for _ in 0..2 {
+            let mut metadata = table.current_table_metadata()?.clone();
+            metadata
+                .properties
+                .get_or_insert_with(HashMap::new)
+                .extend([
+                    (
+                        "write.metadata.previous-versions-max".to_string(),
+                        "1".to_string(),
+                    ),
+                    (
+                        "write.metadata.delete-after-commit.enabled".to_string(),
+                        "true".to_string(),
+                    ),
+                ]);
+            table.commit(metadata).await?;
+        }
+        assert_eq!(
+            metadata_io.files.lock().unwrap().keys().collect::<Vec<_>>(),
+            vec![
+                "metadata/v2.metadata.json",
+                "metadata/v3.metadata.json",
+                "metadata/version-hint.text",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_with_version_hint_file() -> Result<()> {
+        let testdata = env::current_dir()?.join("testdata/simple_table/metadata");
+        let v1 = std::fs::read(testdata.join("v1.metadata.json"))?;
+        let v2 = std::fs::read(testdata.join("v2.metadata.json"))?;
+
+        let op = Operator::new(Memory::default())?.finish();
+        op.write("metadata/v1.metadata.json", v1).await?;
+        op.write("metadata/v2.metadata.json", v2).await?;
+
+        // Pointer of version number.
+        op.write("metadata/current", "1\n").await?;
+        let mut table = Table::new(op.clone()).with_version_hint_file("current");
+        table.load().await?;
+        assert_eq!(table.current_metadata_version(), Some(1));
+
+        // Pointer of relative metadata path.
+        op.write("metadata/current", "metadata/v1.metadata.json")
+            .await?;
+        let mut table = Table::new(op.clone()).with_version_hint_file("current");
+        table.load().await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v1.metadata.json")
+        );
+
+        // Pointer of absolute metadata location.
+        op.write(
+            "metadata/current",
+            "s3://bucket/db/tbl/metadata/v1.metadata.json",
+        )
+        .await?;
+        let mut table = Table::new(op.clone()).with_version_hint_file("current");
+        table.load().await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v1.metadata.json")
+        );
+        assert!(table
+            .reachable_files()
+            .await?
+            .contains(&"metadata/current".to_string()));
+
+        // Invalid pointer is rejected.
+        op.write("metadata/current", "not a pointer").await?;
+        let mut table = Table::new(op.clone()).with_version_hint_file("current");
+        assert!(table.load().await.is_err());
+
+        // The default hint file is absent, so the latest one is loaded.
+        let mut table = Table::new(op);
+        table.load().await?;
+        assert_eq!(table.current_metadata_version(), Some(2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_manifest_summary() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        assert_eq!(table.manifest_summary().await?, ManifestSummary::default());
+
+        for name in ["a", "b"] {
+            table
+                .new_append()
+                .add_data_file(data_file(name, Some("us"), 3))
+                .commit()
+                .await?;
+        }
+
+        let summary = table.manifest_summary().await?;
+        assert_eq!(summary.manifest_count, 2);
+        assert_eq!(summary.data_manifest_count, 2);
+        assert_eq!(summary.delete_manifest_count, 0);
+        assert_eq!(summary.manifest_count_by_spec, HashMap::from([(0, 2)]));
+        assert!(summary.total_manifest_bytes > 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_data_files_for_snapshots() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+        // Fork two heads from the same history.
+        let staged = table
+            .new_append()
+            .add_data_file(data_file("b", Some("us"), 2))
+            .stage("w1")
+            .await?;
+        table
+            .new_append()
+            .add_data_file(data_file("c", Some("eu"), 3))
+            .commit()
+            .await?;
+        let current = table.current_table_metadata()?.current_snapshot_id.unwrap();
+
+        let mut data_files: Vec<_> = table
+            .data_files_for_snapshots(&[staged, current])
+            .await?
+            .into_iter()
+            .map(|(id, v)| (id, v.file_path))
+            .collect();
+        data_files.sort();
+        let mut expected = vec![
+            (staged, "memory:///tbl/data/a.parquet".to_string()),
+            (staged, "memory:///tbl/data/b.parquet".to_string()),
+            (current, "memory:///tbl/data/a.parquet".to_string()),
+            (current, "memory:///tbl/data/c.parquet".to_string()),
+        ];
+        expected.sort();
+        assert_eq!(data_files, expected);
+
+        assert!(table.data_files_for_snapshots(&[-2]).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_load_policy() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        for name in ["a", "b"] {
+            table
+                .new_append()
+                .add_data_file(data_file(name, Some("us"), 1))
+                .commit()
+                .await?;
+        }
+        let op = table.operator().clone();
+        let load = |policy: Option<LoadPolicy>| {
+            let op = op.clone();
+            async move {
+                let mut table = Table::new(op);
+                if let Some(policy) = policy {
+                    table = table.with_load_policy(policy);
+                }
+                table.load().await.map(|_| table)
+            }
+        };
+
+        // Stale hint is trusted by default.
+        op.write("metadata/version-hint.text", "1").await?;
+        let table = load(None).await?;
+        assert_eq!(table.current_metadata_version(), Some(1));
+        assert_eq!(
+            table.load_report(),
+            Some(&LoadReport {
+                metadata_path: "metadata/v1.metadata.json".to_string(),
+                ..Default::default()
+            })
+        );
+        let err = load(Some(LoadPolicy::Strict)).await.err().unwrap();
+        assert!(err.to_string().contains("stale"), "{err}");
+        let table = load(Some(LoadPolicy::Lenient)).await?;
+        assert_eq!(table.current_metadata_version(), Some(3));
+        assert_eq!(
+            table.load_report().unwrap().stale_version_hint.as_deref(),
+            Some("metadata/v1.metadata.json")
+        );
+        assert_eq!(op.read("metadata/version-hint.text").await?, b"1");
+        let table = load(Some(LoadPolicy::RepairHint)).await?;
+        assert_eq!(table.current_metadata_version(), Some(3));
+        assert_eq!(op.read("metadata/version-hint.text").await?, b"3");
+
+        // Missing hint.
+        op.delete("metadata/version-hint.text").await?;
+        assert!(load(Some(LoadPolicy::Strict)).await.is_err());
+        let table = load(None).await?;
+        assert_eq!(table.current_metadata_version(), Some(3));
+        assert!(table.load_report().unwrap().version_hint_missing);
+        load(Some(LoadPolicy::RepairHint)).await?;
+        assert_eq!(op.read("metadata/version-hint.text").await?, b"3");
+
+        // Ambiguous versions.
+        op.write(
+            "metadata/00003-copy.metadata.json",
+            op.read("metadata/v3.metadata.json").await?,
+        )
+        .await?;
+        let err = load(Some(LoadPolicy::Strict)).await.err().unwrap();
+        assert!(err.to_string().contains("ambiguous"), "{err}");
+        let table = load(Some(LoadPolicy::Lenient)).await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v3.metadata.json")
+        );
+
+        // Missing manifest.
+        let (_, report) = table.scan().plan_files_with_report().await?;
+        let missing = report.manifest_paths[0].clone();
+        op.delete(&table.rel_path(&missing)?).await?;
+        assert!(load(None).await?.current_data_files().await.is_err());
+        assert_eq!(table.current_data_files().await?.len(), 1);
+        let (data_files, report) = table.scan().plan_files_with_report().await?;
+        assert_eq!(data_files.len(), 1);
+        assert_eq!(report.skipped_manifest_paths, vec![missing]);
+
+        // Missing delete manifests are never skipped.
+        let mut table = create_table("2").await?;
+        let mut deletes = data_file("deletes", Some("us"), 1);
+        deletes.content = types::DataContentType::PostionDeletes;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .add_data_file(deletes)
+            .commit()
+            .await?;
+        let table = table.with_load_policy(LoadPolicy::Lenient);
+        let snapshot = table.ancestors()?.next().unwrap()?;
+        let manifests = table.read_manifest_list(snapshot).await?;
+        let delete_manifest = manifests
+            .iter()
+            .find(|v| v.content == types::ManifestContentType::Deletes)
+            .unwrap();
+        table
+            .operator()
+            .delete(&table.rel_path(&delete_manifest.manifest_path)?)
+            .await?;
+        assert!(table.scan().plan_files().await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_snapshot_log() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        assert!(table.snapshot_log().is_empty());
+        for name in ["a", "b"] {
+            table
+                .new_append()
+                .add_data_file(data_file(name, Some("us"), 1))
+                .commit()
+                .await?;
+        }
+
+        let snapshot_ids: Vec<_> = table
+            .ancestors()?
+            .map(|v| v.map(|v| v.snapshot_id))
+            .collect::<Result<_>>()?;
+        let log = table.snapshot_log();
+        assert_eq!(
+            log.iter().map(|v| v.snapshot_id).collect::<Vec<_>>(),
+            snapshot_ids.into_iter().rev().collect::<Vec<_>>()
+        );
+        assert!(log[0].timestamp_ms <= log[1].timestamp_ms);
+
+        // The log is kept after reloading.
+        let mut reloaded = Table::new(table.operator().clone());
+        reloaded.load().await?;
+        assert_eq!(reloaded.snapshot_log(), log);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_check() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        assert!(table.check().await?.is_empty());
+
+        for name in ["a", "b"] {
+            let path = format!("data/{name}.parquet");
+            table.operator().write(&path, "").await?;
+            table
+                .new_append()
+                .add_data_file(data_file(name, Some("us"), 1))
+                .commit()
+                .await?;
+        }
+        assert!(table.check().await?.is_empty());
+
+        table.operator().delete("data/a.parquet").await?;
+        assert_eq!(
+            table.check().await?,
+            vec![CheckIssue::MissingFile(
+                "memory:///tbl/data/a.parquet".to_string()
+            )]
+        );
+        table.operator().write("data/a.parquet", "").await?;
+
+        // Break the metadata in every possible way.
+        let mut metadata = table.current_table_metadata()?.clone();
+        let snapshots = metadata.snapshots.as_mut().unwrap();
+        let (first, second) = (snapshots[0].snapshot_id, snapshots[1].snapshot_id);
+        snapshots[0].parent_snapshot_id = Some(second);
+        snapshots[1].schema_id = Some(100);
+        snapshots[1].manifest_list = "memory:///tbl/metadata/not-exist.avro".to_string();
+        metadata.current_snapshot_id = Some(42);
+        let table = Table::from_metadata_bytes(
+            table.operator().clone(),
+            &types::serialize_table_metadata(&metadata)?,
+        )?;
+        let issues = table.check().await?;
+        assert_eq!(
+            issues,
+            vec![
+                CheckIssue::MissingCurrentSnapshot(42),
+                CheckIssue::MissingSchema(100),
+                CheckIssue::SnapshotCycle(vec![first, second]),
+                CheckIssue::MissingFile("memory:///tbl/metadata/not-exist.avro".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_read_raw() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+
+        let meta = table.current_table_metadata()?;
+        let manifest_list = &meta.snapshots.as_ref().unwrap()[0].manifest_list;
+        let bs = table.read_raw(manifest_list).await?;
+        assert_eq!(types::parse_manifest_list(&bs)?.len(), 1);
+        assert_eq!(table.read_raw(&table.rel_path(manifest_list)?).await?, bs);
+
+        assert!(table.read_raw("metadata/not-exist.avro").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_current_manifest_list_path() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        assert!(table.current_manifest_list_path().await.is_err());
+
+        for name in ["a", "b"] {
+            table
+                .new_append()
+                .add_data_file(data_file(name, Some("us"), 1))
+                .commit()
+                .await?;
+        }
+
+        let meta = table.current_table_metadata()?;
+        let snapshot_id = meta.current_snapshot_id.unwrap();
+        let current = meta
+            .snapshots
+            .iter()
+            .flatten()
+            .find(|v| v.snapshot_id == snapshot_id)
+            .unwrap();
+
+        // The table doesn't need to be loaded.
+        let unloaded = Table::new(table.operator().clone());
+        assert_eq!(
+            unloaded.current_manifest_list_path().await?,
+            current.manifest_list
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_commit_keep_unknown_fields() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+
+        // Add an unknown field like written by newer engines.
+        let path = table.current_metadata_path().unwrap().to_string();
+        let mut value: serde_json::Value = serde_json::from_slice(&table.op.read(&path).await?)?;
+        value["future-field"] = serde_json::json!({"nested": [1, 2]});
+        table.op.write(&path, serde_json::to_vec(&value)?).await?;
+        table.load().await?;
+
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+
+        let mut reloaded = Table::new(table.op.clone());
+        reloaded.load().await?;
+        assert_ne!(reloaded.current_metadata_path(), Some(path.as_str()));
+        let metadata = reloaded.current_table_metadata()?;
+        assert_eq!(
+            metadata
+                .unknown_fields
+                .get("future-field")
+                .map(|v| v.as_str()),
+            Some(r#"{"nested":[1,2]}"#)
+        );
+        assert!(metadata.current_snapshot_id.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_properties_changed_between() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        let mut metadata = table.current_table_metadata()?.clone();
+        metadata.properties = Some(HashMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ]));
+        table.commit(metadata.clone()).await?;
+        metadata.properties = Some(HashMap::from([
+            ("b".to_string(), "3".to_string()),
+            ("c".to_string(), "4".to_string()),
+        ]));
+        table.commit(metadata).await?;
+        assert_eq!(table.metadata_versions(), vec![1, 2, 3]);
+
+        assert_eq!(
+            table.properties_changed_between(2, 3)?,
+            HashMap::from([
+                ("a".to_string(), (Some("1".to_string()), None)),
+                (
+                    "b".to_string(),
+                    (Some("2".to_string()), Some("3".to_string()))
+                ),
+                ("c".to_string(), (None, Some("4".to_string()))),
+            ])
+        );
+        assert!(table.properties_changed_between(3, 3)?.is_empty());
+        assert!(table.properties_changed_between(1, 4).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_delete_ratio_by_partition() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        assert!(table.delete_ratio_by_partition().await?.is_empty());
+
+        let deletes = |name, region, count| {
+            let mut v = data_file(name, Some(region), count);
+            v.content = types::DataContentType::PostionDeletes;
+            v
+        };
+        table
+            .new_append()
+            .add_data_files([
+                data_file("a", Some("us"), 6),
+                data_file("b", Some("us"), 4),
+                data_file("c", Some("eu"), 5),
+                deletes("d", "us", 2),
+                deletes("e", "gb", 1),
+            ])
+            .commit()
+            .await?;
+
+        let partition = |region: &str| types::PartitionValues {
+            spec_id: 0,
+            values: vec![Some(types::Datum::String(region.to_string()))],
+        };
+        let ratios = table.delete_ratio_by_partition().await?;
+        assert_eq!(ratios.len(), 3);
+        assert_eq!(ratios[&partition("us")], 0.2);
+        assert_eq!(ratios[&partition("eu")], 0.0);
+        assert!(ratios[&partition("gb")].is_infinite());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_read_at_tag() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+        let mut metadata = table.current_table_metadata()?.clone();
+        let tagged = metadata.current_snapshot_id.unwrap();
+        metadata.refs.get_or_insert_with(HashMap::new).insert(
+            "release-2024-q1".to_string(),
+            types::SnapshotReference {
+                snapshot_id: tagged,
+                typ: types::SnapshotReferenceType::Tag,
+                min_snapshots_to_keep: None,
+                max_snapshot_age_ms: None,
+                max_ref_age_ms: None,
+            },
+        );
+        table.commit(metadata).await?;
+        table
+            .new_append()
+            .add_data_file(data_file("b", Some("eu"), 2))
+            .commit()
+            .await?;
+
+        let paths = |files: Vec<types::DataFile>| -> Vec<String> {
+            files.into_iter().map(|v| v.file_path).collect()
+        };
+        assert_eq!(
+            paths(table.data_files_at_tag("release-2024-q1").await?),
+            vec!["memory:///tbl/data/a.parquet"]
+        );
+        assert_eq!(table.current_data_files().await?.len(), 2);
+        assert!(table.data_files_at_tag("missing").await.is_err());
+        // Branches are not tags.
+        assert!(table.data_files_at_tag("main").await.is_err());
+
+        table.load_tag("release-2024-q1").await?;
+        assert_eq!(
+            table.current_table_metadata()?.current_snapshot_id,
+            Some(tagged)
+        );
+        assert_eq!(
+            paths(table.current_data_files().await?),
+            vec!["memory:///tbl/data/a.parquet"]
+        );
+        // The pinned table is read-only until loaded again.
+        assert!(table
+            .new_append()
+            .add_data_file(data_file("c", None, 1))
+            .commit()
+            .await
+            .is_err());
+        table.load().await?;
+        assert_eq!(table.current_data_files().await?.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_create_as() -> Result<()> {
+        use crate::operation::test_utils::*;
+
+        let template = create_table("2").await?;
+        let metadata = template.current_table_metadata()?;
+        let schema = metadata.current_schema()?.clone();
+        let spec = metadata.partition_specs[0].clone();
 
-            table_metadata: HashMap::new(),
+        let op = Operator::new(Memory::default())?.finish();
+        let table = Table::create_as(
+            op.clone(),
+            "memory:///tbl",
+            schema.clone(),
+            spec.clone(),
+            vec![data_file("a", Some("us"), 1), data_file("b", Some("eu"), 2)],
+        )
+        .await?;
+        assert_eq!(
+            current_data_file_paths(&table).await?,
+            vec![
+                "memory:///tbl/data/a.parquet",
+                "memory:///tbl/data/b.parquet"
+            ]
+        );
 
-            current_version: 0,
-            current_location: None,
-        }
-    }
+        assert_eq!(table.metadata_versions(), vec![1]);
 
-    /// Load metadata and manifest from storage.
-    pub async fn load(&mut self) -> Result<()> {
-        let path = if self.is_version_hint_exist().await? {
-            let version_hint = self.read_version_hint().await?;
-            format!("metadata/v{}.metadata.json", version_hint)
-        } else {
-            let files = self.list_table_metadata_paths().await?;
+        // The first snapshot is written in the only version of metadata.
+        let mut loaded = Table::new(op.clone());
+        loaded.load().await?;
+        assert_eq!(loaded.current_metadata_version(), Some(1));
+        assert_eq!(loaded.estimated_row_count().await?, 3);
+        let metadata = loaded.current_table_metadata()?;
+        assert_eq!(metadata.last_sequence_number, 1);
+        assert_eq!(metadata.metadata_log, Some(vec![]));
+        assert!(!op.is_exist("metadata/v2.metadata.json").await?);
 
-            files
-                .into_iter()
-                .last()
-                .ok_or_else(|| anyhow!("no table metadata found"))?
-        };
+        // Tables can't be created twice.
+        assert!(
+            Table::create_as(op, "memory:///tbl", schema.clone(), spec.clone(), vec![])
+                .await
+                .is_err()
+        );
 
-        let metadata = self.read_table_metadata(&path).await?;
-        // TODO: check if the metadata is out of date.
-        self.current_version = metadata.last_updated_ms;
-        self.current_location = Some(metadata.location.clone());
-        self.table_metadata
-            .insert(metadata.last_updated_ms, metadata);
+        let op = Operator::new(Memory::default())?.finish();
+        let table = Table::create_as(op, "memory:///tbl", schema, spec, vec![]).await?;
+        assert_eq!(table.current_table_metadata()?.current_snapshot_id, None);
 
         Ok(())
     }
 
-    /// Open an iceberg table by uri
-    pub async fn open(uri: &str) -> Result<Table> {
-        // Todo(xudong): inferring storage types by uri
-        let mut builder = Fs::default();
-        builder.root(uri);
+    #[tokio::test]
+    async fn test_table_with_clock() -> Result<()> {
+        use crate::operation::test_utils::*;
 
-        let op = Operator::new(builder)?
-            .layer(LoggingLayer::default())
-            .finish();
+        struct FixedClock(i64);
 
-        let mut table = Table::new(op);
+        impl Clock for FixedClock {
+            fn now_ms(&self) -> i64 {
+                self.0
+            }
+        }
+
+        let created = create_table("2").await?;
+        let metadata = created.current_table_metadata()?;
+        let mut table = Table::create_with_clock(
+            Operator::new(Memory::default())?.finish(),
+            "memory:///tbl",
+            metadata.current_schema()?.clone(),
+            metadata.current_partition_spec()?.clone(),
+            HashMap::new(),
+            Arc::new(FixedClock(1000)),
+        )
+        .await?;
+        assert_eq!(table.current_table_metadata()?.last_updated_ms, 1000);
+
+        table
+            .new_append()
+            .add_data_file(data_file("a", Some("us"), 1))
+            .commit()
+            .await?;
+        let metadata = table.current_table_metadata()?;
+        // `last-updated-ms` is still increasing.
+        assert_eq!(metadata.last_updated_ms, 1001);
+        assert_eq!(metadata.snapshots.as_ref().unwrap()[0].timestamp_ms, 1000);
+        assert_eq!(
+            metadata.snapshot_log.as_ref().unwrap()[0].timestamp_ms,
+            1000
+        );
+
+        // The clock of loaded tables can be replaced too.
+        let mut table = Table::new(table.operator().clone()).with_clock(Arc::new(FixedClock(5000)));
         table.load().await?;
-        Ok(table)
+        table
+            .new_append()
+            .add_data_file(data_file("b", Some("us"), 1))
+            .commit()
+            .await?;
+        assert_eq!(table.current_table_metadata()?.last_updated_ms, 5000);
+
+        Ok(())
     }
 
-    /// Fetch current table metadata.
-    pub fn current_table_metadata(&self) -> Result<&types::TableMetadata> {
-        if self.current_version == 0 {
-            return Err(anyhow!("table metadata not loaded yet"));
-        }
+    #[tokio::test]
+    async fn test_table_estimated_row_count() -> Result<()> {
+        use crate::operation::test_utils::*;
 
-        self.table_metadata
-            .get(&self.current_version)
-            .ok_or_else(|| anyhow!("table metadata not found"))
-    }
+        let mut table = create_table("2").await?;
+        assert_eq!(table.estimated_row_count().await?, 0);
 
-    /// # TODO
-    ///
-    /// we will have better API to play with snapshots and partitions.
-    ///
-    /// Currently, we just return all data files of the current version.
-    pub async fn current_data_files(&self) -> Result<Vec<types::DataFile>> {
-        if self.current_version == 0 {
-            return Err(anyhow!("table metadata not loaded yet"));
-        }
+        let mut deletes = data_file("deletes", Some("us"), 2);
+        deletes.content = types::DataContentType::PostionDeletes;
+        let mut equality_deletes = data_file("eq_deletes", Some("us"), 1);
+        equality_deletes.content = types::DataContentType::EqualityDeletes;
+        table
+            .new_append()
+            .add_data_files([
+                data_file("a", Some("us"), 3),
+                data_file("b", None, 4),
+                deletes,
+                equality_deletes,
+            ])
+            .commit()
+            .await?;
+        assert_eq!(table.estimated_row_count().await?, 5);
 
-        let meta = self
-            .table_metadata
-            .get(&self.current_version)
-            .ok_or_else(|| anyhow!("table metadata not found"))?;
+        Ok(())
+    }
 
-        let current_snapshot_id = meta
-            .current_snapshot_id
-            .ok_or_else(|| anyhow!("current snapshot id is empty"))?;
-        let current_snapshot = meta
-            .snapshots
-            .as_ref()
-            .ok_or_else(|| anyhow!("snapshots is emppty"))?
-            .iter()
-            .find(|v| v.snapshot_id == current_snapshot_id)
-            .ok_or_else(|| anyhow!("snapshot with id {} is not found", current_snapshot_id))?;
+    #[tokio::test]
+    async fn test_table_column_range() -> Result<()> {
+        use crate::operation::test_utils::*;
 
-        let manifest_list_path = self.rel_path(&current_snapshot.manifest_list)?;
-        let manifest_list_content = self.op.read(&manifest_list_path).await?;
-        let manifest_list = types::parse_manifest_list(&manifest_list_content)?;
+        let mut table = create_table("2").await?;
+        assert_eq!(table.column_range("id").await?, None);
+        assert!(table.column_range("missing").await.is_err());
+
+        let with_bounds = |name, lower: i64, upper: i64| {
+            let mut v = data_file(name, Some("us"), 10);
+            v.lower_bounds = Some(HashMap::from([(1, types::Datum::Long(lower).to_bytes())]));
+            v.upper_bounds = Some(HashMap::from([(1, types::Datum::Long(upper).to_bytes())]));
+            v.null_value_counts = Some(HashMap::from([(1, 0)]));
+            v
+        };
+        let mut nulls = data_file("nulls", Some("us"), 5);
+        nulls.null_value_counts = Some(HashMap::from([(1, 5)]));
+        table
+            .new_append()
+            .add_data_files([with_bounds("a", 3, 8), with_bounds("b", -2, 5), nulls])
+            .commit()
+            .await?;
+        assert_eq!(
+            table.column_range("id").await?,
+            Some((types::Datum::Long(-2), types::Datum::Long(8)))
+        );
 
-        let manifest_path = self.rel_path(&manifest_list.manifest_path)?;
-        let manifest_content = self.op.read(&manifest_path).await?;
-        let (_, manifest_files) = types::parse_manifest_file(&manifest_content)?;
+        // Range is unknown if any file lacks bounds.
+        table
+            .new_append()
+            .add_data_file(data_file("c", Some("us"), 1))
+            .commit()
+            .await?;
+        assert_eq!(table.column_range("id").await?, None);
 
-        Ok(manifest_files.into_iter().map(|v| v.data_file).collect())
+        Ok(())
     }
 
-    /// Get the relpath related to the base of table location.
-    pub fn rel_path(&self, path: &str) -> Result<String> {
-        let location = self
-            .current_location
-            .as_ref()
-            .ok_or_else(|| anyhow!("table location is empty, maybe it's not loaded?"))?;
+    #[tokio::test]
+    async fn test_table_ancestors() -> Result<()> {
+        use crate::operation::test_utils::*;
 
-        path.strip_prefix(location)
-            .ok_or_else(|| {
-                anyhow!(
-                    "path {} is not starts with table location {}",
-                    path,
-                    location
-                )
-            })
-            .map(|v| v.to_string())
-    }
+        let mut table = create_table("2").await?;
+        assert_eq!(table.ancestors()?.count(), 0);
 
-    /// Check if version hint file exist.
-    async fn is_version_hint_exist(&self) -> Result<bool> {
-        self.op
-            .is_exist("metadata/version-hint.text")
-            .await
-            .map_err(|e| anyhow!("check if version hint exist failed: {}", e))
-    }
+        for name in ["a", "b", "c"] {
+            table
+                .new_append()
+                .add_data_files([data_file(name, Some("us"), 1)])
+                .commit()
+                .await?;
+        }
+        let meta = table.current_table_metadata()?;
+        let ancestors = table.ancestors()?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(ancestors.len(), 3);
+        assert_eq!(Some(ancestors[0].snapshot_id), meta.current_snapshot_id);
+        assert_eq!(ancestors[2].parent_snapshot_id, None);
+        for v in ancestors.windows(2) {
+            assert_eq!(v[0].parent_snapshot_id, Some(v[1].snapshot_id));
+        }
 
-    /// Read version hint of table.
-    async fn read_version_hint(&self) -> Result<i32> {
-        let content = self.op.read("metadata/version-hint.text").await?;
-        let version_hint = String::from_utf8(content)?;
+        // Parents form a cycle.
+        let mut metadata = meta.clone();
+        let current = metadata.current_snapshot_id;
+        for snapshot in metadata.snapshots.iter_mut().flatten() {
+            if snapshot.parent_snapshot_id.is_none() {
+                snapshot.parent_snapshot_id = current;
+            }
+        }
+        let table = Table::from_metadata_bytes(
+            table.operator().clone(),
+            &types::serialize_table_metadata(&metadata)?,
+        )?;
+        let ancestors: Vec<_> = table.ancestors()?.collect();
+        assert_eq!(ancestors.len(), 4);
+        assert!(ancestors[3].is_err());
 
-        version_hint
-            .parse()
-            .map_err(|e| anyhow!("parse version hint failed: {}", e))
+        Ok(())
     }
 
-    /// Read table metadata of the given version.
-    async fn read_table_metadata(&self, path: &str) -> Result<types::TableMetadata> {
-        let content = self.op.read(path).await?;
+    #[tokio::test]
+    async fn test_table_location_with_trailing_slash() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let mut metadata = types::parse_table_metadata(&std::fs::read(format!(
+            "{path}/metadata/v2.metadata.json"
+        ))?)?;
+        metadata.location.push('/');
 
-        let metadata = types::parse_table_metadata(&content)?;
+        let mut builder = Fs::default();
+        builder.root(&path);
+        let op = Operator::new(builder)?.finish();
 
-        Ok(metadata)
-    }
+        let table = Table::from_metadata_bytes(op, &types::serialize_table_metadata(&metadata)?)?;
+        let paths = table.current_data_file_paths().await?;
+        assert_eq!(paths.len(), 3);
+        assert!(paths.iter().all(|v| v.starts_with("/data/")));
 
-    /// List all paths of table metadata files.
-    ///
-    /// The returned paths are sorted by name.
-    ///
-    /// TODO: we can imporve this by only fetch the latest metadata.
-    async fn list_table_metadata_paths(&self) -> Result<Vec<String>> {
-        let mut lister = self
-            .op
-            .list("metadata/")
-            .await
-            .map_err(|err| anyhow!("list metadata failed: {}", err))?;
+        Ok(())
+    }
 
-        let mut paths = vec![];
+    #[tokio::test]
+    async fn test_table_current_data_file_paths_keep_encoding() -> Result<()> {
+        use crate::operation::test_utils::*;
 
-        while let Some(entry) = lister.next().await {
-            let entry = entry.map_err(|err| anyhow!("list metadata entry failed: {}", err))?;
+        let mut table = create_table("2").await?;
+        table
+            .new_append()
+            .add_data_file(data_file("a%20b", Some("us"), 1))
+            .add_data_file(data_file("a b", Some("us"), 1))
+            .add_data_file(data_file("100%25", Some("us"), 1))
+            .commit()
+            .await?;
 
-            // Only push into paths if the entry is a metadata file.
-            if entry.path().ends_with(".metadata.json") {
-                paths.push(entry.path().to_string());
-            }
-        }
+        let mut paths = table.current_data_file_paths().await?;
+        paths.sort();
+        // `a b` is the same file as `a%20b`, and `100%25` is not decoded.
+        assert_eq!(paths, vec!["/data/100%25.parquet", "/data/a%20b.parquet"]);
 
-        // Make the returned paths sorted by name.
+        // Recorded paths are kept by `current_data_files`, and compared by
+        // their normalized form.
+        let mut paths: Vec<_> = table
+            .current_data_files()
+            .await?
+            .into_iter()
+            .map(|v| v.file_path)
+            .collect();
         paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "memory:///tbl/data/100%25.parquet",
+                "memory:///tbl/data/a b.parquet",
+                "memory:///tbl/data/a%20b.parquet"
+            ]
+        );
+        let normalized = paths
+            .iter()
+            .map(|v| table.normalized_path(v))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            normalized,
+            vec![
+                "/data/100%.parquet",
+                "/data/a b.parquet",
+                "/data/a b.parquet"
+            ]
+        );
+        assert_eq!(
+            table.normalized_path("/data/a%20b.parquet")?,
+            "/data/a b.parquet"
+        );
+        assert!(table.normalized_path("memory:///other/a.parquet").is_err());
 
-        Ok(paths)
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::env;
+    #[cfg(feature = "io_parquet")]
+    #[tokio::test]
+    async fn test_table_read_data_file() -> Result<()> {
+        use futures::TryStreamExt;
+
+        let path = format!(
+            "{}/testdata/simple_table",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let mut builder = Fs::default();
+        builder.root(&path);
+        let op = Operator::new(builder)?.finish();
+        let table = Table::open_in(op, "/").await?;
+
+        for data_file in table.current_data_files().await? {
+            let batches: Vec<_> = table
+                .read_data_file(&data_file)
+                .await?
+                .try_collect()
+                .await?;
+            let rows: usize = batches.iter().map(|v| v.num_rows()).sum();
+            assert_eq!(rows as i64, data_file.record_count);
+        }
 
-    use opendal::{layers::LoggingLayer, services::Fs};
+        let mut data_file = table.current_data_files().await?.remove(0);
+        data_file.file_format = types::DataFileFormat::Avro;
+        assert!(table.read_data_file(&data_file).await.is_err());
 
-    use super::*;
+        Ok(())
+    }
 
     #[tokio::test]
-    async fn test_table_version_hint() -> Result<()> {
+    async fn test_table_from_metadata_bytes() -> Result<()> {
         let path = format!(
             "{}/testdata/simple_table",
             env::current_dir()
                 .expect("current_dir must exist")
                 .to_string_lossy()
         );
+        let bs = std::fs::read(format!("{path}/metadata/v2.metadata.json"))?;
 
         let mut builder = Fs::default();
         builder.root(&path);
+        let op = Operator::new(builder)?.finish();
 
-        let op = Operator::new(builder)?
-            .layer(LoggingLayer::default())
-            .finish();
+        let mut table = Table::from_metadata_bytes(op, &bs)?;
+        assert_eq!(table.current_metadata_path(), None);
+        assert_eq!(table.current_metadata_version(), Some(2));
+        assert_eq!(
+            table.current_table_metadata()?.last_updated_ms,
+            1686911671713
+        );
+        assert_eq!(table.current_data_files().await?.len(), 3);
 
-        let table = Table::new(op);
+        // Table loaded from bytes can't be committed.
+        let metadata = table.current_table_metadata()?.clone();
+        assert!(table.commit(metadata).await.is_err());
 
-        let version_hint = table.read_version_hint().await?;
+        assert!(
+            Table::from_metadata_bytes(Operator::new(Memory::default())?.finish(), b"{}").is_err()
+        );
+
+        Ok(())
+    }
 
-        assert_eq!(version_hint, 2);
+    #[tokio::test]
+    async fn test_table_open_with_metadata_location() -> Result<()> {
+        let location = format!(
+            "file://{}/testdata/simple_table/metadata/v1.metadata.json",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let table = Table::open_with_metadata_location(&location).await?;
+
+        let table_metadata = table.current_table_metadata()?;
+        assert_eq!(table_metadata.last_updated_ms, 1686911664577);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_table_read_table_metadata() -> Result<()> {
+    async fn test_table_reachable_files() -> Result<()> {
         let path = format!(
             "{}/testdata/simple_table",
             env::current_dir()
@@ -239,33 +4024,167 @@ mod tests {
                 .to_string_lossy()
         );
 
-        let mut builder = Fs::default();
-        builder.root(&path);
+        let table = Table::open(&path).await?;
 
-        let op = Operator::new(builder)?
-            .layer(LoggingLayer::default())
-            .finish();
+        let files = table.reachable_files().await?;
+        assert_eq!(
+            files,
+            vec![
+                "data/00000-0-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet",
+                "data/00001-1-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet",
+                "data/00002-2-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet",
+                "metadata/10d28031-9739-484c-92db-cdf2975cead4-m0.avro",
+                "metadata/snap-1646658105718557341-1-10d28031-9739-484c-92db-cdf2975cead4.avro",
+                "metadata/v1.metadata.json",
+                "metadata/v2.metadata.json",
+                "metadata/version-hint.text",
+            ]
+        );
 
-        let table = Table::new(op);
+        Ok(())
+    }
 
-        let table_v1 = table
-            .read_table_metadata("metadata/v1.metadata.json")
-            .await?;
+    #[tokio::test]
+    async fn test_table_create() -> Result<()> {
+        let op = Operator::new(Memory::default())?.finish();
 
-        assert_eq!(table_v1.format_version, types::TableFormatVersion::V1);
-        assert_eq!(table_v1.last_updated_ms, 1686911664577);
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![types::Field {
+                id: 1,
+                name: "tags".to_string(),
+                required: false,
+                field_type: types::Any::List(types::List {
+                    element_id: 2,
+                    element_required: true,
+                    element_type: types::Any::Primitive(types::Primitive::String).into(),
+                }),
+                comment: None,
+            }],
+        };
+        let partition_spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+        let properties = HashMap::from([("format-version".to_string(), "1".to_string())]);
 
-        let table_v2 = table
-            .read_table_metadata("metadata/v2.metadata.json")
+        let table = Table::create(
+            op.clone(),
+            "memory:///tbl/",
+            schema,
+            partition_spec,
+            properties,
+        )
+        .await?;
+
+        let table_metadata = table.current_table_metadata()?;
+        assert_eq!(table_metadata.format_version, types::TableFormatVersion::V1);
+        assert_eq!(table_metadata.location, "memory:///tbl");
+        assert_eq!(table_metadata.last_column_id, 2);
+        assert_eq!(table_metadata.last_partition_id, 999);
+        assert_eq!(table_metadata.properties, Some(HashMap::new()));
+
+        let mut table = Table::new(op);
+        table.load().await?;
+        assert_eq!(
+            table.current_metadata_path(),
+            Some("metadata/v1.metadata.json")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_commit_trim_metadata_log() -> Result<()> {
+        for delete_after_commit in [true, false] {
+            let op = Operator::new(Memory::default())?.finish();
+            let schema = types::Schema {
+                schema_id: 0,
+                identifier_field_ids: None,
+                fields: vec![],
+            };
+            let partition_spec = types::PartitionSpec {
+                spec_id: 0,
+                fields: vec![],
+            };
+            let properties = HashMap::from([
+                (
+                    "write.metadata.previous-versions-max".to_string(),
+                    "2".to_string(),
+                ),
+                (
+                    "write.metadata.delete-after-commit.enabled".to_string(),
+                    delete_after_commit.to_string(),
+                ),
+            ]);
+            let mut table = Table::create(
+                op.clone(),
+                "memory:///tbl",
+                schema,
+                partition_spec,
+                properties,
+            )
             .await?;
-        assert_eq!(table_v2.format_version, types::TableFormatVersion::V1);
-        assert_eq!(table_v2.last_updated_ms, 1686911671713);
+
+            for name in ["a", "b", "c", "d"] {
+                table
+                    .add_column(name, types::Any::Primitive(types::Primitive::Int), None)
+                    .await?;
+            }
+
+            assert_eq!(
+                table.current_metadata_path(),
+                Some("metadata/v5.metadata.json")
+            );
+            let metadata_log: Vec<_> = table
+                .current_table_metadata()?
+                .metadata_log
+                .iter()
+                .flatten()
+                .map(|v| v.metadata_file.as_str())
+                .collect();
+            assert_eq!(
+                metadata_log,
+                vec![
+                    "memory:///tbl/metadata/v3.metadata.json",
+                    "memory:///tbl/metadata/v4.metadata.json"
+                ]
+            );
+
+            // Trimmed metadata files are only deleted if enabled.
+            for (version, trimmed) in [(1, true), (2, true), (3, false), (4, false)] {
+                assert_eq!(
+                    op.is_exist(&format!("metadata/v{version}.metadata.json"))
+                        .await?,
+                    !(trimmed && delete_after_commit)
+                );
+            }
+        }
 
         Ok(())
     }
 
+    #[test]
+    fn test_parse_metadata_version() {
+        let cases = vec![
+            ("metadata/v1.metadata.json", Some(1)),
+            ("metadata/v12.metadata.json", Some(12)),
+            (
+                "metadata/00005-b1ba2aeb-91d1-4c3f-8b0b-37d4ab7b8b07.metadata.json",
+                Some(5),
+            ),
+            ("metadata/version-hint.text", None),
+            ("metadata/vx.metadata.json", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_metadata_version(input), expected, "{input}");
+        }
+    }
+
     #[tokio::test]
-    async fn test_table_load() -> Result<()> {
+    async fn test_table_current_data_files() -> Result<()> {
         let path = format!(
             "{}/testdata/simple_table",
             env::current_dir()
@@ -283,17 +4202,19 @@ mod tests {
         let mut table = Table::new(op);
         table.load().await?;
 
-        let table_metadata = table.current_table_metadata()?;
-        assert_eq!(table_metadata.format_version, types::TableFormatVersion::V1);
-        assert_eq!(table_metadata.last_updated_ms, 1686911671713);
+        let data_files = table.current_data_files().await?;
+        assert_eq!(data_files.len(), 3);
+        assert_eq!(data_files[0].file_path, "/opt/bitnami/spark/warehouse/db/table/data/00000-0-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
+        assert_eq!(data_files[1].file_path, "/opt/bitnami/spark/warehouse/db/table/data/00001-1-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
+        assert_eq!(data_files[2].file_path, "/opt/bitnami/spark/warehouse/db/table/data/00002-2-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_table_load_without_version_hint() -> Result<()> {
+    async fn test_table_without_snapshot() -> Result<()> {
         let path = format!(
-            "{}/testdata/no_hint_table",
+            "{}/testdata/empty_table",
             env::current_dir()
                 .expect("current_dir must exist")
                 .to_string_lossy()
@@ -301,29 +4222,29 @@ mod tests {
 
         let mut builder = Fs::default();
         builder.root(&path);
+        let op = Operator::new(builder)?.finish();
 
-        let op = Operator::new(builder)?
-            .layer(LoggingLayer::default())
-            .finish();
-
-        let mut table = Table::new(op);
+        let mut table = Table::new(op.clone());
         table.load().await?;
 
-        let table_metadata = table.current_table_metadata()?;
-        assert_eq!(table_metadata.format_version, types::TableFormatVersion::V1);
-        assert_eq!(table_metadata.last_updated_ms, 1672981042425);
+        // `current-snapshot-id` of `-1` means no current snapshot.
+        assert_eq!(table.current_table_metadata()?.current_snapshot_id, None);
+        assert!(table.current_data_files().await?.is_empty());
+        assert!(table.current_data_file_paths().await?.is_empty());
+        assert_eq!(table.estimated_row_count().await?, 0);
+        assert!(table.scan().plan_files().await?.is_empty());
         assert_eq!(
-            table_metadata.location,
-            "s3://testbucket/iceberg_data/iceberg_ctl/iceberg_db/iceberg_tbl"
+            types::parse_current_manifest_list(&op.read("metadata/v1.metadata.json").await?)?,
+            None
         );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_table_current_data_files() -> Result<()> {
+    async fn test_table_v3() -> Result<()> {
         let path = format!(
-            "{}/testdata/simple_table",
+            "{}/testdata/v3_table",
             env::current_dir()
                 .expect("current_dir must exist")
                 .to_string_lossy()
@@ -331,19 +4252,19 @@ mod tests {
 
         let mut builder = Fs::default();
         builder.root(&path);
-
-        let op = Operator::new(builder)?
-            .layer(LoggingLayer::default())
-            .finish();
+        let op = Operator::new(builder)?.finish();
 
         let mut table = Table::new(op);
         table.load().await?;
 
-        let data_files = table.current_data_files().await?;
-        assert_eq!(data_files.len(), 3);
-        assert_eq!(data_files[0].file_path, "/opt/bitnami/spark/warehouse/db/table/data/00000-0-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
-        assert_eq!(data_files[1].file_path, "/opt/bitnami/spark/warehouse/db/table/data/00001-1-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
-        assert_eq!(data_files[2].file_path, "/opt/bitnami/spark/warehouse/db/table/data/00002-2-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
+        let metadata = table.current_table_metadata()?;
+        assert_eq!(metadata.format_version, types::TableFormatVersion::V3);
+        assert!(table.current_data_files().await?.is_empty());
+        assert!(table.scan().plan_files().await?.is_empty());
+
+        // Writing is not supported yet.
+        let err = table.new_append().commit().await.unwrap_err();
+        assert!(err.to_string().contains("not supported"), "{err}");
 
         Ok(())
     }
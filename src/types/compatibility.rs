@@ -0,0 +1,324 @@
+//! compatibility module checks whether data written by one schema can be
+//! read by another one.
+
+use std::collections::HashMap;
+
+use super::Any;
+use super::Primitive;
+use super::Schema;
+
+/// SchemaCompatibility is the result of `schema_compatible`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaCompatibility {
+    /// Fields of the target schema which can't be read from source data,
+    /// empty if the schemas are compatible.
+    pub incompatibilities: Vec<SchemaIncompatibility>,
+}
+
+impl SchemaCompatibility {
+    /// Whether source data can be read by the target schema safely.
+    pub fn is_compatible(&self) -> bool {
+        self.incompatibilities.is_empty()
+    }
+}
+
+/// SchemaIncompatibility describes a field of the target schema which
+/// can't be read from source data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaIncompatibility {
+    /// Path of the field in target schema like `a.b`, elements of lists
+    /// and keys and values of maps are named `element`, `key` and `value`.
+    pub path: String,
+    /// Id of the field.
+    pub field_id: i32,
+    /// Why the field can't be read.
+    pub reason: String,
+}
+
+/// Check whether data written by `source` schema can be read by `target`
+/// schema safely.
+///
+/// Fields are matched by id like iceberg does on read. Fields dropped by
+/// target are ignored, and fields missing in source must be optional in
+/// target. Types must be the same, or promoted by rules of the spec:
+///
+/// - `int` to `long`
+/// - `float` to `double`
+/// - `decimal(P, S)` to `decimal(P', S)` where `P' > P`
+///
+/// Reference: [Schema Evolution](https://iceberg.apache.org/spec/#schema-evolution)
+pub fn schema_compatible(source: &Schema, target: &Schema) -> SchemaCompatibility {
+    let mut sources = HashMap::new();
+    for field in &source.fields {
+        index_field(&mut sources, field.id, field.required, &field.field_type);
+    }
+
+    let mut checker = Checker {
+        sources,
+        incompatibilities: vec![],
+    };
+    for field in &target.fields {
+        checker.check(&field.name, field.id, field.required, &field.field_type);
+    }
+
+    SchemaCompatibility {
+        incompatibilities: checker.incompatibilities,
+    }
+}
+
+/// Whether values of type `from` can be read as type `to` by the type
+/// promotion rules of the spec.
+pub fn can_promote(from: &Primitive, to: &Primitive) -> bool {
+    match (from, to) {
+        (Primitive::Int, Primitive::Long) | (Primitive::Float, Primitive::Double) => true,
+        (
+            Primitive::Decimal { precision, scale },
+            Primitive::Decimal {
+                precision: to_precision,
+                scale: to_scale,
+            },
+        ) => scale == to_scale && precision < to_precision,
+        _ => false,
+    }
+}
+
+/// Index the field and all nested fields by id.
+fn index_field<'a>(
+    sources: &mut HashMap<i32, (bool, &'a Any)>,
+    id: i32,
+    required: bool,
+    typ: &'a Any,
+) {
+    sources.insert(id, (required, typ));
+    match typ {
+        Any::Primitive(_) => {}
+        Any::Struct(v) => {
+            for field in &v.fields {
+                index_field(sources, field.id, field.required, &field.field_type);
+            }
+        }
+        Any::List(v) => index_field(sources, v.element_id, v.element_required, &v.element_type),
+        Any::Map(v) => {
+            index_field(sources, v.key_id, true, &v.key_type);
+            index_field(sources, v.value_id, v.value_required, &v.value_type);
+        }
+        Any::Unknown(_) => {}
+    }
+}
+
+/// Describe the type in messages.
+fn describe(typ: &Any) -> String {
+    match typ {
+        Any::Primitive(v) => format!("{v:?}"),
+        Any::Struct(_) => "struct".to_string(),
+        Any::List(_) => "list".to_string(),
+        Any::Map(_) => "map".to_string(),
+        Any::Unknown(v) => v.clone(),
+    }
+}
+
+struct Checker<'a> {
+    sources: HashMap<i32, (bool, &'a Any)>,
+    incompatibilities: Vec<SchemaIncompatibility>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, path: &str, field_id: i32, reason: String) {
+        self.incompatibilities.push(SchemaIncompatibility {
+            path: path.to_string(),
+            field_id,
+            reason,
+        });
+    }
+
+    /// Check the field of target schema against the source field with the
+    /// same id.
+    fn check(&mut self, path: &str, id: i32, required: bool, typ: &Any) {
+        let Some(&(source_required, source_type)) = self.sources.get(&id) else {
+            if required {
+                self.report(path, id, "required field is missing in source".to_string());
+            }
+            return;
+        };
+
+        if required && !source_required {
+            self.report(
+                path,
+                id,
+                "optional field in source can't be read as required".to_string(),
+            );
+        }
+
+        match (source_type, typ) {
+            (Any::Primitive(from), Any::Primitive(to)) => {
+                if from != to && !can_promote(from, to) {
+                    self.report(
+                        path,
+                        id,
+                        format!("type {from:?} can't be promoted to {to:?}"),
+                    );
+                }
+            }
+            (Any::Struct(_), Any::Struct(v)) => {
+                for field in &v.fields {
+                    self.check(
+                        &format!("{path}.{}", field.name),
+                        field.id,
+                        field.required,
+                        &field.field_type,
+                    );
+                }
+            }
+            (Any::List(_), Any::List(v)) => self.check(
+                &format!("{path}.element"),
+                v.element_id,
+                v.element_required,
+                &v.element_type,
+            ),
+            (Any::Map(_), Any::Map(v)) => {
+                self.check(&format!("{path}.key"), v.key_id, true, &v.key_type);
+                self.check(
+                    &format!("{path}.value"),
+                    v.value_id,
+                    v.value_required,
+                    &v.value_type,
+                );
+            }
+            (Any::Unknown(from), Any::Unknown(to)) if from == to => {}
+            (from, to) => self.report(
+                path,
+                id,
+                format!("{} can't be read as {}", describe(from), describe(to)),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Field;
+    use crate::types::List;
+    use crate::types::Struct;
+
+    fn field(id: i32, name: &str, required: bool, typ: Any) -> Field {
+        Field {
+            id,
+            name: name.to_string(),
+            required,
+            field_type: typ,
+            comment: None,
+        }
+    }
+
+    fn schema(fields: Vec<Field>) -> Schema {
+        Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields,
+        }
+    }
+
+    #[test]
+    fn test_schema_compatible() {
+        let decimal = |precision, scale| Any::Primitive(Primitive::Decimal { precision, scale });
+        let source = schema(vec![
+            field(1, "id", true, Any::Primitive(Primitive::Int)),
+            field(2, "score", false, Any::Primitive(Primitive::Float)),
+            field(3, "amount", false, decimal(9, 2)),
+            field(
+                4,
+                "tags",
+                false,
+                Any::List(List {
+                    element_id: 5,
+                    element_required: false,
+                    element_type: Box::new(Any::Primitive(Primitive::String)),
+                }),
+            ),
+            field(6, "dropped", true, Any::Primitive(Primitive::Long)),
+        ]);
+
+        // Promotions, renames, dropped and new optional fields are allowed.
+        let target = schema(vec![
+            field(1, "user_id", true, Any::Primitive(Primitive::Long)),
+            field(2, "score", false, Any::Primitive(Primitive::Double)),
+            field(3, "amount", false, decimal(18, 2)),
+            field(
+                4,
+                "tags",
+                false,
+                Any::List(List {
+                    element_id: 5,
+                    element_required: false,
+                    element_type: Box::new(Any::Primitive(Primitive::String)),
+                }),
+            ),
+            field(7, "added", false, Any::Primitive(Primitive::Boolean)),
+        ]);
+        assert!(schema_compatible(&source, &target).is_compatible());
+        // Promotions can't be reversed.
+        assert_eq!(
+            schema_compatible(&target, &source)
+                .incompatibilities
+                .iter()
+                .map(|v| v.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["id", "score", "amount", "dropped"]
+        );
+
+        let target = schema(vec![
+            field(1, "id", true, Any::Primitive(Primitive::String)),
+            field(2, "score", true, Any::Primitive(Primitive::Float)),
+            field(3, "amount", false, decimal(18, 3)),
+            field(
+                4,
+                "tags",
+                false,
+                Any::List(List {
+                    element_id: 5,
+                    element_required: true,
+                    element_type: Box::new(Any::Primitive(Primitive::String)),
+                }),
+            ),
+            field(6, "dropped", true, Any::Struct(Struct { fields: vec![] })),
+            field(7, "added", true, Any::Primitive(Primitive::Boolean)),
+        ]);
+        let result = schema_compatible(&source, &target);
+        assert_eq!(
+            result.incompatibilities,
+            vec![
+                SchemaIncompatibility {
+                    path: "id".to_string(),
+                    field_id: 1,
+                    reason: "type Int can't be promoted to String".to_string(),
+                },
+                SchemaIncompatibility {
+                    path: "score".to_string(),
+                    field_id: 2,
+                    reason: "optional field in source can't be read as required".to_string(),
+                },
+                SchemaIncompatibility {
+                    path: "amount".to_string(),
+                    field_id: 3,
+                    reason: "type Decimal { precision: 9, scale: 2 } can't be promoted to Decimal { precision: 18, scale: 3 }".to_string(),
+                },
+                SchemaIncompatibility {
+                    path: "tags.element".to_string(),
+                    field_id: 5,
+                    reason: "optional field in source can't be read as required".to_string(),
+                },
+                SchemaIncompatibility {
+                    path: "dropped".to_string(),
+                    field_id: 6,
+                    reason: "Long can't be read as struct".to_string(),
+                },
+                SchemaIncompatibility {
+                    path: "added".to_string(),
+                    field_id: 7,
+                    reason: "required field is missing in source".to_string(),
+                },
+            ]
+        );
+    }
+}
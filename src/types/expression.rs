@@ -0,0 +1,890 @@
+use std::collections::HashMap;
+
+use super::Any;
+use super::DataFile;
+use super::Datum;
+use super::Field;
+use super::PartitionField;
+use super::PartitionSpec;
+use super::Primitive;
+use super::Schema;
+use super::Transform;
+use crate::Result;
+
+/// Expression is a boolean predicate on columns of table.
+///
+/// Columns are referenced by field id, and literals must be of the same
+/// type as the column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// Always true.
+    AlwaysTrue,
+    /// Always false.
+    AlwaysFalse,
+    /// Both expressions are true.
+    And(Box<Expression>, Box<Expression>),
+    /// Either expression is true.
+    Or(Box<Expression>, Box<Expression>),
+    /// The column is null.
+    IsNull(i32),
+    /// The column is not null.
+    NotNull(i32),
+    /// The column equals to the literal.
+    Eq(i32, Datum),
+    /// The column doesn't equal to the literal.
+    NotEq(i32, Datum),
+    /// The column is less than the literal.
+    Lt(i32, Datum),
+    /// The column is less than or equal to the literal.
+    LtEq(i32, Datum),
+    /// The column is greater than the literal.
+    Gt(i32, Datum),
+    /// The column is greater than or equal to the literal.
+    GtEq(i32, Datum),
+    /// The column equals to any of the literals.
+    In(i32, Vec<Datum>),
+}
+
+impl Expression {
+    /// Combine two expressions with `AND`.
+    pub fn and(self, other: Expression) -> Expression {
+        Expression::And(Box::new(self), Box::new(other))
+    }
+
+    /// Combine two expressions with `OR`.
+    pub fn or(self, other: Expression) -> Expression {
+        Expression::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Check if any row of the data file could match the expression by
+    /// its statistics.
+    ///
+    /// The evaluation is inclusive: `false` is returned only if no row of
+    /// the file can match, and missing statistics are treated as could
+    /// match. Floating NaNs are not counted in bounds, so files may
+    /// contain NaN can't be pruned by bounds for NaN literals.
+    pub fn can_match_file(&self, stats: &DataFileStats) -> bool {
+        if stats.record_count == 0 {
+            return false;
+        }
+
+        self.eval(stats)
+    }
+
+    /// Project the expression on source columns into an expression on
+    /// partition fields of the spec, which references partition field
+    /// ids instead.
+    ///
+    /// The projection is inclusive: if a row matches the expression, its
+    /// partition tuple must match the projected expression, so partitions
+    /// not matching the projection can be pruned safely. For example,
+    /// `ts >= '2023-01-01 12:00:00'` is projected to `day(ts) >= 19358`.
+    ///
+    /// Columns without partition fields and transforms which can't be
+    /// projected like `bucket` are projected as `AlwaysTrue`.
+    pub fn project(&self, spec: &PartitionSpec) -> Result<Expression> {
+        Ok(match self {
+            Expression::AlwaysTrue => Expression::AlwaysTrue,
+            Expression::AlwaysFalse => Expression::AlwaysFalse,
+            Expression::And(a, b) => and(a.project(spec)?, b.project(spec)?),
+            Expression::Or(a, b) => or(a.project(spec)?, b.project(spec)?),
+            _ => {
+                let mut projected = Expression::AlwaysTrue;
+                if let Some(id) = self.field_id() {
+                    for field in spec.fields_for_source(id) {
+                        projected = and(projected, self.project_field(field)?);
+                    }
+                }
+                projected
+            }
+        })
+    }
+
+    /// Field id referenced by the predicate, `None` for compound
+    /// expressions.
+    fn field_id(&self) -> Option<i32> {
+        match self {
+            Expression::AlwaysTrue
+            | Expression::AlwaysFalse
+            | Expression::And(_, _)
+            | Expression::Or(_, _) => None,
+            Expression::IsNull(id)
+            | Expression::NotNull(id)
+            | Expression::Eq(id, _)
+            | Expression::NotEq(id, _)
+            | Expression::Lt(id, _)
+            | Expression::LtEq(id, _)
+            | Expression::Gt(id, _)
+            | Expression::GtEq(id, _)
+            | Expression::In(id, _) => Some(*id),
+        }
+    }
+
+    /// Project the predicate on source column into the partition field.
+    fn project_field(&self, field: &PartitionField) -> Result<Expression> {
+        let id = field.partition_field_id;
+        let apply = |v: &Datum| field.transform.apply(v);
+        // Transformed values of `In` are deduplicated.
+        let apply_all = |values: &[Datum]| -> Result<Vec<Datum>> {
+            let mut projected: Vec<Datum> = vec![];
+            for v in values {
+                if let Some(v) = apply(v)? {
+                    if !projected.contains(&v) {
+                        projected.push(v);
+                    }
+                }
+            }
+            Ok(projected)
+        };
+
+        match field.transform {
+            Transform::Void => return Ok(Expression::AlwaysTrue),
+            Transform::Identity => {
+                return Ok(match self {
+                    Expression::IsNull(_) => Expression::IsNull(id),
+                    Expression::NotNull(_) => Expression::NotNull(id),
+                    Expression::Eq(_, v) => Expression::Eq(id, v.clone()),
+                    Expression::NotEq(_, v) => Expression::NotEq(id, v.clone()),
+                    Expression::Lt(_, v) => Expression::Lt(id, v.clone()),
+                    Expression::LtEq(_, v) => Expression::LtEq(id, v.clone()),
+                    Expression::Gt(_, v) => Expression::Gt(id, v.clone()),
+                    Expression::GtEq(_, v) => Expression::GtEq(id, v.clone()),
+                    Expression::In(_, values) => Expression::In(id, values.clone()),
+                    _ => Expression::AlwaysTrue,
+                });
+            }
+            Transform::Bucket(_) => {
+                // Hash doesn't preserve order, only equality is projected.
+                let projected = match self {
+                    Expression::IsNull(_) => Some(Expression::IsNull(id)),
+                    Expression::NotNull(_) => Some(Expression::NotNull(id)),
+                    Expression::Eq(_, v) => apply(v)?.map(|v| Expression::Eq(id, v)),
+                    Expression::In(_, values) => Some(Expression::In(id, apply_all(values)?)),
+                    _ => None,
+                };
+                return Ok(projected.unwrap_or(Expression::AlwaysTrue));
+            }
+            Transform::Truncate(_)
+            | Transform::Year
+            | Transform::Month
+            | Transform::Day
+            | Transform::Hour => {}
+        }
+
+        // Transforms left are monotonic, so the range of source values is
+        // projected into the range of transformed values. Strict bounds
+        // are turned into inclusive ones by the adjacent value if possible.
+        let projected = match self {
+            Expression::IsNull(_) => Some(Expression::IsNull(id)),
+            Expression::NotNull(_) => Some(Expression::NotNull(id)),
+            Expression::Eq(_, v) => apply(v)?.map(|v| Expression::Eq(id, v)),
+            Expression::Lt(_, v) => apply(&adjacent(v, -1).unwrap_or_else(|| v.clone()))?
+                .map(|v| Expression::LtEq(id, v)),
+            Expression::LtEq(_, v) => apply(v)?.map(|v| Expression::LtEq(id, v)),
+            Expression::Gt(_, v) => apply(&adjacent(v, 1).unwrap_or_else(|| v.clone()))?
+                .map(|v| Expression::GtEq(id, v)),
+            Expression::GtEq(_, v) => apply(v)?.map(|v| Expression::GtEq(id, v)),
+            Expression::In(_, values) => Some(Expression::In(id, apply_all(values)?)),
+            // Transformed values are not unique, `NotEq` can't be projected.
+            _ => None,
+        };
+
+        Ok(projected.unwrap_or(Expression::AlwaysTrue))
+    }
+
+    /// Check if the partition tuple could match the expression on
+    /// partition fields, which is usually projected by `project`.
+    ///
+    /// Values of partition are in the same order as fields of the spec.
+    /// Fields not found in the partition and literals of different types
+    /// are treated as could match.
+    pub fn can_match_partition(&self, spec: &PartitionSpec, partition: &[Option<Datum>]) -> bool {
+        let value = |id: &i32| -> Option<Option<&Datum>> {
+            let idx = spec
+                .fields
+                .iter()
+                .position(|v| v.partition_field_id == *id)?;
+            partition.get(idx).map(|v| v.as_ref())
+        };
+        // Compare the value with literal, nulls never match and unknown
+        // values could match.
+        let compare = |id: &i32, l: &Datum, f: fn(&Datum, &Datum) -> bool| match value(id) {
+            None => true,
+            Some(None) => false,
+            Some(Some(v)) => !comparable(v, l) || f(v, l),
+        };
+
+        match self {
+            Expression::AlwaysTrue => true,
+            Expression::AlwaysFalse => false,
+            Expression::And(a, b) => {
+                a.can_match_partition(spec, partition) && b.can_match_partition(spec, partition)
+            }
+            Expression::Or(a, b) => {
+                a.can_match_partition(spec, partition) || b.can_match_partition(spec, partition)
+            }
+            Expression::IsNull(id) => value(id).is_none_or(|v| v.is_none()),
+            Expression::NotNull(id) => value(id).is_none_or(|v| v.is_some()),
+            Expression::Eq(id, l) => compare(id, l, |v, l| v == l),
+            Expression::NotEq(id, l) => compare(id, l, |v, l| v != l),
+            Expression::Lt(id, l) => compare(id, l, |v, l| v < l),
+            Expression::LtEq(id, l) => compare(id, l, |v, l| v <= l),
+            Expression::Gt(id, l) => compare(id, l, |v, l| v > l),
+            Expression::GtEq(id, l) => compare(id, l, |v, l| v >= l),
+            Expression::In(id, values) => values.iter().any(|l| compare(id, l, |v, l| v == l)),
+        }
+    }
+
+    fn eval(&self, stats: &DataFileStats) -> bool {
+        match self {
+            Expression::AlwaysTrue => true,
+            Expression::AlwaysFalse => false,
+            Expression::And(a, b) => a.eval(stats) && b.eval(stats),
+            Expression::Or(a, b) => a.eval(stats) || b.eval(stats),
+            Expression::IsNull(id) => stats.null_value_counts.get(id) != Some(&0),
+            Expression::NotNull(id) => !stats.contains_nulls_only(*id),
+            // Bounds can't prove that all values equal to the literal.
+            Expression::NotEq(_, _) => true,
+            Expression::Eq(id, v) => stats.may_contain(*id, v),
+            Expression::In(id, values) => values.iter().any(|v| stats.may_contain(*id, v)),
+            Expression::Lt(id, v) => {
+                stats.may_compare(*id, v, |lower, _| lower.is_none_or(|b| b < v))
+            }
+            Expression::LtEq(id, v) => {
+                stats.may_compare(*id, v, |lower, _| lower.is_none_or(|b| b <= v))
+            }
+            Expression::Gt(id, v) => {
+                stats.may_compare(*id, v, |_, upper| upper.is_none_or(|b| b > v))
+            }
+            Expression::GtEq(id, v) => {
+                stats.may_compare(*id, v, |_, upper| upper.is_none_or(|b| b >= v))
+            }
+        }
+    }
+}
+
+/// DataFileStats is the typed statistics of a data file used to evaluate
+/// expressions.
+///
+/// Statistics are keyed by field id, and absent for unknown columns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataFileStats {
+    /// Number of records in the file.
+    pub record_count: i64,
+    /// Number of values in each column, including nulls and NaNs.
+    pub value_counts: HashMap<i32, i64>,
+    /// Number of null values in each column.
+    pub null_value_counts: HashMap<i32, i64>,
+    /// Number of NaN values in each float and double column.
+    pub nan_value_counts: HashMap<i32, i64>,
+    /// Lower bounds of non-null, non-NaN values in each column.
+    pub lower_bounds: HashMap<i32, Datum>,
+    /// Upper bounds of non-null, non-NaN values in each column.
+    pub upper_bounds: HashMap<i32, Datum>,
+}
+
+impl DataFileStats {
+    /// Build statistics of data file, bounds are decoded by types of the
+    /// schema.
+    ///
+    /// Bounds of columns not found in schema are ignored.
+    pub fn try_new(data_file: &DataFile, schema: &Schema) -> Result<Self> {
+        let mut types = HashMap::new();
+        collect_primitive_types(&schema.fields, &mut types);
+
+        let decode = |bounds: &Option<HashMap<i32, Vec<u8>>>| -> Result<HashMap<i32, Datum>> {
+            let mut decoded = HashMap::new();
+            for (id, bs) in bounds.iter().flatten() {
+                if let Some(typ) = types.get(id) {
+                    let v = Datum::from_bytes(bs, typ)
+                        .map_err(|err| err.with_context("field_id", id.to_string()))?;
+                    decoded.insert(*id, v);
+                }
+            }
+            Ok(decoded)
+        };
+
+        Ok(Self {
+            record_count: data_file.record_count,
+            value_counts: data_file.value_counts.clone().unwrap_or_default(),
+            null_value_counts: data_file.null_value_counts.clone().unwrap_or_default(),
+            nan_value_counts: data_file.nan_value_counts.clone().unwrap_or_default(),
+            lower_bounds: decode(&data_file.lower_bounds)?,
+            upper_bounds: decode(&data_file.upper_bounds)?,
+        })
+    }
+
+    /// Check if all values of the column are null.
+    fn contains_nulls_only(&self, id: i32) -> bool {
+        matches!(
+            (self.value_counts.get(&id), self.null_value_counts.get(&id)),
+            (Some(values), Some(nulls)) if values == nulls
+        )
+    }
+
+    /// Check if all values of the column are null or NaN.
+    fn contains_nulls_or_nans_only(&self, id: i32) -> bool {
+        let (Some(values), Some(nulls)) =
+            (self.value_counts.get(&id), self.null_value_counts.get(&id))
+        else {
+            return false;
+        };
+        values - nulls == self.nan_value_counts.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Check if the column may contain NaN values, unknown counts are
+    /// treated as containing NaN.
+    fn may_contain_nan(&self, id: i32) -> bool {
+        self.nan_value_counts.get(&id) != Some(&0) && !self.contains_nulls_only(id)
+    }
+
+    /// Check if the column may contain the value.
+    fn may_contain(&self, id: i32, v: &Datum) -> bool {
+        if is_nan(v) {
+            return self.may_contain_nan(id);
+        }
+        self.may_compare(id, v, |lower, upper| {
+            lower.is_none_or(|b| b <= v) && upper.is_none_or(|b| v <= b)
+        })
+    }
+
+    /// Check if non-null, non-NaN values of the column may match the
+    /// comparison with bounds.
+    ///
+    /// `f` is called with lower and upper bounds, bounds which are
+    /// missing or not comparable with the literal are passed as `None`.
+    fn may_compare(
+        &self,
+        id: i32,
+        v: &Datum,
+        f: impl Fn(Option<&Datum>, Option<&Datum>) -> bool,
+    ) -> bool {
+        if self.contains_nulls_or_nans_only(id) {
+            return false;
+        }
+        // Comparison with NaN is always false, but be conservative for
+        // invalid literals.
+        if is_nan(v) {
+            return true;
+        }
+
+        let lower = self.lower_bounds.get(&id).filter(|b| comparable(b, v));
+        let upper = self.upper_bounds.get(&id).filter(|b| comparable(b, v));
+        // Bounds written by legacy writers may be NaN.
+        if lower.is_some_and(is_nan) || upper.is_some_and(is_nan) {
+            return true;
+        }
+
+        f(lower, upper)
+    }
+}
+
+/// Combine two expressions with `AND`, constant expressions are folded.
+fn and(a: Expression, b: Expression) -> Expression {
+    match (a, b) {
+        (Expression::AlwaysFalse, _) | (_, Expression::AlwaysFalse) => Expression::AlwaysFalse,
+        (Expression::AlwaysTrue, v) | (v, Expression::AlwaysTrue) => v,
+        (a, b) => a.and(b),
+    }
+}
+
+/// Combine two expressions with `OR`, constant expressions are folded.
+fn or(a: Expression, b: Expression) -> Expression {
+    match (a, b) {
+        (Expression::AlwaysTrue, _) | (_, Expression::AlwaysTrue) => Expression::AlwaysTrue,
+        (Expression::AlwaysFalse, v) | (v, Expression::AlwaysFalse) => v,
+        (a, b) => a.or(b),
+    }
+}
+
+/// Get the adjacent value of discrete types by adding `delta`, `None`
+/// for continuous types or overflow.
+fn adjacent(v: &Datum, delta: i32) -> Option<Datum> {
+    match v {
+        Datum::Int(v) => v.checked_add(delta).map(Datum::Int),
+        Datum::Date(v) => v.checked_add(delta).map(Datum::Date),
+        Datum::Long(v) => v.checked_add(delta as i64).map(Datum::Long),
+        Datum::Decimal(v) => v.checked_add(delta as i128).map(Datum::Decimal),
+        Datum::Timestamp(v) => v.checked_add(delta as i64).map(Datum::Timestamp),
+        Datum::Timestamptz(v) => v.checked_add(delta as i64).map(Datum::Timestamptz),
+        _ => None,
+    }
+}
+
+/// Check if the bound can be compared with the literal.
+fn comparable(bound: &Datum, v: &Datum) -> bool {
+    bound.partial_cmp(v).is_some()
+}
+
+fn is_nan(v: &Datum) -> bool {
+    match v {
+        Datum::Float(v) => v.is_nan(),
+        Datum::Double(v) => v.is_nan(),
+        _ => false,
+    }
+}
+
+/// Collect types of primitive fields including fields of nested structs.
+fn collect_primitive_types(fields: &[Field], types: &mut HashMap<i32, Primitive>) {
+    for field in fields {
+        match &field.field_type {
+            Any::Primitive(v) => {
+                types.insert(field.id, *v);
+            }
+            Any::Struct(v) => collect_primitive_types(&v.fields, types),
+            Any::List(_) | Any::Map(_) | Any::Unknown(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stats of a file with 10 rows: column 1 is long in [10, 20] with 2
+    /// nulls, column 2 is double in [-1.0, 1.0] with 3 NaNs, column 3 is
+    /// all null, column 4 is string without bounds.
+    fn stats() -> DataFileStats {
+        DataFileStats {
+            record_count: 10,
+            value_counts: HashMap::from([(1, 10), (2, 10), (3, 10)]),
+            null_value_counts: HashMap::from([(1, 2), (2, 0), (3, 10)]),
+            nan_value_counts: HashMap::from([(2, 3)]),
+            lower_bounds: HashMap::from([(1, Datum::Long(10)), (2, Datum::Double((-1.0).into()))]),
+            upper_bounds: HashMap::from([(1, Datum::Long(20)), (2, Datum::Double(1.0.into()))]),
+        }
+    }
+
+    fn long(v: i64) -> Datum {
+        Datum::Long(v)
+    }
+
+    fn double(v: f64) -> Datum {
+        Datum::Double(v.into())
+    }
+
+    #[test]
+    fn test_can_match_is_null() {
+        let stats = stats();
+        assert!(Expression::IsNull(1).can_match_file(&stats));
+        assert!(!Expression::IsNull(2).can_match_file(&stats));
+        assert!(Expression::IsNull(3).can_match_file(&stats));
+        assert!(Expression::IsNull(4).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_not_null() {
+        let stats = stats();
+        assert!(Expression::NotNull(1).can_match_file(&stats));
+        assert!(Expression::NotNull(2).can_match_file(&stats));
+        assert!(!Expression::NotNull(3).can_match_file(&stats));
+        assert!(Expression::NotNull(4).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_eq() {
+        let stats = stats();
+        assert!(!Expression::Eq(1, long(9)).can_match_file(&stats));
+        assert!(Expression::Eq(1, long(10)).can_match_file(&stats));
+        assert!(Expression::Eq(1, long(15)).can_match_file(&stats));
+        assert!(Expression::Eq(1, long(20)).can_match_file(&stats));
+        assert!(!Expression::Eq(1, long(21)).can_match_file(&stats));
+        assert!(!Expression::Eq(2, double(1.5)).can_match_file(&stats));
+        assert!(!Expression::Eq(3, long(1)).can_match_file(&stats));
+        assert!(Expression::Eq(4, Datum::String("a".to_string())).can_match_file(&stats));
+        // Mismatched literal is not pruned.
+        assert!(Expression::Eq(1, Datum::Int(100)).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_eq_nan() {
+        let mut stats = stats();
+        assert!(Expression::Eq(2, double(f64::NAN)).can_match_file(&stats));
+        // Unknown NaN counts are treated as containing NaN.
+        stats.nan_value_counts.clear();
+        assert!(Expression::Eq(2, double(f64::NAN)).can_match_file(&stats));
+        stats.nan_value_counts.insert(2, 0);
+        assert!(!Expression::Eq(2, double(f64::NAN)).can_match_file(&stats));
+        // Bounds of non-NaN values are still used.
+        assert!(Expression::Eq(2, double(0.0)).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_not_eq() {
+        let stats = stats();
+        assert!(Expression::NotEq(1, long(15)).can_match_file(&stats));
+        assert!(Expression::NotEq(1, long(100)).can_match_file(&stats));
+
+        // Not pruned even if all values equal to the literal, which can't
+        // be proved by bounds.
+        let mut stats = stats;
+        stats.lower_bounds.insert(1, long(15));
+        stats.upper_bounds.insert(1, long(15));
+        assert!(Expression::NotEq(1, long(15)).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_lt() {
+        let stats = stats();
+        assert!(!Expression::Lt(1, long(5)).can_match_file(&stats));
+        assert!(!Expression::Lt(1, long(10)).can_match_file(&stats));
+        assert!(Expression::Lt(1, long(11)).can_match_file(&stats));
+        assert!(Expression::Lt(1, long(30)).can_match_file(&stats));
+        assert!(!Expression::Lt(2, double(-1.0)).can_match_file(&stats));
+        assert!(Expression::Lt(2, double(0.0)).can_match_file(&stats));
+        assert!(!Expression::Lt(3, long(1)).can_match_file(&stats));
+        // Columns without bounds are not pruned.
+        assert!(Expression::Lt(4, Datum::String("a".to_string())).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_lt_eq() {
+        let stats = stats();
+        assert!(!Expression::LtEq(1, long(9)).can_match_file(&stats));
+        assert!(Expression::LtEq(1, long(10)).can_match_file(&stats));
+        assert!(Expression::LtEq(1, long(30)).can_match_file(&stats));
+        assert!(Expression::LtEq(2, double(-1.0)).can_match_file(&stats));
+        assert!(!Expression::LtEq(3, long(1)).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_gt() {
+        let stats = stats();
+        assert!(!Expression::Gt(1, long(25)).can_match_file(&stats));
+        assert!(!Expression::Gt(1, long(20)).can_match_file(&stats));
+        assert!(Expression::Gt(1, long(19)).can_match_file(&stats));
+        assert!(Expression::Gt(1, long(0)).can_match_file(&stats));
+        assert!(!Expression::Gt(2, double(1.0)).can_match_file(&stats));
+        assert!(!Expression::Gt(3, long(1)).can_match_file(&stats));
+        assert!(Expression::Gt(4, Datum::String("a".to_string())).can_match_file(&stats));
+
+        // Only upper bound is used.
+        let mut stats = stats;
+        stats.lower_bounds.clear();
+        assert!(Expression::Gt(1, long(19)).can_match_file(&stats));
+        assert!(Expression::Lt(1, long(0)).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_gt_eq() {
+        let stats = stats();
+        assert!(!Expression::GtEq(1, long(21)).can_match_file(&stats));
+        assert!(Expression::GtEq(1, long(20)).can_match_file(&stats));
+        assert!(Expression::GtEq(1, long(0)).can_match_file(&stats));
+        assert!(Expression::GtEq(2, double(1.0)).can_match_file(&stats));
+        assert!(!Expression::GtEq(3, long(1)).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_in() {
+        let stats = stats();
+        assert!(!Expression::In(1, vec![long(1), long(30)]).can_match_file(&stats));
+        assert!(Expression::In(1, vec![long(1), long(15)]).can_match_file(&stats));
+        assert!(!Expression::In(1, vec![]).can_match_file(&stats));
+        assert!(Expression::In(2, vec![double(5.0), double(f64::NAN)]).can_match_file(&stats));
+        assert!(!Expression::In(3, vec![long(1)]).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_nans_only() {
+        let mut stats = stats();
+        stats.nan_value_counts.insert(2, 10);
+        stats.lower_bounds.remove(&2);
+        stats.upper_bounds.remove(&2);
+        assert!(!Expression::Lt(2, double(0.0)).can_match_file(&stats));
+        assert!(!Expression::Gt(2, double(0.0)).can_match_file(&stats));
+        assert!(!Expression::Eq(2, double(0.0)).can_match_file(&stats));
+        assert!(Expression::Eq(2, double(f64::NAN)).can_match_file(&stats));
+
+        // NaN bounds written by legacy writers can't be used.
+        let mut stats = self::stats();
+        stats.upper_bounds.insert(2, double(f64::NAN));
+        assert!(Expression::Gt(2, double(5.0)).can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_can_match_compound() {
+        let stats = stats();
+        let matched = Expression::Eq(1, long(15));
+        let not_matched = Expression::Eq(1, long(30));
+        assert!(matched
+            .clone()
+            .and(Expression::AlwaysTrue)
+            .can_match_file(&stats));
+        assert!(!matched
+            .clone()
+            .and(not_matched.clone())
+            .can_match_file(&stats));
+        assert!(matched.or(not_matched.clone()).can_match_file(&stats));
+        assert!(!not_matched
+            .or(Expression::AlwaysFalse)
+            .can_match_file(&stats));
+
+        // Empty files never match.
+        let stats = DataFileStats {
+            record_count: 0,
+            ..Default::default()
+        };
+        assert!(!Expression::AlwaysTrue.can_match_file(&stats));
+    }
+
+    #[test]
+    fn test_data_file_stats() -> Result<()> {
+        let schema = Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![Field {
+                id: 1,
+                name: "id".to_string(),
+                required: false,
+                field_type: Any::Primitive(Primitive::Long),
+                comment: None,
+            }],
+        };
+        let data_file = DataFile {
+            content: super::super::DataContentType::Data,
+            file_path: "a.parquet".to_string(),
+            file_format: super::super::DataFileFormat::Parquet,
+            partition: vec![],
+            record_count: 3,
+            file_size_in_bytes: 100,
+            column_sizes: None,
+            value_counts: Some(HashMap::from([(1, 3)])),
+            null_value_counts: Some(HashMap::from([(1, 0)])),
+            nan_value_counts: None,
+            distinct_counts: None,
+            lower_bounds: Some(HashMap::from([(1, long(1).to_bytes()), (2, vec![1])])),
+            upper_bounds: Some(HashMap::from([(1, long(3).to_bytes())])),
+            key_metadata: None,
+            split_offsets: vec![],
+            equality_ids: None,
+            sort_order_id: None,
+        };
+
+        let stats = DataFileStats::try_new(&data_file, &schema)?;
+        assert_eq!(
+            stats,
+            DataFileStats {
+                record_count: 3,
+                value_counts: HashMap::from([(1, 3)]),
+                null_value_counts: HashMap::from([(1, 0)]),
+                nan_value_counts: HashMap::new(),
+                lower_bounds: HashMap::from([(1, long(1))]),
+                upper_bounds: HashMap::from([(1, long(3))]),
+            }
+        );
+        assert!(!Expression::Gt(1, long(3)).can_match_file(&stats));
+
+        let mut data_file = data_file;
+        data_file.lower_bounds = Some(HashMap::from([(1, vec![1])]));
+        assert!(DataFileStats::try_new(&data_file, &schema).is_err());
+
+        Ok(())
+    }
+
+    fn partition_spec() -> PartitionSpec {
+        let field = |source_column_id, partition_field_id, transform| PartitionField {
+            source_column_id,
+            partition_field_id,
+            transform,
+            name: format!("p{partition_field_id}"),
+        };
+        PartitionSpec {
+            spec_id: 0,
+            fields: vec![
+                field(1, 1000, Transform::Truncate(10)),
+                field(5, 1001, Transform::Day),
+                field(5, 1002, Transform::Hour),
+                field(6, 1003, Transform::Month),
+                field(7, 1004, Transform::Identity),
+                field(8, 1005, Transform::Bucket(16)),
+                field(9, 1006, Transform::Truncate(2)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_project_identity() -> Result<()> {
+        let spec = partition_spec();
+        let v = Datum::String("us".to_string());
+        assert_eq!(
+            Expression::Eq(7, v.clone()).project(&spec)?,
+            Expression::Eq(1004, v.clone())
+        );
+        assert_eq!(
+            Expression::NotEq(7, v.clone()).project(&spec)?,
+            Expression::NotEq(1004, v)
+        );
+        assert_eq!(
+            Expression::IsNull(7).project(&spec)?,
+            Expression::IsNull(1004)
+        );
+
+        // Columns without partition fields are not projected.
+        assert_eq!(
+            Expression::Eq(2, double(1.0)).project(&spec)?,
+            Expression::AlwaysTrue
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_truncate() -> Result<()> {
+        let spec = partition_spec();
+        let cases = [
+            (Expression::Eq(1, long(15)), Expression::Eq(1000, long(10))),
+            (
+                Expression::Lt(1, long(20)),
+                Expression::LtEq(1000, long(10)),
+            ),
+            (
+                Expression::LtEq(1, long(20)),
+                Expression::LtEq(1000, long(20)),
+            ),
+            (
+                Expression::Gt(1, long(19)),
+                Expression::GtEq(1000, long(20)),
+            ),
+            (
+                Expression::GtEq(1, long(-1)),
+                Expression::GtEq(1000, long(-10)),
+            ),
+            (
+                Expression::In(1, vec![long(1), long(5), long(11)]),
+                Expression::In(1000, vec![long(0), long(10)]),
+            ),
+            (Expression::NotEq(1, long(15)), Expression::AlwaysTrue),
+            (Expression::NotNull(1), Expression::NotNull(1000)),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(expr.project(&spec)?, expected, "{expr:?}");
+        }
+
+        // Strict bounds of strings are kept inclusive.
+        let string = |v: &str| Datum::String(v.to_string());
+        assert_eq!(
+            Expression::Lt(9, string("abc")).project(&spec)?,
+            Expression::LtEq(1006, string("ab"))
+        );
+        assert_eq!(
+            Expression::Gt(9, string("abc")).project(&spec)?,
+            Expression::GtEq(1006, string("ab"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_dates() -> Result<()> {
+        let spec = partition_spec();
+        // 2023-01-01 00:00:00
+        let ts = Datum::Timestamp(1672531200000000);
+
+        assert_eq!(
+            Expression::GtEq(5, ts.clone()).project(&spec)?,
+            Expression::GtEq(1001, Datum::Date(19358))
+                .and(Expression::GtEq(1002, Datum::Int(19358 * 24)))
+        );
+        // The previous microsecond is in the previous day.
+        assert_eq!(
+            Expression::Lt(5, ts.clone()).project(&spec)?,
+            Expression::LtEq(1001, Datum::Date(19357))
+                .and(Expression::LtEq(1002, Datum::Int(19358 * 24 - 1)))
+        );
+        assert_eq!(
+            Expression::Eq(6, Datum::Date(19358)).project(&spec)?,
+            Expression::Eq(1003, Datum::Int(53 * 12))
+        );
+        assert_eq!(
+            Expression::Gt(6, Datum::Date(19357)).project(&spec)?,
+            Expression::GtEq(1003, Datum::Int(53 * 12))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_compound() -> Result<()> {
+        let spec = partition_spec();
+        let v = Datum::String("us".to_string());
+
+        // Only equality on bucket can be projected.
+        assert_eq!(
+            Expression::Eq(8, long(34)).project(&spec)?,
+            Expression::Eq(1005, Datum::Int(3))
+        );
+        assert_eq!(
+            Expression::In(8, vec![long(34), long(34)]).project(&spec)?,
+            Expression::In(1005, vec![Datum::Int(3)])
+        );
+        assert_eq!(
+            Expression::Lt(8, long(1)).project(&spec)?,
+            Expression::AlwaysTrue
+        );
+        assert_eq!(
+            Expression::Eq(7, v.clone())
+                .and(Expression::Lt(8, long(1)))
+                .project(&spec)?,
+            Expression::Eq(1004, v.clone())
+        );
+        assert_eq!(
+            Expression::Eq(7, v.clone())
+                .or(Expression::Lt(8, long(1)))
+                .project(&spec)?,
+            Expression::AlwaysTrue
+        );
+        assert_eq!(
+            Expression::Eq(7, v.clone())
+                .or(Expression::AlwaysFalse)
+                .project(&spec)?,
+            Expression::Eq(1004, v)
+        );
+
+        // Literals must match the type of source column.
+        assert!(Expression::Eq(6, long(1)).project(&spec).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_can_match_partition() {
+        let spec = partition_spec();
+        // Values of fields other than 1000 and 1001 are null.
+        let mut partition = vec![None; spec.fields.len()];
+        partition[0] = Some(long(10));
+        partition[1] = Some(Datum::Date(19358));
+
+        let cases = [
+            (Expression::Eq(1000, long(10)), true),
+            (Expression::Eq(1000, long(20)), false),
+            (Expression::NotEq(1000, long(10)), false),
+            (Expression::Lt(1000, long(10)), false),
+            (Expression::LtEq(1000, long(10)), true),
+            (Expression::Gt(1001, Datum::Date(19357)), true),
+            (Expression::GtEq(1001, Datum::Date(19359)), false),
+            (Expression::In(1000, vec![long(0), long(10)]), true),
+            (Expression::IsNull(1004), true),
+            (Expression::NotNull(1004), false),
+            (Expression::Eq(1004, Datum::String("us".to_string())), false),
+            // Unknown fields and literals of other types could match.
+            (Expression::Eq(2000, long(1)), true),
+            (Expression::Eq(1000, Datum::Int(1)), true),
+            (
+                Expression::Eq(1000, long(20)).or(Expression::Eq(1001, Datum::Date(19358))),
+                true,
+            ),
+            (
+                Expression::Eq(1000, long(10)).and(Expression::IsNull(1001)),
+                false,
+            ),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(
+                expr.can_match_partition(&spec, &partition),
+                expected,
+                "{expr:?}"
+            );
+        }
+    }
+}
@@ -1,9 +1,19 @@
 //! in_memory module provides the definition of iceberg in-memory data types.
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::str::FromStr;
+
+use ordered_float::OrderedFloat;
+
+use crate::Error;
+use crate::ErrorKind;
+use crate::Result;
 
 /// All data types are either primitives or nested types, which are maps, lists, or structs.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Any {
     /// A Primitive type
     Primitive(Primitive),
@@ -13,10 +23,16 @@ pub enum Any {
     List(List),
     /// A Map type
     Map(Map),
+    /// A type introduced by newer format versions which is not supported
+    /// yet, like `variant` and `timestamp_ns` of v3, kept by its name.
+    ///
+    /// Metadata and partitions on other columns still work, but values of
+    /// the type can't be read.
+    Unknown(String),
 }
 
 /// Primitive Types within a schema.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Primitive {
     /// True or False
     Boolean,
@@ -79,13 +95,13 @@ pub enum Primitive {
 /// - Fields may be any type.
 /// - Fields may have an optional comment or doc string.
 /// - Fields can have default values.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Struct {
     pub fields: Vec<Field>,
 }
 
 /// A Field is the field of a struct.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Field {
     /// An integer id that is unique in the table schema
     pub id: i32,
@@ -95,7 +111,8 @@ pub struct Field {
     pub required: bool,
     /// Field can have any type
     pub field_type: Any,
-    /// Fields can have any optional comment or doc string.
+    /// Fields can have any optional comment or doc string, which is stored
+    /// as `doc` in schema json.
     pub comment: Option<String>,
 }
 
@@ -104,7 +121,7 @@ pub struct Field {
 /// - The element field has an integer id that is unique in the table schema.
 /// - Elements can be either optional or required.
 /// - Element types may be any type.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct List {
     /// an integer id that is unique in the table schema.
     pub element_id: i32,
@@ -119,7 +136,7 @@ pub struct List {
 /// - Both the key field and value field each have an integer id that is unique in the table schema.
 /// - Map keys are required and map values can be either optional or required.
 /// - Both map keys and map values may be any type, including nested types.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Map {
     /// an integer id that is unique in the table schema
     pub key_id: i32,
@@ -138,7 +155,7 @@ pub struct Map {
 ///
 /// All data types are either primitives or nested types, which are maps, lists, or structs.
 /// A table schema is also a struct type.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Schema {
     /// The unique id for this schema.
     pub schema_id: i32,
@@ -149,6 +166,61 @@ pub struct Schema {
     pub fields: Vec<Field>,
 }
 
+impl Schema {
+    /// Find the field by dotted path of names like `address.city`, which
+    /// goes into nested structs.
+    pub fn field_by_path(&self, path: &str) -> Option<&Field> {
+        let mut fields = &self.fields;
+        let mut names = path.split('.').peekable();
+        while let Some(name) = names.next() {
+            let field = fields.iter().find(|v| v.name == name)?;
+            if names.peek().is_none() {
+                return Some(field);
+            }
+            match &field.field_type {
+                Any::Struct(v) => fields = &v.fields,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Find the field by dotted path of names like `field_by_path`, but
+    /// names are compared case-insensitively.
+    ///
+    /// Returns an error if a name matches more than one field, like `id`
+    /// and `ID` in the same struct.
+    pub fn field_by_name_case_insensitive(&self, path: &str) -> Result<Option<&Field>> {
+        let mut fields = &self.fields;
+        let mut names = path.split('.').peekable();
+        while let Some(name) = names.next() {
+            let name = name.to_lowercase();
+            let mut matched = fields.iter().filter(|v| v.name.to_lowercase() == name);
+            let Some(field) = matched.next() else {
+                return Ok(None);
+            };
+            if let Some(other) = matched.next() {
+                return Err(Error::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "name {name} is ambiguous, which matches both {} and {}",
+                        field.name, other.name
+                    ),
+                )
+                .with_context("path", path));
+            }
+            if names.peek().is_none() {
+                return Ok(Some(field));
+            }
+            match &field.field_type {
+                Any::Struct(v) => fields = &v.fields,
+                _ => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+}
+
 /// Transform is used to transform predicates to partition predicates,
 /// in addition to transforming data values.
 ///
@@ -161,7 +233,7 @@ pub struct Schema {
 /// predicates and partition predicates.
 ///
 /// All transforms must return `null` for a `null` input value.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Transform {
     /// Source value, unmodified
     ///
@@ -246,6 +318,185 @@ pub enum Transform {
     Void,
 }
 
+impl Transform {
+    /// Apply the transform on the source value, `None` means the result
+    /// is null.
+    pub fn apply(&self, v: &Datum) -> Result<Option<Datum>> {
+        const MICROS_PER_HOUR: i64 = 3_600_000_000;
+        const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+        let days = |v: &Datum| match v {
+            Datum::Date(v) => Some(*v as i64),
+            Datum::Timestamp(v) | Datum::Timestamptz(v) => Some(v.div_euclid(MICROS_PER_DAY)),
+            _ => None,
+        };
+
+        let result = match (self, v) {
+            (Transform::Identity, v) => Some(v.clone()),
+            (Transform::Void, _) => return Ok(None),
+            (Transform::Truncate(w), _) if *w <= 0 => None,
+            (Transform::Bucket(n), _) if *n <= 0 => None,
+            (Transform::Bucket(n), v) => {
+                bucket_hash(v).map(|hash| Datum::Int((hash & i32::MAX) % n))
+            }
+            (Transform::Truncate(w), Datum::Int(v)) => Some(Datum::Int(v - v.rem_euclid(*w))),
+            (Transform::Truncate(w), Datum::Long(v)) => {
+                Some(Datum::Long(v - v.rem_euclid(*w as i64)))
+            }
+            (Transform::Truncate(w), Datum::Decimal(v)) => {
+                Some(Datum::Decimal(v - v.rem_euclid(*w as i128)))
+            }
+            (Transform::Truncate(w), Datum::String(v)) => {
+                Some(Datum::String(v.chars().take(*w as usize).collect()))
+            }
+            (Transform::Truncate(w), Datum::Binary(v)) => {
+                Some(Datum::Binary(v.iter().take(*w as usize).copied().collect()))
+            }
+            (Transform::Year, v) => days(v).map(|v| Datum::Int(year_month_of_days(v).0 - 1970)),
+            (Transform::Month, v) => days(v).map(|v| {
+                let (year, month) = year_month_of_days(v);
+                Datum::Int((year - 1970) * 12 + month - 1)
+            }),
+            (Transform::Day, v) => days(v).map(|v| Datum::Date(v as i32)),
+            (Transform::Hour, Datum::Timestamp(v) | Datum::Timestamptz(v)) => {
+                Some(Datum::Int(v.div_euclid(MICROS_PER_HOUR) as i32))
+            }
+            _ => None,
+        };
+
+        result.map(Some).ok_or_else(|| {
+            Error::new(
+                ErrorKind::IcebergFeatureUnsupported,
+                "transform on the value is not supported",
+            )
+            .with_context("transform", format!("{self:?}"))
+            .with_context("value", format!("{v:?}"))
+        })
+    }
+}
+
+impl Transform {
+    /// Verify the hash of bucket transform against reference values
+    /// published in the iceberg spec.
+    ///
+    /// A mismatched hash puts rows into wrong buckets silently, which
+    /// breaks partition pruning of tables shared with other engines.
+    ///
+    /// Reference:
+    ///
+    /// - [Appendix B: 32-bit Hash Requirements](https://iceberg.apache.org/spec/#appendix-b-32-bit-hash-requirements)
+    pub fn verify_against_spec() -> Result<()> {
+        let uuid = uuid::Uuid::parse_str("f79c3e09-677c-4bbd-a479-3f349cb785e7")
+            .expect("uuid literal must be valid");
+        let cases = [
+            (Datum::Int(34), 2017239379),
+            (Datum::Long(34), 2017239379),
+            // 14.20
+            (Datum::Decimal(1420), -500754589),
+            // 2017-11-16
+            (Datum::Date(17486), -653330422),
+            // 22:31:08
+            (Datum::Time(81068000000), -662762989),
+            // 2017-11-16T22:31:08
+            (Datum::Timestamp(1510871468000000), -2047944441),
+            // 2017-11-16T14:31:08-08:00
+            (Datum::Timestamptz(1510871468000000), -2047944441),
+            (Datum::String("iceberg".to_string()), 1210000089),
+            (Datum::Uuid(uuid), 1488055340),
+            (Datum::Fixed(vec![0, 1, 2, 3]), -188683207),
+            (Datum::Binary(vec![0, 1, 2, 3]), -188683207),
+        ];
+
+        for (v, expected) in cases {
+            let hash = bucket_hash(&v);
+            if hash != Some(expected) {
+                return Err(Error::new(
+                    ErrorKind::Unexpected,
+                    "hash of bucket transform doesn't match the spec",
+                )
+                .with_context("value", format!("{v:?}"))
+                .with_context("expected", expected.to_string())
+                .with_context("actual", format!("{hash:?}")));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash the value for bucket transform, `None` if the type can't be
+/// bucketed.
+///
+/// Ints and dates are hashed as longs, so that promoting `int` to
+/// `long` doesn't change the bucket.
+fn bucket_hash(v: &Datum) -> Option<i32> {
+    let hash = match v {
+        Datum::Int(v) | Datum::Date(v) => murmur3_32(&(*v as i64).to_le_bytes()),
+        Datum::Long(v) | Datum::Time(v) | Datum::Timestamp(v) | Datum::Timestamptz(v) => {
+            murmur3_32(&v.to_le_bytes())
+        }
+        // Minimum bytes of two's-complement big-endian unscaled value.
+        Datum::Decimal(_) => murmur3_32(&v.to_bytes()),
+        Datum::String(v) => murmur3_32(v.as_bytes()),
+        Datum::Uuid(v) => murmur3_32(v.as_bytes()),
+        Datum::Fixed(v) | Datum::Binary(v) => murmur3_32(v),
+        Datum::Boolean(_) | Datum::Float(_) | Datum::Double(_) => return None,
+    };
+
+    Some(hash as i32)
+}
+
+/// 32-bit Murmur3 hash, x86 variant, seeded with 0.
+fn murmur3_32(bs: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut h: u32 = 0;
+    let mut chunks = bs.chunks_exact(4);
+    for chunk in &mut chunks {
+        let k = u32::from_le_bytes(chunk.try_into().expect("chunk must be 4 bytes"));
+        h = (h ^ mix(k))
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe6546b64);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let k = tail.iter().rev().fold(0u32, |k, b| (k << 8) | *b as u32);
+        h ^= mix(k);
+    }
+
+    h ^= bs.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85ebca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2ae35);
+    h ^= h >> 16;
+    h
+}
+
+/// Convert days from 1970-01-01 into the year and month (1 to 12) of
+/// civil calendar.
+///
+/// Reference:
+///
+/// - [chrono-Compatible Low-Level Date Algorithms](https://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+fn year_month_of_days(days: i64) -> (i32, i32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year as i32, month as i32)
+}
+
 /// Data files are stored in manifests with a tuple of partition values
 /// that are used in scans to filter out files that cannot contain records
 ///  that match the scan’s filter predicate.
@@ -255,7 +506,7 @@ pub enum Transform {
 /// as the partition spec is the same for the data files.)
 ///
 /// Tables are configured with a partition spec that defines how to produce a tuple of partition values from a record.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct PartitionSpec {
     /// The spec id.
     pub spec_id: i32,
@@ -263,8 +514,22 @@ pub struct PartitionSpec {
     pub fields: Vec<PartitionField>,
 }
 
+impl PartitionSpec {
+    /// Get partition fields derived from the given source column, in
+    /// the order of spec.
+    ///
+    /// A source column may be used by several partition fields, like
+    /// `day(ts)` and `bucket(16, ts)`.
+    pub fn fields_for_source(&self, source_id: i32) -> Vec<&PartitionField> {
+        self.fields
+            .iter()
+            .filter(|v| v.source_column_id == source_id)
+            .collect()
+    }
+}
+
 /// Field of the specified partition spec.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct PartitionField {
     /// A source column id from the table’s schema
     pub source_column_id: i32,
@@ -284,6 +549,45 @@ pub struct PartitionField {
     pub name: String,
 }
 
+impl PartitionField {
+    /// Get the type of partition values produced by the transform on the
+    /// source column in schema.
+    pub fn result_type(&self, schema: &Schema) -> Result<Primitive> {
+        let source_type = match find_field_type(&schema.fields, self.source_column_id) {
+            Some(Any::Primitive(v)) => *v,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    "source column of partition field is not a primitive column in schema",
+                )
+                .with_context("partition_field", &self.name)
+                .with_context("source_column_id", self.source_column_id.to_string()))
+            }
+        };
+
+        Ok(match self.transform {
+            Transform::Identity | Transform::Truncate(_) | Transform::Void => source_type,
+            Transform::Bucket(_) | Transform::Year | Transform::Month | Transform::Hour => {
+                Primitive::Int
+            }
+            Transform::Day => Primitive::Date,
+        })
+    }
+}
+
+/// Find the type of field by id, fields nested in structs are included.
+fn find_field_type(fields: &[Field], id: i32) -> Option<&Any> {
+    fields.iter().find_map(|v| {
+        if v.id == id {
+            return Some(&v.field_type);
+        }
+        match &v.field_type {
+            Any::Struct(s) => find_field_type(&s.fields, id),
+            _ => None,
+        }
+    })
+}
+
 /// Users can sort their data within partitions by columns to gain
 /// performance. The information on how the data is sorted can be declared
 /// per data or delete file, by a sort order.
@@ -292,7 +596,7 @@ pub struct PartitionField {
 /// - Sorting floating-point numbers should produce the following behavior:
 ///   `-NaN` < `-Infinity` < `-value` < `-0` < `0` < `value` < `Infinity`
 ///   < `NaN`
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct SortOrder {
     /// The sort order id of this SortOrder
     pub order_id: i32,
@@ -302,7 +606,7 @@ pub struct SortOrder {
 }
 
 /// Field of the specified sort order.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct SortField {
     /// A source column id from the table’s schema
     pub source_column_id: i32,
@@ -321,7 +625,7 @@ pub struct SortField {
 }
 
 /// sort direction, that can only be either `asc` or `desc`
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum SortDirection {
     ASC,
     DESC,
@@ -329,7 +633,7 @@ pub enum SortDirection {
 
 /// A null order that describes the order of null values when sorted.
 /// Can only be either nulls-first or nulls-last
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum NullOrder {
     First,
     Last,
@@ -428,8 +732,6 @@ pub struct ManifestList {
 /// Field summary for partition field in the spec.
 ///
 /// Each field in the list corresponds to a field in the manifest file’s partition spec.
-///
-/// TODO: add lower_bound and upper_bound support
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct FieldSummary {
     /// field: 509
@@ -441,6 +743,81 @@ pub struct FieldSummary {
     /// Whether the manifest contains at least one partition with a NaN
     /// value for the field
     pub contains_nan: Option<bool>,
+    /// field: 510
+    ///
+    /// Lower bound for the non-null, non-NaN values in the partition field,
+    /// or null if all values are null or NaN
+    ///
+    /// Reference:
+    ///
+    /// - [Binary single-value serialization](https://iceberg.apache.org/spec/#binary-single-value-serialization)
+    pub lower_bound: Option<Vec<u8>>,
+    /// field: 511
+    ///
+    /// Upper bound for the non-null, non-NaN values in the partition field,
+    /// or null if all values are null or NaN
+    pub upper_bound: Option<Vec<u8>>,
+}
+
+impl ManifestList {
+    /// Decode summaries of partition fields with typed bounds.
+    ///
+    /// `partition_spec` must be the spec of manifest identified by
+    /// `partition_spec_id`, and `schema` is used to decide types of
+    /// partition fields. `None` will be returned if summaries are absent.
+    pub fn partition_summaries(
+        &self,
+        partition_spec: &PartitionSpec,
+        schema: &Schema,
+    ) -> Result<Option<Vec<PartitionFieldSummary>>> {
+        let Some(partitions) = &self.partitions else {
+            return Ok(None);
+        };
+        if partitions.len() != partition_spec.fields.len() {
+            return Err(Error::new(
+                ErrorKind::IcebergDataInvalid,
+                "partition summaries don't match the partition spec",
+            )
+            .with_context("manifest_path", &self.manifest_path)
+            .with_context("expected", partition_spec.fields.len().to_string())
+            .with_context("actual", partitions.len().to_string()));
+        }
+
+        let mut summaries = Vec::with_capacity(partitions.len());
+        for (summary, field) in partitions.iter().zip(&partition_spec.fields) {
+            let typ = field.result_type(schema)?;
+            let decode = |v: &Option<Vec<u8>>| {
+                v.as_ref()
+                    .map(|bs| Datum::from_bytes(bs, &typ))
+                    .transpose()
+                    .map_err(|err| err.with_context("partition_field", &field.name))
+            };
+            summaries.push(PartitionFieldSummary {
+                contains_null: summary.contains_null,
+                contains_nan: summary.contains_nan,
+                lower_bound: decode(&summary.lower_bound)?,
+                upper_bound: decode(&summary.upper_bound)?,
+            });
+        }
+
+        Ok(Some(summaries))
+    }
+}
+
+/// Summary of partition field with typed bounds, decoded from
+/// [`FieldSummary`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PartitionFieldSummary {
+    /// Whether the manifest contains at least one partition with a null
+    /// value for the field.
+    pub contains_null: bool,
+    /// Whether the manifest contains at least one partition with a NaN
+    /// value for the field, `None` if unknown.
+    pub contains_nan: Option<bool>,
+    /// Lower bound for the non-null, non-NaN values in the partition field.
+    pub lower_bound: Option<Datum>,
+    /// Upper bound for the non-null, non-NaN values in the partition field.
+    pub upper_bound: Option<Datum>,
 }
 
 /// A manifest is an immutable Avro file that lists data files or delete
@@ -473,7 +850,30 @@ pub struct ManifestFile {
     pub data_file: DataFile,
 }
 
-/// FIXME: partition_spec is not parsed.
+impl ManifestFile {
+    /// Resolve the data sequence number of the entry read from the given
+    /// manifest.
+    ///
+    /// Null sequence numbers of added entries are inherited from the
+    /// manifest. Manifests written before a table is upgraded from v1 to
+    /// v2 have sequence number 0, so all their entries, no matter added or
+    /// existing, have sequence number 0 and are older than any v2 delete.
+    pub fn data_sequence_number(&self, manifest: &ManifestList) -> Result<i64> {
+        match self.sequence_number {
+            Some(v) => Ok(v),
+            None if self.status == ManifestStatus::Added || manifest.sequence_number == 0 => {
+                Ok(manifest.sequence_number)
+            }
+            None => Err(Error::new(
+                ErrorKind::IcebergDataInvalid,
+                "sequence number of entry which is not added can't be inherited",
+            )
+            .with_context("manifest", &manifest.manifest_path)
+            .with_context("file_path", &self.data_file.file_path)),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ManifestMetadata {
     /// The table schema at the time the manifest
@@ -481,12 +881,9 @@ pub struct ManifestMetadata {
     pub schema: Schema,
     /// ID of the schema used to write the manifest as a string
     pub schema_id: i32,
-
-    /// The partition spec used  to write the manifest
-    ///
-    /// FIXME: we should parse this field.
-    // pub partition_spec: Option<PartitionSpec>,
-
+    /// The partition spec used to write the manifest, which decides the
+    /// types of partition values of entries.
+    pub partition_spec: PartitionSpec,
     /// ID of the partition spec used to write the manifest as a string
     pub partition_spec_id: i32,
     /// Table format version number of the manifest as a string
@@ -496,14 +893,14 @@ pub struct ManifestMetadata {
 }
 
 /// Type of content files tracked by the manifest
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum ManifestContentType {
     Data,
     Deletes,
 }
 
 /// Used to track additions and deletions in ManifestEntry.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum ManifestStatus {
     /// Value: 0
     Existing,
@@ -536,8 +933,9 @@ pub struct DataFile {
     /// Partition data tuple, schema based on the partition spec output using
     /// partition field ids for the struct field ids
     ///
-    /// TODO: we need to support partition in data file.
-    pub partition: (),
+    /// Values are stored in the same order as the fields of partition spec
+    /// used to write the manifest, `None` means the value is null.
+    pub partition: Vec<Option<Datum>>,
     /// field id: 103
     ///
     /// Number of records in this file
@@ -637,9 +1035,231 @@ pub struct DataFile {
     pub sort_order_id: Option<i32>,
 }
 
+// `HashMap` doesn't implement `Hash`, so maps are hashed in key order.
+impl Hash for DataFile {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.content.hash(state);
+        self.file_path.hash(state);
+        self.file_format.hash(state);
+        self.partition.hash(state);
+        self.record_count.hash(state);
+        self.file_size_in_bytes.hash(state);
+        hash_map(self.column_sizes.as_ref(), state);
+        hash_map(self.value_counts.as_ref(), state);
+        hash_map(self.null_value_counts.as_ref(), state);
+        hash_map(self.nan_value_counts.as_ref(), state);
+        hash_map(self.distinct_counts.as_ref(), state);
+        hash_map(self.lower_bounds.as_ref(), state);
+        hash_map(self.upper_bounds.as_ref(), state);
+        self.key_metadata.hash(state);
+        self.split_offsets.hash(state);
+        self.equality_ids.hash(state);
+        self.sort_order_id.hash(state);
+    }
+}
+
+impl DataFile {
+    /// Number of null values of the column, `None` if unknown.
+    pub fn null_value_count(&self, field_id: i32) -> Option<i64> {
+        self.null_value_counts.as_ref()?.get(&field_id).copied()
+    }
+
+    /// Number of NaN values of the float or double column, `None` if
+    /// unknown.
+    pub fn nan_value_count(&self, field_id: i32) -> Option<i64> {
+        self.nan_value_counts.as_ref()?.get(&field_id).copied()
+    }
+
+    /// Size in bytes of the column on disk, `None` if unknown.
+    pub fn column_size(&self, field_id: i32) -> Option<i64> {
+        self.column_sizes.as_ref()?.get(&field_id).copied()
+    }
+
+    /// Check if the float or double column may contain NaN values.
+    ///
+    /// NaNs are not counted in bounds, so a file which may contain NaN
+    /// can't be pruned by the range of bounds. Unknown counts are treated
+    /// as containing NaN.
+    pub fn may_contain_nan(&self, field_id: i32) -> bool {
+        self.nan_value_count(field_id) != Some(0)
+    }
+
+    /// Lower bound of the column decoded as `typ`, `None` if unknown.
+    pub fn lower_bound(&self, field_id: i32, typ: &Primitive) -> Result<Option<Datum>> {
+        bound(self.lower_bounds.as_ref(), field_id, typ)
+    }
+
+    /// Upper bound of the column decoded as `typ`, `None` if unknown.
+    pub fn upper_bound(&self, field_id: i32, typ: &Primitive) -> Result<Option<Datum>> {
+        bound(self.upper_bounds.as_ref(), field_id, typ)
+    }
+}
+
+/// Decode the bound of column from bounds map.
+fn bound(
+    bounds: Option<&HashMap<i32, Vec<u8>>>,
+    field_id: i32,
+    typ: &Primitive,
+) -> Result<Option<Datum>> {
+    let Some(bs) = bounds.and_then(|v| v.get(&field_id)) else {
+        return Ok(None);
+    };
+
+    Datum::from_bytes(bs, typ)
+        .map(Some)
+        .map_err(|err| err.with_context("field_id", field_id.to_string()))
+}
+
+/// Datum is a single value of iceberg primitive types.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Datum {
+    /// True or False
+    Boolean(bool),
+    /// 32-bit signed integer
+    Int(i32),
+    /// 64-bit signed integer
+    Long(i64),
+    /// 32-bit IEEE 753 floating bit
+    Float(OrderedFloat<f32>),
+    /// 64-bit IEEE 753 floating bit
+    Double(OrderedFloat<f64>),
+    /// Unscaled value of fixed point decimal, the precision and scale are
+    /// decided by the type.
+    Decimal(i128),
+    /// Calendar date without timezone or time, days from 1970-01-01
+    Date(i32),
+    /// Time of day without date or timezone, microseconds from midnight
+    Time(i64),
+    /// Timestamp without timezone, microseconds from 1970-01-01 00:00:00
+    Timestamp(i64),
+    /// Timestamp with timezone, microseconds from 1970-01-01 00:00:00 UTC
+    Timestamptz(i64),
+    /// Arbitrary-length character sequences
+    String(String),
+    /// Universally Unique Identifiers
+    Uuid(uuid::Uuid),
+    /// Fixed length byte array
+    Fixed(Vec<u8>),
+    /// Arbitrary-length byte array.
+    Binary(Vec<u8>),
+}
+
+impl Datum {
+    /// Encode datum by binary single-value serialization.
+    ///
+    /// Reference:
+    ///
+    /// - [Binary single-value serialization](https://iceberg.apache.org/spec/#binary-single-value-serialization)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Datum::Boolean(v) => vec![*v as u8],
+            Datum::Int(v) | Datum::Date(v) => v.to_le_bytes().to_vec(),
+            Datum::Long(v) | Datum::Time(v) | Datum::Timestamp(v) | Datum::Timestamptz(v) => {
+                v.to_le_bytes().to_vec()
+            }
+            Datum::Float(v) => v.to_le_bytes().to_vec(),
+            Datum::Double(v) => v.to_le_bytes().to_vec(),
+            Datum::Decimal(v) => {
+                // Minimum number of bytes of two's-complement big-endian.
+                let bs = v.to_be_bytes();
+                let mut start = 0;
+                while start < bs.len() - 1
+                    && ((bs[start] == 0x00 && bs[start + 1] & 0x80 == 0)
+                        || (bs[start] == 0xff && bs[start + 1] & 0x80 != 0))
+                {
+                    start += 1;
+                }
+                bs[start..].to_vec()
+            }
+            Datum::String(v) => v.as_bytes().to_vec(),
+            Datum::Uuid(v) => v.as_bytes().to_vec(),
+            Datum::Fixed(v) | Datum::Binary(v) => v.clone(),
+        }
+    }
+
+    /// Decode datum of the given type from binary single-value
+    /// serialization.
+    pub fn from_bytes(bs: &[u8], typ: &Primitive) -> Result<Datum> {
+        fn invalid(typ: &Primitive, bs: &[u8]) -> Error {
+            Error::new(
+                ErrorKind::IcebergDataInvalid,
+                format!("bytes are invalid for type {typ}"),
+            )
+            .with_context("length", bs.len().to_string())
+        }
+        fn array<const N: usize>(typ: &Primitive, bs: &[u8]) -> Result<[u8; N]> {
+            bs.try_into().map_err(|_| invalid(typ, bs))
+        }
+
+        let v = match typ {
+            Primitive::Boolean => match bs {
+                [0] => Datum::Boolean(false),
+                [1] => Datum::Boolean(true),
+                _ => return Err(invalid(typ, bs)),
+            },
+            Primitive::Int => Datum::Int(i32::from_le_bytes(array(typ, bs)?)),
+            Primitive::Date => Datum::Date(i32::from_le_bytes(array(typ, bs)?)),
+            Primitive::Long => Datum::Long(i64::from_le_bytes(array(typ, bs)?)),
+            Primitive::Time => Datum::Time(i64::from_le_bytes(array(typ, bs)?)),
+            Primitive::Timestamp => Datum::Timestamp(i64::from_le_bytes(array(typ, bs)?)),
+            Primitive::Timestampz => Datum::Timestamptz(i64::from_le_bytes(array(typ, bs)?)),
+            Primitive::Float => Datum::Float(f32::from_le_bytes(array(typ, bs)?).into()),
+            Primitive::Double => Datum::Double(f64::from_le_bytes(array(typ, bs)?).into()),
+            Primitive::Decimal { .. } => {
+                if bs.is_empty() || bs.len() > 16 {
+                    return Err(invalid(typ, bs));
+                }
+                let fill = if bs[0] & 0x80 != 0 { 0xFF } else { 0 };
+                let mut buf = [fill; 16];
+                buf[16 - bs.len()..].copy_from_slice(bs);
+                Datum::Decimal(i128::from_be_bytes(buf))
+            }
+            Primitive::String => Datum::String(
+                String::from_utf8(bs.to_vec()).map_err(|err| invalid(typ, bs).set_source(err))?,
+            ),
+            Primitive::Uuid => Datum::Uuid(uuid::Uuid::from_bytes(array(typ, bs)?)),
+            Primitive::Fixed(size) => {
+                if bs.len() as u64 != *size {
+                    return Err(invalid(typ, bs));
+                }
+                Datum::Fixed(bs.to_vec())
+            }
+            Primitive::Binary => Datum::Binary(bs.to_vec()),
+        };
+
+        Ok(v)
+    }
+}
+
+/// Datums are only comparable with datums of the same type.
+///
+/// Strings are compared by unicode code points, and binaries are compared
+/// by unsigned bytes lexicographically.
+impl PartialOrd for Datum {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Datum::Boolean(a), Datum::Boolean(b)) => a.partial_cmp(b),
+            (Datum::Int(a), Datum::Int(b)) | (Datum::Date(a), Datum::Date(b)) => a.partial_cmp(b),
+            (Datum::Long(a), Datum::Long(b))
+            | (Datum::Time(a), Datum::Time(b))
+            | (Datum::Timestamp(a), Datum::Timestamp(b))
+            | (Datum::Timestamptz(a), Datum::Timestamptz(b)) => a.partial_cmp(b),
+            (Datum::Float(a), Datum::Float(b)) => a.partial_cmp(b),
+            (Datum::Double(a), Datum::Double(b)) => a.partial_cmp(b),
+            (Datum::Decimal(a), Datum::Decimal(b)) => a.partial_cmp(b),
+            (Datum::String(a), Datum::String(b)) => a.partial_cmp(b),
+            (Datum::Uuid(a), Datum::Uuid(b)) => a.partial_cmp(b),
+            (Datum::Fixed(a), Datum::Fixed(b)) | (Datum::Binary(a), Datum::Binary(b)) => {
+                a.partial_cmp(b)
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Type of content stored by the data file: data, equality deletes, or
 /// position deletes (all v1 files are data files)
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum DataContentType {
     /// value: 0
     Data,
@@ -650,13 +1270,41 @@ pub enum DataContentType {
 }
 
 /// Format of this data.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum DataFileFormat {
     Avro,
     Orc,
     Parquet,
 }
 
+impl FromStr for DataFileFormat {
+    type Err = Error;
+
+    /// Parse the format name case-insensitively, like `parquet` used by
+    /// table property `write.format.default`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "avro" => Ok(DataFileFormat::Avro),
+            "orc" => Ok(DataFileFormat::Orc),
+            "parquet" => Ok(DataFileFormat::Parquet),
+            v => Err(Error::new(
+                ErrorKind::IcebergFeatureUnsupported,
+                format!("data file format {:?} is not supported", v),
+            )),
+        }
+    }
+}
+
+/// Partition values of data files and delete files, along with the id of
+/// partition spec they belong to.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct PartitionValues {
+    /// Id of the partition spec.
+    pub spec_id: i32,
+    /// Values in the order of fields of the partition spec.
+    pub values: Vec<Option<Datum>>,
+}
+
 /// Snapshot of contains all data of a table at a point in time.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Snapshot {
@@ -709,11 +1357,130 @@ pub struct Snapshot {
     pub schema_id: Option<i64>,
 }
 
+impl Hash for Snapshot {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.snapshot_id.hash(state);
+        self.parent_snapshot_id.hash(state);
+        self.sequence_number.hash(state);
+        self.timestamp_ms.hash(state);
+        self.manifest_list.hash(state);
+        hash_map(Some(&self.summary), state);
+        self.schema_id.hash(state);
+    }
+}
+
+impl Snapshot {
+    /// Operation of the snapshot parsed from `operation` in summary, `None`
+    /// if it's absent, which is allowed for v1 tables.
+    pub fn operation(&self) -> Option<SnapshotOperation> {
+        self.summary
+            .get("operation")
+            .map(|v| SnapshotOperation::from(v.as_str()))
+    }
+
+    /// Parse the well-known fields of summary.
+    ///
+    /// Counts absent from summary, like in snapshots written by older
+    /// writers, are `None`. Returns an error if a count is not an integer.
+    pub fn typed_summary(&self) -> Result<SnapshotSummary> {
+        let count = |key: &str| -> Result<Option<i64>> {
+            self.summary
+                .get(key)
+                .map(|v| {
+                    v.parse().map_err(|err| {
+                        Error::new(
+                            ErrorKind::IcebergDataInvalid,
+                            format!("summary {key} of snapshot must be an integer"),
+                        )
+                        .with_context("snapshot_id", self.snapshot_id.to_string())
+                        .with_context("value", v)
+                        .set_source(err)
+                    })
+                })
+                .transpose()
+        };
+
+        Ok(SnapshotSummary {
+            operation: self.operation(),
+            total_data_files: count("total-data-files")?,
+            total_records: count("total-records")?,
+            total_files_size: count("total-files-size")?,
+            total_delete_files: count("total-delete-files")?,
+            total_position_deletes: count("total-position-deletes")?,
+            total_equality_deletes: count("total-equality-deletes")?,
+        })
+    }
+}
+
+/// Well-known fields of snapshot summary, see `Snapshot::typed_summary`.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct SnapshotSummary {
+    /// Operation of the snapshot.
+    pub operation: Option<SnapshotOperation>,
+    /// Number of live data files.
+    pub total_data_files: Option<i64>,
+    /// Number of records in live data files.
+    pub total_records: Option<i64>,
+    /// Size of live data files and delete files in bytes.
+    pub total_files_size: Option<i64>,
+    /// Number of live delete files.
+    pub total_delete_files: Option<i64>,
+    /// Number of records in live position delete files.
+    pub total_position_deletes: Option<i64>,
+    /// Number of records in live equality delete files.
+    pub total_equality_deletes: Option<i64>,
+}
+
+/// Operation of a snapshot, which is recorded as `operation` in its
+/// summary.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum SnapshotOperation {
+    /// Only data files were added and no files were removed.
+    Append,
+    /// Data and delete files were added and removed without changing table
+    /// data, like compaction.
+    Replace,
+    /// Data and delete files were added and removed in a logical overwrite.
+    Overwrite,
+    /// Data files were removed and their contents logically deleted, or
+    /// delete files were added to delete rows.
+    Delete,
+    /// Operations unknown to icelake, which are kept as is.
+    Other(String),
+}
+
+impl SnapshotOperation {
+    /// Name of the operation written in summary.
+    pub fn as_str(&self) -> &str {
+        match self {
+            SnapshotOperation::Append => "append",
+            SnapshotOperation::Replace => "replace",
+            SnapshotOperation::Overwrite => "overwrite",
+            SnapshotOperation::Delete => "delete",
+            SnapshotOperation::Other(v) => v,
+        }
+    }
+}
+
+impl From<&str> for SnapshotOperation {
+    fn from(v: &str) -> Self {
+        match v {
+            "append" => SnapshotOperation::Append,
+            "replace" => SnapshotOperation::Replace,
+            "overwrite" => SnapshotOperation::Overwrite,
+            "delete" => SnapshotOperation::Delete,
+            v => SnapshotOperation::Other(v.to_string()),
+        }
+    }
+}
+
 /// timestamp and snapshot ID pairs that encodes changes to the current
 /// snapshot for the table.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct SnapshotLog {
+    /// The time in milliseconds when the snapshot became current.
     pub timestamp_ms: i64,
+    /// The id of snapshot which became current.
     pub snapshot_id: i64,
 }
 
@@ -723,7 +1490,7 @@ pub struct SnapshotLog {
 /// references that can be updated by committing a new snapshot as the
 /// branch’s referenced snapshot using the Commit Conflict Resolution and
 /// Retry procedures.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct SnapshotReference {
     /// A reference’s snapshot ID. The tagged snapshot or latest snapshot of
     /// a branch.
@@ -756,7 +1523,7 @@ pub struct SnapshotReference {
 }
 
 /// Type of the reference
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum SnapshotReferenceType {
     Tag,
     Branch,
@@ -764,7 +1531,7 @@ pub enum SnapshotReferenceType {
 
 /// timestamp and metadata file location pairs that encodes changes to the
 /// previous metadata files for the table
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct MetadataLog {
     pub timestamp_ms: i64,
     pub metadata_file: String,
@@ -862,11 +1629,681 @@ pub struct TableMetadata {
     /// There is always a main branch reference pointing to the
     /// `current-snapshot-id` even if the refs map is null.
     pub refs: Option<HashMap<String, SnapshotReference>>,
+    /// Top level fields unknown to icelake, like the ones added by newer
+    /// versions of iceberg, keyed by name with values in json.
+    ///
+    /// They are written back as is, so that they are preserved across
+    /// commits. Unknown fields nested in snapshots, schemas and other
+    /// objects are not kept.
+    pub unknown_fields: BTreeMap<String, String>,
+    /// Vendor extensions extracted by hooks registered with
+    /// `register_table_metadata_hook`, keyed by names chosen by hooks.
+    ///
+    /// They are never written into metadata files, and not hashed since
+    /// they depend on the hooks registered in this process.
+    pub extensions: BTreeMap<String, String>,
+}
+
+impl Hash for TableMetadata {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.format_version.hash(state);
+        self.table_uuid.hash(state);
+        self.location.hash(state);
+        self.last_sequence_number.hash(state);
+        self.last_updated_ms.hash(state);
+        self.last_column_id.hash(state);
+        self.schemas.hash(state);
+        self.current_schema_id.hash(state);
+        self.partition_specs.hash(state);
+        self.default_spec_id.hash(state);
+        self.last_partition_id.hash(state);
+        hash_map(self.properties.as_ref(), state);
+        self.current_snapshot_id.hash(state);
+        self.snapshots.hash(state);
+        self.snapshot_log.hash(state);
+        self.metadata_log.hash(state);
+        self.sort_orders.hash(state);
+        self.default_sort_order_id.hash(state);
+        hash_map(self.refs.as_ref(), state);
+        self.unknown_fields.hash(state);
+    }
+}
+
+impl TableMetadata {
+    /// Current schema of the table.
+    pub fn current_schema(&self) -> Result<&Schema> {
+        self.schemas
+            .iter()
+            .find(|v| v.schema_id == self.current_schema_id)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    format!("current schema {} is not found", self.current_schema_id),
+                )
+            })
+    }
+
+    /// Default partition spec of the table.
+    pub fn current_partition_spec(&self) -> Result<&PartitionSpec> {
+        self.partition_specs
+            .iter()
+            .find(|v| v.spec_id == self.default_spec_id)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    format!(
+                        "default partition spec {} is not found",
+                        self.default_spec_id
+                    ),
+                )
+            })
+    }
 }
 
 /// Table format version number.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum TableFormatVersion {
     V1,
     V2,
+    /// Only metadata of v3 tables can be read, new types are parsed as
+    /// `Any::Unknown` and writing data is not supported yet.
+    V3,
+}
+
+/// Hash the optional map in key order, so that equal maps have the same
+/// hash regardless of the iteration order.
+fn hash_map<K: Ord + Hash, V: Hash, H: Hasher>(map: Option<&HashMap<K, V>>, state: &mut H) {
+    let entries = map.map(|map| {
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        entries
+    });
+    entries.hash(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datum_bytes_round_trip() {
+        let cases = [
+            (Datum::Boolean(true), Primitive::Boolean, vec![1]),
+            (Datum::Int(-2), Primitive::Int, vec![0xFE, 0xFF, 0xFF, 0xFF]),
+            (
+                Datum::Long(1),
+                Primitive::Long,
+                vec![1, 0, 0, 0, 0, 0, 0, 0],
+            ),
+            (
+                Datum::Float(1.0.into()),
+                Primitive::Float,
+                vec![0, 0, 0x80, 0x3F],
+            ),
+            (
+                Datum::Double((-2.0).into()),
+                Primitive::Double,
+                vec![0, 0, 0, 0, 0, 0, 0, 0xC0],
+            ),
+            (
+                Datum::Decimal(-1),
+                Primitive::Decimal {
+                    precision: 9,
+                    scale: 2,
+                },
+                vec![0xFF],
+            ),
+            (
+                Datum::Decimal(128),
+                Primitive::Decimal {
+                    precision: 9,
+                    scale: 2,
+                },
+                vec![0x00, 0x80],
+            ),
+            (
+                Datum::Decimal(-129),
+                Primitive::Decimal {
+                    precision: 9,
+                    scale: 2,
+                },
+                vec![0xFF, 0x7F],
+            ),
+            (Datum::Date(19524), Primitive::Date, vec![0x44, 0x4C, 0, 0]),
+            (
+                Datum::Timestamptz(1),
+                Primitive::Timestampz,
+                vec![1, 0, 0, 0, 0, 0, 0, 0],
+            ),
+            (
+                Datum::String("ab".to_string()),
+                Primitive::String,
+                b"ab".to_vec(),
+            ),
+            (
+                Datum::Uuid(uuid::Uuid::from_u128(1)),
+                Primitive::Uuid,
+                [vec![0; 15], vec![1]].concat(),
+            ),
+            (Datum::Fixed(vec![1, 2]), Primitive::Fixed(2), vec![1, 2]),
+            (Datum::Boolean(false), Primitive::Boolean, vec![0]),
+            (Datum::Int(i32::MIN), Primitive::Int, vec![0, 0, 0, 0x80]),
+            (Datum::Long(-1), Primitive::Long, vec![0xFF; 8]),
+            (
+                Datum::Double(f64::INFINITY.into()),
+                Primitive::Double,
+                vec![0, 0, 0, 0, 0, 0, 0xF0, 0x7F],
+            ),
+            (
+                Datum::Decimal(0),
+                Primitive::Decimal {
+                    precision: 9,
+                    scale: 2,
+                },
+                vec![0x00],
+            ),
+            (
+                Datum::Decimal(1234),
+                Primitive::Decimal {
+                    precision: 9,
+                    scale: 2,
+                },
+                vec![0x04, 0xD2],
+            ),
+            (
+                Datum::Decimal(i128::MAX),
+                Primitive::Decimal {
+                    precision: 38,
+                    scale: 0,
+                },
+                [vec![0x7F], vec![0xFF; 15]].concat(),
+            ),
+            (
+                Datum::Decimal(i128::MIN),
+                Primitive::Decimal {
+                    precision: 38,
+                    scale: 0,
+                },
+                [vec![0x80], vec![0x00; 15]].concat(),
+            ),
+            (
+                Datum::Time(3_600_000_000),
+                Primitive::Time,
+                vec![0x00, 0xA4, 0x93, 0xD6, 0, 0, 0, 0],
+            ),
+            (Datum::Timestamp(-1), Primitive::Timestamp, vec![0xFF; 8]),
+            (
+                Datum::String("冰湖".to_string()),
+                Primitive::String,
+                "冰湖".as_bytes().to_vec(),
+            ),
+            (Datum::String(String::new()), Primitive::String, vec![]),
+            (Datum::Binary(vec![]), Primitive::Binary, vec![]),
+            (
+                Datum::Binary(vec![0x00, 0xFF]),
+                Primitive::Binary,
+                vec![0x00, 0xFF],
+            ),
+        ];
+
+        for (datum, typ, bs) in cases {
+            assert_eq!(datum.to_bytes(), bs, "{datum:?}");
+            assert_eq!(Datum::from_bytes(&bs, &typ).unwrap(), datum);
+        }
+
+        assert!(Datum::from_bytes(&[1, 2], &Primitive::Int).is_err());
+        assert!(Datum::from_bytes(&[2], &Primitive::Boolean).is_err());
+        assert!(Datum::from_bytes(&[0xFF], &Primitive::String).is_err());
+        assert!(Datum::from_bytes(&[1; 4], &Primitive::Long).is_err());
+        assert!(Datum::from_bytes(&[1; 8], &Primitive::Float).is_err());
+        assert!(Datum::from_bytes(&[1; 15], &Primitive::Uuid).is_err());
+        assert!(Datum::from_bytes(&[1; 3], &Primitive::Fixed(2)).is_err());
+        let decimal = Primitive::Decimal {
+            precision: 9,
+            scale: 2,
+        };
+        assert!(Datum::from_bytes(&[], &decimal).is_err());
+        assert!(Datum::from_bytes(&[1; 17], &decimal).is_err());
+        // Redundant sign bytes are accepted.
+        assert_eq!(
+            Datum::from_bytes(&[0xFF, 0xFF, 0xFF], &decimal).unwrap(),
+            Datum::Decimal(-1)
+        );
+        // NaN is encoded as is.
+        let nan = Datum::Float(f32::NAN.into());
+        assert!(matches!(
+            Datum::from_bytes(&nan.to_bytes(), &Primitive::Float).unwrap(),
+            Datum::Float(v) if v.is_nan()
+        ));
+    }
+
+    #[test]
+    fn test_manifest_list_partition_summaries() {
+        let schema = Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![Field {
+                id: 1,
+                name: "ts".to_string(),
+                required: false,
+                field_type: Any::Primitive(Primitive::Timestamp),
+                comment: None,
+            }],
+        };
+        let field = |id: i32, transform: Transform| PartitionField {
+            source_column_id: 1,
+            partition_field_id: id,
+            transform,
+            name: format!("p{id}"),
+        };
+        let spec = PartitionSpec {
+            spec_id: 0,
+            fields: vec![
+                field(1000, Transform::Day),
+                field(1001, Transform::Identity),
+            ],
+        };
+        let mut manifest = ManifestList {
+            manifest_path: "m0.avro".to_string(),
+            manifest_length: 1,
+            partition_spec_id: 0,
+            content: ManifestContentType::Data,
+            sequence_number: 1,
+            min_sequence_number: 1,
+            added_snapshot_id: 1,
+            added_files_count: 1,
+            existing_files_count: 0,
+            deleted_files_count: 0,
+            added_rows_count: 1,
+            existing_rows_count: 0,
+            deleted_rows_count: 0,
+            partitions: None,
+            key_metadata: None,
+        };
+        assert_eq!(manifest.partition_summaries(&spec, &schema).unwrap(), None);
+
+        manifest.partitions = Some(vec![
+            FieldSummary {
+                contains_null: false,
+                contains_nan: None,
+                lower_bound: Some(Datum::Date(19000).to_bytes()),
+                upper_bound: Some(Datum::Date(19001).to_bytes()),
+            },
+            FieldSummary {
+                contains_null: true,
+                contains_nan: Some(false),
+                lower_bound: None,
+                upper_bound: None,
+            },
+        ]);
+        assert_eq!(
+            manifest.partition_summaries(&spec, &schema).unwrap(),
+            Some(vec![
+                PartitionFieldSummary {
+                    contains_null: false,
+                    contains_nan: None,
+                    lower_bound: Some(Datum::Date(19000)),
+                    upper_bound: Some(Datum::Date(19001)),
+                },
+                PartitionFieldSummary {
+                    contains_null: true,
+                    contains_nan: Some(false),
+                    lower_bound: None,
+                    upper_bound: None,
+                },
+            ])
+        );
+
+        // Summaries must match the partition spec.
+        let spec = PartitionSpec {
+            spec_id: 0,
+            fields: vec![field(1000, Transform::Day)],
+        };
+        assert!(manifest.partition_summaries(&spec, &schema).is_err());
+    }
+
+    #[test]
+    fn test_manifest_file_data_sequence_number() {
+        let manifest = |sequence_number| ManifestList {
+            manifest_path: "m0.avro".to_string(),
+            manifest_length: 1,
+            partition_spec_id: 0,
+            content: ManifestContentType::Data,
+            sequence_number,
+            min_sequence_number: sequence_number,
+            added_snapshot_id: 1,
+            added_files_count: 1,
+            existing_files_count: 0,
+            deleted_files_count: 0,
+            added_rows_count: 1,
+            existing_rows_count: 0,
+            deleted_rows_count: 0,
+            partitions: None,
+            key_metadata: None,
+        };
+        let entry = |status, sequence_number| ManifestFile {
+            status,
+            snapshot_id: None,
+            sequence_number,
+            file_sequence_number: None,
+            data_file: DataFile {
+                content: DataContentType::Data,
+                file_path: "data/a.parquet".to_string(),
+                file_format: DataFileFormat::Parquet,
+                partition: vec![],
+                record_count: 1,
+                file_size_in_bytes: 10,
+                column_sizes: None,
+                value_counts: None,
+                null_value_counts: None,
+                nan_value_counts: None,
+                distinct_counts: None,
+                lower_bounds: None,
+                upper_bounds: None,
+                key_metadata: None,
+                split_offsets: vec![],
+                equality_ids: None,
+                sort_order_id: None,
+            },
+        };
+
+        // Explicit sequence numbers are kept.
+        let v = entry(ManifestStatus::Existing, Some(3));
+        assert_eq!(v.data_sequence_number(&manifest(5)).unwrap(), 3);
+        // Added entries inherit from the manifest.
+        let v = entry(ManifestStatus::Added, None);
+        assert_eq!(v.data_sequence_number(&manifest(5)).unwrap(), 5);
+        // Entries of manifests written by v1 are always 0.
+        let v = entry(ManifestStatus::Existing, None);
+        assert_eq!(v.data_sequence_number(&manifest(0)).unwrap(), 0);
+        assert!(v.data_sequence_number(&manifest(5)).is_err());
+    }
+
+    #[test]
+    fn test_data_file_hash() {
+        use std::collections::HashSet;
+
+        let data_file = |counts: &[(i32, i64)]| DataFile {
+            content: DataContentType::Data,
+            file_path: "data/a.parquet".to_string(),
+            file_format: DataFileFormat::Parquet,
+            partition: vec![Some(Datum::Double(f64::NAN.into())), None],
+            record_count: 10,
+            file_size_in_bytes: 100,
+            column_sizes: None,
+            value_counts: Some(counts.iter().copied().collect()),
+            null_value_counts: None,
+            nan_value_counts: None,
+            distinct_counts: None,
+            lower_bounds: None,
+            upper_bounds: None,
+            key_metadata: None,
+            split_offsets: vec![],
+            equality_ids: None,
+            sort_order_id: None,
+        };
+
+        // Maps with different insertion order are the same.
+        let mut set = HashSet::new();
+        set.insert(data_file(&(0..64).map(|v| (v, 10)).collect::<Vec<_>>()));
+        set.insert(data_file(
+            &(0..64).rev().map(|v| (v, 10)).collect::<Vec<_>>(),
+        ));
+        assert_eq!(set.len(), 1);
+
+        set.insert(data_file(&[(1, 10)]));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_table_metadata_hash() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let path = format!(
+            "{}/testdata/simple_table/metadata/v2.metadata.json",
+            std::env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let metadata = crate::types::parse_table_metadata(
+            &std::fs::read(path).expect("read_file must succeed"),
+        )
+        .expect("parse_table_metadata must succeed");
+        let hash = |v: &TableMetadata| {
+            let mut hasher = DefaultHasher::new();
+            v.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        // Extensions don't change the hash.
+        let mut extended = metadata.clone();
+        extended
+            .extensions
+            .insert("vendor".to_string(), "{}".to_string());
+        assert_eq!(hash(&extended), hash(&metadata));
+
+        let mut changed = metadata.clone();
+        changed
+            .unknown_fields
+            .insert("future-field".to_string(), "{}".to_string());
+        assert_ne!(hash(&changed), hash(&metadata));
+    }
+
+    #[test]
+    fn test_transform_apply() {
+        // 1969-12-31 23:00:00
+        let ts = Datum::Timestamp(-3_600_000_000);
+        let cases = [
+            (Transform::Identity, Datum::Long(3), Some(Datum::Long(3))),
+            (Transform::Void, Datum::Long(3), None),
+            (
+                Transform::Truncate(10),
+                Datum::Int(-1),
+                Some(Datum::Int(-10)),
+            ),
+            (
+                Transform::Truncate(10),
+                Datum::Long(15),
+                Some(Datum::Long(10)),
+            ),
+            (
+                Transform::Truncate(50),
+                Datum::Decimal(1065),
+                Some(Datum::Decimal(1050)),
+            ),
+            (
+                Transform::Truncate(3),
+                Datum::String("iceberg".to_string()),
+                Some(Datum::String("ice".to_string())),
+            ),
+            (
+                Transform::Truncate(2),
+                Datum::Binary(vec![1, 2, 3]),
+                Some(Datum::Binary(vec![1, 2])),
+            ),
+            (Transform::Year, ts.clone(), Some(Datum::Int(-1))),
+            (Transform::Month, ts.clone(), Some(Datum::Int(-1))),
+            (Transform::Day, ts.clone(), Some(Datum::Date(-1))),
+            (Transform::Hour, ts, Some(Datum::Int(-1))),
+            // 2020-02-29
+            (Transform::Year, Datum::Date(18321), Some(Datum::Int(50))),
+            (Transform::Month, Datum::Date(18321), Some(Datum::Int(601))),
+            (Transform::Day, Datum::Date(18321), Some(Datum::Date(18321))),
+            // 2023-03-01 00:00:00 UTC
+            (
+                Transform::Month,
+                Datum::Timestamptz(1677628800000000),
+                Some(Datum::Int(638)),
+            ),
+            // 2017239379 % 16
+            (Transform::Bucket(16), Datum::Int(34), Some(Datum::Int(3))),
+            // (-188683207 & i32::MAX) % 16
+            (
+                Transform::Bucket(16),
+                Datum::Binary(vec![0, 1, 2, 3]),
+                Some(Datum::Int(9)),
+            ),
+        ];
+        for (transform, v, expected) in cases {
+            assert_eq!(
+                transform.apply(&v).unwrap(),
+                expected,
+                "{transform:?} {v:?}"
+            );
+        }
+
+        assert!(Transform::Hour.apply(&Datum::Date(1)).is_err());
+        assert!(Transform::Bucket(0).apply(&Datum::Int(1)).is_err());
+        assert!(Transform::Bucket(16)
+            .apply(&Datum::Double(1.0.into()))
+            .is_err());
+        assert!(Transform::Truncate(0).apply(&Datum::Int(1)).is_err());
+    }
+
+    #[test]
+    fn test_schema_field_by_path() {
+        let field = |id, name: &str, field_type| Field {
+            id,
+            name: name.to_string(),
+            required: false,
+            field_type,
+            comment: None,
+        };
+        let schema = Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![
+                field(1, "id", Any::Primitive(Primitive::Long)),
+                field(
+                    2,
+                    "address",
+                    Any::Struct(Struct {
+                        fields: vec![field(3, "city", Any::Primitive(Primitive::String))],
+                    }),
+                ),
+            ],
+        };
+
+        assert_eq!(schema.field_by_path("id").map(|v| v.id), Some(1));
+        assert_eq!(schema.field_by_path("address").map(|v| v.id), Some(2));
+        assert_eq!(schema.field_by_path("address.city").map(|v| v.id), Some(3));
+        assert_eq!(schema.field_by_path("address.zip"), None);
+        assert_eq!(schema.field_by_path("id.city"), None);
+        assert_eq!(schema.field_by_path(""), None);
+        assert_eq!(schema.field_by_path("ID"), None);
+
+        let find = |path| {
+            schema
+                .field_by_name_case_insensitive(path)
+                .unwrap()
+                .map(|v| v.id)
+        };
+        assert_eq!(find("ID"), Some(1));
+        assert_eq!(find("Address.CITY"), Some(3));
+        assert_eq!(find("address.zip"), None);
+        assert_eq!(find("ID.city"), None);
+
+        let mut schema = schema.clone();
+        schema
+            .fields
+            .push(field(4, "Id", Any::Primitive(Primitive::Long)));
+        assert!(schema.field_by_name_case_insensitive("id").is_err());
+        assert_eq!(schema.field_by_path("Id").map(|v| v.id), Some(4));
+    }
+
+    #[test]
+    fn test_partition_spec_fields_for_source() {
+        let field = |source_column_id, partition_field_id, transform, name: &str| PartitionField {
+            source_column_id,
+            partition_field_id,
+            transform,
+            name: name.to_string(),
+        };
+        let spec = PartitionSpec {
+            spec_id: 1,
+            fields: vec![
+                field(1, 1000, Transform::Day, "ts_day"),
+                field(2, 1001, Transform::Identity, "region"),
+                field(1, 1002, Transform::Bucket(16), "ts_bucket"),
+            ],
+        };
+
+        let names = |id| -> Vec<&str> {
+            spec.fields_for_source(id)
+                .into_iter()
+                .map(|v| v.name.as_str())
+                .collect()
+        };
+        assert_eq!(names(1), vec!["ts_day", "ts_bucket"]);
+        assert_eq!(names(2), vec!["region"]);
+        assert!(names(3).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_operation() {
+        let mut snapshot = Snapshot {
+            snapshot_id: 1,
+            parent_snapshot_id: None,
+            sequence_number: 0,
+            timestamp_ms: 0,
+            manifest_list: "snap-1.avro".to_string(),
+            summary: HashMap::new(),
+            schema_id: None,
+        };
+        assert_eq!(snapshot.operation(), None);
+
+        for (name, operation) in [
+            ("append", SnapshotOperation::Append),
+            ("replace", SnapshotOperation::Replace),
+            ("overwrite", SnapshotOperation::Overwrite),
+            ("delete", SnapshotOperation::Delete),
+            ("merge", SnapshotOperation::Other("merge".to_string())),
+        ] {
+            snapshot
+                .summary
+                .insert("operation".to_string(), name.to_string());
+            assert_eq!(snapshot.operation(), Some(operation.clone()));
+            assert_eq!(operation.as_str(), name);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_typed_summary() {
+        let mut snapshot = Snapshot {
+            snapshot_id: 1,
+            parent_snapshot_id: None,
+            sequence_number: 0,
+            timestamp_ms: 0,
+            manifest_list: "snap-1.avro".to_string(),
+            summary: HashMap::new(),
+            schema_id: None,
+        };
+        assert_eq!(
+            snapshot.typed_summary().unwrap(),
+            SnapshotSummary::default()
+        );
+
+        snapshot.summary = HashMap::from([
+            ("operation".to_string(), "delete".to_string()),
+            ("total-records".to_string(), "10".to_string()),
+            ("total-delete-files".to_string(), "2".to_string()),
+            ("total-equality-deletes".to_string(), "3".to_string()),
+        ]);
+        let summary = snapshot.typed_summary().unwrap();
+        assert_eq!(summary.operation, Some(SnapshotOperation::Delete));
+        assert_eq!(summary.total_records, Some(10));
+        assert_eq!(summary.total_delete_files, Some(2));
+        assert_eq!(summary.total_position_deletes, None);
+        assert_eq!(summary.total_equality_deletes, Some(3));
+
+        snapshot
+            .summary
+            .insert("total-position-deletes".to_string(), "x".to_string());
+        assert!(snapshot.typed_summary().is_err());
+    }
+
+    #[test]
+    fn test_transform_verify_against_spec() {
+        Transform::verify_against_spec().unwrap();
+    }
 }
@@ -4,8 +4,16 @@
 mod in_memory;
 pub use in_memory::*;
 
+mod expression;
+pub use expression::*;
+
+mod compatibility;
+pub use compatibility::*;
+
 mod on_disk;
 pub use on_disk::*;
 
 #[cfg(feature = "arrow_schema")]
 mod to_arrow;
+#[cfg(feature = "arrow_schema")]
+pub use to_arrow::ARROW_FIELD_DOC_KEY;
@@ -0,0 +1,408 @@
+use std::borrow::Cow;
+
+use crate::Error;
+use crate::ErrorKind;
+use crate::Result;
+
+/// Magic bytes of avro object container file.
+const AVRO_MAGIC: &[u8] = b"Obj\x01";
+
+/// Check that the bytes look like an avro object container file before
+/// handing them to the avro crate, so that files written in JSON by some
+/// writers are reported clearly instead of by a cryptic avro error.
+///
+/// `name` is the kind of file, like `manifest list`. Bytes which are
+/// neither avro nor JSON are left to the avro crate to report.
+pub(crate) fn check_avro_format(bs: &[u8], name: &str) -> Result<()> {
+    if bs.starts_with(AVRO_MAGIC) {
+        return Ok(());
+    }
+    match bs.iter().find(|v| !v.is_ascii_whitespace()) {
+        Some(b'{') | Some(b'[') => Err(Error::new(
+            ErrorKind::IcebergDataInvalid,
+            format!("expected Avro {name}, found JSON"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Normalize the schema in header of avro object container file, so
+/// that it can be parsed by the avro crate.
+///
+/// Java writes logical types on `fixed` in the flat form like
+/// `{"type": "fixed", "size": 16, "logicalType": "uuid"}`, which is
+/// rejected by the avro crate. Decimals are rewritten into the nested
+/// form, and other logical types on `fixed` are dropped, whose values
+/// will be cast by the iceberg type instead.
+///
+/// The bytes are returned as is if the schema doesn't need rewriting.
+pub(crate) fn normalize_avro_schema(bs: &[u8]) -> Result<Cow<'_, [u8]>> {
+    let Some(mut rest) = bs.strip_prefix(AVRO_MAGIC) else {
+        // Let the avro crate report the error.
+        return Ok(Cow::Borrowed(bs));
+    };
+
+    let mut metadata = read_metadata(&mut rest)?;
+
+    let Some((_, schema)) = metadata.iter_mut().find(|(k, _)| k == b"avro.schema") else {
+        return Ok(Cow::Borrowed(bs));
+    };
+    let mut json: serde_json::Value = serde_json::from_slice(schema).map_err(|err| {
+        Error::new(ErrorKind::IcebergDataInvalid, "avro schema is invalid").set_source(err)
+    })?;
+    if !normalize_fixed_logical_types(&mut json) {
+        return Ok(Cow::Borrowed(bs));
+    }
+    *schema = serde_json::to_vec(&json).map_err(|err| {
+        Error::new(ErrorKind::Unexpected, "serialize avro schema failed").set_source(err)
+    })?;
+
+    let mut normalized = AVRO_MAGIC.to_vec();
+    write_metadata(&mut normalized, &metadata);
+    // Sync marker and data blocks are kept.
+    normalized.extend_from_slice(rest);
+
+    Ok(Cow::Owned(normalized))
+}
+
+/// Replace the schema in header of avro object container file written by
+/// the avro crate, which drops custom attributes like `field-id` of the
+/// schema it's given.
+///
+/// `schema` must be the schema used to write the file, attributes added to
+/// it don't change the encoding of values. The bytes are returned as is if
+/// they have no header, like a file without any values.
+pub(crate) fn replace_avro_schema(bs: Vec<u8>, schema: &serde_json::Value) -> Result<Vec<u8>> {
+    let Some(mut rest) = bs.strip_prefix(AVRO_MAGIC) else {
+        return Ok(bs);
+    };
+
+    let mut metadata = read_metadata(&mut rest)?;
+    let schema = serde_json::to_vec(schema).map_err(|err| {
+        Error::new(ErrorKind::Unexpected, "serialize avro schema failed").set_source(err)
+    })?;
+    match metadata.iter_mut().find(|(k, _)| k == b"avro.schema") {
+        Some((_, v)) => *v = schema,
+        None => metadata.push((b"avro.schema".to_vec(), schema)),
+    }
+
+    let mut replaced = AVRO_MAGIC.to_vec();
+    write_metadata(&mut replaced, &metadata);
+    // Sync marker and data blocks are kept.
+    replaced.extend_from_slice(rest);
+
+    Ok(replaced)
+}
+
+/// Read the schema in header of avro object container file as is,
+/// including custom attributes dropped by the avro crate.
+#[cfg(test)]
+pub(crate) fn read_avro_schema(bs: &[u8]) -> Result<serde_json::Value> {
+    let mut rest = bs.strip_prefix(AVRO_MAGIC).ok_or_else(truncated)?;
+    let metadata = read_metadata(&mut rest)?;
+    let (_, schema) = metadata
+        .iter()
+        .find(|(k, _)| k == b"avro.schema")
+        .ok_or_else(truncated)?;
+    serde_json::from_slice(schema).map_err(|err| {
+        Error::new(ErrorKind::IcebergDataInvalid, "avro schema is invalid").set_source(err)
+    })
+}
+
+/// Collect `field-id` of all fields in avro schema by their dotted paths,
+/// like `data_file.file_path`.
+#[cfg(test)]
+pub(crate) fn avro_field_ids(
+    schema: &serde_json::Value,
+) -> std::collections::BTreeMap<String, i64> {
+    fn visit(
+        v: &serde_json::Value,
+        prefix: &str,
+        ids: &mut std::collections::BTreeMap<String, i64>,
+    ) {
+        match v {
+            serde_json::Value::Array(items) => {
+                items.iter().for_each(|v| visit(v, prefix, ids));
+            }
+            serde_json::Value::Object(obj) => {
+                if let Some(fields) = obj.get("fields").and_then(|v| v.as_array()) {
+                    for field in fields {
+                        let name = field["name"].as_str().unwrap_or_default();
+                        let path = match prefix {
+                            "" => name.to_string(),
+                            _ => format!("{prefix}.{name}"),
+                        };
+                        if let Some(id) = field.get("field-id").and_then(|v| v.as_i64()) {
+                            ids.insert(path.clone(), id);
+                        }
+                        visit(&field["type"], &path, ids);
+                    }
+                }
+                if let Some(id) = obj.get("element-id").and_then(|v| v.as_i64()) {
+                    ids.insert(format!("{prefix}.element"), id);
+                }
+                if let Some(items) = obj.get("items") {
+                    visit(items, prefix, ids);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut ids = std::collections::BTreeMap::new();
+    visit(schema, "", &mut ids);
+    ids
+}
+
+/// Rewrite logical types on `fixed` in schema, returns whether the
+/// schema is changed.
+fn normalize_fixed_logical_types(v: &mut serde_json::Value) -> bool {
+    match v {
+        serde_json::Value::Array(items) => items
+            .iter_mut()
+            .map(normalize_fixed_logical_types)
+            .fold(false, |changed, v| changed | v),
+        serde_json::Value::Object(obj) => {
+            if obj.get("type").and_then(|v| v.as_str()) == Some("fixed")
+                && obj.contains_key("logicalType")
+            {
+                let logical_type = obj.remove("logicalType");
+                if logical_type.as_ref().and_then(|v| v.as_str()) == Some("decimal") {
+                    let mut decimal = serde_json::Map::new();
+                    decimal.insert("logicalType".to_string(), "decimal".into());
+                    for key in ["precision", "scale"] {
+                        if let Some(v) = obj.remove(key) {
+                            decimal.insert(key.to_string(), v);
+                        }
+                    }
+                    let fixed = std::mem::take(obj);
+                    decimal.insert("type".to_string(), fixed.into());
+                    *obj = decimal;
+                }
+                return true;
+            }
+
+            obj.values_mut()
+                .map(normalize_fixed_logical_types)
+                .fold(false, |changed, v| changed | v)
+        }
+        _ => false,
+    }
+}
+
+/// Read the metadata map in header.
+fn read_metadata(bs: &mut &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut metadata = vec![];
+    loop {
+        let mut count = read_long(bs)?;
+        if count == 0 {
+            return Ok(metadata);
+        }
+        if count < 0 {
+            // Size of the block in bytes is not used.
+            read_long(bs)?;
+            count = -count;
+        }
+        for _ in 0..count {
+            let key = read_bytes(bs)?;
+            let value = read_bytes(bs)?;
+            metadata.push((key, value));
+        }
+    }
+}
+
+/// Write the metadata map in a single block.
+fn write_metadata(buf: &mut Vec<u8>, metadata: &[(Vec<u8>, Vec<u8>)]) {
+    write_long(buf, metadata.len() as i64);
+    for (key, value) in metadata {
+        write_long(buf, key.len() as i64);
+        buf.extend_from_slice(key);
+        write_long(buf, value.len() as i64);
+        buf.extend_from_slice(value);
+    }
+    write_long(buf, 0);
+}
+
+fn truncated() -> Error {
+    Error::new(ErrorKind::IcebergDataInvalid, "avro header is truncated")
+}
+
+/// Read a zigzag encoded long.
+fn read_long(bs: &mut &[u8]) -> Result<i64> {
+    let mut v: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (b, rest) = bs.split_first().ok_or_else(truncated)?;
+        *bs = rest;
+        v |= ((b & 0x7F) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok((v >> 1) as i64 ^ -((v & 1) as i64));
+        }
+    }
+
+    Err(Error::new(
+        ErrorKind::IcebergDataInvalid,
+        "avro long is overflow",
+    ))
+}
+
+/// Read length prefixed bytes.
+fn read_bytes(bs: &mut &[u8]) -> Result<Vec<u8>> {
+    let len = read_long(bs)?;
+    if len < 0 || len as usize > bs.len() {
+        return Err(truncated());
+    }
+    let (v, rest) = bs.split_at(len as usize);
+    *bs = rest;
+    Ok(v.to_vec())
+}
+
+/// Write a zigzag encoded long.
+fn write_long(buf: &mut Vec<u8>, v: i64) {
+    let mut v = ((v << 1) ^ (v >> 63)) as u64;
+    while v >= 0x80 {
+        buf.push((v as u8 & 0x7F) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+/// Rewrite the avro object container file with the given codec, user
+/// metadata and values are kept.
+#[cfg(test)]
+pub(crate) fn rewrite_with_codec(bs: &[u8], codec: apache_avro::Codec) -> Vec<u8> {
+    let reader = apache_avro::Reader::new(bs).expect("read avro must succeed");
+    let schema = reader.writer_schema().clone();
+    let metadata = reader.user_metadata().clone();
+    let values: Vec<_> = reader
+        .collect::<std::result::Result<_, _>>()
+        .expect("read avro values must succeed");
+
+    let mut writer = apache_avro::Writer::with_codec(&schema, vec![], codec);
+    for (k, v) in metadata {
+        writer
+            .add_user_metadata(k, v)
+            .expect("add user metadata must succeed");
+    }
+    writer.extend(values).expect("write avro must succeed");
+    writer.into_inner().expect("write avro must succeed")
+}
+
+#[cfg(test)]
+mod tests {
+    use apache_avro::Reader;
+    use apache_avro::Schema;
+    use apache_avro::Writer;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize_fixed_logical_types() {
+        let mut schema = json!({
+            "type": "record",
+            "name": "r102",
+            "fields": [
+                {
+                    "name": "amount",
+                    "type": ["null", {
+                        "type": "fixed",
+                        "name": "fixed",
+                        "size": 4,
+                        "logicalType": "decimal",
+                        "precision": 9,
+                        "scale": 2,
+                    }],
+                    "field-id": 1000,
+                },
+                {
+                    "name": "id",
+                    "type": {"type": "fixed", "name": "uuid_fixed", "size": 16, "logicalType": "uuid"},
+                    "field-id": 1001,
+                },
+                {"name": "ts", "type": {"type": "long", "logicalType": "timestamp-micros"}},
+            ],
+        });
+        assert!(Schema::parse(&schema).is_err());
+
+        assert!(normalize_fixed_logical_types(&mut schema));
+        assert_eq!(
+            schema["fields"][0]["type"][1],
+            json!({
+                "type": {"type": "fixed", "name": "fixed", "size": 4},
+                "logicalType": "decimal",
+                "precision": 9,
+                "scale": 2,
+            })
+        );
+        assert_eq!(
+            schema["fields"][1]["type"],
+            json!({"type": "fixed", "name": "uuid_fixed", "size": 16})
+        );
+        assert!(Schema::parse(&schema).is_ok());
+        assert!(!normalize_fixed_logical_types(&mut schema));
+    }
+
+    #[test]
+    fn test_normalize_avro_schema() -> anyhow::Result<()> {
+        let schema = Schema::parse(&json!({
+            "type": "record",
+            "name": "r",
+            "fields": [{"name": "id", "type": {"type": "fixed", "name": "f", "size": 2}}],
+        }))?;
+        let mut writer = Writer::new(&schema, vec![]);
+        writer.add_user_metadata("k".to_string(), "v")?;
+        writer.append(apache_avro::types::Value::Record(vec![(
+            "id".to_string(),
+            apache_avro::types::Value::Fixed(2, vec![1, 2]),
+        )]))?;
+        let bs = writer.into_inner()?;
+
+        // Schema without logical types on fixed is kept.
+        assert!(matches!(normalize_avro_schema(&bs)?, Cow::Borrowed(_)));
+
+        // Inject a logical type on fixed like Java does.
+        let mut rest = &bs[AVRO_MAGIC.len()..];
+        let mut metadata = read_metadata(&mut rest)?;
+        for (key, value) in &mut metadata {
+            if key == b"avro.schema" {
+                *value = serde_json::to_vec(&json!({
+                    "type": "record",
+                    "name": "r",
+                    "fields": [{
+                        "name": "id",
+                        "type": {"type": "fixed", "name": "f", "size": 2, "logicalType": "uuid"},
+                    }],
+                }))?;
+            }
+        }
+        let mut injected = AVRO_MAGIC.to_vec();
+        write_metadata(&mut injected, &metadata);
+        injected.extend_from_slice(rest);
+        assert!(Reader::new(&injected[..]).is_err());
+
+        let normalized = normalize_avro_schema(&injected)?;
+        let reader = Reader::new(&normalized[..])?;
+        assert_eq!(
+            reader.user_metadata().get("k").map(|v| v.as_slice()),
+            Some("v".as_bytes())
+        );
+        let values: Vec<_> = reader.collect::<std::result::Result<_, _>>()?;
+        assert_eq!(values.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_avro_format() {
+        assert!(check_avro_format(b"Obj\x01rest", "manifest list").is_ok());
+        // Unknown formats are left to the avro crate.
+        assert!(check_avro_format(b"PAR1", "manifest list").is_ok());
+
+        for bs in [&b"[{\"manifest_path\": \"a\"}]"[..], b"  \n{}"] {
+            let err = check_avro_format(bs, "manifest list").unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("expected Avro manifest list, found JSON"));
+        }
+    }
+}
@@ -1,25 +1,97 @@
 use std::collections::HashMap;
 
 use apache_avro::from_value;
+use apache_avro::types::Value;
+use apache_avro::Codec;
 use apache_avro::Reader;
+use apache_avro::Schema as AvroSchema;
+use apache_avro::Writer;
 use serde::Deserialize;
+use serde_json::json;
 use serde_with::serde_as;
 use serde_with::Bytes;
 
+use super::avro::normalize_avro_schema;
+use super::avro::replace_avro_schema;
 use super::parse_schema;
+use super::partition_spec::PartitionField;
+use super::schema::Schema;
 use crate::types;
 use crate::Error;
 use crate::ErrorKind;
 use crate::Result;
 
 /// Parse manifest file from avro bytes.
+///
+/// Partition values are decoded by the partition spec in the manifest
+/// header.
 pub fn parse_manifest_file(
     bs: &[u8],
 ) -> Result<(types::ManifestMetadata, Vec<types::ManifestFile>)> {
-    let reader = Reader::new(bs)?;
+    let mut entries = Vec::new();
+    let metadata = parse_manifest_file_inner(bs, None, |_, entry| {
+        entries.push(entry);
+        Ok(())
+    })?;
+    Ok((metadata, entries))
+}
+
+/// Parse manifest file from avro bytes.
+///
+/// Partition values are decoded by the spec in `partition_specs` (usually
+/// `TableMetadata.partition_specs`) identified by the `partition-spec-id`
+/// of the manifest, so that manifests written under older specs of a table
+/// with evolved partitioning are decoded correctly.
+pub fn parse_manifest_file_with_partition_specs(
+    bs: &[u8],
+    partition_specs: &[types::PartitionSpec],
+) -> Result<(types::ManifestMetadata, Vec<types::ManifestFile>)> {
+    let mut entries = Vec::new();
+    let metadata = parse_manifest_file_inner(bs, Some(partition_specs), |_, entry| {
+        entries.push(entry);
+        Ok(())
+    })?;
+    Ok((metadata, entries))
+}
+
+/// Parse manifest file from avro bytes, and pass entries to `visit` one
+/// by one as they are decoded instead of collecting them.
+///
+/// Partition values are decoded the same as
+/// `parse_manifest_file_with_partition_specs`. This is used to filter
+/// entries of huge manifests without buffering all of them.
+pub fn visit_manifest_file(
+    bs: &[u8],
+    partition_specs: &[types::PartitionSpec],
+    visit: impl FnMut(&types::ManifestMetadata, types::ManifestFile) -> Result<()>,
+) -> Result<types::ManifestMetadata> {
+    parse_manifest_file_inner(bs, Some(partition_specs), visit)
+}
+
+fn parse_manifest_file_inner(
+    bs: &[u8],
+    partition_specs: Option<&[types::PartitionSpec]>,
+    mut visit: impl FnMut(&types::ManifestMetadata, types::ManifestFile) -> Result<()>,
+) -> Result<types::ManifestMetadata> {
+    let bs = normalize_avro_schema(bs)?;
+    let reader = Reader::new(&bs[..])?;
 
     // Parse manifest metadata
     let meta = reader.user_metadata();
+    let partition_spec_id = match meta.get("partition-spec-id") {
+        None => 0,
+        Some(v) => {
+            let v = String::from_utf8_lossy(v);
+            v.parse().map_err(|err| {
+                Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    format!("partition-spec-id {:?} is invalid", v),
+                )
+                .set_source(err)
+            })?
+        }
+    };
+
     let metadata = types::ManifestMetadata {
         schema: parse_schema(meta.get("schema").ok_or_else(|| {
             Error::new(
@@ -42,24 +114,28 @@ pub fn parse_manifest_file(
                 }
             }
         },
-        partition_spec_id: {
-            match meta.get("partition-spec-id") {
-                None => 0,
-                Some(v) => {
-                    let v = String::from_utf8_lossy(v);
-                    v.parse().map_err(|err| {
-                        Error::new(
-                            ErrorKind::IcebergDataInvalid,
-                            format!("partition-spec-id {:?} is invalid", v),
-                        )
-                        .set_source(err)
-                    })?
-                }
+        partition_spec: {
+            let fields: Vec<PartitionField> =
+                serde_json::from_slice(meta.get("partition-spec").ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::IcebergDataInvalid,
+                        "partition-spec is required in manifest metadata but not found",
+                    )
+                })?)?;
+            let mut partition_fields = Vec::with_capacity(fields.len());
+            for field in fields {
+                partition_fields.push(field.try_into()?);
+            }
+            types::PartitionSpec {
+                spec_id: partition_spec_id,
+                fields: partition_fields,
             }
         },
+        partition_spec_id,
         format_version: {
+            // Manifests written by v1 writers may not have format-version.
             match meta.get("format-version") {
-                None => 0,
+                None => 1,
                 Some(v) => {
                     let v = String::from_utf8_lossy(v);
                     v.parse().map_err(|err| {
@@ -72,42 +148,643 @@ pub fn parse_manifest_file(
                 }
             }
         },
-        content: {
-            let c = match meta.get("partition-spec-id") {
-                None => 0,
-                Some(v) => {
-                    let v = String::from_utf8_lossy(v);
-                    v.parse().map_err(|err| {
-                        Error::new(
-                            ErrorKind::IcebergDataInvalid,
-                            format!("partition-spec-id {:?} is invalid", v),
-                        )
-                        .set_source(err)
-                    })?
-                }
-            };
-
-            match c {
-                0 => types::ManifestContentType::Data,
-                1 => types::ManifestContentType::Deletes,
-                _ => {
-                    return Err(Error::new(
-                        ErrorKind::IcebergDataInvalid,
-                        format!("content type {} is invalid", c),
-                    ))
-                }
+        content: match meta.get("content").map(|v| v.as_slice()) {
+            None | Some(b"data") => types::ManifestContentType::Data,
+            Some(b"deletes") => types::ManifestContentType::Deletes,
+            Some(v) => {
+                return Err(Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    format!("content type {:?} is invalid", String::from_utf8_lossy(v)),
+                ))
             }
         },
     };
 
+    let partition_spec = match partition_specs {
+        None => &metadata.partition_spec,
+        Some(specs) => specs
+            .iter()
+            .find(|v| v.spec_id == metadata.partition_spec_id)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    "partition spec of manifest is not found",
+                )
+                .with_context("partition_spec_id", metadata.partition_spec_id.to_string())
+            })?,
+    };
+    // The type of partition value is unknown if the source column is not
+    // found, it will be decoded by the avro type instead.
+    let partition_types: Vec<_> = partition_spec
+        .fields
+        .iter()
+        .map(|field| field.result_type(&metadata.schema).ok())
+        .collect();
+
     // Parse manifest entries
-    let mut entries = Vec::new();
     for value in reader {
-        let v = value?;
-        entries.push(from_value::<ManifestFile>(&v)?.try_into()?);
+        let mut v = value?;
+        // Partition values could be any avro types which are not supported
+        // by serde, take them out before deserializing.
+        let partition = take_partition(&mut v, &partition_types)?;
+        let mut entry: types::ManifestFile = from_value::<ManifestFile>(&v)?.try_into()?;
+        entry.data_file.partition = partition;
+        visit(&metadata, entry)?;
     }
 
-    Ok((metadata, entries))
+    Ok(metadata)
+}
+
+/// Serialize manifest file into avro bytes.
+///
+/// The manifest will be written in the format version of `metadata`, and
+/// `metadata.partition_spec` is used to decide the schema of partition
+/// values.
+///
+/// # Notes
+///
+/// The avro crate we are using doesn't keep custom attributes, so the
+/// schema in header is replaced after writing to keep the `field-id` of
+/// avro fields, which are used by readers like Java to resolve fields.
+pub fn serialize_manifest_file(
+    metadata: &types::ManifestMetadata,
+    entries: &[types::ManifestFile],
+) -> Result<Vec<u8>> {
+    let partition_spec = &metadata.partition_spec;
+    if metadata.partition_spec_id != partition_spec.spec_id {
+        return Err(Error::new(
+            ErrorKind::InvalidArgument,
+            "partition spec doesn't match manifest metadata",
+        )
+        .with_context("partition_spec_id", metadata.partition_spec_id.to_string())
+        .with_context("spec_id", partition_spec.spec_id.to_string()));
+    }
+
+    let mut partition_types = Vec::with_capacity(partition_spec.fields.len());
+    for field in &partition_spec.fields {
+        partition_types.push(field.result_type(&metadata.schema)?);
+    }
+
+    let schema_json =
+        manifest_entry_schema(metadata.format_version, partition_spec, &partition_types)?;
+    let schema = AvroSchema::parse(&schema_json)?;
+
+    let mut writer = Writer::with_codec(&schema, Vec::new(), Codec::Deflate);
+    writer.add_user_metadata(
+        "schema".to_string(),
+        serde_json::to_vec(&Schema::from(metadata.schema.clone()))?,
+    )?;
+    writer.add_user_metadata("schema-id".to_string(), metadata.schema_id.to_string())?;
+    writer.add_user_metadata(
+        "partition-spec".to_string(),
+        serde_json::to_vec(
+            &partition_spec
+                .fields
+                .iter()
+                .cloned()
+                .map(PartitionField::from)
+                .collect::<Vec<_>>(),
+        )?,
+    )?;
+    writer.add_user_metadata(
+        "partition-spec-id".to_string(),
+        metadata.partition_spec_id.to_string(),
+    )?;
+    writer.add_user_metadata(
+        "format-version".to_string(),
+        metadata.format_version.to_string(),
+    )?;
+    if metadata.format_version == 2 {
+        writer.add_user_metadata(
+            "content".to_string(),
+            match metadata.content {
+                types::ManifestContentType::Data => "data",
+                types::ManifestContentType::Deletes => "deletes",
+            },
+        )?;
+    }
+
+    for entry in entries {
+        writer.append(manifest_entry_value(
+            metadata.format_version,
+            partition_spec,
+            &partition_types,
+            entry,
+        )?)?;
+    }
+
+    replace_avro_schema(writer.into_inner()?, &schema_json)
+}
+
+/// Default block size in bytes written for v1 manifests.
+///
+/// `block_size_in_bytes` is required by v1 but not used by readers.
+const DEFAULT_BLOCK_SIZE_IN_BYTES: i64 = 64 * 1024 * 1024;
+
+/// Build the avro schema of manifest entry, with the `field-id` of spec.
+fn manifest_entry_schema(
+    format_version: i32,
+    partition_spec: &types::PartitionSpec,
+    partition_types: &[types::Primitive],
+) -> Result<serde_json::Value> {
+    if format_version != 1 && format_version != 2 {
+        return Err(Error::new(
+            ErrorKind::IcebergFeatureUnsupported,
+            format!("manifest format version {format_version} is not supported"),
+        ));
+    }
+
+    let required = |name: &str, id: i32, typ: serde_json::Value| json!({"name": name, "type": typ, "field-id": id});
+    let optional = |name: &str, id: i32, typ: serde_json::Value| json!({"name": name, "type": ["null", typ], "default": null, "field-id": id});
+    let map = |name: &str, id: i32, key_id: i32, value_id: i32, value_type: &str| {
+        optional(
+            name,
+            id,
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "record",
+                    "name": format!("k{key_id}_v{value_id}"),
+                    "fields": [
+                        required("key", key_id, json!("int")),
+                        required("value", value_id, json!(value_type)),
+                    ],
+                },
+                "logicalType": "map",
+            }),
+        )
+    };
+
+    let partition_fields: Vec<_> = partition_spec
+        .fields
+        .iter()
+        .zip(partition_types)
+        .map(|(field, typ)| {
+            optional(
+                &field.name,
+                field.partition_field_id,
+                avro_primitive_schema(field.partition_field_id, typ),
+            )
+        })
+        .collect();
+
+    let mut data_file_fields = vec![];
+    if format_version == 2 {
+        data_file_fields.push(required("content", 134, json!("int")));
+    }
+    data_file_fields.extend([
+        required("file_path", 100, json!("string")),
+        required("file_format", 101, json!("string")),
+        required(
+            "partition",
+            102,
+            json!({"type": "record", "name": "r102", "fields": partition_fields}),
+        ),
+        required("record_count", 103, json!("long")),
+        required("file_size_in_bytes", 104, json!("long")),
+    ]);
+    if format_version == 1 {
+        data_file_fields.push(required("block_size_in_bytes", 105, json!("long")));
+    }
+    data_file_fields.extend([
+        map("column_sizes", 108, 117, 118, "long"),
+        map("value_counts", 109, 119, 120, "long"),
+        map("null_value_counts", 110, 121, 122, "long"),
+        map("nan_value_counts", 137, 138, 139, "long"),
+        map("distinct_counts", 111, 123, 124, "long"),
+        map("lower_bounds", 125, 126, 127, "bytes"),
+        map("upper_bounds", 128, 129, 130, "bytes"),
+        optional("key_metadata", 131, json!("bytes")),
+        optional(
+            "split_offsets",
+            132,
+            json!({"type": "array", "items": "long", "element-id": 133}),
+        ),
+    ]);
+    if format_version == 2 {
+        data_file_fields.push(optional(
+            "equality_ids",
+            135,
+            json!({"type": "array", "items": "int", "element-id": 136}),
+        ));
+    }
+    data_file_fields.push(optional("sort_order_id", 140, json!("int")));
+
+    let mut fields = vec![
+        required("status", 0, json!("int")),
+        optional("snapshot_id", 1, json!("long")),
+    ];
+    if format_version == 2 {
+        fields.push(optional("sequence_number", 3, json!("long")));
+        fields.push(optional("file_sequence_number", 4, json!("long")));
+    }
+    fields.push(required(
+        "data_file",
+        2,
+        json!({"type": "record", "name": "r2", "fields": data_file_fields}),
+    ));
+
+    Ok(json!({"type": "record", "name": "manifest_entry", "fields": fields}))
+}
+
+/// Build the avro schema of a primitive partition type.
+///
+/// Named avro types are suffixed by the partition field id to make sure
+/// they are unique in the schema.
+fn avro_primitive_schema(field_id: i32, typ: &types::Primitive) -> serde_json::Value {
+    match typ {
+        types::Primitive::Boolean => json!("boolean"),
+        types::Primitive::Int => json!("int"),
+        types::Primitive::Long => json!("long"),
+        types::Primitive::Float => json!("float"),
+        types::Primitive::Double => json!("double"),
+        // The avro crate only accepts decimal on fixed in the nested form.
+        types::Primitive::Decimal { precision, scale } => json!({
+            "type": {
+                "type": "fixed",
+                "name": format!("decimal_{field_id}"),
+                "size": decimal_required_bytes(*precision),
+            },
+            "logicalType": "decimal",
+            "precision": precision,
+            "scale": scale,
+        }),
+        types::Primitive::Date => json!({"type": "int", "logicalType": "date"}),
+        types::Primitive::Time => json!({"type": "long", "logicalType": "time-micros"}),
+        types::Primitive::Timestamp | types::Primitive::Timestampz => {
+            json!({"type": "long", "logicalType": "timestamp-micros"})
+        }
+        types::Primitive::String => json!("string"),
+        types::Primitive::Uuid => json!({"type": "string", "logicalType": "uuid"}),
+        types::Primitive::Fixed(size) => json!({
+            "type": "fixed",
+            "name": format!("fixed_{field_id}"),
+            "size": size,
+        }),
+        types::Primitive::Binary => json!("bytes"),
+    }
+}
+
+/// Get the minimal bytes required to store a decimal of given precision.
+fn decimal_required_bytes(precision: u8) -> usize {
+    (1..=16)
+        .find(|n| 2f64.powi(8 * *n as i32 - 1) >= 10f64.powi(precision as i32))
+        .unwrap_or(16)
+}
+
+/// Build the avro value of manifest entry.
+fn manifest_entry_value(
+    format_version: i32,
+    partition_spec: &types::PartitionSpec,
+    partition_types: &[types::Primitive],
+    entry: &types::ManifestFile,
+) -> Result<Value> {
+    fn optional(v: Option<Value>) -> Value {
+        match v {
+            None => Value::Union(0, Box::new(Value::Null)),
+            Some(v) => Value::Union(1, Box::new(v)),
+        }
+    }
+    fn map<T: Clone>(v: &Option<HashMap<i32, T>>, f: impl Fn(T) -> Value) -> Value {
+        optional(v.as_ref().map(|m| {
+            let mut entries: Vec<_> = m.iter().collect();
+            entries.sort_by_key(|(k, _)| **k);
+            Value::Array(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| {
+                        Value::Record(vec![
+                            ("key".to_string(), Value::Int(*k)),
+                            ("value".to_string(), f(v.clone())),
+                        ])
+                    })
+                    .collect(),
+            )
+        }))
+    }
+
+    let data_file = &entry.data_file;
+    if data_file.partition.len() != partition_types.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidArgument,
+            "partition values of data file don't match partition spec",
+        )
+        .with_context("file_path", &data_file.file_path));
+    }
+    let mut partition = Vec::with_capacity(partition_types.len());
+    for ((value, typ), field) in data_file
+        .partition
+        .iter()
+        .zip(partition_types)
+        .zip(&partition_spec.fields)
+    {
+        let value = match value {
+            None => None,
+            Some(v) => Some(datum_to_avro(v, typ)?),
+        };
+        partition.push((field.name.clone(), optional(value)));
+    }
+
+    let mut data_file_fields = vec![];
+    if format_version == 2 {
+        data_file_fields.push((
+            "content",
+            Value::Int(match data_file.content {
+                types::DataContentType::Data => 0,
+                types::DataContentType::PostionDeletes => 1,
+                types::DataContentType::EqualityDeletes => 2,
+            }),
+        ));
+    }
+    data_file_fields.extend([
+        ("file_path", Value::String(data_file.file_path.clone())),
+        (
+            "file_format",
+            Value::String(
+                match data_file.file_format {
+                    types::DataFileFormat::Avro => "AVRO",
+                    types::DataFileFormat::Orc => "ORC",
+                    types::DataFileFormat::Parquet => "PARQUET",
+                }
+                .to_string(),
+            ),
+        ),
+        ("partition", Value::Record(partition)),
+        ("record_count", Value::Long(data_file.record_count)),
+        (
+            "file_size_in_bytes",
+            Value::Long(data_file.file_size_in_bytes),
+        ),
+    ]);
+    if format_version == 1 {
+        data_file_fields.push((
+            "block_size_in_bytes",
+            Value::Long(DEFAULT_BLOCK_SIZE_IN_BYTES),
+        ));
+    }
+    data_file_fields.extend([
+        ("column_sizes", map(&data_file.column_sizes, Value::Long)),
+        ("value_counts", map(&data_file.value_counts, Value::Long)),
+        (
+            "null_value_counts",
+            map(&data_file.null_value_counts, Value::Long),
+        ),
+        (
+            "nan_value_counts",
+            map(&data_file.nan_value_counts, Value::Long),
+        ),
+        (
+            "distinct_counts",
+            map(&data_file.distinct_counts, Value::Long),
+        ),
+        ("lower_bounds", map(&data_file.lower_bounds, Value::Bytes)),
+        ("upper_bounds", map(&data_file.upper_bounds, Value::Bytes)),
+        (
+            "key_metadata",
+            optional(data_file.key_metadata.clone().map(Value::Bytes)),
+        ),
+        (
+            "split_offsets",
+            optional(Some(Value::Array(
+                data_file
+                    .split_offsets
+                    .iter()
+                    .map(|v| Value::Long(*v))
+                    .collect(),
+            ))),
+        ),
+    ]);
+    if format_version == 2 {
+        data_file_fields.push((
+            "equality_ids",
+            optional(
+                data_file
+                    .equality_ids
+                    .as_ref()
+                    .map(|v| Value::Array(v.iter().map(|v| Value::Int(*v)).collect())),
+            ),
+        ));
+    }
+    data_file_fields.push((
+        "sort_order_id",
+        optional(data_file.sort_order_id.map(Value::Int)),
+    ));
+
+    let mut fields = vec![
+        (
+            "status",
+            Value::Int(match entry.status {
+                types::ManifestStatus::Existing => 0,
+                types::ManifestStatus::Added => 1,
+                types::ManifestStatus::Deleted => 2,
+            }),
+        ),
+        ("snapshot_id", optional(entry.snapshot_id.map(Value::Long))),
+    ];
+    if format_version == 2 {
+        fields.push((
+            "sequence_number",
+            optional(entry.sequence_number.map(Value::Long)),
+        ));
+        fields.push((
+            "file_sequence_number",
+            optional(entry.file_sequence_number.map(Value::Long)),
+        ));
+    }
+    fields.push((
+        "data_file",
+        Value::Record(
+            data_file_fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        ),
+    ));
+
+    Ok(Value::Record(
+        fields
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+    ))
+}
+
+/// Convert datum into avro value of the given type.
+fn datum_to_avro(datum: &types::Datum, typ: &types::Primitive) -> Result<Value> {
+    let v = match (datum, typ) {
+        (types::Datum::Boolean(v), types::Primitive::Boolean) => Value::Boolean(*v),
+        (types::Datum::Int(v), types::Primitive::Int) => Value::Int(*v),
+        (types::Datum::Long(v), types::Primitive::Long) => Value::Long(*v),
+        (types::Datum::Float(v), types::Primitive::Float) => Value::Float(v.0),
+        (types::Datum::Double(v), types::Primitive::Double) => Value::Double(v.0),
+        (types::Datum::Decimal(v), types::Primitive::Decimal { precision, .. }) => {
+            let size = decimal_required_bytes(*precision);
+            Value::Decimal(v.to_be_bytes()[16 - size..].to_vec().into())
+        }
+        (types::Datum::Date(v) | types::Datum::Int(v), types::Primitive::Date) => Value::Date(*v),
+        (types::Datum::Time(v), types::Primitive::Time) => Value::TimeMicros(*v),
+        (types::Datum::Timestamp(v), types::Primitive::Timestamp)
+        | (types::Datum::Timestamptz(v), types::Primitive::Timestampz) => {
+            Value::TimestampMicros(*v)
+        }
+        (types::Datum::String(v), types::Primitive::String) => Value::String(v.clone()),
+        (types::Datum::Uuid(v), types::Primitive::Uuid) => Value::Uuid(*v),
+        (types::Datum::Fixed(v), types::Primitive::Fixed(size)) if v.len() as u64 == *size => {
+            Value::Fixed(v.len(), v.clone())
+        }
+        (types::Datum::Binary(v), types::Primitive::Binary) => Value::Bytes(v.clone()),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("datum {datum:?} doesn't match type {typ}"),
+            ))
+        }
+    };
+
+    Ok(v)
+}
+
+/// Take partition values out of the avro value of manifest entry.
+///
+/// Values are decoded into the types of partition fields in order.
+fn take_partition(
+    entry: &mut Value,
+    partition_types: &[Option<types::Primitive>],
+) -> Result<Vec<Option<types::Datum>>> {
+    let data_file = match entry {
+        Value::Record(fields) => fields
+            .iter_mut()
+            .find(|(k, _)| k == "data_file")
+            .map(|(_, v)| v),
+        _ => None,
+    };
+    let Some(Value::Record(data_file)) = data_file else {
+        return Err(Error::new(
+            ErrorKind::IcebergDataInvalid,
+            "data_file is required in manifest entry but not found",
+        ));
+    };
+    let Some(idx) = data_file.iter().position(|(k, _)| k == "partition") else {
+        return Ok(vec![]);
+    };
+    let Value::Record(partition) = data_file.remove(idx).1 else {
+        return Err(Error::new(
+            ErrorKind::IcebergDataInvalid,
+            "partition of data file is not a record",
+        ));
+    };
+
+    if partition.len() != partition_types.len() {
+        return Err(Error::new(
+            ErrorKind::IcebergDataInvalid,
+            "partition of data file doesn't match the partition spec",
+        )
+        .with_context("expected", partition_types.len().to_string())
+        .with_context("actual", partition.len().to_string()));
+    }
+
+    let mut values = Vec::with_capacity(partition.len());
+    for ((name, value), typ) in partition.iter().zip(partition_types) {
+        let datum = avro_to_datum(value)
+            .and_then(|v| match (v, typ) {
+                (Some(v), Some(typ)) => cast_datum(v, typ).map(Some),
+                (v, _) => Ok(v),
+            })
+            .map_err(|err| err.with_context("partition", name))?;
+        values.push(datum);
+    }
+    Ok(values)
+}
+
+/// Cast datum decoded by avro type into the given type.
+///
+/// Writers may omit logical types of avro schema, for example, a date
+/// could be written as an int.
+fn cast_datum(datum: types::Datum, typ: &types::Primitive) -> Result<types::Datum> {
+    let v = match (datum, typ) {
+        (types::Datum::Int(v), types::Primitive::Date) => types::Datum::Date(v),
+        (types::Datum::Long(v), types::Primitive::Time) => types::Datum::Time(v),
+        (types::Datum::Long(v), types::Primitive::Timestamp) => types::Datum::Timestamp(v),
+        (types::Datum::Long(v) | types::Datum::Timestamp(v), types::Primitive::Timestampz) => {
+            types::Datum::Timestamptz(v)
+        }
+        (types::Datum::String(v), types::Primitive::Uuid) => {
+            types::Datum::Uuid(uuid::Uuid::parse_str(&v).map_err(|err| {
+                Error::new(ErrorKind::IcebergDataInvalid, "uuid value is invalid").set_source(err)
+            })?)
+        }
+        (types::Datum::Fixed(v), types::Primitive::Uuid) => {
+            types::Datum::Uuid(uuid::Uuid::from_slice(&v).map_err(|err| {
+                Error::new(ErrorKind::IcebergDataInvalid, "uuid value is invalid").set_source(err)
+            })?)
+        }
+        (types::Datum::Binary(v), types::Primitive::Fixed(_)) => types::Datum::Fixed(v),
+        (types::Datum::Fixed(v) | types::Datum::Binary(v), types::Primitive::Decimal { .. }) => {
+            types::Datum::Decimal(decimal_from_be_bytes(&v)?)
+        }
+        (datum, typ) => {
+            datum_to_avro(&datum, typ).map_err(|_| {
+                Error::new(
+                    ErrorKind::IcebergDataInvalid,
+                    format!("partition value {datum:?} doesn't match type {typ}"),
+                )
+            })?;
+            datum
+        }
+    };
+
+    Ok(v)
+}
+
+/// Decode decimal from its big-endian two's-complement bytes.
+fn decimal_from_be_bytes(bs: &[u8]) -> Result<i128> {
+    if bs.len() > 16 {
+        return Err(Error::new(
+            ErrorKind::IcebergDataInvalid,
+            "decimal value is out of range",
+        ));
+    }
+    let fill = if bs.first().is_some_and(|v| *v & 0x80 != 0) {
+        0xFF
+    } else {
+        0
+    };
+    let mut buf = [fill; 16];
+    buf[16 - bs.len()..].copy_from_slice(bs);
+    Ok(i128::from_be_bytes(buf))
+}
+
+/// Convert avro value into datum, `None` will be returned for null.
+fn avro_to_datum(v: &Value) -> Result<Option<types::Datum>> {
+    let v = match v {
+        Value::Null => return Ok(None),
+        Value::Union(_, v) => return avro_to_datum(v),
+        Value::Boolean(v) => types::Datum::Boolean(*v),
+        Value::Int(v) => types::Datum::Int(*v),
+        Value::Long(v) => types::Datum::Long(*v),
+        Value::Float(v) => types::Datum::Float((*v).into()),
+        Value::Double(v) => types::Datum::Double((*v).into()),
+        Value::Decimal(v) => {
+            types::Datum::Decimal(decimal_from_be_bytes(&Vec::<u8>::try_from(v)?)?)
+        }
+        Value::Date(v) => types::Datum::Date(*v),
+        Value::TimeMillis(v) => types::Datum::Time(*v as i64 * 1000),
+        Value::TimeMicros(v) => types::Datum::Time(*v),
+        Value::TimestampMillis(v) => types::Datum::Timestamp(*v * 1000),
+        Value::TimestampMicros(v) => types::Datum::Timestamp(*v),
+        Value::String(v) => types::Datum::String(v.clone()),
+        Value::Uuid(v) => types::Datum::Uuid(*v),
+        Value::Fixed(_, v) => types::Datum::Fixed(v.clone()),
+        Value::Bytes(v) => types::Datum::Binary(v.clone()),
+        v => {
+            return Err(Error::new(
+                ErrorKind::IcebergDataInvalid,
+                format!("partition value {v:?} is not a primitive value"),
+            ))
+        }
+    };
+
+    Ok(Some(v))
 }
 
 #[derive(Deserialize)]
@@ -153,7 +830,8 @@ struct DataFile {
     upper_bounds: Option<Vec<BytesEntry>>,
     #[serde_as(as = "Option<Bytes>")]
     key_metadata: Option<Vec<u8>>,
-    split_offsets: Vec<i64>,
+    #[serde(default)]
+    split_offsets: Option<Vec<i64>>,
     equality_ids: Option<Vec<i32>>,
     sort_order_id: Option<i32>,
 }
@@ -166,7 +844,8 @@ impl TryFrom<DataFile> for types::DataFile {
             content: parse_data_content_type(v.content)?,
             file_path: v.file_path,
             file_format: parse_data_file_format(&v.file_format)?,
-            partition: (),
+            // Partition values are parsed from the raw avro value.
+            partition: vec![],
             record_count: v.record_count,
             file_size_in_bytes: v.file_size_in_bytes,
             column_sizes: v.column_sizes.map(parse_i64_entry),
@@ -177,7 +856,7 @@ impl TryFrom<DataFile> for types::DataFile {
             lower_bounds: v.lower_bounds.map(parse_bytes_entry),
             upper_bounds: v.upper_bounds.map(parse_bytes_entry),
             key_metadata: v.key_metadata,
-            split_offsets: v.split_offsets,
+            split_offsets: v.split_offsets.unwrap_or_default(),
             equality_ids: v.equality_ids,
             sort_order_id: v.sort_order_id,
         })
@@ -241,15 +920,7 @@ fn parse_data_content_type(v: i32) -> Result<types::DataContentType> {
 }
 
 fn parse_data_file_format(s: &str) -> Result<types::DataFileFormat> {
-    match s.to_lowercase().as_str() {
-        "avro" => Ok(types::DataFileFormat::Avro),
-        "orc" => Ok(types::DataFileFormat::Orc),
-        "parquet" => Ok(types::DataFileFormat::Parquet),
-        v => Err(Error::new(
-            ErrorKind::IcebergFeatureUnsupported,
-            format!("data file format {:?} is not supported", v),
-        )),
-    }
+    s.parse()
 }
 
 #[cfg(test)]
@@ -259,6 +930,9 @@ mod tests {
     use apache_avro::from_value;
     use apache_avro::Reader;
 
+    use super::super::avro::avro_field_ids;
+    use super::super::avro::read_avro_schema;
+    use super::super::avro::rewrite_with_codec;
     use super::*;
     use anyhow::Result;
 
@@ -333,6 +1007,10 @@ mod tests {
                     ]
                 },
                 schema_id: 0,
+                partition_spec: types::PartitionSpec {
+                    spec_id: 0,
+                    fields: vec![],
+                },
                 partition_spec_id: 0,
                 format_version: 1,
                 content: types::ManifestContentType::Data
@@ -343,6 +1021,283 @@ mod tests {
         assert_eq!(manifests[0].data_file.file_path, "/opt/bitnami/spark/warehouse/db/table/data/00000-0-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
         assert_eq!(manifests[1].data_file.file_path, "/opt/bitnami/spark/warehouse/db/table/data/00001-1-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
         assert_eq!(manifests[2].data_file.file_path, "/opt/bitnami/spark/warehouse/db/table/data/00002-2-b8982382-f016-467a-84e4-5e6bbe0ff19a-00001.parquet");
+        assert!(manifests.iter().all(|v| v.data_file.partition.is_empty()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_manifest_with_codecs() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table/metadata/10d28031-9739-484c-92db-cdf2975cead4-m0.avro",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let bs = fs::read(path).expect("read_file must succeed");
+        let expected = parse_manifest_file(&bs)?;
+
+        for codec in [Codec::Null, Codec::Deflate, Codec::Snappy, Codec::Zstandard] {
+            let bs = rewrite_with_codec(&bs, codec);
+            assert_eq!(parse_manifest_file(&bs)?, expected, "{codec:?}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_manifest_file_with_partition_specs() -> Result<()> {
+        let path = format!(
+            "{}/testdata/spec_evolution_table/metadata/b5e19954-cc4e-457b-9725-9ae7e6ef4889-m0.avro",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let bs = fs::read(path).expect("read_file must succeed");
+
+        let region_spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: vec![types::PartitionField {
+                source_column_id: 3,
+                partition_field_id: 1000,
+                transform: types::Transform::Identity,
+                name: "region".to_string(),
+            }],
+        };
+        let day_spec = types::PartitionSpec {
+            spec_id: 1,
+            fields: vec![types::PartitionField {
+                source_column_id: 2,
+                partition_field_id: 1001,
+                transform: types::Transform::Day,
+                name: "ts_day".to_string(),
+            }],
+        };
+
+        // The manifest is written under spec 0 while the default spec is 1.
+        let (meta, entries) = parse_manifest_file_with_partition_specs(
+            &bs,
+            &[region_spec.clone(), day_spec.clone()],
+        )?;
+        assert_eq!(meta.partition_spec_id, 0);
+        assert_eq!(meta.partition_spec, region_spec);
+        assert_eq!(
+            entries[0].data_file.partition,
+            vec![Some(types::Datum::String("us".to_string()))]
+        );
+
+        // Decoding by another spec must fail instead of returning wrong values.
+        let wrong_spec = types::PartitionSpec {
+            spec_id: 0,
+            ..day_spec.clone()
+        };
+        assert!(parse_manifest_file_with_partition_specs(&bs, &[wrong_spec]).is_err());
+        assert!(parse_manifest_file_with_partition_specs(&bs, &[day_spec]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_manifest_file() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table/metadata/10d28031-9739-484c-92db-cdf2975cead4-m0.avro",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let bs = fs::read(path).expect("read_file must succeed");
+        let (meta, manifests) = parse_manifest_file(&bs)?;
+
+        for (format_version, content) in [
+            (1, types::ManifestContentType::Data),
+            (2, types::ManifestContentType::Data),
+            (2, types::ManifestContentType::Deletes),
+        ] {
+            let meta = types::ManifestMetadata {
+                format_version,
+                content,
+                ..meta.clone()
+            };
+            let bs = serialize_manifest_file(&meta, &manifests)?;
+            assert_eq!(parse_manifest_file(&bs)?, (meta, manifests.clone()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_manifest_file_field_ids() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table/metadata/10d28031-9739-484c-92db-cdf2975cead4-m0.avro",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let bs = fs::read(path).expect("read_file must succeed");
+        let (meta, manifests) = parse_manifest_file(&bs)?;
+        // Written by java in v1.
+        let expected = avro_field_ids(&read_avro_schema(&bs)?);
+
+        let bs = serialize_manifest_file(&meta, &manifests)?;
+        let ids = avro_field_ids(&read_avro_schema(&bs)?);
+        // `distinct_counts` is not written by java anymore.
+        assert_eq!(ids.get("data_file.distinct_counts"), Some(&111));
+        for (name, id) in &expected {
+            assert_eq!(ids.get(name), Some(id), "field id of {name} must match");
+        }
+
+        let meta = types::ManifestMetadata {
+            format_version: 2,
+            ..meta
+        };
+        let bs = serialize_manifest_file(&meta, &manifests)?;
+        let ids = avro_field_ids(&read_avro_schema(&bs)?);
+        assert_eq!(ids.get("sequence_number"), Some(&3));
+        assert_eq!(ids.get("file_sequence_number"), Some(&4));
+        assert_eq!(ids.get("data_file.content"), Some(&134));
+        assert_eq!(ids.get("data_file.equality_ids"), Some(&135));
+        assert_eq!(ids.get("data_file.equality_ids.element"), Some(&136));
+        for (name, id) in expected {
+            if name == "data_file.block_size_in_bytes" {
+                continue;
+            }
+            assert_eq!(ids.get(&name), Some(&id), "field id of {name} must match");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_manifest_file_with_partition() -> Result<()> {
+        let columns = [
+            types::Primitive::Boolean,
+            types::Primitive::Int,
+            types::Primitive::Long,
+            types::Primitive::Float,
+            types::Primitive::Double,
+            types::Primitive::Decimal {
+                precision: 10,
+                scale: 2,
+            },
+            types::Primitive::Date,
+            types::Primitive::Time,
+            types::Primitive::Timestamp,
+            types::Primitive::String,
+            types::Primitive::Uuid,
+            types::Primitive::Fixed(3),
+            types::Primitive::Binary,
+            types::Primitive::Timestamp,
+        ];
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: columns
+                .iter()
+                .enumerate()
+                .map(|(idx, typ)| types::Field {
+                    id: idx as i32 + 1,
+                    name: format!("c{idx}"),
+                    required: false,
+                    field_type: types::Any::Primitive(*typ),
+                    comment: None,
+                })
+                .collect(),
+        };
+        let partition_spec = types::PartitionSpec {
+            spec_id: 0,
+            fields: columns
+                .iter()
+                .enumerate()
+                .map(|(idx, _)| types::PartitionField {
+                    source_column_id: idx as i32 + 1,
+                    partition_field_id: 1000 + idx as i32,
+                    transform: if idx == columns.len() - 1 {
+                        types::Transform::Day
+                    } else {
+                        types::Transform::Identity
+                    },
+                    name: format!("p{idx}"),
+                })
+                .collect(),
+        };
+        let partition = vec![
+            Some(types::Datum::Boolean(true)),
+            Some(types::Datum::Int(-1)),
+            Some(types::Datum::Long(1 << 40)),
+            Some(types::Datum::Float(1.5.into())),
+            None,
+            Some(types::Datum::Decimal(-12345)),
+            Some(types::Datum::Date(19000)),
+            Some(types::Datum::Time(3_600_000_000)),
+            Some(types::Datum::Timestamp(1_686_911_671_713_000)),
+            Some(types::Datum::String("iceberg".to_string())),
+            Some(types::Datum::Uuid(uuid::Uuid::new_v4())),
+            Some(types::Datum::Fixed(vec![1, 2, 3])),
+            Some(types::Datum::Binary(vec![4, 5])),
+            Some(types::Datum::Date(19524)),
+        ];
+
+        let meta = types::ManifestMetadata {
+            schema,
+            schema_id: 0,
+            partition_spec,
+            partition_spec_id: 0,
+            format_version: 2,
+            content: types::ManifestContentType::Data,
+        };
+        let entry = types::ManifestFile {
+            status: types::ManifestStatus::Added,
+            snapshot_id: Some(1),
+            sequence_number: None,
+            file_sequence_number: None,
+            data_file: types::DataFile {
+                content: types::DataContentType::Data,
+                file_path: "s3://bucket/tbl/data/a.parquet".to_string(),
+                file_format: types::DataFileFormat::Parquet,
+                partition,
+                record_count: 1,
+                file_size_in_bytes: 10,
+                column_sizes: Some(HashMap::from([(1, 5)])),
+                value_counts: Some(HashMap::from([(1, 1)])),
+                null_value_counts: Some(HashMap::from([(1, 0)])),
+                nan_value_counts: None,
+                distinct_counts: None,
+                lower_bounds: Some(HashMap::from([(1, vec![1, 0, 0, 0])])),
+                upper_bounds: Some(HashMap::from([(1, vec![2, 0, 0, 0])])),
+                key_metadata: None,
+                split_offsets: vec![4],
+                equality_ids: None,
+                sort_order_id: Some(0),
+            },
+        };
+
+        let bs = serialize_manifest_file(&meta, std::slice::from_ref(&entry))?;
+        assert_eq!(
+            parse_manifest_file(&bs)?,
+            (meta.clone(), vec![entry.clone()])
+        );
+        // Partition fields are written with their partition field ids.
+        let ids = avro_field_ids(&read_avro_schema(&bs)?);
+        for (idx, field) in meta.partition_spec.fields.iter().enumerate() {
+            assert_eq!(
+                ids.get(&format!("data_file.partition.p{idx}")),
+                Some(&(field.partition_field_id as i64))
+            );
+        }
+
+        // Partition values must match the partition spec.
+        let mut invalid = entry.clone();
+        invalid.data_file.partition[0] = Some(types::Datum::Int(1));
+        assert!(serialize_manifest_file(&meta, &[invalid]).is_err());
+
+        // Partition spec must match the partition spec id.
+        let invalid = types::ManifestMetadata {
+            partition_spec_id: 1,
+            ..meta
+        };
+        assert!(serialize_manifest_file(&invalid, &[entry]).is_err());
 
         Ok(())
     }
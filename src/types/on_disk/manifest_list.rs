@@ -1,26 +1,268 @@
 use serde::Deserialize;
+use serde_json::json;
+use serde_with::serde_as;
+use serde_with::Bytes;
 
+use super::avro::check_avro_format;
+use super::avro::replace_avro_schema;
 use crate::types;
 use crate::Error;
 use crate::ErrorKind;
 use crate::Result;
 use apache_avro::from_value;
+use apache_avro::types::Value;
+use apache_avro::Codec;
 use apache_avro::Reader;
+use apache_avro::Schema as AvroSchema;
+use apache_avro::Writer;
 
-/// Parse manifest list from json bytes.
+/// Parse manifest list from avro bytes.
 ///
-/// QUESTION: Will we have more than one manifest list in a single file?
-pub fn parse_manifest_list(bs: &[u8]) -> Result<types::ManifestList> {
-    let mut reader = Reader::new(bs)?;
+/// A manifest list contains one entry for every manifest file of the
+/// snapshot. Manifest lists written in JSON are rejected with a clear
+/// error, since the spec requires avro.
+pub fn parse_manifest_list(bs: &[u8]) -> Result<Vec<types::ManifestList>> {
+    check_avro_format(bs, "manifest list")?;
+    let reader = Reader::new(bs)?;
 
     // Parse manifest entries
-    let value = reader
-        .next()
-        .ok_or_else(|| Error::new(ErrorKind::IcebergDataInvalid, "manifest list is empty"))??;
+    let mut entries = Vec::new();
+    for value in reader {
+        let v = value?;
+        entries.push(from_value::<ManifestList>(&v)?.try_into()?);
+    }
+
+    Ok(entries)
+}
+
+/// Serialize manifest list of the given snapshot into avro bytes.
+///
+/// Like manifest files, avro fields are written with the `field-id` of
+/// the spec.
+pub fn serialize_manifest_list(
+    format_version: types::TableFormatVersion,
+    snapshot: &types::Snapshot,
+    entries: &[types::ManifestList],
+) -> Result<Vec<u8>> {
+    if format_version == types::TableFormatVersion::V3 {
+        return Err(Error::new(
+            ErrorKind::IcebergFeatureUnsupported,
+            "writing v3 manifest list is not supported yet",
+        ));
+    }
+    let schema_json = manifest_list_schema(format_version);
+    let schema = AvroSchema::parse(&schema_json)?;
+
+    let mut writer = Writer::with_codec(&schema, Vec::new(), Codec::Deflate);
+    writer.add_user_metadata("snapshot-id".to_string(), snapshot.snapshot_id.to_string())?;
+    if let Some(v) = snapshot.parent_snapshot_id {
+        writer.add_user_metadata("parent-snapshot-id".to_string(), v.to_string())?;
+    }
+    match format_version {
+        types::TableFormatVersion::V1 => {
+            writer.add_user_metadata("format-version".to_string(), "1")?;
+        }
+        types::TableFormatVersion::V2 => {
+            writer.add_user_metadata(
+                "sequence-number".to_string(),
+                snapshot.sequence_number.to_string(),
+            )?;
+            writer.add_user_metadata("format-version".to_string(), "2")?;
+        }
+        types::TableFormatVersion::V3 => unreachable!("v3 is rejected above"),
+    }
+
+    for entry in entries {
+        writer.append(manifest_list_value(format_version, entry))?;
+    }
+
+    replace_avro_schema(writer.into_inner()?, &schema_json)
+}
+
+/// Build the avro schema of manifest list, with the `field-id` of spec.
+fn manifest_list_schema(format_version: types::TableFormatVersion) -> serde_json::Value {
+    let required = |name: &str, id: i32, typ: serde_json::Value| json!({"name": name, "type": typ, "field-id": id});
+    let optional = |name: &str, id: i32, typ: serde_json::Value| json!({"name": name, "type": ["null", typ], "default": null, "field-id": id});
+    let partitions = optional(
+        "partitions",
+        507,
+        json!({
+            "type": "array",
+            "items": {
+                "type": "record",
+                "name": "r508",
+                "fields": [
+                    required("contains_null", 509, json!("boolean")),
+                    optional("contains_nan", 518, json!("boolean")),
+                    optional("lower_bound", 510, json!("bytes")),
+                    optional("upper_bound", 511, json!("bytes")),
+                ],
+            },
+            "element-id": 508,
+        }),
+    );
+
+    let fields = match format_version {
+        types::TableFormatVersion::V1 => vec![
+            required("manifest_path", 500, json!("string")),
+            required("manifest_length", 501, json!("long")),
+            required("partition_spec_id", 502, json!("int")),
+            optional("added_snapshot_id", 503, json!("long")),
+            optional("added_data_files_count", 504, json!("int")),
+            optional("existing_data_files_count", 505, json!("int")),
+            optional("deleted_data_files_count", 506, json!("int")),
+            partitions,
+            optional("added_rows_count", 512, json!("long")),
+            optional("existing_rows_count", 513, json!("long")),
+            optional("deleted_rows_count", 514, json!("long")),
+        ],
+        types::TableFormatVersion::V2 => vec![
+            required("manifest_path", 500, json!("string")),
+            required("manifest_length", 501, json!("long")),
+            required("partition_spec_id", 502, json!("int")),
+            required("content", 517, json!("int")),
+            required("sequence_number", 515, json!("long")),
+            required("min_sequence_number", 516, json!("long")),
+            required("added_snapshot_id", 503, json!("long")),
+            required("added_data_files_count", 504, json!("int")),
+            required("existing_data_files_count", 505, json!("int")),
+            required("deleted_data_files_count", 506, json!("int")),
+            required("added_rows_count", 512, json!("long")),
+            required("existing_rows_count", 513, json!("long")),
+            required("deleted_rows_count", 514, json!("long")),
+            partitions,
+            optional("key_metadata", 519, json!("bytes")),
+        ],
+        types::TableFormatVersion::V3 => unreachable!("v3 is rejected by serialize_manifest_list"),
+    };
+
+    json!({"type": "record", "name": "manifest_file", "fields": fields})
+}
 
-    from_value::<ManifestList>(&value)?.try_into()
+/// Build the avro value of manifest list entry.
+fn manifest_list_value(
+    format_version: types::TableFormatVersion,
+    entry: &types::ManifestList,
+) -> Value {
+    fn optional(v: Option<Value>) -> Value {
+        match v {
+            None => Value::Union(0, Box::new(Value::Null)),
+            Some(v) => Value::Union(1, Box::new(v)),
+        }
+    }
+
+    let partitions = optional(entry.partitions.as_ref().map(|v| {
+        Value::Array(
+            v.iter()
+                .map(|v| {
+                    Value::Record(vec![
+                        ("contains_null".to_string(), Value::Boolean(v.contains_null)),
+                        (
+                            "contains_nan".to_string(),
+                            optional(v.contains_nan.map(Value::Boolean)),
+                        ),
+                        (
+                            "lower_bound".to_string(),
+                            optional(v.lower_bound.clone().map(Value::Bytes)),
+                        ),
+                        (
+                            "upper_bound".to_string(),
+                            optional(v.upper_bound.clone().map(Value::Bytes)),
+                        ),
+                    ])
+                })
+                .collect(),
+        )
+    }));
+
+    let fields = match format_version {
+        types::TableFormatVersion::V1 => vec![
+            ("manifest_path", Value::String(entry.manifest_path.clone())),
+            ("manifest_length", Value::Long(entry.manifest_length)),
+            ("partition_spec_id", Value::Int(entry.partition_spec_id)),
+            (
+                "added_snapshot_id",
+                optional(Some(Value::Long(entry.added_snapshot_id))),
+            ),
+            (
+                "added_data_files_count",
+                optional(Some(Value::Int(entry.added_files_count))),
+            ),
+            (
+                "existing_data_files_count",
+                optional(Some(Value::Int(entry.existing_files_count))),
+            ),
+            (
+                "deleted_data_files_count",
+                optional(Some(Value::Int(entry.deleted_files_count))),
+            ),
+            ("partitions", partitions),
+            (
+                "added_rows_count",
+                optional(Some(Value::Long(entry.added_rows_count))),
+            ),
+            (
+                "existing_rows_count",
+                optional(Some(Value::Long(entry.existing_rows_count))),
+            ),
+            (
+                "deleted_rows_count",
+                optional(Some(Value::Long(entry.deleted_rows_count))),
+            ),
+        ],
+        types::TableFormatVersion::V2 => vec![
+            ("manifest_path", Value::String(entry.manifest_path.clone())),
+            ("manifest_length", Value::Long(entry.manifest_length)),
+            ("partition_spec_id", Value::Int(entry.partition_spec_id)),
+            (
+                "content",
+                Value::Int(match entry.content {
+                    types::ManifestContentType::Data => 0,
+                    types::ManifestContentType::Deletes => 1,
+                }),
+            ),
+            ("sequence_number", Value::Long(entry.sequence_number)),
+            (
+                "min_sequence_number",
+                Value::Long(entry.min_sequence_number),
+            ),
+            ("added_snapshot_id", Value::Long(entry.added_snapshot_id)),
+            (
+                "added_data_files_count",
+                Value::Int(entry.added_files_count),
+            ),
+            (
+                "existing_data_files_count",
+                Value::Int(entry.existing_files_count),
+            ),
+            (
+                "deleted_data_files_count",
+                Value::Int(entry.deleted_files_count),
+            ),
+            ("added_rows_count", Value::Long(entry.added_rows_count)),
+            (
+                "existing_rows_count",
+                Value::Long(entry.existing_rows_count),
+            ),
+            ("deleted_rows_count", Value::Long(entry.deleted_rows_count)),
+            ("partitions", partitions),
+            (
+                "key_metadata",
+                optional(entry.key_metadata.clone().map(Value::Bytes)),
+            ),
+        ],
+        types::TableFormatVersion::V3 => unreachable!("v3 is rejected by serialize_manifest_list"),
+    };
+
+    Value::Record(
+        fields
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+    )
 }
 
+#[serde_as]
 #[derive(Deserialize)]
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 struct ManifestList {
@@ -35,11 +277,11 @@ struct ManifestList {
     min_sequence_number: i64,
     #[serde(default)]
     added_snapshot_id: i64,
-    #[serde(default)]
+    #[serde(default, alias = "added_data_files_count")]
     added_files_count: i32,
-    #[serde(default)]
+    #[serde(default, alias = "existing_data_files_count")]
     existing_files_count: i32,
-    #[serde(default)]
+    #[serde(default, alias = "deleted_data_files_count")]
     deleted_files_count: i32,
     #[serde(default)]
     added_rows_count: i64,
@@ -48,6 +290,7 @@ struct ManifestList {
     #[serde(default)]
     deleted_rows_count: i64,
     partitions: Option<Vec<FieldSummary>>,
+    #[serde_as(as = "Option<Bytes>")]
     key_metadata: Option<Vec<u8>>,
 }
 
@@ -97,6 +340,7 @@ impl TryFrom<ManifestList> for types::ManifestList {
     }
 }
 
+#[serde_as]
 #[derive(Deserialize)]
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 struct FieldSummary {
@@ -109,6 +353,14 @@ struct FieldSummary {
     /// Whether the manifest contains at least one partition with a NaN
     /// value for the field
     contains_nan: Option<bool>,
+    /// field: 510
+    #[serde_as(as = "Option<Bytes>")]
+    #[serde(default)]
+    lower_bound: Option<Vec<u8>>,
+    /// field: 511
+    #[serde_as(as = "Option<Bytes>")]
+    #[serde(default)]
+    upper_bound: Option<Vec<u8>>,
 }
 
 impl TryFrom<FieldSummary> for types::FieldSummary {
@@ -118,16 +370,22 @@ impl TryFrom<FieldSummary> for types::FieldSummary {
         Ok(types::FieldSummary {
             contains_null: v.contains_null,
             contains_nan: v.contains_nan,
+            lower_bound: v.lower_bound,
+            upper_bound: v.upper_bound,
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::{env, fs};
 
     use anyhow::Result;
 
+    use super::super::avro::avro_field_ids;
+    use super::super::avro::read_avro_schema;
+    use super::super::avro::rewrite_with_codec;
     use super::*;
 
     #[test]
@@ -160,7 +418,7 @@ mod tests {
                 sequence_number: 0,
                 min_sequence_number: 0,
                 added_snapshot_id: 1646658105718557341,
-                added_files_count: 0,
+                added_files_count: 3,
                 existing_files_count: 0,
                 deleted_files_count: 0,
                 added_rows_count: 3,
@@ -187,8 +445,9 @@ mod tests {
 
         let manifest_list = parse_manifest_list(&bs)?;
 
+        assert_eq!(manifest_list.len(), 1);
         assert_eq!(
-           manifest_list,
+           manifest_list[0],
             types::ManifestList {
                 manifest_path: "/opt/bitnami/spark/warehouse/db/table/metadata/10d28031-9739-484c-92db-cdf2975cead4-m0.avro".to_string(),
                 manifest_length: 5806,
@@ -197,7 +456,7 @@ mod tests {
                 sequence_number: 0,
                 min_sequence_number: 0,
                 added_snapshot_id: 1646658105718557341,
-                added_files_count: 0,
+                added_files_count: 3,
                 existing_files_count: 0,
                 deleted_files_count: 0,
                 added_rows_count: 3,
@@ -210,4 +469,116 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_manifest_list_with_codecs() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table/metadata/snap-1646658105718557341-1-10d28031-9739-484c-92db-cdf2975cead4.avro",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let bs = fs::read(path).expect("read_file must succeed");
+        let expected = parse_manifest_list(&bs)?;
+
+        for codec in [Codec::Null, Codec::Deflate, Codec::Snappy, Codec::Zstandard] {
+            let bs = rewrite_with_codec(&bs, codec);
+            assert_eq!(parse_manifest_list(&bs)?, expected, "{codec:?}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_manifest_list_in_json() {
+        let bs = br#"[{"manifest_path": "s3://bucket/metadata/m0.avro"}]"#;
+        let err = parse_manifest_list(bs).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("expected Avro manifest list, found JSON"));
+    }
+
+    #[test]
+    fn test_serialize_manifest_list() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table/metadata/snap-1646658105718557341-1-10d28031-9739-484c-92db-cdf2975cead4.avro",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let bs = fs::read(path).expect("read_file must succeed");
+        let mut manifest_list = parse_manifest_list(&bs)?;
+        manifest_list[0].partitions = Some(vec![types::FieldSummary {
+            contains_null: true,
+            contains_nan: Some(false),
+            lower_bound: Some(vec![1, 0, 0, 0]),
+            upper_bound: Some(vec![0xff, 0, 0, 0]),
+        }]);
+        let snapshot = types::Snapshot {
+            snapshot_id: 1646658105718557341,
+            parent_snapshot_id: None,
+            sequence_number: 0,
+            timestamp_ms: 1686911671713,
+            manifest_list: "snap.avro".to_string(),
+            summary: HashMap::new(),
+            schema_id: None,
+        };
+
+        for format_version in [types::TableFormatVersion::V1, types::TableFormatVersion::V2] {
+            let bs = serialize_manifest_list(format_version, &snapshot, &manifest_list)?;
+            assert_eq!(parse_manifest_list(&bs)?, manifest_list);
+
+            let reader = Reader::new(&bs[..])?;
+            assert_eq!(
+                reader
+                    .user_metadata()
+                    .get("snapshot-id")
+                    .map(|v| v.as_slice()),
+                Some("1646658105718557341".as_bytes())
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_manifest_list_field_ids() -> Result<()> {
+        let path = format!(
+            "{}/testdata/simple_table/metadata/snap-1646658105718557341-1-10d28031-9739-484c-92db-cdf2975cead4.avro",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let bs = fs::read(path).expect("read_file must succeed");
+        let manifest_list = parse_manifest_list(&bs)?;
+        // Written by java in v1.
+        let expected = avro_field_ids(&read_avro_schema(&bs)?);
+        let snapshot = types::Snapshot {
+            snapshot_id: 1646658105718557341,
+            parent_snapshot_id: None,
+            sequence_number: 0,
+            timestamp_ms: 1686911671713,
+            manifest_list: "snap.avro".to_string(),
+            summary: HashMap::new(),
+            schema_id: None,
+        };
+
+        let bs = serialize_manifest_list(types::TableFormatVersion::V1, &snapshot, &manifest_list)?;
+        let ids = avro_field_ids(&read_avro_schema(&bs)?);
+        assert_eq!(ids, expected);
+
+        let bs = serialize_manifest_list(types::TableFormatVersion::V2, &snapshot, &manifest_list)?;
+        let ids = avro_field_ids(&read_avro_schema(&bs)?);
+        assert_eq!(ids.get("content"), Some(&517));
+        assert_eq!(ids.get("sequence_number"), Some(&515));
+        assert_eq!(ids.get("min_sequence_number"), Some(&516));
+        assert_eq!(ids.get("key_metadata"), Some(&519));
+        for (name, id) in expected {
+            assert_eq!(ids.get(&name), Some(&id), "field id of {name} must match");
+        }
+
+        Ok(())
+    }
 }
@@ -1,17 +1,25 @@
 //! on_disk module provides the definition of iceberg on-disk data
 //! formats and the convert functions to in-memory.
 
+mod avro;
+
 mod manifest_file;
 pub use manifest_file::parse_manifest_file;
+pub use manifest_file::parse_manifest_file_with_partition_specs;
+pub use manifest_file::serialize_manifest_file;
+pub use manifest_file::visit_manifest_file;
 
 mod manifest_list;
 pub use manifest_list::parse_manifest_list;
+pub use manifest_list::serialize_manifest_list;
 
 mod partition_spec;
 pub use partition_spec::parse_partition_spec;
+pub use partition_spec::serialize_partition_spec;
 
 mod schema;
 pub use schema::parse_schema;
+pub use schema::serialize_schema;
 
 mod sort_order;
 pub use sort_order::parse_sort_order;
@@ -23,6 +31,10 @@ mod snapshot;
 pub use snapshot::parse_snapshot;
 
 mod table_metadata;
+pub use table_metadata::parse_current_manifest_list;
 pub use table_metadata::parse_table_metadata;
+pub use table_metadata::register_table_metadata_hook;
+pub use table_metadata::serialize_table_metadata;
+pub use table_metadata::TableMetadataHook;
 
 mod types;
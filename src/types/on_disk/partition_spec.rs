@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use serde::Serialize;
 
 use crate::types;
 
@@ -13,7 +14,12 @@ pub fn parse_partition_spec(bs: &[u8]) -> Result<types::PartitionSpec> {
     t.try_into()
 }
 
-#[derive(Deserialize)]
+/// Serialize partition spec into json bytes.
+pub fn serialize_partition_spec(v: &types::PartitionSpec) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&PartitionSpec::from(v.clone()))?)
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct PartitionSpec {
     spec_id: i32,
@@ -36,15 +42,35 @@ impl TryFrom<PartitionSpec> for types::PartitionSpec {
     }
 }
 
-#[derive(Deserialize)]
+impl From<types::PartitionSpec> for PartitionSpec {
+    fn from(v: types::PartitionSpec) -> Self {
+        PartitionSpec {
+            spec_id: v.spec_id,
+            fields: v.fields.into_iter().map(PartitionField::from).collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
-struct PartitionField {
+pub struct PartitionField {
     source_id: i32,
     field_id: i32,
     name: String,
     transform: String,
 }
 
+impl From<types::PartitionField> for PartitionField {
+    fn from(v: types::PartitionField) -> Self {
+        PartitionField {
+            source_id: v.source_column_id,
+            field_id: v.partition_field_id,
+            name: v.name,
+            transform: v.transform.to_string(),
+        }
+    }
+}
+
 impl TryFrom<PartitionField> for types::PartitionField {
     type Error = Error;
 
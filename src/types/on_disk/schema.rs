@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use serde::Serialize;
 
 use crate::types;
 use crate::Error;
@@ -12,14 +13,36 @@ pub fn parse_schema(schema: &[u8]) -> Result<types::Schema> {
     schema.try_into()
 }
 
-#[derive(Deserialize)]
+/// Serialize schema into json bytes.
+pub fn serialize_schema(v: &types::Schema) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&Schema::from(v.clone()))?)
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Schema {
+    /// Schema is always a struct type.
+    #[serde(rename = "type", default)]
+    typ: String,
+    /// Optional for schemas written by old v1 writers, defaults to 0.
+    #[serde(default)]
     schema_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     identifier_field_ids: Option<Vec<i32>>,
     fields: Vec<Field>,
 }
 
+impl From<types::Schema> for Schema {
+    fn from(v: types::Schema) -> Self {
+        Schema {
+            typ: "struct".to_string(),
+            schema_id: v.schema_id,
+            identifier_field_ids: v.identifier_field_ids,
+            fields: v.fields.into_iter().map(Field::from).collect(),
+        }
+    }
+}
+
 impl TryFrom<Schema> for types::Schema {
     type Error = Error;
 
@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
 use serde::Deserialize;
+use serde::Serialize;
 
+use super::types::long_or_string;
+use super::types::option_long_or_string;
 use crate::types;
 use crate::Error;
 use crate::Result;
@@ -12,20 +15,42 @@ pub fn parse_snapshot(bs: &[u8]) -> Result<types::Snapshot> {
     v.try_into()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 #[serde(rename_all = "kebab-case")]
 pub struct Snapshot {
+    #[serde(deserialize_with = "long_or_string")]
     snapshot_id: i64,
+    #[serde(
+        default,
+        deserialize_with = "option_long_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
     parent_snapshot_id: Option<i64>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "long_or_string")]
     sequence_number: i64,
+    #[serde(deserialize_with = "long_or_string")]
     timestamp_ms: i64,
     manifest_list: String,
     summary: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     schema_id: Option<i64>,
 }
 
+impl From<types::Snapshot> for Snapshot {
+    fn from(v: types::Snapshot) -> Self {
+        Snapshot {
+            snapshot_id: v.snapshot_id,
+            parent_snapshot_id: v.parent_snapshot_id,
+            sequence_number: v.sequence_number,
+            timestamp_ms: v.timestamp_ms,
+            manifest_list: v.manifest_list,
+            summary: v.summary,
+            schema_id: v.schema_id,
+        }
+    }
+}
+
 impl TryFrom<Snapshot> for types::Snapshot {
     type Error = Error;
 
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use serde::Serialize;
 
 use crate::types;
 use crate::Error;
@@ -13,7 +14,7 @@ pub fn parse_sort_order(bs: &[u8]) -> Result<types::SortOrder> {
     t.try_into()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct SortOrder {
     order_id: i32,
@@ -36,7 +37,16 @@ impl TryFrom<SortOrder> for types::SortOrder {
     }
 }
 
-#[derive(Deserialize)]
+impl From<types::SortOrder> for SortOrder {
+    fn from(v: types::SortOrder) -> Self {
+        SortOrder {
+            order_id: v.order_id,
+            fields: v.fields.into_iter().map(SortField::from).collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 struct SortField {
     transform: String,
@@ -45,6 +55,25 @@ struct SortField {
     null_order: String,
 }
 
+impl From<types::SortField> for SortField {
+    fn from(v: types::SortField) -> Self {
+        SortField {
+            transform: v.transform.to_string(),
+            source_id: v.source_column_id,
+            direction: match v.direction {
+                types::SortDirection::ASC => "asc",
+                types::SortDirection::DESC => "desc",
+            }
+            .to_string(),
+            null_order: match v.null_order {
+                types::NullOrder::First => "nulls-first",
+                types::NullOrder::Last => "nulls-last",
+            }
+            .to_string(),
+        }
+    }
+}
+
 impl TryFrom<SortField> for types::SortField {
     type Error = Error;
 
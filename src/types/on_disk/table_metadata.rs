@@ -1,47 +1,547 @@
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
 
+use serde::de;
+use serde::de::IgnoredAny;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
 use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
 
+use super::partition_spec::PartitionField;
 use super::partition_spec::PartitionSpec;
 use super::schema::Schema;
 use super::snapshot::Snapshot;
 use super::sort_order::SortOrder;
+use super::types::long_or_string;
+use super::types::option_snapshot_id;
 use crate::types;
 use crate::Error;
 use crate::ErrorKind;
 use crate::Result;
 
 /// Parse table metadata from json bytes.
+///
+/// Returns a `Corrupt` error naming the offending field if a field is
+/// missing or mistyped.
+///
+/// # Memory
+///
+/// Metadata is deserialized from bytes into typed structs directly, no
+/// intermediate json tree is built. Top level fields are validated in a
+/// separate pass which skips nested values without buffering them. So
+/// besides the input bytes, the peak memory is about the size of the
+/// parsed metadata, which grows linearly with the number of snapshots and
+/// their summaries.
+///
+/// # Unknown fields
+///
+/// Unknown fields are ignored, so that tables written by newer versions of
+/// iceberg can be read. Unknown top level fields are kept in
+/// `unknown_fields` and written back by `serialize_table_metadata`.
+///
+/// Unknown fields nested in snapshots, schemas, partition specs, sort
+/// orders, refs and other objects are not kept, they are dropped once the
+/// metadata is written back.
+///
+/// # Hooks
+///
+/// Hooks registered by `register_table_metadata_hook` are called in order
+/// after parsing. The json tree of metadata is only built if any hook is
+/// registered.
 pub fn parse_table_metadata(bs: &[u8]) -> Result<types::TableMetadata> {
-    let v: TableMetadata = serde_json::from_slice(bs)?;
-    v.try_into()
+    let unknown_fields = validate_table_metadata(bs)?;
+
+    let v: TableMetadata = serde_json::from_slice(bs)
+        .map_err(|err| Error::new(ErrorKind::Corrupt, format!("invalid metadata: {err}")))?;
+    let mut metadata: types::TableMetadata = v.try_into()?;
+    metadata.unknown_fields = unknown_fields;
+
+    let hooks = TABLE_METADATA_HOOKS
+        .read()
+        .expect("table metadata hooks must not be poisoned")
+        .clone();
+    if !hooks.is_empty() {
+        let value: serde_json::Value = serde_json::from_slice(bs)?;
+        for hook in hooks {
+            hook(&value, &mut metadata);
+        }
+    }
+    Ok(metadata)
+}
+
+/// TableMetadataHook is called with the json value and the parsed table
+/// metadata, see `register_table_metadata_hook`.
+pub type TableMetadataHook = fn(&serde_json::Value, &mut types::TableMetadata);
+
+/// Hooks called after parsing table metadata, in registration order.
+static TABLE_METADATA_HOOKS: RwLock<Vec<TableMetadataHook>> = RwLock::new(Vec::new());
+
+/// Register a hook called by `parse_table_metadata` after parsing.
+///
+/// Hooks let embedders capture vendor extensions icelake doesn't model,
+/// like namespaced properties, usually into `TableMetadata::extensions`.
+/// Hooks are process wide and can't be unregistered.
+pub fn register_table_metadata_hook(hook: TableMetadataHook) {
+    TABLE_METADATA_HOOKS
+        .write()
+        .expect("table metadata hooks must not be poisoned")
+        .push(hook);
+}
+
+/// Json kind of a top level field of table metadata.
+#[derive(Clone, Copy)]
+enum FieldKind {
+    Integer,
+    /// A long which could be quoted as a string.
+    Long,
+    String,
+    Array,
+    Object,
+}
+
+impl FieldKind {
+    fn matches(self, v: &JsonKind) -> bool {
+        match self {
+            FieldKind::Integer => matches!(v, JsonKind::Integer(_)),
+            FieldKind::Long => match v {
+                JsonKind::Integer(_) => true,
+                JsonKind::String(v) => v.parse::<i64>().is_ok(),
+                _ => false,
+            },
+            FieldKind::String => matches!(v, JsonKind::String(_)),
+            FieldKind::Array => matches!(v, JsonKind::Array),
+            FieldKind::Object => matches!(v, JsonKind::Object),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            FieldKind::Integer => "an integer",
+            FieldKind::Long => "an integer or a string of integer",
+            FieldKind::String => "a string",
+            FieldKind::Array => "an array",
+            FieldKind::Object => "an object",
+        }
+    }
+}
+
+/// Top level fields of table metadata as `(name, required, kind)`.
+///
+/// Fields like `schemas` are required by v2 but optional for v1, they are
+/// validated while converting into `types::TableMetadata`.
+const TABLE_METADATA_FIELDS: &[(&str, bool, FieldKind)] = &[
+    ("format-version", true, FieldKind::Integer),
+    ("table-uuid", true, FieldKind::String),
+    ("location", true, FieldKind::String),
+    ("last-sequence-number", false, FieldKind::Long),
+    ("last-updated-ms", true, FieldKind::Long),
+    ("last-column-id", true, FieldKind::Integer),
+    ("schema", false, FieldKind::Object),
+    ("schemas", false, FieldKind::Array),
+    ("current-schema-id", false, FieldKind::Integer),
+    ("partition-spec", false, FieldKind::Array),
+    ("partition-specs", false, FieldKind::Array),
+    ("default-spec-id", false, FieldKind::Integer),
+    ("last-partition-id", false, FieldKind::Integer),
+    ("properties", false, FieldKind::Object),
+    ("current-snapshot-id", false, FieldKind::Long),
+    ("snapshots", false, FieldKind::Array),
+    ("snapshot-log", false, FieldKind::Array),
+    ("metadata-log", false, FieldKind::Array),
+    ("sort-orders", false, FieldKind::Array),
+    ("default-sort-order-id", false, FieldKind::Integer),
+    ("refs", false, FieldKind::Object),
+];
+
+/// Json value of a top level field, whose nested values are skipped
+/// without being buffered.
+enum JsonKind {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    /// Numbers out of the range of long.
+    Number(String),
+    String(String),
+    Array,
+    Object,
+}
+
+impl fmt::Display for JsonKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonKind::Null => write!(f, "null"),
+            JsonKind::Bool(v) => write!(f, "{v}"),
+            JsonKind::Integer(v) => write!(f, "{v}"),
+            JsonKind::Number(v) => write!(f, "{v}"),
+            JsonKind::String(v) => write!(f, "{}", serde_json::Value::from(v.as_str())),
+            JsonKind::Array => write!(f, "[...]"),
+            JsonKind::Object => write!(f, "{{...}}"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonKind {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct JsonKindVisitor;
+
+        impl<'de> Visitor<'de> for JsonKindVisitor {
+            type Value = JsonKind;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any json value")
+            }
+
+            fn visit_unit<E: de::Error>(self) -> std::result::Result<JsonKind, E> {
+                Ok(JsonKind::Null)
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<JsonKind, E> {
+                Ok(JsonKind::Bool(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<JsonKind, E> {
+                Ok(JsonKind::Integer(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<JsonKind, E> {
+                Ok(match i64::try_from(v) {
+                    Ok(v) => JsonKind::Integer(v),
+                    Err(_) => JsonKind::Number(v.to_string()),
+                })
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<JsonKind, E> {
+                Ok(JsonKind::Number(v.to_string()))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<JsonKind, E> {
+                Ok(JsonKind::String(v.to_string()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<JsonKind, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(JsonKind::Array)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<JsonKind, A::Error> {
+                while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+                Ok(JsonKind::Object)
+            }
+        }
+
+        deserializer.deserialize_any(JsonKindVisitor)
+    }
 }
 
-#[derive(Deserialize)]
+/// Top level fields of table metadata, with json kinds of known fields
+/// and json values of unknown fields.
+struct TopLevelFields {
+    known: HashMap<String, JsonKind>,
+    unknown: BTreeMap<String, String>,
+}
+
+impl<'de> Deserialize<'de> for TopLevelFields {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TopLevelFieldsVisitor;
+
+        impl<'de> Visitor<'de> for TopLevelFieldsVisitor {
+            type Value = TopLevelFields;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a json object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<TopLevelFields, A::Error> {
+                let mut fields = TopLevelFields {
+                    known: HashMap::new(),
+                    unknown: BTreeMap::new(),
+                };
+                while let Some(name) = map.next_key::<String>()? {
+                    if is_known_field(&name) {
+                        let v = map.next_value::<JsonKind>()?;
+                        fields.known.insert(name, v);
+                    } else {
+                        let v = map.next_value::<serde_json::Value>()?;
+                        fields.unknown.insert(name, v.to_string());
+                    }
+                }
+                Ok(fields)
+            }
+        }
+
+        deserializer.deserialize_map(TopLevelFieldsVisitor)
+    }
+}
+
+/// Check if the top level field is known by icelake.
+fn is_known_field(name: &str) -> bool {
+    TABLE_METADATA_FIELDS.iter().any(|(v, _, _)| *v == name)
+}
+
+/// Check that top level fields of table metadata exist and have the
+/// expected json kinds.
+///
+/// Returns json values of unknown top level fields.
+fn validate_table_metadata(bs: &[u8]) -> Result<BTreeMap<String, String>> {
+    let fields: TopLevelFields = serde_json::from_slice(bs).map_err(|err| {
+        if err.classify() == serde_json::error::Category::Data {
+            Error::new(ErrorKind::Corrupt, "metadata must be a json object")
+        } else {
+            Error::new(ErrorKind::Corrupt, "metadata is not valid json").set_source(err)
+        }
+    })?;
+    let object = fields.known;
+
+    for (name, required, kind) in TABLE_METADATA_FIELDS {
+        match object.get(*name) {
+            None if *required => {
+                return Err(Error::new(
+                    ErrorKind::Corrupt,
+                    format!("missing required field '{name}'"),
+                )
+                .with_context("field", *name))
+            }
+            None | Some(JsonKind::Null) if !required => {}
+            Some(v) if kind.matches(v) => {}
+            v => {
+                return Err(Error::new(
+                    ErrorKind::Corrupt,
+                    format!(
+                        "field '{name}' must be {}, but got {}",
+                        kind.as_str(),
+                        v.unwrap_or(&JsonKind::Null)
+                    ),
+                )
+                .with_context("field", *name))
+            }
+        }
+    }
+
+    Ok(fields.unknown)
+}
+
+/// Parse only the manifest list of current snapshot from table metadata
+/// json bytes.
+///
+/// Other fields like schemas and snapshot summaries are skipped, which is
+/// much cheaper than `parse_table_metadata` on tables with long history.
+/// Returns `None` if the table has no current snapshot.
+pub fn parse_current_manifest_list(bs: &[u8]) -> Result<Option<String>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct PartialSnapshot {
+        #[serde(deserialize_with = "long_or_string")]
+        snapshot_id: i64,
+        manifest_list: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct PartialTableMetadata {
+        #[serde(default, deserialize_with = "option_snapshot_id")]
+        current_snapshot_id: Option<i64>,
+        /// `null` is accepted like the full metadata, as no snapshot.
+        #[serde(default)]
+        snapshots: Option<Vec<PartialSnapshot>>,
+    }
+
+    let v: PartialTableMetadata = serde_json::from_slice(bs)
+        .map_err(|err| Error::new(ErrorKind::Corrupt, format!("invalid metadata: {err}")))?;
+    let Some(snapshot_id) = v.current_snapshot_id else {
+        return Ok(None);
+    };
+
+    v.snapshots
+        .unwrap_or_default()
+        .into_iter()
+        .find(|v| v.snapshot_id == snapshot_id)
+        .map(|v| Some(v.manifest_list))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::Corrupt,
+                format!("current snapshot {snapshot_id} is not found in snapshots"),
+            )
+        })
+}
+
+/// Serialize table metadata into json bytes.
+///
+/// Unknown top level fields are written after known fields, unless they
+/// conflict with known fields. Unknown nested fields are not kept by
+/// `parse_table_metadata`, so they are never written.
+pub fn serialize_table_metadata(v: &types::TableMetadata) -> Result<Vec<u8>> {
+    #[derive(Serialize)]
+    struct TableMetadataWithUnknownFields<'a> {
+        #[serde(flatten)]
+        metadata: TableMetadata,
+        #[serde(flatten)]
+        unknown_fields: BTreeMap<&'a str, serde_json::Value>,
+    }
+
+    let mut unknown_fields = BTreeMap::new();
+    for (name, value) in &v.unknown_fields {
+        if is_known_field(name) {
+            continue;
+        }
+        let value = serde_json::from_str(value).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidArgument,
+                format!("value of unknown field {name} is not valid json"),
+            )
+            .set_source(err)
+        })?;
+        unknown_fields.insert(name.as_str(), value);
+    }
+
+    let v = TableMetadataWithUnknownFields {
+        metadata: TableMetadata::from(v.clone()),
+        unknown_fields,
+    };
+    Ok(serde_json::to_vec_pretty(&v)?)
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 struct TableMetadata {
     format_version: i32,
     table_uuid: String,
     location: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "long_or_string")]
     last_sequence_number: i64,
+    #[serde(deserialize_with = "long_or_string")]
     last_updated_ms: i64,
     last_column_id: i32,
-    schemas: Vec<Schema>,
-    current_schema_id: i32,
-    partition_specs: Vec<PartitionSpec>,
-    default_spec_id: i32,
-    last_partition_id: i32,
+    /// The current schema, only written for v1 tables.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema: Option<Schema>,
+    /// Optional for v1 tables, built from `schema` if absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schemas: Option<Vec<Schema>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_schema_id: Option<i32>,
+    /// The fields of default partition spec, only written for v1 tables.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partition_spec: Option<Vec<PartitionField>>,
+    /// Optional for v1 tables, built from `partition_spec` if absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partition_specs: Option<Vec<PartitionSpec>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default_spec_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_partition_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     properties: Option<HashMap<String, String>>,
+    #[serde(
+        default,
+        deserialize_with = "option_snapshot_id",
+        skip_serializing_if = "Option::is_none"
+    )]
     current_snapshot_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     snapshots: Option<Vec<Snapshot>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     snapshot_log: Option<Vec<SnapshotLog>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     metadata_log: Option<Vec<MetadataLog>>,
+    /// Optional for v1 tables, defaults to the unsorted order if absent.
+    #[serde(default)]
     sort_orders: Vec<SortOrder>,
+    #[serde(default)]
     default_sort_order_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     refs: Option<HashMap<String, SnapshotReference>>,
 }
 
+impl From<types::TableMetadata> for TableMetadata {
+    fn from(v: types::TableMetadata) -> Self {
+        // v1 tables require the current schema and the default partition
+        // spec to be written in the singular fields.
+        let (schema, partition_spec) = match v.format_version {
+            types::TableFormatVersion::V1 => (
+                v.schemas
+                    .iter()
+                    .find(|schema| schema.schema_id == v.current_schema_id)
+                    .cloned()
+                    .map(Schema::from),
+                v.partition_specs
+                    .iter()
+                    .find(|spec| spec.spec_id == v.default_spec_id)
+                    .map(|spec| {
+                        spec.fields
+                            .iter()
+                            .cloned()
+                            .map(PartitionField::from)
+                            .collect()
+                    }),
+            ),
+            types::TableFormatVersion::V2 | types::TableFormatVersion::V3 => (None, None),
+        };
+
+        TableMetadata {
+            format_version: match v.format_version {
+                types::TableFormatVersion::V1 => 1,
+                types::TableFormatVersion::V2 => 2,
+                types::TableFormatVersion::V3 => 3,
+            },
+            table_uuid: v.table_uuid,
+            location: v.location,
+            last_sequence_number: v.last_sequence_number,
+            last_updated_ms: v.last_updated_ms,
+            last_column_id: v.last_column_id,
+            schema,
+            schemas: Some(v.schemas.into_iter().map(Schema::from).collect()),
+            current_schema_id: Some(v.current_schema_id),
+            partition_spec,
+            partition_specs: Some(
+                v.partition_specs
+                    .into_iter()
+                    .map(PartitionSpec::from)
+                    .collect(),
+            ),
+            default_spec_id: Some(v.default_spec_id),
+            last_partition_id: Some(v.last_partition_id),
+            properties: v.properties,
+            current_snapshot_id: v.current_snapshot_id,
+            snapshots: v
+                .snapshots
+                .map(|v| v.into_iter().map(Snapshot::from).collect()),
+            snapshot_log: v
+                .snapshot_log
+                .map(|v| v.into_iter().map(SnapshotLog::from).collect()),
+            metadata_log: v
+                .metadata_log
+                .map(|v| v.into_iter().map(MetadataLog::from).collect()),
+            sort_orders: v.sort_orders.into_iter().map(SortOrder::from).collect(),
+            default_sort_order_id: v.default_sort_order_id,
+            refs: v.refs.map(|v| {
+                v.into_iter()
+                    .map(|(k, v)| (k, SnapshotReference::from(v)))
+                    .collect()
+            }),
+        }
+    }
+}
+
 impl TryFrom<TableMetadata> for types::TableMetadata {
     type Error = Error;
 
@@ -49,6 +549,7 @@ impl TryFrom<TableMetadata> for types::TableMetadata {
         let format_version = match v.format_version {
             1 => types::TableFormatVersion::V1,
             2 => types::TableFormatVersion::V2,
+            3 => types::TableFormatVersion::V3,
             _ => {
                 return Err(Error::new(
                     ErrorKind::IcebergDataInvalid,
@@ -57,15 +558,78 @@ impl TryFrom<TableMetadata> for types::TableMetadata {
             }
         };
 
-        let mut schemas = Vec::with_capacity(v.schemas.len());
-        for schema in v.schemas {
-            schemas.push(schema.try_into()?);
-        }
+        // v1 tables may only have the singular `schema` field.
+        let (schemas, current_schema_id) = match (v.schemas, v.schema) {
+            (Some(schemas), schema) => {
+                let mut result: Vec<types::Schema> = Vec::with_capacity(schemas.len());
+                for schema in schemas {
+                    result.push(schema.try_into()?);
+                }
+                let current_schema_id = match (v.current_schema_id, schema) {
+                    (Some(id), _) => id,
+                    (None, Some(schema)) => types::Schema::try_from(schema)?.schema_id,
+                    (None, None) => {
+                        return Err(Error::new(
+                            ErrorKind::Corrupt,
+                            "missing required field 'current-schema-id'",
+                        )
+                        .with_context("field", "current-schema-id"))
+                    }
+                };
+                (result, current_schema_id)
+            }
+            (None, Some(schema)) => {
+                let schema: types::Schema = schema.try_into()?;
+                let current_schema_id = schema.schema_id;
+                (vec![schema], current_schema_id)
+            }
+            (None, None) => {
+                return Err(
+                    Error::new(ErrorKind::Corrupt, "missing required field 'schemas'")
+                        .with_context("field", "schemas"),
+                )
+            }
+        };
 
-        let mut partition_specs = Vec::with_capacity(v.partition_specs.len());
-        for partition_spec in v.partition_specs {
-            partition_specs.push(partition_spec.try_into()?);
-        }
+        // v1 tables may only have the singular `partition-spec` field, which
+        // is the default spec with id 0.
+        let (partition_specs, default_spec_id) = match (v.partition_specs, v.partition_spec) {
+            (Some(specs), _) => {
+                let mut result = Vec::with_capacity(specs.len());
+                for spec in specs {
+                    result.push(spec.try_into()?);
+                }
+                (result, v.default_spec_id.unwrap_or_default())
+            }
+            (None, Some(fields)) => {
+                let mut result = Vec::with_capacity(fields.len());
+                for field in fields {
+                    result.push(field.try_into()?);
+                }
+                let spec = types::PartitionSpec {
+                    spec_id: 0,
+                    fields: result,
+                };
+                (vec![spec], 0)
+            }
+            (None, None) => {
+                return Err(Error::new(
+                    ErrorKind::Corrupt,
+                    "missing required field 'partition-specs'",
+                )
+                .with_context("field", "partition-specs"))
+            }
+        };
+        let last_partition_id = match v.last_partition_id {
+            Some(id) => id,
+            // Partition field ids start from 1000.
+            None => partition_specs
+                .iter()
+                .flat_map(|spec| spec.fields.iter())
+                .map(|field| field.partition_field_id)
+                .max()
+                .unwrap_or(999),
+        };
 
         let snapshots = match v.snapshots {
             Some(v) => {
@@ -80,10 +644,23 @@ impl TryFrom<TableMetadata> for types::TableMetadata {
 
         let snapshot_log = match v.snapshot_log {
             Some(v) => {
-                let mut snapshot_log = Vec::with_capacity(v.len());
+                let mut snapshot_log: Vec<types::SnapshotLog> = Vec::with_capacity(v.len());
                 for snapshot in v {
                     snapshot_log.push(snapshot.try_into()?);
                 }
+                // Time travel by timestamp relies on the order required by
+                // the spec.
+                if let Some(w) = snapshot_log
+                    .windows(2)
+                    .find(|w| w[1].timestamp_ms < w[0].timestamp_ms)
+                {
+                    return Err(Error::new(
+                        ErrorKind::Corrupt,
+                        "snapshot-log is not sorted by timestamp",
+                    )
+                    .with_context("field", "snapshot-log")
+                    .with_context("snapshot-id", w[1].snapshot_id.to_string()));
+                }
                 Some(snapshot_log)
             }
             None => None,
@@ -104,6 +681,12 @@ impl TryFrom<TableMetadata> for types::TableMetadata {
         for sort_order in v.sort_orders {
             sort_orders.push(sort_order.try_into()?);
         }
+        if sort_orders.is_empty() {
+            sort_orders.push(types::SortOrder {
+                order_id: 0,
+                fields: vec![],
+            });
+        }
 
         let refs = match v.refs {
             Some(v) => {
@@ -124,10 +707,10 @@ impl TryFrom<TableMetadata> for types::TableMetadata {
             last_updated_ms: v.last_updated_ms,
             last_column_id: v.last_column_id,
             schemas,
-            current_schema_id: v.current_schema_id,
+            current_schema_id,
             partition_specs,
-            default_spec_id: v.default_spec_id,
-            last_partition_id: v.last_partition_id,
+            default_spec_id,
+            last_partition_id,
             properties: v.properties,
             current_snapshot_id: v.current_snapshot_id,
             snapshots,
@@ -136,17 +719,30 @@ impl TryFrom<TableMetadata> for types::TableMetadata {
             sort_orders,
             default_sort_order_id: v.default_sort_order_id,
             refs,
+            unknown_fields: BTreeMap::new(),
+            extensions: BTreeMap::new(),
         })
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 struct SnapshotLog {
+    #[serde(deserialize_with = "long_or_string")]
     timestamp_ms: i64,
+    #[serde(deserialize_with = "long_or_string")]
     snapshot_id: i64,
 }
 
+impl From<types::SnapshotLog> for SnapshotLog {
+    fn from(v: types::SnapshotLog) -> Self {
+        SnapshotLog {
+            timestamp_ms: v.timestamp_ms,
+            snapshot_id: v.snapshot_id,
+        }
+    }
+}
+
 impl TryFrom<SnapshotLog> for types::SnapshotLog {
     type Error = Error;
 
@@ -158,13 +754,23 @@ impl TryFrom<SnapshotLog> for types::SnapshotLog {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 struct MetadataLog {
+    #[serde(deserialize_with = "long_or_string")]
     timestamp_ms: i64,
     metadata_file: String,
 }
 
+impl From<types::MetadataLog> for MetadataLog {
+    fn from(v: types::MetadataLog) -> Self {
+        MetadataLog {
+            timestamp_ms: v.timestamp_ms,
+            metadata_file: v.metadata_file,
+        }
+    }
+}
+
 impl TryFrom<MetadataLog> for types::MetadataLog {
     type Error = Error;
 
@@ -176,17 +782,37 @@ impl TryFrom<MetadataLog> for types::MetadataLog {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 struct SnapshotReference {
+    #[serde(deserialize_with = "long_or_string")]
     snapshot_id: i64,
     #[serde(rename = "type")]
     typ: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     min_snapshots_to_keep: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     max_snapshot_age_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     max_ref_age_ms: Option<i64>,
 }
 
+impl From<types::SnapshotReference> for SnapshotReference {
+    fn from(v: types::SnapshotReference) -> Self {
+        SnapshotReference {
+            snapshot_id: v.snapshot_id,
+            typ: match v.typ {
+                types::SnapshotReferenceType::Tag => "tag",
+                types::SnapshotReferenceType::Branch => "branch",
+            }
+            .to_string(),
+            min_snapshots_to_keep: v.min_snapshots_to_keep,
+            max_snapshot_age_ms: v.max_snapshot_age_ms,
+            max_ref_age_ms: v.max_ref_age_ms,
+        }
+    }
+}
+
 impl TryFrom<SnapshotReference> for types::SnapshotReference {
     type Error = Error;
 
@@ -238,6 +864,207 @@ mod tests {
         assert_eq!(metadata.last_column_id, 2);
     }
 
+    #[test]
+    fn test_parse_table_metadata_with_quoted_longs() {
+        let testdata = format!(
+            "{}/testdata",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let numbers = parse_table_metadata(
+            &fs::read(format!("{testdata}/simple_table/metadata/v2.metadata.json"))
+                .expect("read_file must succeed"),
+        )
+        .expect("parse_table_metadata with numbers must succeed");
+        let quoted = parse_table_metadata(
+            &fs::read(format!("{testdata}/quoted_longs.metadata.json"))
+                .expect("read_file must succeed"),
+        )
+        .expect("parse_table_metadata with quoted longs must succeed");
+        assert_eq!(quoted.current_snapshot_id, Some(1646658105718557341));
+        assert_eq!(quoted.last_updated_ms, 1686911671713);
+        assert_eq!(quoted, numbers);
+
+        // Longs are always written as numbers.
+        let bs = serialize_table_metadata(&quoted).expect("serialize_table_metadata must succeed");
+        let value: serde_json::Value = serde_json::from_slice(&bs).expect("json must be valid");
+        assert!(value["current-snapshot-id"].is_i64());
+
+        let invalid = String::from_utf8(
+            fs::read(format!("{testdata}/quoted_longs.metadata.json"))
+                .expect("read_file must succeed"),
+        )
+        .expect("fixture must be utf-8")
+        .replace("\"1686911671713\"", "\"not a long\"");
+        let err = parse_table_metadata(invalid.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Corrupt);
+    }
+
+    #[test]
+    fn test_parse_table_metadata_with_unknown_fields() {
+        let testdata = format!(
+            "{}/testdata",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let known = parse_table_metadata(
+            &fs::read(format!("{testdata}/simple_table/metadata/v2.metadata.json"))
+                .expect("read_file must succeed"),
+        )
+        .expect("parse_table_metadata must succeed");
+        // `statistics` is written by spark but not supported yet.
+        assert_eq!(
+            known.unknown_fields,
+            BTreeMap::from([("statistics".to_string(), "[]".to_string())])
+        );
+
+        let metadata = parse_table_metadata(
+            &fs::read(format!("{testdata}/unknown_fields.metadata.json"))
+                .expect("read_file must succeed"),
+        )
+        .expect("parse_table_metadata with unknown fields must succeed");
+        assert_eq!(
+            metadata.unknown_fields,
+            BTreeMap::from([
+                (
+                    "future-field".to_string(),
+                    r#"{"nested":[1,2]}"#.to_string()
+                ),
+                ("statistics".to_string(), "[]".to_string()),
+            ])
+        );
+        let mut without_future_field = metadata.clone();
+        without_future_field.unknown_fields.remove("future-field");
+        assert_eq!(without_future_field, known);
+
+        // Unknown top level fields are written back.
+        let bs =
+            serialize_table_metadata(&metadata).expect("serialize_table_metadata must succeed");
+        let value: serde_json::Value = serde_json::from_slice(&bs).expect("json must be valid");
+        assert_eq!(value["future-field"]["nested"], serde_json::json!([1, 2]));
+        let actual = parse_table_metadata(&bs).expect("parse_table_metadata must succeed");
+        assert_eq!(actual, metadata);
+
+        // Unknown fields conflicting with known fields are not written.
+        let mut conflicted = metadata;
+        conflicted
+            .unknown_fields
+            .insert("location".to_string(), r#""/tmp""#.to_string());
+        let bs =
+            serialize_table_metadata(&conflicted).expect("serialize_table_metadata must succeed");
+        let actual = parse_table_metadata(&bs).expect("parse_table_metadata must succeed");
+        assert_eq!(actual.location, conflicted.location);
+    }
+
+    #[test]
+    fn test_parse_current_manifest_list() {
+        let parse = |v: serde_json::Value| {
+            parse_current_manifest_list(&serde_json::to_vec(&v).expect("json must be valid"))
+        };
+
+        let manifest_list = parse(serde_json::json!({
+            "current-snapshot-id": 1,
+            "snapshots": [{"snapshot-id": 1, "manifest-list": "s3://bucket/snap-1.avro"}],
+        }))
+        .expect("parse_current_manifest_list must succeed");
+        assert_eq!(manifest_list.as_deref(), Some("s3://bucket/snap-1.avro"));
+
+        // Null or absent snapshots are the same as no snapshot.
+        for snapshots in [Some(serde_json::Value::Null), None] {
+            let mut value = serde_json::json!({"current-snapshot-id": -1});
+            if let Some(snapshots) = snapshots {
+                value["snapshots"] = snapshots;
+            }
+            assert_eq!(
+                parse(value.clone()).expect("parse_current_manifest_list must succeed"),
+                None
+            );
+
+            value["current-snapshot-id"] = serde_json::json!(1);
+            let err = parse(value).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Corrupt);
+        }
+    }
+
+    #[test]
+    fn test_parse_table_metadata_drops_nested_unknown_fields() {
+        let path = format!(
+            "{}/testdata/unknown_fields.metadata.json",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let mut value: serde_json::Value =
+            serde_json::from_slice(&fs::read(path).expect("read_file must succeed"))
+                .expect("json must be valid");
+        value["snapshots"][0]["future-snapshot-field"] = serde_json::json!(1);
+        value["schemas"][0]["future-schema-field"] = serde_json::json!(2);
+        value["partition-specs"][0]["future-spec-field"] = serde_json::json!(3);
+
+        // Nested unknown fields are ignored while reading.
+        let metadata =
+            parse_table_metadata(&serde_json::to_vec(&value).expect("json must be valid"))
+                .expect("parse_table_metadata must succeed");
+        assert!(!metadata
+            .unknown_fields
+            .contains_key("future-snapshot-field"));
+
+        // But they are not written back, only top level ones are.
+        let bs =
+            serialize_table_metadata(&metadata).expect("serialize_table_metadata must succeed");
+        let actual: serde_json::Value = serde_json::from_slice(&bs).expect("json must be valid");
+        assert_eq!(actual["future-field"]["nested"], serde_json::json!([1, 2]));
+        assert!(actual["snapshots"][0]
+            .get("future-snapshot-field")
+            .is_none());
+        assert!(actual["schemas"][0].get("future-schema-field").is_none());
+        assert!(actual["partition-specs"][0]
+            .get("future-spec-field")
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_table_metadata_v1_without_arrays() {
+        let path = format!(
+            "{}/testdata/legacy_v1_table/metadata/v1.metadata.json",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let bs = fs::read(path).expect("read_file must succeed");
+
+        let metadata = parse_table_metadata(&bs).expect("parse_table_metadata v1 must succeed");
+
+        assert_eq!(metadata.format_version, types::TableFormatVersion::V1);
+        assert_eq!(metadata.schemas.len(), 1);
+        let schema = metadata
+            .current_schema()
+            .expect("current schema must exist");
+        assert_eq!(schema.schema_id, 0);
+        assert_eq!(schema.fields.len(), 3);
+        assert_eq!(metadata.partition_specs.len(), 1);
+        let spec = metadata
+            .current_partition_spec()
+            .expect("current partition spec must exist");
+        assert_eq!(spec.spec_id, 0);
+        assert_eq!(spec.fields.len(), 1);
+        assert_eq!(spec.fields[0].name, "x");
+        assert_eq!(spec.fields[0].source_column_id, 1);
+        assert_eq!(metadata.last_partition_id, 1000);
+        assert_eq!(metadata.sort_orders.len(), 1);
+
+        // Arrays are always written after serialization.
+        let bs =
+            serialize_table_metadata(&metadata).expect("serialize_table_metadata must succeed");
+        let actual = parse_table_metadata(&bs).expect("parse_table_metadata must succeed");
+        assert_eq!(actual, metadata);
+    }
+
     #[test]
     fn test_parse_table_metadata_v2() {
         let path = format!(
@@ -258,4 +1085,170 @@ mod tests {
         assert_eq!(metadata.last_column_id, 2);
         assert_eq!(metadata.current_snapshot_id, Some(1646658105718557341));
     }
+
+    #[test]
+    fn test_parse_table_metadata_v3() {
+        let path = format!(
+            "{}/testdata/v3_table/metadata/v1.metadata.json",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+
+        let bs = fs::read(path).expect("read_file must succeed");
+
+        let metadata = parse_table_metadata(&bs).expect("parse_table_metadata v3 must succeed");
+
+        assert_eq!(metadata.format_version, types::TableFormatVersion::V3);
+        let schema = metadata
+            .current_schema()
+            .expect("current schema must exist");
+        let field_types: Vec<_> = schema.fields.iter().map(|v| &v.field_type).collect();
+        assert_eq!(
+            field_types,
+            vec![
+                &types::Any::Primitive(types::Primitive::Long),
+                &types::Any::Unknown("timestamp_ns".to_string()),
+                &types::Any::Unknown("variant".to_string()),
+                &types::Any::Unknown("geometry(srid:4326)".to_string()),
+                &types::Any::Primitive(types::Primitive::String),
+            ]
+        );
+        let spec = metadata
+            .current_partition_spec()
+            .expect("current partition spec must exist");
+        assert_eq!(
+            spec.fields[0].result_type(schema).unwrap(),
+            types::Primitive::String
+        );
+
+        // Types unknown to v3 are still rejected.
+        let mut value: serde_json::Value = serde_json::from_slice(&bs).unwrap();
+        value["schemas"][0]["fields"][1]["type"] = serde_json::json!("timestamp_ps");
+        let err = parse_table_metadata(&serde_json::to_vec(&value).unwrap()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("type \"timestamp_ps\" is not valid schema type"));
+    }
+
+    #[test]
+    fn test_parse_table_metadata_corrupt() {
+        let path = format!(
+            "{}/testdata/simple_table/metadata/v1.metadata.json",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let bs = fs::read(path).expect("read_file must succeed");
+        let value: serde_json::Value = serde_json::from_slice(&bs).expect("json must be valid");
+
+        let mut missing = value.clone();
+        missing.as_object_mut().unwrap().remove("format-version");
+        let err = parse_table_metadata(&serde_json::to_vec(&missing).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Corrupt);
+        assert!(err
+            .to_string()
+            .contains("missing required field 'format-version'"));
+
+        let mut mistyped = value.clone();
+        mistyped["last-column-id"] = serde_json::json!("2");
+        let err = parse_table_metadata(&serde_json::to_vec(&mistyped).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Corrupt);
+        assert!(err
+            .to_string()
+            .contains("field 'last-column-id' must be an integer, but got \"2\""));
+
+        let mut missing = value.clone();
+        let object = missing.as_object_mut().unwrap();
+        object.remove("schema");
+        object.remove("schemas");
+        let err = parse_table_metadata(&serde_json::to_vec(&missing).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Corrupt);
+        assert!(err.to_string().contains("missing required field 'schemas'"));
+
+        let mut unsorted = value.clone();
+        unsorted["snapshot-log"] = serde_json::json!([
+            {"timestamp-ms": 2, "snapshot-id": 1},
+            {"timestamp-ms": 1, "snapshot-id": 2},
+        ]);
+        let err = parse_table_metadata(&serde_json::to_vec(&unsorted).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Corrupt);
+        assert!(err
+            .to_string()
+            .contains("snapshot-log is not sorted by timestamp"));
+
+        let err = parse_table_metadata(b"[]").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Corrupt);
+        assert!(err.to_string().contains("metadata must be a json object"));
+
+        let err = parse_table_metadata(b"{").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Corrupt);
+        assert!(err.to_string().contains("metadata is not valid json"));
+    }
+
+    #[test]
+    fn test_parse_table_metadata_with_many_snapshots() {
+        let path = format!(
+            "{}/testdata/simple_table/metadata/v2.metadata.json",
+            env::current_dir()
+                .expect("current_dir must exist")
+                .to_string_lossy()
+        );
+        let bs = fs::read(path).expect("read_file must succeed");
+        let mut value: serde_json::Value = serde_json::from_slice(&bs).expect("json must be valid");
+
+        let count = 10_000;
+        let template = value["snapshots"][0].clone();
+        let snapshots: Vec<_> = (1..=count)
+            .map(|id| {
+                let mut snapshot = template.clone();
+                snapshot["snapshot-id"] = id.into();
+                if id > 1 {
+                    snapshot["parent-snapshot-id"] = (id - 1).into();
+                }
+                snapshot["sequence-number"] = id.into();
+                snapshot["manifest-list"] = format!("/tmp/snap-{id}.avro").into();
+                snapshot
+            })
+            .collect();
+        value["snapshots"] = snapshots.into();
+        value["current-snapshot-id"] = count.into();
+        value["last-sequence-number"] = count.into();
+        value["snapshot-log"] = serde_json::json!([]);
+        value["refs"] = serde_json::json!({
+            "main": {"snapshot-id": count, "type": "branch"},
+        });
+
+        let bs = serde_json::to_vec(&value).expect("serialize json must succeed");
+        let metadata = parse_table_metadata(&bs).expect("parse_table_metadata must succeed");
+        let snapshots = metadata.snapshots.expect("snapshots must exist");
+        assert_eq!(snapshots.len(), count as usize);
+        assert_eq!(snapshots[count as usize - 1].snapshot_id, count);
+        assert_eq!(metadata.current_snapshot_id, Some(count));
+    }
+
+    #[test]
+    fn test_serialize_table_metadata() {
+        for path in [
+            "simple_table/metadata/v2.metadata.json",
+            "no_hint_table/metadata/00005-032145b7-6a0c-4a53-bc3d-b7b571ccab3b.metadata.json",
+            "v3_table/metadata/v1.metadata.json",
+        ] {
+            let path = format!(
+                "{}/testdata/{path}",
+                env::current_dir()
+                    .expect("current_dir must exist")
+                    .to_string_lossy()
+            );
+
+            let bs = fs::read(path).expect("read_file must succeed");
+            let metadata = parse_table_metadata(&bs).expect("parse_table_metadata must succeed");
+
+            let bs =
+                serialize_table_metadata(&metadata).expect("serialize_table_metadata must succeed");
+            let actual = parse_table_metadata(&bs).expect("parse_table_metadata must succeed");
+
+            assert_eq!(actual, metadata);
+        }
+    }
 }
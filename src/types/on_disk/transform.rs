@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::types;
 use crate::Error;
 use crate::ErrorKind;
@@ -27,6 +29,16 @@ pub fn parse_transform(s: &str) -> Result<types::Transform> {
                     .set_source(err)
                 })?;
 
+            // Catch a broken hash early, before any rows are bucketed.
+            #[cfg(debug_assertions)]
+            {
+                static VERIFY: std::sync::Once = std::sync::Once::new();
+                VERIFY.call_once(|| {
+                    types::Transform::verify_against_spec()
+                        .expect("bucket transform must match the spec")
+                });
+            }
+
             types::Transform::Bucket(length)
         }
         v if v.starts_with("truncate") => {
@@ -57,6 +69,22 @@ pub fn parse_transform(s: &str) -> Result<types::Transform> {
     Ok(t)
 }
 
+/// Format transform into the string represent used in json.
+impl fmt::Display for types::Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            types::Transform::Identity => write!(f, "identity"),
+            types::Transform::Year => write!(f, "year"),
+            types::Transform::Month => write!(f, "month"),
+            types::Transform::Day => write!(f, "day"),
+            types::Transform::Hour => write!(f, "hour"),
+            types::Transform::Void => write!(f, "void"),
+            types::Transform::Bucket(length) => write!(f, "bucket[{length}]"),
+            types::Transform::Truncate(width) => write!(f, "truncate[{width}]"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,7 +107,12 @@ mod tests {
         for (input, expected) in cases {
             let actual = parse_transform(input).unwrap();
 
-            assert_eq!(actual, expected, "transform is not match for {input}")
+            assert_eq!(actual, expected, "transform is not match for {input}");
+            assert_eq!(
+                expected.to_string(),
+                input,
+                "display is not match for {input}"
+            );
         }
     }
 }
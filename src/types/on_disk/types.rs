@@ -4,8 +4,11 @@ use std::marker::PhantomData;
 use serde::de;
 use serde::de::MapAccess;
 use serde::de::Visitor;
+use serde::ser::SerializeMap;
 use serde::Deserialize;
 use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 
 use crate::types;
 use crate::Error;
@@ -78,6 +81,7 @@ impl TryFrom<Types> for types::Any {
                     .trim_start_matches('(')
                     .trim_end_matches(')')
                     .split(',')
+                    .map(|v| v.trim())
                     .collect::<Vec<_>>();
                 if parts.len() != 2 {
                     return Err(Error::new(
@@ -153,6 +157,7 @@ impl TryFrom<Types> for types::Any {
                     value_type: Box::new((*value_type).try_into()?),
                 })
             }
+            v if is_v3_type(v) => types::Any::Unknown(v.to_string()),
             v => {
                 return Err(Error::new(
                     ErrorKind::IcebergDataInvalid,
@@ -165,7 +170,112 @@ impl TryFrom<Types> for types::Any {
     }
 }
 
-#[derive(Deserialize)]
+/// Check if the type is introduced by format v3, which is parsed as
+/// `Any::Unknown` until it's supported.
+fn is_v3_type(typ: &str) -> bool {
+    matches!(
+        typ,
+        "timestamp_ns" | "timestamptz_ns" | "variant" | "unknown" | "geometry" | "geography"
+    ) || typ.starts_with("geometry(")
+        || typ.starts_with("geography(")
+}
+
+impl Serialize for Types {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.typ.as_str() {
+            "struct" => {
+                let mut m = serializer.serialize_map(Some(2))?;
+                m.serialize_entry("type", &self.typ)?;
+                m.serialize_entry("fields", &self.fields)?;
+                m.end()
+            }
+            "list" => {
+                let mut m = serializer.serialize_map(Some(4))?;
+                m.serialize_entry("type", &self.typ)?;
+                m.serialize_entry("element-id", &self.element_id)?;
+                m.serialize_entry("element-required", &self.element_required)?;
+                m.serialize_entry("element", &self.element)?;
+                m.end()
+            }
+            "map" => {
+                let mut m = serializer.serialize_map(Some(6))?;
+                m.serialize_entry("type", &self.typ)?;
+                m.serialize_entry("key-id", &self.key_id)?;
+                m.serialize_entry("key", &self.key)?;
+                m.serialize_entry("value-id", &self.value_id)?;
+                m.serialize_entry("value-required", &self.value_required)?;
+                m.serialize_entry("value", &self.value)?;
+                m.end()
+            }
+            v => serializer.serialize_str(v),
+        }
+    }
+}
+
+impl From<types::Any> for Types {
+    fn from(v: types::Any) -> Self {
+        match v {
+            types::Any::Primitive(v) => Types {
+                typ: v.to_string(),
+                ..Default::default()
+            },
+            types::Any::Struct(v) => Types {
+                typ: "struct".to_string(),
+                fields: v.fields.into_iter().map(Field::from).collect(),
+                ..Default::default()
+            },
+            types::Any::List(v) => Types {
+                typ: "list".to_string(),
+                element_id: v.element_id,
+                element_required: v.element_required,
+                element: Some(Box::new((*v.element_type).into())),
+                ..Default::default()
+            },
+            types::Any::Unknown(v) => Types {
+                typ: v,
+                ..Default::default()
+            },
+            types::Any::Map(v) => Types {
+                typ: "map".to_string(),
+                key_id: v.key_id,
+                key: Some(Box::new((*v.key_type).into())),
+                value_id: v.value_id,
+                value_required: v.value_required,
+                value: Some(Box::new((*v.value_type).into())),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Format primitive type into the string represent used in json.
+impl fmt::Display for types::Primitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            types::Primitive::Boolean => write!(f, "boolean"),
+            types::Primitive::Int => write!(f, "int"),
+            types::Primitive::Long => write!(f, "long"),
+            types::Primitive::Float => write!(f, "float"),
+            types::Primitive::Double => write!(f, "double"),
+            types::Primitive::Decimal { precision, scale } => {
+                write!(f, "decimal({precision}, {scale})")
+            }
+            types::Primitive::Date => write!(f, "date"),
+            types::Primitive::Time => write!(f, "time"),
+            types::Primitive::Timestamp => write!(f, "timestamp"),
+            types::Primitive::Timestampz => write!(f, "timestamptz"),
+            types::Primitive::String => write!(f, "string"),
+            types::Primitive::Uuid => write!(f, "uuid"),
+            types::Primitive::Fixed(length) => write!(f, "fixed[{length}]"),
+            types::Primitive::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Field {
     id: i32,
@@ -173,9 +283,22 @@ pub struct Field {
     required: bool,
     #[serde(rename = "type", deserialize_with = "string_or_struct")]
     typ: Types,
+    #[serde(skip_serializing_if = "Option::is_none")]
     doc: Option<String>,
 }
 
+impl From<types::Field> for Field {
+    fn from(v: types::Field) -> Self {
+        Field {
+            id: v.id,
+            name: v.name,
+            required: v.required,
+            typ: v.field_type.into(),
+            doc: v.comment,
+        }
+    }
+}
+
 impl TryFrom<Field> for types::Field {
     type Error = Error;
 
@@ -268,3 +391,67 @@ where
 
     deserializer.deserialize_any(StringOrStruct(PhantomData))
 }
+
+/// Deserialize a long from either a json number or a quoted string, since
+/// some producers quote large ids that exceed the safe integer range.
+pub fn long_or_string<'de, D>(deserializer: D) -> std::result::Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct LongOrString;
+
+    impl<'de> Visitor<'de> for LongOrString {
+        type Value = i64;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("long or string of long")
+        }
+
+        fn visit_i64<E>(self, value: i64) -> std::result::Result<i64, E>
+        where
+            E: de::Error,
+        {
+            Ok(value)
+        }
+
+        fn visit_u64<E>(self, value: u64) -> std::result::Result<i64, E>
+        where
+            E: de::Error,
+        {
+            i64::try_from(value).map_err(|_| E::custom(format!("long {value} is out of range")))
+        }
+
+        fn visit_str<E>(self, value: &str) -> std::result::Result<i64, E>
+        where
+            E: de::Error,
+        {
+            value
+                .parse()
+                .map_err(|_| E::custom(format!("string {value:?} is not a valid long")))
+        }
+    }
+
+    deserializer.deserialize_any(LongOrString)
+}
+
+/// Deserialize an optional long like `long_or_string`.
+pub fn option_long_or_string<'de, D>(deserializer: D) -> std::result::Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Long(#[serde(deserialize_with = "long_or_string")] i64);
+
+    Ok(Option::<Long>::deserialize(deserializer)?.map(|v| v.0))
+}
+
+/// Deserialize an optional snapshot id like `option_long_or_string`.
+///
+/// `-1` is written by some writers for tables without snapshot, which
+/// means the same as null per the spec.
+pub fn option_snapshot_id<'de, D>(deserializer: D) -> std::result::Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(option_long_or_string(deserializer)?.filter(|v| *v != -1))
+}
@@ -6,9 +6,13 @@ use super::in_memory as types;
 use arrow_schema::{
     ArrowError, DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema, TimeUnit,
 };
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::sync::Arc;
 
+/// Key of arrow field metadata to store the doc string of field.
+pub const ARROW_FIELD_DOC_KEY: &str = "doc";
+
 impl TryFrom<types::Schema> for ArrowSchema {
     type Error = ArrowError;
 
@@ -26,14 +30,23 @@ impl TryFrom<types::Schema> for ArrowSchema {
 impl TryFrom<types::Field> for ArrowField {
     type Error = ArrowError;
 
+    /// Required fields are converted into non-nullable fields, and the doc
+    /// string is kept in metadata with key `ARROW_FIELD_DOC_KEY`.
     fn try_from(value: types::Field) -> Result<Self, Self::Error> {
-        Ok(ArrowField::new_dict(
+        let field = ArrowField::new_dict(
             value.name,
             value.field_type.try_into()?,
             !value.required,
             value.id as i64,
             false,
-        ))
+        );
+
+        Ok(match value.comment {
+            Some(doc) => {
+                field.with_metadata(HashMap::from([(ARROW_FIELD_DOC_KEY.to_string(), doc)]))
+            }
+            None => field,
+        })
     }
 }
 
@@ -43,6 +56,9 @@ impl TryFrom<types::Any> for ArrowDataType {
     fn try_from(value: types::Any) -> Result<Self, Self::Error> {
         match value {
             super::Any::Primitive(v) => v.try_into(),
+            super::Any::Unknown(v) => Err(ArrowError::SchemaError(format!(
+                "type {v} can't be converted to arrow since it's not supported yet"
+            ))),
             super::Any::Struct(v) => {
                 let mut fields = vec![];
                 for f in v.fields {
@@ -164,4 +180,60 @@ mod tests {
         assert_eq!(arrow_schema.fields()[1].name(), "data");
         assert_eq!(arrow_schema.fields()[1].data_type(), &ArrowDataType::Utf8);
     }
+
+    #[test]
+    fn test_try_into_arrow_field_nullability_and_doc() {
+        let field = |id, name: &str, required, field_type| types::Field {
+            id,
+            name: name.to_string(),
+            required,
+            field_type,
+            comment: None,
+        };
+        let mut id = field(1, "id", true, types::Any::Primitive(types::Primitive::Long));
+        id.comment = Some("unique id".to_string());
+        let schema = types::Schema {
+            schema_id: 0,
+            identifier_field_ids: None,
+            fields: vec![
+                id,
+                field(
+                    2,
+                    "address",
+                    false,
+                    types::Any::Struct(types::Struct {
+                        fields: vec![field(
+                            3,
+                            "city",
+                            true,
+                            types::Any::Primitive(types::Primitive::String),
+                        )],
+                    }),
+                ),
+            ],
+        };
+
+        let arrow_schema = ArrowSchema::try_from(schema).unwrap();
+        let id = arrow_schema.field_with_name("id").unwrap();
+        assert!(!id.is_nullable());
+        assert_eq!(
+            id.metadata().get(ARROW_FIELD_DOC_KEY).map(|v| v.as_str()),
+            Some("unique id")
+        );
+        let address = arrow_schema.field_with_name("address").unwrap();
+        assert!(address.is_nullable());
+        assert!(address.metadata().is_empty());
+        let ArrowDataType::Struct(fields) = address.data_type() else {
+            panic!("address must be a struct");
+        };
+        assert!(!fields[0].is_nullable());
+    }
+
+    #[test]
+    fn test_try_into_arrow_unknown_type() {
+        let err = ArrowDataType::try_from(types::Any::Unknown("variant".to_string())).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("type variant can't be converted to arrow"));
+    }
 }
@@ -0,0 +1,62 @@
+//! Hooks of table metadata are registered process wide and can't be
+//! removed, so they are tested in a separate test binary to keep other
+//! tests unaffected.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+
+use icelake::types;
+
+#[test]
+fn test_parse_table_metadata_with_hook() {
+    fn hook(value: &serde_json::Value, metadata: &mut types::TableMetadata) {
+        if let Some(v) = value.get("test-vendor-ext") {
+            metadata
+                .extensions
+                .insert("test-vendor".to_string(), v.to_string());
+        }
+    }
+    types::register_table_metadata_hook(hook);
+
+    let path = format!(
+        "{}/testdata/simple_table/metadata/v2.metadata.json",
+        env::current_dir()
+            .expect("current_dir must exist")
+            .to_string_lossy()
+    );
+    let bs = fs::read(path).expect("read_file must succeed");
+    let metadata = types::parse_table_metadata(&bs).expect("parse_table_metadata must succeed");
+    assert!(metadata.extensions.is_empty());
+
+    let mut value: serde_json::Value = serde_json::from_slice(&bs).expect("json must be valid");
+    value["test-vendor-ext"] = serde_json::json!({"blob": "s3://bucket/blob"});
+    let metadata = types::parse_table_metadata(&serde_json::to_vec(&value).unwrap())
+        .expect("parse_table_metadata must succeed");
+    assert_eq!(
+        metadata.extensions,
+        BTreeMap::from([(
+            "test-vendor".to_string(),
+            r#"{"blob":"s3://bucket/blob"}"#.to_string()
+        )])
+    );
+
+    // Extensions are not written back.
+    let bs =
+        types::serialize_table_metadata(&metadata).expect("serialize_table_metadata must succeed");
+    let value: serde_json::Value = serde_json::from_slice(&bs).expect("json must be valid");
+    assert!(value.get("extensions").is_none());
+    assert!(value.get("test-vendor").is_none());
+    // The unknown field read by hook is written back as is, but only once.
+    assert_eq!(
+        value["test-vendor-ext"],
+        serde_json::json!({"blob": "s3://bucket/blob"})
+    );
+    assert_eq!(
+        String::from_utf8(bs)
+            .expect("json must be valid utf-8")
+            .matches("s3://bucket/blob")
+            .count(),
+        1
+    );
+}